@@ -0,0 +1,191 @@
+//! Per-run token and spend ceilings, estimated before any request is sent.
+//!
+//! The estimate counts one summary request per diff plus the final message request, using
+//! [`estimate_tokens`](crate::llm::context::estimate_tokens) for the diffs and fixed allowances
+//! for the prompt around them and the replies.
+
+use crate::llm::usage::price_per_million;
+
+/// Prompt tokens a request carries besides the diff: instructions, branch, file list.
+const PROMPT_OVERHEAD_TOKENS: u64 = 600;
+
+/// Reply tokens allowed for one per-file summary.
+const SUMMARY_REPLY_TOKENS: u64 = 150;
+
+/// Reply tokens allowed for the final message.
+const MESSAGE_REPLY_TOKENS: u64 = 500;
+
+/// Ceilings from `max_tokens_per_run` and `max_cost_per_run`; `None` means no limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Budget {
+    pub max_tokens: Option<u64>,
+    pub max_cost: Option<f64>,
+}
+
+impl Budget {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_tokens.is_none() && self.max_cost.is_none()
+    }
+}
+
+/// Expected size of a run; `cost` is `None` when either model's price is unknown.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RunEstimate {
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost: Option<f64>,
+}
+
+impl RunEstimate {
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
+
+/// Estimate a run that summarizes diffs of `diff_tokens` each with `summary_model`, then
+/// writes the message with `final_model`.
+pub fn estimate_run(diff_tokens: &[usize], summary_model: &str, final_model: &str) -> RunEstimate {
+    let files = diff_tokens.len() as u64;
+    let diffs: u64 = diff_tokens.iter().map(|&t| t as u64).sum();
+
+    let summary_prompt = diffs + files * PROMPT_OVERHEAD_TOKENS;
+    let summary_reply = files * SUMMARY_REPLY_TOKENS;
+    let final_prompt = PROMPT_OVERHEAD_TOKENS + files * SUMMARY_REPLY_TOKENS;
+    let final_reply = MESSAGE_REPLY_TOKENS;
+
+    let cost = price_per_million(summary_model)
+        .zip(price_per_million(final_model))
+        .map(|((sp, sc), (fp, fc))| {
+            (summary_prompt as f64 * sp
+                + summary_reply as f64 * sc
+                + final_prompt as f64 * fp
+                + final_reply as f64 * fc)
+                / 1_000_000.0
+        });
+
+    RunEstimate {
+        requests: files + 1,
+        prompt_tokens: summary_prompt + final_prompt,
+        completion_tokens: summary_reply + final_reply,
+        cost,
+    }
+}
+
+/// Which ceiling `estimate` breaks, described for an error or warning; `None` when it fits.
+/// A cost ceiling can't be checked without a known price and is treated as met.
+pub fn over_budget(estimate: &RunEstimate, budget: &Budget) -> Option<String> {
+    if let Some(max) = budget.max_tokens
+        && estimate.total_tokens() > max
+    {
+        return Some(format!(
+            "~{} tokens over {} request(s) exceeds max_tokens_per_run ({max})",
+            estimate.total_tokens(),
+            estimate.requests
+        ));
+    }
+    if let (Some(max), Some(cost)) = (budget.max_cost, estimate.cost)
+        && cost > max
+    {
+        return Some(format!(
+            "~${cost:.2} over {} request(s) exceeds max_cost_per_run (${max:.2})",
+            estimate.requests
+        ));
+    }
+    None
+}
+
+/// The largest per-diff token count that brings the run within `budget` once every diff is
+/// cut to it, or `None` when even empty diffs would not fit.
+pub fn diff_cap(
+    diff_tokens: &[usize],
+    summary_model: &str,
+    final_model: &str,
+    budget: &Budget,
+) -> Option<usize> {
+    let fits = |cap: usize| {
+        let capped: Vec<usize> = diff_tokens.iter().map(|&t| t.min(cap)).collect();
+        over_budget(&estimate_run(&capped, summary_model, final_model), budget).is_none()
+    };
+    if !fits(0) {
+        return None;
+    }
+    let (mut low, mut high) = (0, diff_tokens.iter().copied().max().unwrap_or(0));
+    while low < high {
+        let mid = low + (high - low).div_ceil(2);
+        if fits(mid) {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    Some(low)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimates_summaries_and_the_final_message() {
+        let estimate = estimate_run(&[1_000, 3_000], "llama3.1:8b", "llama3.1:8b");
+        assert_eq!(estimate.requests, 3);
+        assert_eq!(estimate.prompt_tokens, 4_000 + 2 * 600 + 600 + 2 * 150);
+        assert_eq!(estimate.completion_tokens, 2 * 150 + 500);
+        assert_eq!(estimate.cost, None);
+
+        let priced = estimate_run(&[1_000_000], "gpt-4o-mini", "gpt-4o-mini");
+        assert!(
+            priced.cost.is_some_and(|c| c > 0.15 && c < 0.16),
+            "{priced:?}"
+        );
+    }
+
+    #[test]
+    fn reports_the_ceiling_that_was_broken() {
+        let estimate = estimate_run(&[40_000], "gpt-4o", "gpt-4o");
+        let tokens = Budget {
+            max_tokens: Some(10_000),
+            max_cost: None,
+        };
+        assert!(
+            over_budget(&estimate, &tokens)
+                .unwrap()
+                .contains("max_tokens_per_run")
+        );
+
+        let cost = Budget {
+            max_tokens: None,
+            max_cost: Some(0.05),
+        };
+        assert!(
+            over_budget(&estimate, &cost)
+                .unwrap()
+                .contains("max_cost_per_run")
+        );
+        assert_eq!(over_budget(&estimate, &Budget::default()), None);
+
+        let unpriced = estimate_run(&[40_000], "my-finetune", "my-finetune");
+        assert_eq!(over_budget(&unpriced, &cost), None);
+    }
+
+    #[test]
+    fn finds_the_largest_cap_that_fits() {
+        let budget = Budget {
+            max_tokens: Some(20_000),
+            max_cost: None,
+        };
+        let diffs = [500, 40_000, 60_000];
+        let cap = diff_cap(&diffs, "m", "m", &budget).unwrap();
+        let capped: Vec<usize> = diffs.iter().map(|&t| t.min(cap)).collect();
+        assert!(over_budget(&estimate_run(&capped, "m", "m"), &budget).is_none());
+        let over: Vec<usize> = diffs.iter().map(|&t| t.min(cap + 1)).collect();
+        assert!(over_budget(&estimate_run(&over, "m", "m"), &budget).is_some());
+
+        let tiny = Budget {
+            max_tokens: Some(100),
+            max_cost: None,
+        };
+        assert_eq!(diff_cap(&diffs, "m", "m", &tiny), None);
+    }
+}
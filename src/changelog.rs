@@ -0,0 +1,144 @@
+//! Keep-a-Changelog entries generated alongside the commit message.
+//!
+//! The model answers with one `Category: text` line; [`insert_unreleased`] files it under the
+//! matching `###` heading of the `## [Unreleased]` section, creating either when missing.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// Keep-a-Changelog change types, in the order the spec lists them.
+pub const CATEGORIES: &[&str] = &[
+    "Added",
+    "Changed",
+    "Deprecated",
+    "Removed",
+    "Fixed",
+    "Security",
+];
+
+const UNRELEASED_HEADING: &str = "## [Unreleased]";
+
+/// One user-facing changelog bullet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangelogEntry {
+    /// One of [`CATEGORIES`]
+    pub category: &'static str,
+    pub text: String,
+}
+
+impl ChangelogEntry {
+    /// Parse a model reply such as `Fixed: Crash when the config file is empty`.
+    ///
+    /// Only the first non-empty line is used; an unknown or missing category becomes "Changed".
+    pub fn parse(reply: &str) -> Option<Self> {
+        let line = reply.lines().map(str::trim).find(|l| !l.is_empty())?;
+        let line = line.trim_start_matches(['-', '*']).trim();
+        let (category, text) = match line.split_once(':') {
+            Some((head, rest)) => match CATEGORIES
+                .iter()
+                .find(|c| c.eq_ignore_ascii_case(head.trim().trim_matches(['#', '*', ' '])))
+            {
+                Some(category) => (*category, rest.trim()),
+                None => ("Changed", line),
+            },
+            None => ("Changed", line),
+        };
+        (!text.is_empty()).then(|| ChangelogEntry {
+            category,
+            text: text.to_string(),
+        })
+    }
+}
+
+/// Insert `entry` at the top of its category in the `## [Unreleased]` section of `content`.
+///
+/// A missing Unreleased section is created above the first release heading (or at the end
+/// of a changelog that has none), and a missing category heading is created at its top.
+pub fn insert_unreleased(content: &str, entry: &ChangelogEntry) -> String {
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let bullet = format!("- {}", entry.text);
+    let category_heading = format!("### {}", entry.category);
+
+    let unreleased = lines.iter().position(|l| is_unreleased_heading(l));
+    let Some(start) = unreleased else {
+        let at = lines
+            .iter()
+            .position(|l| l.starts_with("## "))
+            .unwrap_or(lines.len());
+        let mut section = vec![
+            UNRELEASED_HEADING.to_string(),
+            String::new(),
+            category_heading,
+            bullet,
+            String::new(),
+        ];
+        if at == lines.len() {
+            section.pop();
+        }
+        if at > 0 && !lines[at - 1].trim().is_empty() {
+            section.insert(0, String::new());
+        }
+        lines.splice(at..at, section);
+        return join_lines(&lines, content);
+    };
+
+    let end = lines[start + 1..]
+        .iter()
+        .position(|l| l.starts_with("## "))
+        .map_or(lines.len(), |i| start + 1 + i);
+    match lines[start + 1..end]
+        .iter()
+        .position(|l| l.trim().eq_ignore_ascii_case(&category_heading))
+    {
+        Some(i) => lines.insert(start + 2 + i, bullet),
+        None => {
+            // Skip the blank line that usually follows the Unreleased heading.
+            let at = if lines.get(start + 1).is_some_and(|l| l.trim().is_empty()) {
+                start + 2
+            } else {
+                start + 1
+            };
+            let mut block = vec![category_heading, bullet];
+            if at < lines.len() {
+                block.push(String::new());
+            }
+            if at == start + 1 {
+                block.insert(0, String::new());
+            }
+            lines.splice(at..at, block);
+        }
+    }
+    join_lines(&lines, content)
+}
+
+/// Add `entry` to the changelog at `path`, creating the file when it does not exist.
+pub fn update_changelog_file(path: &Path, entry: &ChangelogEntry) -> Result<()> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => "# Changelog\n".to_string(),
+        Err(err) => {
+            return Err(err).with_context(|| format!("failed to read {}", path.display()));
+        }
+    };
+    fs::write(path, insert_unreleased(&content, entry))
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+fn is_unreleased_heading(line: &str) -> bool {
+    let Some(title) = line.strip_prefix("## ") else {
+        return false;
+    };
+    title
+        .trim()
+        .trim_matches(['[', ']'])
+        .eq_ignore_ascii_case("unreleased")
+}
+
+fn join_lines(lines: &[String], original: &str) -> String {
+    let mut out = lines.join("\n");
+    if original.is_empty() || original.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
@@ -1,41 +1,174 @@
-use clap::{ArgAction, Parser, Subcommand};
+use clap::{ArgAction, CommandFactory, Parser, Subcommand};
+use std::ffi::OsString;
 
 /// CLI options
 #[derive(Parser, Debug)]
 #[command(
     name = "commitbot",
     disable_version_flag = true,
-    about = "LLM-assisted Git commit message generator"
+    args_override_self = true,
+    about = "LLM-assisted Git commit message generator",
+    after_help = "Exit codes:\n  0  success\n  1  error\n  2  no staged changes / empty diff (exit 0 instead with --allow-empty)\n  3  aborted by user"
 )]
 pub struct Cli {
     /// Interactive mode: classify each file and do per-file summaries
     #[arg(long, global = true)]
     pub ask: bool,
 
+    /// Default category highlighted in interactive mode (1=main, 2=supporting,
+    /// 3=consequence, 4=ignore). Press 'a' to apply it to all remaining files.
+    #[arg(long, global = true, value_name = "N", value_parser = clap::value_parser!(u8).range(1..=4))]
+    pub default_category: Option<u8>,
+
+    /// Also write the commit message to FILE (e.g. "$1" in a prepare-commit-msg hook)
+    #[arg(long, global = true, value_name = "FILE")]
+    pub message_file: Option<String>,
+
+    /// Also generate a one-line Keep-a-Changelog entry for the commit
+    #[arg(long, global = true)]
+    pub changelog: bool,
+
+    /// Add the changelog entry under "## [Unreleased]" in FILE (implies --changelog)
+    #[arg(long, global = true, value_name = "FILE")]
+    pub changelog_file: Option<String>,
+
+    /// Overwrite a --message-file that already holds a message without asking
+    #[arg(long, global = true)]
+    pub force: bool,
+
+    /// Answer every confirmation with its default (resume, overwrite, skip the ticket prompt)
+    /// and, with --ask, classify files as --default-category without the menu
+    #[arg(short, long, global = true)]
+    pub yes: bool,
+
+    /// Exit 0 instead of 2 when there is nothing staged to describe
+    #[arg(long, global = true)]
+    pub allow_empty: bool,
+
+    /// Leave whitespace-only changes out of the staged diffs (git diff -w)
+    #[arg(long, global = true)]
+    pub ignore_whitespace: bool,
+
+    /// Send word-level staged diffs instead of whole lines (for reflowed text)
+    #[arg(long, global = true)]
+    pub word_diff: bool,
+
+    /// Also describe untracked files, without staging them (a preview of the message)
+    #[arg(long, global = true)]
+    pub include_untracked: bool,
+
     /// Stage all changes before generating the commit message
     #[arg(short, long, global = true)]
     pub stage: bool,
 
+    /// Show the model up to N recent commit messages as examples of the repository's style
+    #[arg(long, global = true, value_name = "N")]
+    pub style_from_history: Option<usize>,
+
+    /// Plain output without colors or styling (also honors the NO_COLOR environment variable)
+    #[arg(long, global = true)]
+    pub no_color: bool,
+
     /// Max concurrent requests to the LLM API
     #[arg(long, global = true)]
     pub max: Option<usize>,
 
+    /// Seconds to wait for a whole LLM request, 0 for no limit (default 90; none for Ollama)
+    #[arg(long, global = true, value_name = "SECS")]
+    pub request_timeout: Option<usize>,
+
+    /// Seconds to wait for a connection to the LLM API, 0 for no limit (the default)
+    #[arg(long, global = true, value_name = "SECS")]
+    pub connect_timeout: Option<usize>,
+
+    /// PEM file of extra root certificates to trust, e.g. for a TLS-inspecting proxy
+    #[arg(long, global = true, value_name = "FILE")]
+    pub ca_bundle: Option<String>,
+
+    /// PEM client certificate for mutual TLS; may include the private key
+    #[arg(long, global = true, value_name = "FILE")]
+    pub client_cert: Option<String>,
+
+    /// PEM private key for --client-cert, if kept in a separate file
+    #[arg(long, global = true, value_name = "FILE")]
+    pub client_key: Option<String>,
+
+    /// Extra HTTP header for every LLM request, as "Name: value" (repeatable)
+    #[arg(long = "header", global = true, value_name = "HEADER")]
+    pub header: Vec<String>,
+
     /// Model name to use (e.g. gpt-4o-mini)
     #[arg(short, long, global = true)]
     pub model: Option<String>,
 
-    /// API key (otherwise uses OPENAI_API_KEY env var)
+    /// Model for per-file summaries (defaults to --model)
+    #[arg(long, global = true, value_name = "MODEL")]
+    pub summary_model: Option<String>,
+
+    /// Model for the final commit message or PR description (defaults to --model)
+    #[arg(long, global = true, value_name = "MODEL")]
+    pub final_model: Option<String>,
+
+    /// API key (otherwise uses OPENAI_API_KEY, or the provider's own variable, e.g. GEMINI_API_KEY)
     #[arg(short = 'k', long, global = true)]
     pub api_key: Option<String>,
 
-    /// LLM provider / API style (openai or ollama)
+    /// LLM provider / API style (openai, azure, openai-compatible, openrouter, ollama, gemini, anthropic, command, or mock)
     #[arg(long, global = true)]
     pub provider: Option<String>,
 
+    /// Shell command for provider=command; reads the prompt as JSON on stdin, prints the reply
+    #[arg(long, global = true, value_name = "CMD")]
+    pub provider_command: Option<String>,
+
     /// Base URL for the selected provider (e.g. http://localhost:11434) llama3.1:8b-instruct-q5_K_M
     #[arg(long, global = true)]
     pub url: Option<String>,
 
+    /// Service preset for provider=openai-compatible (groq, together, mistral, lmstudio, openrouter)
+    #[arg(long, global = true, value_name = "NAME")]
+    pub preset: Option<String>,
+
+    /// OpenAI API style: "chat" (Chat Completions, default) or "responses"
+    #[arg(long, global = true)]
+    pub api: Option<String>,
+
+    /// Reasoning effort for OpenAI reasoning models (minimal, low, medium, high)
+    #[arg(long, global = true, value_name = "LEVEL")]
+    pub reasoning_effort: Option<String>,
+
+    /// Sampling temperature, 0 to 2 (0 for the most repeatable output)
+    #[arg(long, global = true, value_name = "T")]
+    pub temperature: Option<f32>,
+
+    /// Nucleus sampling: only consider tokens within this top probability mass, 0 to 1
+    #[arg(long, global = true, value_name = "P")]
+    pub top_p: Option<f32>,
+
+    /// Cap on generated tokens per request (0 for the provider default)
+    #[arg(long, global = true, value_name = "N")]
+    pub max_output_tokens: Option<usize>,
+
+    /// Language for the generated message (e.g. "es" or "Spanish"); defaults to English
+    #[arg(long, global = true, value_name = "CODE")]
+    pub language: Option<String>,
+
+    /// Voice for generated text: concise, detailed, or formal (default: unchanged)
+    #[arg(long, global = true)]
+    pub tone: Option<String>,
+
+    /// Include the branch name in prompts: auto (skip trunk branches), always, or never
+    #[arg(long, global = true, value_name = "WHEN")]
+    pub branch_context: Option<String>,
+
+    /// Shape of the commit message: grouped (default), plain (subject + prose), or oneline
+    #[arg(long, global = true)]
+    pub style: Option<String>,
+
+    /// Ask the model for the commit message as JSON and format it locally (implies --no-stream)
+    #[arg(long, global = true)]
+    pub structured_output: bool,
+
     /// Read diff from a file instead of git staged changes (use "-" for stdin).
     /// Cannot be used with --ask mode.
     #[arg(long, global = true, value_name = "FILE")]
@@ -50,11 +183,71 @@ pub struct Cli {
     #[arg(long, global = true)]
     pub no_stream: bool,
 
+    /// Fail instead of warning when a diff likely exceeds the model's context window
+    #[arg(long, global = true)]
+    pub strict_context: bool,
+
+    /// Cut diffs that likely exceed the model's context window instead of sending them whole
+    #[arg(long, global = true)]
+    pub trim_context: bool,
+
+    /// Stop (or trim, with --trim-context) when the run is estimated to use more tokens than this
+    #[arg(long, global = true, value_name = "TOKENS")]
+    pub max_tokens_per_run: Option<usize>,
+
+    /// Stop (or trim, with --trim-context) when the run is estimated to cost more than this, in USD
+    #[arg(long, global = true, value_name = "USD")]
+    pub max_cost_per_run: Option<f64>,
+
+    /// Summarize every file again instead of reusing summaries cached by earlier runs
+    #[arg(long, global = true)]
+    pub no_cache: bool,
+
+    /// Fail instead of warning when added lines look like secrets (API keys, private keys, passwords)
+    #[arg(long, global = true)]
+    pub strict_secrets: bool,
+
+    /// Print only the generated message: no progress, banners, or token usage (implies --no-stream)
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// Print requests, tokens, and estimated cost for the run, even with --quiet or in PR mode
+    #[arg(long, global = true)]
+    pub usage: bool,
+
+    /// Print every prompt that would be sent (per-file, final, PR) without contacting the provider
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+
+    /// Save every request and response to DIR (one JSON file each) for bug reports and replays
+    #[arg(long, global = true, value_name = "DIR", conflicts_with = "replay")]
+    pub record: Option<String>,
+
+    /// Answer requests from responses saved with --record instead of contacting the provider
+    #[arg(long, global = true, value_name = "DIR")]
+    pub replay: Option<String>,
+
+    /// Group files by path prefix and describe each group in its own section (repeatable)
+    #[arg(long = "partition", global = true, value_name = "PREFIX")]
+    pub partition: Vec<String>,
+
+    /// Print the generated message as-is, without fixing subject/blank-line conventions
+    #[arg(long, global = true)]
+    pub no_fixups: bool,
+
+    /// Summarize all Consequence files in one combined request instead of one each
+    #[arg(long, global = true)]
+    pub batch_consequence: bool,
+
     /// Increase verbosity (-v, -vv, -vvv)
     #[arg(short = 'v', long = "verbose", action = ArgAction::Count)]
     pub verbose: u8,
 
-    /// Define config file (default: ~/.config/commitbot.toml)
+    /// Append full prompts and raw responses for every LLM call to FILE (JSON Lines)
+    #[arg(long, global = true, value_name = "FILE")]
+    pub debug_log: Option<String>,
+
+    /// Define config file (default: ~/.config/commitbot.toml; env: COMMITBOT_CONFIG)
     #[arg(long, global = true, value_name = "FILE")]
     pub config: Option<String>,
 
@@ -75,9 +268,12 @@ pub enum Command {
     /// This command analyzes a commit range and generates a PR summary.
     /// Example:
     ///   commitbot pr --base main --from feature-branch
+    ///   commitbot pr --since-tag
     Pr {
-        /// Base branch to compare against (e.g. main or develop)
-        base: String,
+        /// Base branch to compare against (e.g. main or develop); with --tag or --since-tag,
+        /// the branch to describe instead
+        #[arg(required_unless_present_any = ["since_tag", "tag"])]
+        base: Option<String>,
 
         /// Optional feature/source branch; defaults to current branch if omitted
         from: Option<String>,
@@ -89,6 +285,33 @@ pub enum Command {
         /// Force using commit-by-commit mode instead of PR grouping
         #[arg(long = "commit")]
         commit_mode: bool,
+
+        /// Forge conventions for change references: "github" (#123) or "gitlab" (!123).
+        /// Auto-detected from remote.origin.url when omitted.
+        #[arg(long)]
+        forge: Option<String>,
+
+        /// Summarize everything since the most recent tag, as release notes
+        #[arg(long, conflicts_with = "tag")]
+        since_tag: bool,
+
+        /// Summarize everything since this tag, as release notes
+        #[arg(long, value_name = "NAME")]
+        tag: Option<String>,
+
+        /// Ignore the repository's pull request template
+        #[arg(long)]
+        no_template: bool,
+    },
+
+    /// Generate an improved message for an existing commit (e.g. for a rebase `reword`)
+    ///
+    /// Prints the message, or writes it with --message-file. Merge commits are rejected.
+    /// Example:
+    ///   commitbot reword HEAD~2
+    Reword {
+        /// Commit to describe (any commit-ish, e.g. HEAD~2 or a hash)
+        rev: String,
     },
 
     /// Freeform summary provided at the end of the command.
@@ -98,3 +321,33 @@ pub enum Command {
     #[command(external_subcommand)]
     Summary(Vec<String>),
 }
+
+/// Merge a repo's `default_args` into the command line `args` (program name first).
+///
+/// Default flags go right after the program name, so flags given explicitly (which come
+/// later) override them. A default subcommand (and everything after it) is appended only
+/// when `args` names no subcommand or summary of its own. `args` are taken as the OS gave
+/// them, so a non-UTF-8 path on the command line is passed through to clap untouched.
+pub fn with_default_args(args: Vec<OsString>, defaults: &[String]) -> Vec<OsString> {
+    if defaults.is_empty() || args.is_empty() {
+        return args;
+    }
+
+    let command = Cli::command();
+    let split = defaults
+        .iter()
+        .position(|arg| command.find_subcommand(arg).is_some())
+        .unwrap_or(defaults.len());
+    let (flags, subcommand) = defaults.split_at(split);
+    let user_subcommand = Cli::try_parse_from(&args).is_ok_and(|cli| cli.command.is_some());
+
+    let mut merged = Vec::with_capacity(args.len() + defaults.len());
+    let mut args = args.into_iter();
+    merged.extend(args.next());
+    merged.extend(flags.iter().map(OsString::from));
+    merged.extend(args);
+    if !user_subcommand {
+        merged.extend(subcommand.iter().map(OsString::from));
+    }
+    merged
+}
@@ -1,4 +1,7 @@
 use anyhow::{anyhow, Context, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process::Command as GitCommand;
 
 /// How we want to summarize a PR.
@@ -20,6 +23,88 @@ impl PrSummaryMode {
     }
 }
 
+/// Pick a summary mode for a commit range when neither `--pr` nor `--commit` is given.
+///
+/// Groups by PR when at least `min_prs` distinct PR numbers appear and at least `min_ratio`
+/// of the commits carry one. Returns the mode and a short explanation for logging.
+pub fn autodetect_summary_mode(
+    items: &[PrItem],
+    min_prs: usize,
+    min_ratio: f64,
+) -> (PrSummaryMode, String) {
+    let distinct: HashSet<u32> = items.iter().filter_map(|i| i.pr_number).collect();
+    let with_pr = items.iter().filter(|i| i.pr_number.is_some()).count();
+    let ratio = if items.is_empty() {
+        0.0
+    } else {
+        with_pr as f64 / items.len() as f64
+    };
+
+    let mode = if distinct.len() >= min_prs && ratio >= min_ratio {
+        PrSummaryMode::ByPrs
+    } else {
+        PrSummaryMode::ByCommits
+    };
+    let reason = format!(
+        "{} distinct PRs (min {min_prs}), {with_pr}/{} commits reference one ({:.0}%, min {:.0}%)",
+        distinct.len(),
+        items.len(),
+        ratio * 100.0,
+        min_ratio * 100.0
+    );
+    (mode, reason)
+}
+
+/// Code-review forge conventions used when reading and rendering PR references.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Forge {
+    /// Pull requests referenced as `#123`
+    #[default]
+    GitHub,
+    /// Merge requests referenced as `!123`
+    GitLab,
+}
+
+impl Forge {
+    /// Parse a forge name such as "github" or "gitlab".
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "github" | "gh" => Some(Forge::GitHub),
+            "gitlab" | "gl" => Some(Forge::GitLab),
+            _ => None,
+        }
+    }
+
+    /// Convert the forge to a string representation.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Forge::GitHub => "github",
+            Forge::GitLab => "gitlab",
+        }
+    }
+
+    /// Character that prefixes change numbers (`#` for PRs, `!` for MRs).
+    pub fn sigil(&self) -> char {
+        match self {
+            Forge::GitHub => '#',
+            Forge::GitLab => '!',
+        }
+    }
+
+    /// Short label for a change request ("PR" or "MR").
+    pub fn change_label(&self) -> &'static str {
+        match self {
+            Forge::GitHub => "PR",
+            Forge::GitLab => "MR",
+        }
+    }
+
+    /// Render a change reference, e.g. "PR #12" or "MR !12".
+    pub fn format_ref(&self, number: u32) -> String {
+        format!("{} {}{}", self.change_label(), self.sigil(), number)
+    }
+}
+
 /// A commit involved in the PR range, plus any detected PR number.
 #[derive(Debug, Clone)]
 pub struct PrItem {
@@ -68,6 +153,14 @@ impl RemoteRepo {
         }
     }
 
+    /// Forge conventions implied by the remote's provider.
+    pub fn forge(&self) -> Forge {
+        match self.provider {
+            GitProvider::GitLab => Forge::GitLab,
+            _ => Forge::GitHub,
+        }
+    }
+
     pub fn commit_url(&self, commit_hash: &str) -> Option<String> {
         match self.provider {
             GitProvider::GitHub => Some(format!("{}/commit/{}", self.web_base_url, commit_hash)),
@@ -85,6 +178,12 @@ impl RemoteRepo {
 
 /// Run a git command and capture stdout as String.
 pub fn git_output(args: &[&str]) -> Result<String> {
+    let bytes = git_output_bytes(args)?;
+    Ok(String::from_utf8_lossy(&bytes).to_string())
+}
+
+/// Run a git command and capture raw stdout, without assuming any encoding.
+fn git_output_bytes(args: &[&str]) -> Result<Vec<u8>> {
     let output = GitCommand::new("git")
         .args(args)
         .output()
@@ -98,7 +197,7 @@ pub fn git_output(args: &[&str]) -> Result<String> {
         ));
     }
 
-    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    Ok(output.stdout)
 }
 
 fn remote_origin_url() -> Option<String> {
@@ -117,6 +216,46 @@ fn remote_origin_url() -> Option<String> {
         .filter(|url| !url.is_empty())
 }
 
+/// Absolute path of the repository's top-level directory.
+pub fn repo_root() -> Result<PathBuf> {
+    let root = git_output(&["rev-parse", "--show-toplevel"])?;
+    Ok(PathBuf::from(root.trim()))
+}
+
+/// Conventional PR/MR template locations, checked in order relative to the repo root.
+const PR_TEMPLATE_PATHS: &[&str] = &[
+    ".github/pull_request_template.md",
+    ".github/PULL_REQUEST_TEMPLATE.md",
+    "pull_request_template.md",
+    "PULL_REQUEST_TEMPLATE.md",
+    "docs/pull_request_template.md",
+    "docs/PULL_REQUEST_TEMPLATE.md",
+    ".gitlab/merge_request_templates/Default.md",
+];
+
+/// The repository's PR template, if it ships one.
+pub fn pr_template() -> Option<String> {
+    pr_template_in(&repo_root().ok()?)
+}
+
+/// The first non-empty PR template under `root`.
+pub fn pr_template_in(root: &Path) -> Option<String> {
+    PR_TEMPLATE_PATHS.iter().find_map(|rel| {
+        let text = fs::read_to_string(root.join(rel)).ok()?;
+        if text.trim().is_empty() {
+            return None;
+        }
+        log::debug!("Using PR template {rel}");
+        Some(text)
+    })
+}
+
+/// The repository's git directory (usually `.git`), where commitbot keeps run state.
+pub fn git_dir() -> Result<PathBuf> {
+    let dir = git_output(&["rev-parse", "--absolute-git-dir"])?;
+    Ok(PathBuf::from(dir.trim()))
+}
+
 /// Get the current branch name.
 pub fn current_branch() -> Result<String> {
     let name = git_output(&["rev-parse", "--abbrev-ref", "HEAD"])?
@@ -136,20 +275,349 @@ pub fn staged_files() -> Result<Vec<String>> {
     Ok(files)
 }
 
+/// Extra `git diff` flags for staged diffs, for commits that reindent or reflow code.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiffOptions {
+    /// Ignore whitespace-only changes (`-w`)
+    pub ignore_whitespace: bool,
+    /// Diff changed words instead of whole lines (`--word-diff=porcelain`)
+    pub word_diff: bool,
+}
+
+impl DiffOptions {
+    /// Flags added to `git diff --cached`.
+    ///
+    /// Porcelain word diffs keep the `+`/`-` line prefixes that line counting relies on.
+    pub fn git_args(&self) -> Vec<&'static str> {
+        let mut args = Vec::new();
+        if self.ignore_whitespace {
+            args.push("-w");
+        }
+        if self.word_diff {
+            args.push("--word-diff=porcelain");
+        }
+        args
+    }
+}
+
 /// Get per-file staged diff.
+///
+/// Submodule bumps also get git's `--submodule=log` listing of the commits in between.
 pub fn staged_diff_for_file(path: &str) -> Result<String> {
-    let diff = git_output(&["diff", "--cached", "--", path])?;
+    staged_diff_for_file_with(path, DiffOptions::default())
+}
+
+/// [`staged_diff_for_file`] with extra diff flags.
+pub fn staged_diff_for_file_with(path: &str, opts: DiffOptions) -> Result<String> {
+    let mut args = vec!["diff", "--cached"];
+    args.extend(opts.git_args());
+    args.extend(["--", path]);
+    let bytes = git_output_bytes(&args)?;
+    let mut diff = decode_diff(&bytes);
+    if parse_submodule_bump(&diff).is_some() {
+        match git_output(&["diff", "--cached", "--submodule=log", "--", path]) {
+            Ok(log) => {
+                diff.push('\n');
+                diff.push_str(&log);
+            }
+            Err(err) => log::debug!("No submodule log for {path}: {err}"),
+        }
+    }
     Ok(diff)
 }
 
+/// Untracked, non-ignored files (`git ls-files --others --exclude-standard`).
+pub fn untracked_files() -> Result<Vec<String>> {
+    let output = git_output(&["ls-files", "--others", "--exclude-standard", "-z"])?;
+    Ok(output
+        .split('\0')
+        .filter(|p| !p.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// An untracked file's contents as an all-added diff, without staging it.
+pub fn untracked_diff_for_file(path: &str) -> Result<String> {
+    let args = ["diff", "--no-index", "--", "/dev/null", path];
+    let output = GitCommand::new("git")
+        .args(args)
+        .output()
+        .with_context(|| format!("failed to run git {:?}", args))?;
+
+    // `--no-index` exits 1 when the files differ, which is always the case here.
+    if !matches!(output.status.code(), Some(0 | 1)) {
+        return Err(anyhow!(
+            "git {:?} exited with status {:?}",
+            args,
+            output.status.code()
+        ));
+    }
+    Ok(decode_diff(&output.stdout))
+}
+
+/// A submodule pointer change parsed from its `Subproject commit` diff.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubmoduleBump {
+    /// Previous commit (short hash); `None` when the submodule was just added
+    pub from: Option<String>,
+    /// New commit (short hash)
+    pub to: String,
+    /// Subjects from a `--submodule=log` listing, oldest last as git prints them
+    pub subjects: Vec<String>,
+}
+
+/// Parse a submodule pointer change; `None` for ordinary files and removed submodules.
+///
+/// Only diffs whose header gives the gitlink mode `160000` count, so a regular file that
+/// happens to contain a `Subproject commit` line is not mistaken for a submodule.
+pub fn parse_submodule_bump(diff: &str) -> Option<SubmoduleBump> {
+    let gitlink = diff
+        .lines()
+        .take_while(|line| !line.starts_with("@@"))
+        .any(|line| {
+            (line.starts_with("index ") || line.contains("mode ")) && line.ends_with(" 160000")
+        });
+    if !gitlink {
+        return None;
+    }
+
+    let mut from = None;
+    let mut to = None;
+    let mut subjects = Vec::new();
+    for line in diff.lines() {
+        if let Some(hash) = line.strip_prefix("-Subproject commit ") {
+            from = Some(short_commit_hash(hash.trim()));
+        } else if let Some(hash) = line.strip_prefix("+Subproject commit ") {
+            to = Some(short_commit_hash(hash.trim()));
+        } else if let Some(subject) = line
+            .strip_prefix("  > ")
+            .or_else(|| line.strip_prefix("  < "))
+        {
+            subjects.push(subject.trim().to_string());
+        }
+    }
+    Some(SubmoduleBump {
+        from,
+        to: to?,
+        subjects,
+    })
+}
+
+/// Decode raw diff bytes as UTF-8, stripping byte-order marks from content lines.
+///
+/// Invalid sequences become U+FFFD; use [`has_replacement_chars`] to flag them.
+pub fn decode_diff(bytes: &[u8]) -> String {
+    let text = String::from_utf8_lossy(bytes);
+    if !text.contains('\u{feff}') {
+        return text.into_owned();
+    }
+
+    text.split_inclusive('\n')
+        .map(|line| {
+            let body = line.strip_prefix(['+', '-', ' ']).unwrap_or(line);
+            match body.strip_prefix('\u{feff}') {
+                Some(rest) => format!("{}{rest}", &line[..line.len() - body.len()]),
+                None => line.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Whether a file's diff is binary: git's "Binary files ... differ" marker or raw NUL bytes.
+pub fn is_binary_diff(diff: &str) -> bool {
+    diff.contains('\0')
+        || diff
+            .lines()
+            .any(|l| l.starts_with("Binary files ") && l.ends_with(" differ"))
+        || diff.lines().any(|l| l == "GIT binary patch")
+}
+
+/// Whether a file's diff deletes it, judging by the header before the first hunk.
+pub fn is_deleted_diff(diff: &str) -> bool {
+    diff.lines()
+        .take_while(|l| !l.starts_with("@@"))
+        .any(|l| l.starts_with("deleted file mode ") || l == "+++ /dev/null")
+}
+
+/// Whether decoding a diff replaced invalid (non-UTF-8) bytes.
+pub fn has_replacement_chars(diff: &str) -> bool {
+    diff.contains(char::REPLACEMENT_CHARACTER)
+}
+
+/// Fail with a readable message (and a close match, if any) when `name` is not a commit-ish.
+pub fn verify_ref(name: &str) -> Result<()> {
+    let spec = format!("{name}^{{commit}}");
+    if git_output(&["rev-parse", "--verify", "--quiet", &spec]).is_ok() {
+        return Ok(());
+    }
+
+    let refs = git_output(&[
+        "for-each-ref",
+        "--format=%(refname:short)",
+        "refs/heads",
+        "refs/remotes",
+        "refs/tags",
+    ])
+    .unwrap_or_default();
+    let candidates: Vec<&str> = refs.lines().map(str::trim).collect();
+    match closest_ref(name, &candidates) {
+        Some(close) => Err(anyhow!("Unknown ref '{name}' — did you mean '{close}'?")),
+        None => Err(anyhow!("Unknown ref '{name}'")),
+    }
+}
+
+/// The candidate within a small edit distance of `name`, preferring the closest.
+pub fn closest_ref<'a>(name: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    let max_distance = (name.chars().count() / 3).clamp(2, 3);
+    candidates
+        .iter()
+        .map(|c| (edit_distance(name, c), *c))
+        .filter(|(d, _)| *d <= max_distance)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, c)| c)
+}
+
+/// Levenshtein distance over chars.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            cur[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+/// Most recent tag reachable from `rev` (`git describe --tags --abbrev=0`).
+pub fn latest_tag(rev: &str) -> Result<String> {
+    let tag = git_output(&["describe", "--tags", "--abbrev=0", rev])
+        .map_err(|_| anyhow!("No tags found; pass a base branch or --tag <name> instead"))?;
+    Ok(tag.trim().to_string())
+}
+
+/// Patch introduced by a single commit, without its log message.
+pub fn commit_diff(rev: &str) -> Result<String> {
+    let bytes = git_output_bytes(&["show", "--format=", "--patch", rev, "--"])?;
+    Ok(decode_diff(&bytes))
+}
+
+/// Full message of a commit.
+pub fn commit_message(rev: &str) -> Result<String> {
+    git_output(&["log", "-1", "--format=%B", rev, "--"])
+}
+
+/// The last `n` non-merge commit messages on HEAD, newest first.
+pub fn recent_commit_messages(n: usize) -> Result<Vec<String>> {
+    let count = n.to_string();
+    let raw = git_output(&["log", "-n", &count, "--no-merges", "--pretty=format:%B%x00"])?;
+    Ok(parse_commit_messages(&raw))
+}
+
+/// Split NUL-terminated `git log` messages, dropping blank ones and autosquash commits
+/// (`fixup!`/`squash!`), which say nothing about the repository's style.
+pub fn parse_commit_messages(raw: &str) -> Vec<String> {
+    raw.split('\0')
+        .map(|m| m.replace("\r\n", "\n").trim().to_string())
+        .filter(|m| !m.is_empty() && !m.starts_with("fixup!") && !m.starts_with("squash!"))
+        .collect()
+}
+
+/// Whether a commit has more than one parent.
+pub fn is_merge_commit(rev: &str) -> Result<bool> {
+    let line = git_output(&["rev-list", "--parents", "-n", "1", rev, "--"])?;
+    Ok(line.split_whitespace().count() > 2)
+}
+
 /// Find the first PR number in a string, based on '#123' pattern.
 pub fn find_first_pr_number(text: &str) -> Option<u32> {
+    find_first_number_after(text, b'#')
+}
+
+/// Find the first change number for the given forge ('#123' or '!123').
+pub fn find_first_change_number(text: &str, forge: Forge) -> Option<u32> {
+    find_first_number_after(text, forge.sigil() as u8)
+}
+
+/// Words that introduce an issue reference rather than a PR ("Fixes #45").
+const ISSUE_KEYWORDS: &[&str] = &[
+    "close",
+    "closes",
+    "closed",
+    "fix",
+    "fixes",
+    "fixed",
+    "resolve",
+    "resolves",
+    "resolved",
+    "ref",
+    "refs",
+    "references",
+    "see",
+    "issue",
+];
+
+/// Find a PR/MR number in a commit body, only where it clearly names one.
+///
+/// Recognized forms are GitHub's `Merge pull request #N` subject, GitLab's
+/// `See merge request group/app!N` trailer, and an explicit `(#N)` / `(!N)` suffix as left by
+/// squash merges. Issue references such as `Fixes #45` are ignored.
+pub fn find_body_change_number(body: &str, forge: Forge) -> Option<u32> {
+    let sigil = forge.sigil();
+    body.lines().find_map(|line| {
+        let line = line.trim();
+        if let Some(rest) = line.strip_prefix("Merge pull request ") {
+            return find_first_number_after(rest, sigil as u8);
+        }
+        if let Some(pos) = line.to_ascii_lowercase().find("merge request ") {
+            let found = find_first_number_after(&line[pos..], sigil as u8);
+            if found.is_some() {
+                return found;
+            }
+        }
+        parenthesized_change_number(line, sigil)
+    })
+}
+
+/// The first `(#N)` in `line` that is not introduced by an issue keyword.
+fn parenthesized_change_number(line: &str, sigil: char) -> Option<u32> {
+    let open = format!("({sigil}");
+    let mut search = 0;
+    while let Some(offset) = line[search..].find(&open) {
+        let start = search + offset;
+        search = start + open.len();
+
+        let digits: String = line[search..]
+            .chars()
+            .take_while(char::is_ascii_digit)
+            .collect();
+        if digits.is_empty() || !line[search + digits.len()..].starts_with(')') {
+            continue;
+        }
+        let previous_word = line[..start]
+            .split_whitespace()
+            .next_back()
+            .unwrap_or("")
+            .trim_end_matches(':')
+            .to_lowercase();
+        if ISSUE_KEYWORDS.contains(&previous_word.as_str()) {
+            continue;
+        }
+        return digits.parse().ok();
+    }
+    None
+}
+
+fn find_first_number_after(text: &str, sigil: u8) -> Option<u32> {
     let bytes = text.as_bytes();
     let len = bytes.len();
     let mut i = 0;
 
     while i < len {
-        if bytes[i] == b'#' {
+        if bytes[i] == sigil {
             let mut j = i + 1;
             let mut value: u32 = 0;
             let mut found_digit = false;
@@ -175,50 +643,51 @@ pub fn find_first_pr_number(text: &str) -> Option<u32> {
     None
 }
 
+/// `git log` format for [`parse_pr_log`]: hash, subject, and body separated by NUL, with each
+/// commit terminated by an ASCII record separator (0x1e). Neither byte can appear in a commit
+/// message, so bodies that contain delimiter-looking lines parse correctly.
+pub const PR_LOG_FORMAT: &str = "%H%x00%s%x00%b%x1e";
+
 /// Collect commits between base..from as PrItem list.
-pub fn collect_pr_items(base: &str, from: &str) -> Result<Vec<PrItem>> {
+pub fn collect_pr_items(base: &str, from: &str, forge: Forge) -> Result<Vec<PrItem>> {
     let range = format!("{base}..{from}");
-    let log_output = git_output(&[
-        "log",
-        "--reverse",
-        "--pretty=format:%H%n%s%n%b%n---END---",
-        &range,
-    ])?;
-
-    if log_output.trim().is_empty() {
-        return Ok(vec![]);
-    }
-
-    let mut items = Vec::new();
-
-    for block in log_output.split("\n---END---") {
-        let block = block.trim();
-        if block.is_empty() {
-            continue;
-        }
-
-        let mut lines = block.lines();
-        let hash = match lines.next() {
-            Some(h) => h.trim().to_string(),
-            None => continue,
-        };
-        let title = lines.next().unwrap_or("").trim().to_string();
-        let body = lines.collect::<Vec<_>>().join("\n");
-
-        let mut pr_number = find_first_pr_number(&title);
-        if pr_number.is_none() {
-            pr_number = find_first_pr_number(&body);
-        }
+    let format = format!("--pretty=format:{PR_LOG_FORMAT}");
+    let log_output = git_output(&["log", "--reverse", &format, &range])?;
+    Ok(parse_pr_log(&log_output, forge))
+}
 
-        items.push(PrItem {
-            commit_hash: hash,
-            title,
-            body,
-            pr_number,
-        });
-    }
+/// Parse `git log` output written with [`PR_LOG_FORMAT`] into [`PrItem`]s.
+///
+/// The PR number comes from the subject, or failing that from a PR-specific reference in the
+/// body (see [`find_body_change_number`]). CRLF line endings are normalized.
+pub fn parse_pr_log(raw: &str, forge: Forge) -> Vec<PrItem> {
+    raw.split('\x1e')
+        .filter_map(|record| {
+            let record = record.trim_start_matches(['\r', '\n']);
+            if record.is_empty() {
+                return None;
+            }
+            let mut fields = record.splitn(3, '\0');
+            let hash = fields.next()?.trim().to_string();
+            let title = fields.next().unwrap_or("").trim().to_string();
+            let body = fields
+                .next()
+                .unwrap_or("")
+                .replace("\r\n", "\n")
+                .trim_end()
+                .to_string();
 
-    Ok(items)
+            let pr_number = find_first_change_number(&title, forge)
+                .or_else(|| find_body_change_number(&body, forge));
+
+            Some(PrItem {
+                commit_hash: hash,
+                title,
+                body,
+                pr_number,
+            })
+        })
+        .collect()
 }
 
 /// Split a combined diff string into (path, diff) pairs, one per file.
@@ -257,6 +726,105 @@ pub fn split_diff_by_file(diff: &str) -> Vec<(String, String)> {
     results
 }
 
+/// One path from `git status --porcelain`, with its index and work-tree status codes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusEntry {
+    pub path: String,
+    /// Staged status (`M`, `A`, `D`, `R`, `?` for untracked, ` ` for none)
+    pub index: char,
+    /// Unstaged status (`M`, `D`, `?` for untracked, ` ` for none)
+    pub worktree: char,
+}
+
+impl StatusEntry {
+    pub fn is_untracked(&self) -> bool {
+        self.index == '?'
+    }
+
+    /// Whether `git add` would change what is staged for this path.
+    pub fn has_unstaged_changes(&self) -> bool {
+        self.worktree != ' '
+    }
+
+    /// The two-letter porcelain code, e.g. " M" or "??".
+    pub fn code(&self) -> String {
+        format!("{}{}", self.index, self.worktree)
+    }
+}
+
+/// Parse `git status --porcelain=v1 -z` output.
+pub fn parse_status_porcelain(output: &str) -> Vec<StatusEntry> {
+    let mut entries = Vec::new();
+    let mut records = output.split('\0').filter(|r| !r.is_empty());
+    while let Some(record) = records.next() {
+        let mut chars = record.chars();
+        let (Some(index), Some(worktree)) = (chars.next(), chars.next()) else {
+            continue;
+        };
+        let path = record.get(3..).unwrap_or_default().to_string();
+        // Renames and copies are followed by the original path, which we don't need.
+        if matches!(index, 'R' | 'C') {
+            records.next();
+        }
+        if !path.is_empty() {
+            entries.push(StatusEntry {
+                path,
+                index,
+                worktree,
+            });
+        }
+    }
+    entries
+}
+
+/// List changed and untracked files in the working tree.
+pub fn working_tree_status() -> Result<Vec<StatusEntry>> {
+    let output = git_output(&["status", "--porcelain=v1", "-z", "--untracked-files=all"])?;
+    Ok(parse_status_porcelain(&output))
+}
+
+/// Stage specific paths (`git add -- <paths>`).
+pub fn stage_paths(paths: &[&str]) -> Result<()> {
+    if paths.is_empty() {
+        return Ok(());
+    }
+    log::info!("Staging {} selected file(s)", paths.len());
+    let mut args = vec!["add", "--"];
+    args.extend_from_slice(paths);
+    git_output(&args)?;
+    Ok(())
+}
+
+/// Write a commit message to the file git hands a `prepare-commit-msg` / `commit-msg` hook.
+///
+/// Comment lines already in the file (git's status template) are kept below the message.
+pub fn write_message_file(path: &Path, message: &str) -> Result<()> {
+    let existing = fs::read_to_string(path).unwrap_or_default();
+    let comments: Vec<&str> = existing.lines().filter(|l| l.starts_with('#')).collect();
+
+    let mut contents = message.trim_end().to_string();
+    contents.push('\n');
+    if !comments.is_empty() {
+        contents.push('\n');
+        contents.push_str(&comments.join("\n"));
+        contents.push('\n');
+    }
+
+    fs::write(path, contents)
+        .with_context(|| format!("failed to write commit message to {}", path.display()))
+}
+
+/// Whether `path` already holds a message, ignoring blank lines and git's `#` comments.
+pub fn message_file_has_content(path: &Path) -> bool {
+    fs::read_to_string(path)
+        .map(|existing| {
+            existing
+                .lines()
+                .any(|l| !l.trim().is_empty() && !l.starts_with('#'))
+        })
+        .unwrap_or(false)
+}
+
 /// Stage all new, modified, and deleted files
 pub fn stage_all() -> Result<()> {
     log::info!("Staging all changes");
@@ -270,6 +838,14 @@ pub fn detect_repo_id() -> Option<String> {
     parse_remote_repo(&remote)?.repo_id()
 }
 
+/// Guess the forge from the `remote.origin.url` host, defaulting to GitHub conventions.
+pub fn detect_forge() -> Forge {
+    remote_origin_url()
+        .and_then(|url| parse_remote_repo(&url))
+        .map(|remote| remote.forge())
+        .unwrap_or_default()
+}
+
 pub fn format_pr_commit_appendix(items: &[PrItem]) -> String {
     if items.is_empty() {
         return String::new();
@@ -283,7 +859,8 @@ pub fn format_pr_commit_appendix_with_remote(
     items: &[PrItem],
     remote: Option<&RemoteRepo>,
 ) -> String {
-    let mut out = String::from("Commits in this PR:\n");
+    let label = remote.map(|r| r.forge()).unwrap_or_default().change_label();
+    let mut out = format!("Commits in this {label}:\n");
     for item in items {
         let short = short_commit_hash(&item.commit_hash);
         let title = item.title.trim();
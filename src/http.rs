@@ -0,0 +1,68 @@
+//! The HTTP client shared by every LLM backend.
+
+use crate::config::Config;
+use anyhow::{Context, Result, anyhow};
+use reqwest::blocking::Client;
+use reqwest::{Certificate, Identity};
+use std::fs;
+use std::path::Path;
+
+/// Build the HTTP client once per run; backends clone it (clones share one connection pool).
+///
+/// Timeouts come from `request_timeout` and `connect_timeout`; hosted APIs default to 90s per
+/// request, while a local Ollama, which can take far longer, has no limit unless configured.
+/// Certificates in `ca_bundle` are trusted in addition to the system roots, and `client_cert`
+/// (with `client_key`) is presented to endpoints that require mutual TLS.
+pub fn shared_client(cfg: &Config) -> Result<Client> {
+    let mut builder = Client::builder().timeout(cfg.request_timeout);
+    if let Some(timeout) = cfg.connect_timeout {
+        builder = builder.connect_timeout(timeout);
+    }
+    if let Some(path) = &cfg.ca_bundle {
+        builder = builder.tls_certs_merge(load_ca_bundle(path)?);
+    }
+    if let Some(cert) = &cfg.client_cert {
+        builder = builder.identity(load_identity(cert, cfg.client_key.as_deref())?);
+    }
+    builder.build().context("failed to build HTTP client")
+}
+
+/// Every certificate in a PEM bundle; a file with none is an error, as it is surely a mistake.
+fn load_ca_bundle(path: &Path) -> Result<Vec<Certificate>> {
+    let pem =
+        fs::read(path).with_context(|| format!("failed to read ca_bundle {}", path.display()))?;
+    let certs = Certificate::from_pem_bundle(&pem)
+        .with_context(|| format!("failed to parse ca_bundle {}", path.display()))?;
+    if certs.is_empty() {
+        return Err(anyhow!(
+            "ca_bundle {} contains no PEM certificates",
+            path.display()
+        ));
+    }
+    log::debug!(
+        "Trusting {} extra certificate(s) from {}",
+        certs.len(),
+        path.display()
+    );
+    Ok(certs)
+}
+
+/// The client identity from a PEM certificate chain and its private key, which may sit in the
+/// same file or in `key`.
+fn load_identity(cert: &Path, key: Option<&Path>) -> Result<Identity> {
+    let mut pem =
+        fs::read(cert).with_context(|| format!("failed to read client_cert {}", cert.display()))?;
+    if let Some(key) = key {
+        pem.push(b'\n');
+        pem.extend(
+            fs::read(key)
+                .with_context(|| format!("failed to read client_key {}", key.display()))?,
+        );
+    }
+    Identity::from_pem(&pem).with_context(|| {
+        format!(
+            "failed to load the client certificate and key from {}",
+            cert.display()
+        )
+    })
+}
@@ -0,0 +1,154 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+use crate::git;
+
+/// File name of the repo-local ignore list.
+pub const IGNORE_FILE_NAME: &str = ".commitbotignore";
+
+/// A single gitignore-style pattern.
+#[derive(Debug, Clone)]
+struct Pattern {
+    glob: String,
+    negated: bool,
+    /// Pattern contains a '/' (other than a trailing one) and is matched from the repo root.
+    anchored: bool,
+    /// Pattern ended with '/' and only matches directories.
+    dir_only: bool,
+}
+
+/// Path patterns in gitignore syntax (`*`, `**`, `?`, `!negation`, `dir/`, `/anchored`).
+///
+/// The last matching pattern wins, so later `!` lines can re-include paths.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+    patterns: Vec<Pattern>,
+}
+
+impl IgnoreRules {
+    /// Parse gitignore-style text; blank lines and `#` comments are skipped.
+    pub fn parse(text: &str) -> Self {
+        let patterns = text
+            .lines()
+            .map(str::trim_end)
+            .filter(|l| !l.trim().is_empty() && !l.starts_with('#'))
+            .map(|line| {
+                let (negated, line) = match line.strip_prefix('!') {
+                    Some(rest) => (true, rest),
+                    None => (false, line.strip_prefix('\\').unwrap_or(line)),
+                };
+                let dir_only = line.ends_with('/');
+                let line = line.trim_end_matches('/');
+                let anchored = line.contains('/');
+                Pattern {
+                    glob: line.trim_start_matches('/').to_string(),
+                    negated,
+                    anchored,
+                    dir_only,
+                }
+            })
+            .collect();
+        Self { patterns }
+    }
+
+    /// Build rules from individual patterns (e.g. config lists).
+    pub fn from_patterns<S: AsRef<str>>(patterns: &[S]) -> Self {
+        let text: Vec<&str> = patterns.iter().map(AsRef::as_ref).collect();
+        Self::parse(&text.join("\n"))
+    }
+
+    /// Load rules from a file; a missing file yields `None`.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        Ok(Some(Self::parse(&text)))
+    }
+
+    /// Whether no patterns were loaded.
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Check a repo-relative path (using '/' separators) against the rules.
+    pub fn is_ignored(&self, path: &str) -> bool {
+        let path = path.trim_start_matches("./").trim_start_matches('/');
+        let mut ignored = false;
+        for pattern in &self.patterns {
+            if pattern_matches(pattern, path) {
+                ignored = !pattern.negated;
+            }
+        }
+        ignored
+    }
+}
+
+fn pattern_matches(pattern: &Pattern, path: &str) -> bool {
+    let components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+    let last = components.len().saturating_sub(1);
+
+    if pattern.anchored {
+        // Match the whole path, or any directory prefix of it.
+        (0..components.len()).any(|i| {
+            let is_dir = i < last;
+            (is_dir || !pattern.dir_only) && glob_match(&pattern.glob, &components[..=i].join("/"))
+        })
+    } else {
+        components.iter().enumerate().any(|(i, component)| {
+            let is_dir = i < last;
+            (is_dir || !pattern.dir_only) && glob_match(&pattern.glob, component)
+        })
+    }
+}
+
+/// Match `text` against a glob where `*` and `?` stop at '/', and `**` spans directories.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    glob_match_bytes(pattern.as_bytes(), text.as_bytes())
+}
+
+fn glob_match_bytes(p: &[u8], t: &[u8]) -> bool {
+    match p.first() {
+        None => t.is_empty(),
+        Some(b'*') if p.get(1) == Some(&b'*') => {
+            let rest = &p[2..];
+            if let Some(after_slash) = rest.strip_prefix(b"/") {
+                // "**/" matches zero or more whole directories.
+                glob_match_bytes(after_slash, t)
+                    || t.iter()
+                        .enumerate()
+                        .any(|(i, &b)| b == b'/' && glob_match_bytes(after_slash, &t[i + 1..]))
+            } else {
+                (0..=t.len()).any(|i| glob_match_bytes(rest, &t[i..]))
+            }
+        }
+        Some(b'*') => {
+            let rest = &p[1..];
+            for i in 0..=t.len() {
+                if glob_match_bytes(rest, &t[i..]) {
+                    return true;
+                }
+                if i < t.len() && t[i] == b'/' {
+                    break;
+                }
+            }
+            false
+        }
+        Some(b'?') => !t.is_empty() && t[0] != b'/' && glob_match_bytes(&p[1..], &t[1..]),
+        Some(&c) => t.first() == Some(&c) && glob_match_bytes(&p[1..], &t[1..]),
+    }
+}
+
+/// Load `.commitbotignore` from the repository root, if present.
+pub fn load_repo_ignore() -> Option<IgnoreRules> {
+    let root = git::repo_root().ok()?;
+    match IgnoreRules::load(&root.join(IGNORE_FILE_NAME)) {
+        Ok(rules) => rules.filter(|r| !r.is_empty()),
+        Err(err) => {
+            log::warn!("Ignoring {IGNORE_FILE_NAME}: {err}");
+            None
+        }
+    }
+}
@@ -2,12 +2,23 @@
 //!
 //! This module contains shared types and functions used across the application.
 
+pub mod budget;
+pub mod changelog;
 pub mod cli_args;
 pub mod config;
 pub mod git;
+pub mod http;
+pub mod ignore;
 pub mod llm;
 pub mod logging;
+pub mod pipeline;
+pub mod rate_limit;
+pub mod run_state;
+pub mod security;
 pub mod setup;
+pub mod summary_cache;
+pub mod text;
+pub mod trivial;
 
 pub use cli_args::{Cli, Command};
 pub use git::{
@@ -15,9 +26,52 @@ pub use git::{
     staged_diff_for_file, staged_files, PrSummaryMode,
 };
 pub use llm::LlmClient;
+pub use config::Config;
+pub use pipeline::{CommitOutput, GenerateOptions, generate_commit_message};
+
+/// Process exit code for unexpected errors.
+pub const EXIT_ERROR: i32 = 1;
+
+/// Process exit code when there are no staged changes (or an empty diff) and
+/// `--allow-empty` was not given.
+pub const EXIT_NO_CHANGES: i32 = 2;
+
+/// Process exit code when the user backs out (Esc in `--ask`, or declining to overwrite
+/// a `--message-file`).
+pub const EXIT_ABORTED: i32 = 3;
+
+/// Why a run failed. Each reason has a stable exit code that hooks and scripts can rely on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitReason {
+    Error,
+    NoChanges,
+    Aborted,
+}
+
+impl ExitReason {
+    /// Classify an error by its marker type ([`pipeline::NoChanges`], [`pipeline::Aborted`]).
+    pub fn of(err: &anyhow::Error) -> Self {
+        if err.downcast_ref::<pipeline::NoChanges>().is_some() {
+            ExitReason::NoChanges
+        } else if err.downcast_ref::<pipeline::Aborted>().is_some() {
+            ExitReason::Aborted
+        } else {
+            ExitReason::Error
+        }
+    }
+
+    /// The process exit code for this reason.
+    pub fn code(self) -> i32 {
+        match self {
+            ExitReason::Error => EXIT_ERROR,
+            ExitReason::NoChanges => EXIT_NO_CHANGES,
+            ExitReason::Aborted => EXIT_ABORTED,
+        }
+    }
+}
 
 /// How the user categorizes each file in interactive mode.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub enum FileCategory {
     Main,        // 1
     Supporting,  // 2
@@ -35,6 +89,48 @@ impl FileCategory {
             FileCategory::Ignored => "ignored",
         }
     }
+
+    /// Heading for the category in interactive output (e.g. "Main purpose").
+    pub fn label(&self) -> &'static str {
+        match self {
+            FileCategory::Main => "Main purpose",
+            FileCategory::Supporting => "Supporting",
+            FileCategory::Consequence => "Consequence",
+            FileCategory::Ignored => "Ignored",
+        }
+    }
+
+    /// Parse a category name such as "main" or "consequence".
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "main" => Some(FileCategory::Main),
+            "supporting" => Some(FileCategory::Supporting),
+            "consequence" => Some(FileCategory::Consequence),
+            "ignored" => Some(FileCategory::Ignored),
+            _ => None,
+        }
+    }
+
+    /// Map the 1–4 menu number used in interactive mode to a category.
+    pub fn from_menu_number(n: u8) -> Option<Self> {
+        match n {
+            1 => Some(FileCategory::Main),
+            2 => Some(FileCategory::Supporting),
+            3 => Some(FileCategory::Consequence),
+            4 => Some(FileCategory::Ignored),
+            _ => None,
+        }
+    }
+
+    /// Position of this category in the interactive menu (0-based).
+    pub fn menu_index(&self) -> usize {
+        match self {
+            FileCategory::Main => 0,
+            FileCategory::Supporting => 1,
+            FileCategory::Consequence => 2,
+            FileCategory::Ignored => 3,
+        }
+    }
 }
 
 /// Represents a single staged file's change and metadata.
@@ -48,4 +144,17 @@ pub struct FileChange {
     pub diff: String,
     /// LLM-generated summary for this file
     pub summary: Option<String>,
+    /// Listed in the prompt but never sent for summarization (e.g. `.commitbotignore`)
+    pub list_only: bool,
+    /// The file was deleted; it is named in the prompt instead of being summarized
+    pub is_deleted: bool,
+}
+
+impl FileChange {
+    /// Whether this file's diff should be sent to the model for a per-file summary.
+    ///
+    /// Submodule bumps are rendered from their commit range instead.
+    pub fn needs_summary(&self) -> bool {
+        !self.list_only && !self.is_deleted && git::parse_submodule_bump(&self.diff).is_none()
+    }
 }
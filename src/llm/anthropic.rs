@@ -0,0 +1,595 @@
+use super::cancel::{self, CancellationToken};
+use super::debug_log::DebugLog;
+use super::error::ApiError;
+use super::retry::RetryPolicy;
+use super::usage::TokenUsage;
+use super::prompt_builder::{self, PromptOptions};
+use super::stream::{
+    DEFAULT_MAX_RESPONSE_BYTES, read_body_capped, read_stream_to_string, sse_data,
+};
+use super::{LlmClient, structured, warn_if_truncated};
+use crate::FileChange;
+use crate::git::{PrItem, PrSummaryMode};
+use anyhow::{Context, Result, anyhow};
+use reqwest::StatusCode;
+use reqwest::blocking::{Client, RequestBuilder};
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use std::io::BufReader;
+use std::sync::{Arc, Mutex};
+
+pub const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
+
+/// Value of the required `anthropic-version` header.
+const API_VERSION: &str = "2023-06-01";
+
+/// `max_tokens` is mandatory in the Messages API; this is used when no limit is configured.
+pub const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// Minimal request/response structs for the Anthropic Messages API.
+#[derive(Serialize)]
+struct MessagesRequest {
+    model: String,
+    max_tokens: u32,
+    system: String,
+    messages: Vec<Message>,
+    stream: bool,
+}
+
+#[derive(Serialize)]
+struct Message {
+    role: String,
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct MessagesResponse {
+    #[serde(default)]
+    content: Vec<ContentBlock>,
+    stop_reason: Option<String>,
+    usage: Option<MessagesUsage>,
+}
+
+#[derive(Deserialize)]
+struct ContentBlock {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct MessagesUsage {
+    #[serde(default)]
+    input_tokens: u32,
+    #[serde(default)]
+    output_tokens: u32,
+}
+
+/// One server-sent event from a streamed Messages response.
+#[derive(Deserialize)]
+struct StreamEvent {
+    #[serde(rename = "type")]
+    kind: String,
+    /// The message envelope of `message_start`, which carries the prompt's token count
+    message: Option<StreamMessage>,
+    delta: Option<StreamDelta>,
+    /// Cumulative counts on `message_delta`
+    usage: Option<MessagesUsage>,
+    error: Option<StreamError>,
+}
+
+#[derive(Deserialize)]
+struct StreamMessage {
+    usage: Option<MessagesUsage>,
+}
+
+#[derive(Deserialize)]
+struct StreamDelta {
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    text: Option<String>,
+    stop_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct StreamError {
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(default)]
+    message: String,
+}
+
+/// What a streamed response reports besides its text: the `stop_reason` and the token counts
+/// from `message_start` and the closing `message_delta`.
+#[derive(Default)]
+struct StreamState {
+    stop_reason: Option<String>,
+    input_tokens: u32,
+    output_tokens: u32,
+}
+
+/// Anthropic (Claude) client using `/v1/messages`.
+pub struct AnthropicClient {
+    client: Client,
+    api_key: String,
+    model: String,
+    api_base_url: String,
+    extra_headers: HeaderMap,
+    stream: bool,
+    max_tokens: u32,
+    prompt_options: PromptOptions,
+    debug_log: Option<Arc<DebugLog>>,
+    max_response_bytes: usize,
+    retry: RetryPolicy,
+    usage: Mutex<TokenUsage>,
+}
+
+impl AnthropicClient {
+    /// `client` is usually [`crate::http::shared_client`], shared with other backends.
+    pub fn new(
+        client: Client,
+        api_key: String,
+        model: String,
+        api_base_url: String,
+        stream: bool,
+    ) -> Self {
+        AnthropicClient {
+            client,
+            api_key,
+            model,
+            api_base_url: api_base_url.trim_end_matches('/').to_string(),
+            extra_headers: HeaderMap::new(),
+            stream,
+            max_tokens: DEFAULT_MAX_TOKENS,
+            prompt_options: PromptOptions::default(),
+            debug_log: None,
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            retry: RetryPolicy::default(),
+            usage: Mutex::new(TokenUsage::default()),
+        }
+    }
+
+    /// Extra headers sent with every request (e.g. for an API gateway).
+    pub fn with_headers(mut self, headers: HeaderMap) -> Self {
+        self.extra_headers = headers;
+        self
+    }
+
+    /// Set the options used when building prompts.
+    pub fn with_prompt_options(mut self, opts: PromptOptions) -> Self {
+        self.prompt_options = opts;
+        self
+    }
+
+    /// Record every prompt/response pair to the given debug log.
+    pub fn with_debug_log(mut self, debug_log: Option<Arc<DebugLog>>) -> Self {
+        self.debug_log = debug_log;
+        self
+    }
+
+    /// Cap on a single response's size (see [`DEFAULT_MAX_RESPONSE_BYTES`]).
+    pub fn with_max_response_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_response_bytes = max_bytes;
+        self
+    }
+
+    /// Retry policy for rate limits, server errors, and dropped connections.
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// `max_tokens` for every request; `None` keeps [`DEFAULT_MAX_TOKENS`].
+    pub fn with_max_output_tokens(mut self, max_tokens: Option<u32>) -> Self {
+        self.max_tokens = max_tokens.unwrap_or(DEFAULT_MAX_TOKENS);
+        self
+    }
+
+    /// Versioned API root; `/v1` is appended unless already present.
+    fn api_root(&self) -> String {
+        if self.api_base_url.ends_with("/v1") {
+            self.api_base_url.clone()
+        } else {
+            format!("{}/v1", self.api_base_url)
+        }
+    }
+
+    fn messages_url(&self) -> String {
+        format!("{}/messages", self.api_root())
+    }
+
+    fn model_url(&self) -> String {
+        format!("{}/models/{}", self.api_root(), self.model)
+    }
+
+    fn authorize(&self, builder: RequestBuilder) -> RequestBuilder {
+        builder
+            .headers(self.extra_headers.clone())
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", API_VERSION)
+    }
+
+    /// Send a prompt pair, recording it to the debug log when enabled.
+    fn request(
+        &self,
+        step: &str,
+        system: String,
+        user: String,
+        stream: bool,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        cancel::check(cancel)?;
+        self.usage.lock().unwrap_or_else(|e| e.into_inner()).requests += 1;
+        let Some(debug_log) = &self.debug_log else {
+            return self.send(system, user, stream, cancel);
+        };
+
+        let result = self.send(system.clone(), user.clone(), stream, cancel);
+        debug_log.record("anthropic", &self.model, step, &system, &user, &result);
+        result
+    }
+
+    fn messages_request(&self, system: String, user: String, stream: bool) -> MessagesRequest {
+        MessagesRequest {
+            model: self.model.clone(),
+            max_tokens: self.max_tokens,
+            system,
+            messages: vec![Message {
+                role: "user".into(),
+                content: user,
+            }],
+            stream,
+        }
+    }
+
+    fn send(
+        &self,
+        system: String,
+        user: String,
+        stream: bool,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        let req = self.messages_request(system, user, stream);
+        if stream {
+            log::info!("Streaming Anthropic model {:?}", &self.model);
+        } else {
+            log::info!("Calling Anthropic model {:?}", &self.model);
+        }
+
+        let resp = self
+            .retry
+            .send("Anthropic", cancel, || {
+                self.authorize(self.client.post(self.messages_url()))
+                    .json(&req)
+                    .send()
+            })
+            .context("failed to send request to Anthropic")?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let text = resp.text().unwrap_or_default();
+            return Err(ApiError::new("Anthropic", status.as_u16(), text).into());
+        }
+
+        if stream {
+            let reader = BufReader::new(resp);
+            let mut state = StreamState::default();
+            let content = read_stream_to_string(reader, cancel, self.max_response_bytes, |line| {
+                parse_stream_line(line, &mut state)
+            })?;
+            warn_if_truncated("Anthropic", state.stop_reason.as_deref());
+            self.record_usage(state.input_tokens, state.output_tokens);
+            return Ok(content);
+        }
+
+        let body = read_body_capped(resp, self.max_response_bytes)?;
+        let parsed: MessagesResponse =
+            serde_json::from_str(&body).context("failed to parse Anthropic response")?;
+        warn_if_truncated("Anthropic", parsed.stop_reason.as_deref());
+        let content =
+            response_text(&parsed).ok_or_else(|| anyhow!("no text returned from Anthropic"))?;
+
+        if let Some(usage) = &parsed.usage {
+            self.record_usage(usage.input_tokens, usage.output_tokens);
+        }
+
+        Ok(content.trim().to_string())
+    }
+
+    fn record_usage(&self, input_tokens: u32, output_tokens: u32) {
+        let (input, output) = (input_tokens as u64, output_tokens as u64);
+        let mut u = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        u.record(input, output, input + output);
+    }
+}
+
+/// Concatenate the `text` blocks of a Messages response.
+fn response_text(resp: &MessagesResponse) -> Option<String> {
+    let text: String = resp
+        .content
+        .iter()
+        .filter(|block| block.kind == "text")
+        .map(|block| block.text.as_str())
+        .collect();
+    (!text.is_empty()).then_some(text)
+}
+
+/// Parse one SSE line of a streamed Messages response, returning any text delta and recording
+/// the token counts and the closing `stop_reason` in `state`.
+fn parse_stream_line(line: &str, state: &mut StreamState) -> Result<Option<String>> {
+    let Some(data) = sse_data(line) else {
+        return Ok(None);
+    };
+    let event: StreamEvent =
+        serde_json::from_str(data).context("failed to parse Anthropic streaming event")?;
+
+    match event.kind.as_str() {
+        "content_block_delta" => Ok(event
+            .delta
+            .filter(|d| d.kind.as_deref() == Some("text_delta"))
+            .and_then(|d| d.text)
+            .filter(|text| !text.is_empty())),
+        "message_start" => {
+            if let Some(usage) = event.message.and_then(|m| m.usage) {
+                state.input_tokens = usage.input_tokens;
+                state.output_tokens = usage.output_tokens;
+            }
+            Ok(None)
+        }
+        "message_delta" => {
+            if let Some(reason) = event.delta.and_then(|d| d.stop_reason) {
+                state.stop_reason = Some(reason);
+            }
+            // the delta's counts are cumulative; input is only repeated by newer API versions
+            if let Some(usage) = event.usage {
+                state.input_tokens = state.input_tokens.max(usage.input_tokens);
+                state.output_tokens = usage.output_tokens;
+            }
+            Ok(None)
+        }
+        "error" => {
+            let error = event.error.unwrap_or(StreamError {
+                kind: String::new(),
+                message: String::new(),
+            });
+            Err(anyhow!(
+                "Anthropic stream failed: {} {}",
+                error.kind,
+                error.message
+            ))
+        }
+        _ => Ok(None),
+    }
+}
+
+impl LlmClient for AnthropicClient {
+    fn validate_model(&self) -> Result<()> {
+        let url = self.model_url();
+        let resp = self
+            .authorize(self.client.get(&url))
+            .send()
+            .context("failed to send model validation request to Anthropic")?;
+
+        if resp.status() == StatusCode::OK {
+            return Ok(());
+        }
+
+        let status = resp.status();
+        let text = resp.text().unwrap_or_default();
+        Err(anyhow!(
+            "Anthropic model validation failed for {:?} at {}: HTTP {} - {}",
+            self.model,
+            url,
+            status.as_u16(),
+            text
+        ))
+    }
+
+    fn summarize_file(
+        &self,
+        branch: &str,
+        file: &FileChange,
+        file_index: usize,
+        total_files: usize,
+        ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        let prompts = prompt_builder::file_summary_prompt(
+            branch,
+            file,
+            file_index,
+            total_files,
+            ticket_summary,
+            &self.prompt_options,
+        );
+        self.request("file_summary", prompts.system, prompts.user, false, cancel)
+    }
+
+    fn summarize_batch(
+        &self,
+        branch: &str,
+        files: &[&FileChange],
+        ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<Vec<Option<String>>> {
+        let prompts = prompt_builder::batch_summary_prompt(
+            branch,
+            files,
+            ticket_summary,
+            &self.prompt_options,
+        );
+        let content = self.request("batch_summary", prompts.system, prompts.user, false, cancel)?;
+        let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+        Ok(prompt_builder::split_batch_summary(&content, &paths))
+    }
+
+    fn generate_commit_message(
+        &self,
+        branch: &str,
+        files: &[FileChange],
+        ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        let prompts = prompt_builder::commit_message_prompt(
+            branch,
+            files,
+            ticket_summary,
+            &self.prompt_options,
+        );
+        let content = self.request(
+            "commit_message",
+            prompts.system,
+            prompts.user,
+            self.stream,
+            cancel,
+        )?;
+        Ok(structured::finish_commit_message(
+            content,
+            &self.prompt_options,
+        ))
+    }
+
+    fn generate_changelog_entry(
+        &self,
+        files: &[FileChange],
+        commit_message: &str,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        let prompts =
+            prompt_builder::changelog_entry_prompt(files, commit_message, &self.prompt_options);
+        self.request("changelog", prompts.system, prompts.user, false, cancel)
+    }
+
+    fn generate_pr_message(
+        &self,
+        base_branch: &str,
+        from_branch: &str,
+        mode: PrSummaryMode,
+        items: &[PrItem],
+        ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        let prompts = prompt_builder::pr_message_prompt(
+            base_branch,
+            from_branch,
+            mode,
+            items,
+            ticket_summary,
+            &self.prompt_options,
+        );
+        self.request(
+            "pr_message",
+            prompts.system,
+            prompts.user,
+            self.stream,
+            cancel,
+        )
+    }
+
+    fn summary_source(&self) -> Option<String> {
+        Some(format!("anthropic:{}:{}", self.api_base_url, self.model))
+    }
+
+    fn take_and_reset_usage(&self) -> Option<TokenUsage> {
+        let mut u = self.usage.lock().unwrap_or_else(|e| {
+            log::warn!("usage mutex was poisoned, recovering token counters");
+            e.into_inner()
+        });
+        u.take(Some(&self.model))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client_with_base(base: &str) -> AnthropicClient {
+        AnthropicClient::new(
+            Client::new(),
+            "key".into(),
+            "claude-sonnet-4-5".into(),
+            base.into(),
+            false,
+        )
+    }
+
+    #[test]
+    fn builds_urls_from_root_or_versioned_base() {
+        let client = client_with_base(DEFAULT_BASE_URL);
+        assert_eq!(
+            client.messages_url(),
+            "https://api.anthropic.com/v1/messages"
+        );
+        assert_eq!(
+            client.model_url(),
+            "https://api.anthropic.com/v1/models/claude-sonnet-4-5"
+        );
+
+        let client = client_with_base("https://gw.example/anthropic/v1/");
+        assert_eq!(
+            client.messages_url(),
+            "https://gw.example/anthropic/v1/messages"
+        );
+    }
+
+    #[test]
+    fn serializes_system_field_and_max_tokens() {
+        let client = client_with_base(DEFAULT_BASE_URL).with_max_output_tokens(Some(1000));
+        let req = client.messages_request("be brief".into(), "hi".into(), true);
+        let value = serde_json::to_value(&req).unwrap();
+        assert_eq!(value["system"], "be brief");
+        assert_eq!(value["max_tokens"], 1000);
+        assert_eq!(value["stream"], true);
+        assert_eq!(value["messages"][0]["role"], "user");
+        assert_eq!(value["messages"][0]["content"], "hi");
+
+        let req =
+            client_with_base(DEFAULT_BASE_URL).messages_request("s".into(), "u".into(), false);
+        assert_eq!(req.max_tokens, DEFAULT_MAX_TOKENS);
+    }
+
+    #[test]
+    fn extracts_text_and_usage() {
+        let body = r#"{
+            "content": [{"type": "text", "text": "Add "}, {"type": "text", "text": "login"}],
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 10, "output_tokens": 2}
+        }"#;
+        let parsed: MessagesResponse = serde_json::from_str(body).unwrap();
+        assert_eq!(response_text(&parsed).as_deref(), Some("Add login"));
+        assert_eq!(parsed.usage.unwrap().output_tokens, 2);
+    }
+
+    #[test]
+    fn parses_stream_events() {
+        let mut state = StreamState::default();
+        let delta = r#"data: {"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Fix"}}"#;
+        assert_eq!(
+            parse_stream_line(delta, &mut state).unwrap().as_deref(),
+            Some("Fix")
+        );
+        assert_eq!(
+            parse_stream_line("event: content_block_delta", &mut state).unwrap(),
+            None
+        );
+        assert_eq!(
+            parse_stream_line(r#"data: {"type": "ping"}"#, &mut state).unwrap(),
+            None
+        );
+
+        let start = r#"data: {"type": "message_start", "message": {"id": "msg_1", "content": [], "usage": {"input_tokens": 25, "output_tokens": 1}}}"#;
+        assert_eq!(parse_stream_line(start, &mut state).unwrap(), None);
+        assert_eq!((state.input_tokens, state.output_tokens), (25, 1));
+
+        let done = r#"data: {"type": "message_delta", "delta": {"stop_reason": "max_tokens"}, "usage": {"output_tokens": 5}}"#;
+        assert_eq!(parse_stream_line(done, &mut state).unwrap(), None);
+        assert_eq!(state.stop_reason.as_deref(), Some("max_tokens"));
+        assert_eq!((state.input_tokens, state.output_tokens), (25, 5));
+        assert!(warn_if_truncated("Anthropic", state.stop_reason.as_deref()));
+
+        let error = r#"data: {"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}}"#;
+        let err = parse_stream_line(error, &mut state).unwrap_err();
+        assert!(err.to_string().contains("overloaded_error Overloaded"));
+    }
+}
@@ -0,0 +1,51 @@
+use anyhow::Result;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Shared flag that asks in-flight LLM work to stop.
+///
+/// Clones share the same flag. Clients check it before sending a request and
+/// between streamed chunks; a blocking non-streamed request finishes first.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation; returns `true` if this call was the one that set it.
+    pub fn cancel(&self) -> bool {
+        !self.0.swap(true, Ordering::Relaxed)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Fail with [`Cancelled`] if cancellation was requested.
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(Cancelled.into());
+        }
+        Ok(())
+    }
+}
+
+/// Check an optional token; `None` never cancels.
+pub fn check(cancel: Option<&CancellationToken>) -> Result<()> {
+    cancel.map_or(Ok(()), CancellationToken::check)
+}
+
+/// Error returned when work stops because its token was cancelled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("request cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
@@ -0,0 +1,346 @@
+//! `--record <dir>` / `--replay <dir>`: save every request and response to disk, and answer
+//! later runs from those files instead of the provider.
+//!
+//! Each exchange is one pretty-printed JSON file named after the step and a fingerprint of the
+//! prompt (`summary-3f2a….json`), holding the full system and user prompts, the provider and
+//! model that answered, and the reply both as the provider sent it and as commitbot used it. A
+//! replayed run rebuilds the same prompts from the same staged changes and looks them up, so a
+//! bad commit message can be reproduced, attached to a bug report, or pinned in a test.
+
+use super::debug_log::DebugLog;
+use super::prompt_builder::{self, PromptOptions, PromptPair};
+use super::{CancellationToken, LlmClient, TokenUsage};
+use crate::FileChange;
+use crate::git::{PrItem, PrSummaryMode};
+use crate::run_state::fingerprint;
+use anyhow::{Context, Result, anyhow};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// One recorded exchange.
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry<T> {
+    step: String,
+    /// Provider and model that answered; absent when the client doesn't report them.
+    provider: Option<String>,
+    model: Option<String>,
+    system: String,
+    user: String,
+    /// The reply as the provider sent it, before cleanup or splitting.
+    raw_response: Option<String>,
+    response: T,
+}
+
+/// Records the wrapped client's exchanges, or replays them without a client.
+pub struct CassetteClient {
+    dir: PathBuf,
+    /// The client being recorded; `None` when replaying.
+    inner: Option<Box<dyn LlmClient>>,
+    prompt_options: PromptOptions,
+    /// Where the recorded client reports its provider, model, and raw replies.
+    raw_replies: Option<Arc<DebugLog>>,
+}
+
+impl CassetteClient {
+    /// Pass requests through to `inner`, saving each exchange in `dir`.
+    pub fn record(dir: &Path, inner: Box<dyn LlmClient>) -> Result<Self> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create cassette directory {}", dir.display()))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            inner: Some(inner),
+            prompt_options: PromptOptions::default(),
+            raw_replies: None,
+        })
+    }
+
+    /// Answer requests from the exchanges saved in `dir`.
+    pub fn replay(dir: &Path) -> Result<Self> {
+        if !dir.is_dir() {
+            return Err(anyhow!("No cassette directory at {}", dir.display()));
+        }
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            inner: None,
+            prompt_options: PromptOptions::default(),
+            raw_replies: None,
+        })
+    }
+
+    /// The options the wrapped client builds its prompts with, so keys match what it sends.
+    pub fn with_prompt_options(mut self, opts: PromptOptions) -> Self {
+        self.prompt_options = opts;
+        self
+    }
+
+    /// Read the provider, model, and unprocessed reply of each recorded exchange from the
+    /// debug log the wrapped client writes to (see [`DebugLog::keeping_replies`]).
+    pub fn with_raw_replies(mut self, debug_log: Arc<DebugLog>) -> Self {
+        self.raw_replies = Some(debug_log);
+        self
+    }
+
+    fn entry_path(&self, step: &str, prompts: &PromptPair) -> PathBuf {
+        let key = fingerprint(&format!("{}\0{}", prompts.system, prompts.user));
+        self.dir.join(format!("{step}-{key}.json"))
+    }
+
+    /// Replay the exchange for `prompts`, or run `send` against the recorded client and save it.
+    fn exchange<T, F>(&self, step: &str, prompts: PromptPair, send: F) -> Result<T>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce(&dyn LlmClient) -> Result<T>,
+    {
+        let path = self.entry_path(step, &prompts);
+        let Some(inner) = &self.inner else {
+            let raw = fs::read_to_string(&path).map_err(|_| {
+                anyhow!(
+                    "No recorded {step} response matches this prompt (looked for {}); \
+                     the changes or settings differ from the recording",
+                    path.display()
+                )
+            })?;
+            let entry: Entry<T> = serde_json::from_str(&raw)
+                .with_context(|| format!("failed to parse {}", path.display()))?;
+            log::debug!("Replayed {step} from {}", path.display());
+            return Ok(entry.response);
+        };
+
+        let raw_replies = self.raw_replies.as_deref();
+        // drop a reply left behind by a call that failed before it was saved
+        raw_replies.and_then(DebugLog::take_reply);
+        let response = send(inner.as_ref())?;
+        let raw = raw_replies.and_then(DebugLog::take_reply);
+        let entry = Entry {
+            step: step.to_string(),
+            provider: raw.as_ref().map(|r| r.provider.clone()),
+            model: raw.as_ref().map(|r| r.model.clone()),
+            system: prompts.system,
+            user: prompts.user,
+            raw_response: raw.and_then(|r| r.response),
+            response,
+        };
+        let json = serde_json::to_string_pretty(&entry)?;
+        fs::write(&path, json).with_context(|| format!("failed to write {}", path.display()))?;
+        log::debug!("Recorded {step} to {}", path.display());
+        Ok(entry.response)
+    }
+}
+
+impl LlmClient for CassetteClient {
+    fn validate_model(&self) -> Result<()> {
+        match &self.inner {
+            Some(inner) => inner.validate_model(),
+            None => Ok(()),
+        }
+    }
+
+    fn summarize_file(
+        &self,
+        branch: &str,
+        file: &FileChange,
+        file_index: usize,
+        total_files: usize,
+        ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        let prompts = prompt_builder::file_summary_prompt(
+            branch,
+            file,
+            file_index,
+            total_files,
+            ticket_summary,
+            &self.prompt_options,
+        );
+        self.exchange("summary", prompts, |llm| {
+            llm.summarize_file(
+                branch,
+                file,
+                file_index,
+                total_files,
+                ticket_summary,
+                cancel,
+            )
+        })
+    }
+
+    fn summarize_batch(
+        &self,
+        branch: &str,
+        files: &[&FileChange],
+        ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<Vec<Option<String>>> {
+        let prompts = prompt_builder::batch_summary_prompt(
+            branch,
+            files,
+            ticket_summary,
+            &self.prompt_options,
+        );
+        self.exchange("batch", prompts, |llm| {
+            llm.summarize_batch(branch, files, ticket_summary, cancel)
+        })
+    }
+
+    fn generate_commit_message(
+        &self,
+        branch: &str,
+        files: &[FileChange],
+        ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        let prompts = prompt_builder::commit_message_prompt(
+            branch,
+            files,
+            ticket_summary,
+            &self.prompt_options,
+        );
+        self.exchange("commit", prompts, |llm| {
+            llm.generate_commit_message(branch, files, ticket_summary, cancel)
+        })
+    }
+
+    fn generate_changelog_entry(
+        &self,
+        files: &[FileChange],
+        commit_message: &str,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        let prompts =
+            prompt_builder::changelog_entry_prompt(files, commit_message, &self.prompt_options);
+        self.exchange("changelog", prompts, |llm| {
+            llm.generate_changelog_entry(files, commit_message, cancel)
+        })
+    }
+
+    fn generate_pr_message(
+        &self,
+        base_branch: &str,
+        from_branch: &str,
+        mode: PrSummaryMode,
+        items: &[PrItem],
+        ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        let prompts = prompt_builder::pr_message_prompt(
+            base_branch,
+            from_branch,
+            mode,
+            items,
+            ticket_summary,
+            &self.prompt_options,
+        );
+        self.exchange("pr", prompts, |llm| {
+            llm.generate_pr_message(
+                base_branch,
+                from_branch,
+                mode,
+                items,
+                ticket_summary,
+                cancel,
+            )
+        })
+    }
+
+    fn summary_source(&self) -> Option<String> {
+        self.inner.as_ref()?.summary_source()
+    }
+
+    fn take_and_reset_usage(&self) -> Option<TokenUsage> {
+        self.inner.as_ref()?.take_and_reset_usage()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FileCategory;
+    use crate::llm::command::CommandClient;
+    use crate::llm::mock::MockClient;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn replays_what_was_recorded() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time before unix epoch")
+            .as_nanos();
+        let dir = std::env::temp_dir().join(format!("commitbot_cassette_{nanos}"));
+        let file = FileChange {
+            path: "src/lib.rs".into(),
+            category: FileCategory::Main,
+            diff: "@@ -1 +1 @@\n-a\n+b\n".into(),
+            summary: None,
+            list_only: false,
+            is_deleted: false,
+        };
+
+        let recorder = CassetteClient::record(&dir, Box::new(MockClient::new())).unwrap();
+        let recorded = recorder
+            .summarize_file("main", &file, 0, 1, None, None)
+            .unwrap();
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        let player = CassetteClient::replay(&dir).unwrap();
+        let replayed = player.summarize_file("main", &file, 0, 1, None, None);
+        assert_eq!(replayed.unwrap(), recorded);
+
+        let err = player
+            .summarize_file("other-branch", &file, 0, 1, None, None)
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("No recorded summary response"),
+            "{err}"
+        );
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn records_who_answered_and_the_raw_reply() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time before unix epoch")
+            .as_nanos();
+        let dir = std::env::temp_dir().join(format!("commitbot_cassette_raw_{nanos}"));
+        let debug_log = Arc::new(DebugLog::in_memory());
+        let inner = CommandClient::new(
+            "printf 'src/lib.rs: Fix login\\nsrc/api.rs: Add route'",
+            "local-7b",
+        )
+        .with_debug_log(Some(debug_log.clone()));
+        let recorder = CassetteClient::record(&dir, Box::new(inner))
+            .unwrap()
+            .with_raw_replies(debug_log);
+        let file = |path: &str| FileChange {
+            path: path.into(),
+            category: FileCategory::Consequence,
+            diff: "@@ -1 +1 @@\n-a\n+b\n".into(),
+            summary: None,
+            list_only: false,
+            is_deleted: false,
+        };
+        let (lib, api) = (file("src/lib.rs"), file("src/api.rs"));
+
+        recorder
+            .summarize_batch("main", &[&lib, &api], None, None)
+            .unwrap();
+
+        let path = fs::read_dir(&dir).unwrap().next().unwrap().unwrap().path();
+        let entry: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(entry["provider"], "command");
+        assert_eq!(entry["model"], "local-7b");
+        assert_eq!(
+            entry["raw_response"],
+            "src/lib.rs: Fix login\nsrc/api.rs: Add route"
+        );
+        assert!(entry["response"].is_array());
+
+        fs::remove_dir_all(dir).ok();
+    }
+}
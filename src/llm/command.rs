@@ -0,0 +1,346 @@
+//! `provider = "command"`: hand each request to a user-configured executable, for backends
+//! commitbot has no native client for (vLLM wrappers, internal CLIs, ...).
+//!
+//! `provider_command` runs through the shell once per request. It receives one JSON object on
+//! stdin:
+//!
+//! ```json
+//! {"step": "file_summary", "model": "default", "system": "...", "user": "..."}
+//! ```
+//!
+//! and prints the completion as plain text on stdout. A non-zero exit fails the request with
+//! whatever the command wrote to stderr.
+
+use super::cancel::{self, CancellationToken};
+use super::context::estimate_tokens;
+use super::debug_log::DebugLog;
+use super::prompt_builder::PromptOptions;
+use super::usage::TokenUsage;
+use super::{LlmClient, prompt_builder, structured};
+use crate::FileChange;
+use crate::git::{PrItem, PrSummaryMode};
+use anyhow::{Context, Result, anyhow};
+use serde::Serialize;
+use std::io::{Read, Write};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often a running command is checked for exit, cancellation, and the timeout.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// What the command reads on stdin.
+#[derive(Debug, Serialize)]
+struct CommandRequest<'a> {
+    step: &'a str,
+    model: &'a str,
+    system: &'a str,
+    user: &'a str,
+}
+
+/// Runs `command` for every request.
+pub struct CommandClient {
+    command: String,
+    model: String,
+    prompt_options: PromptOptions,
+    debug_log: Option<Arc<DebugLog>>,
+    timeout: Option<Duration>,
+    usage: Mutex<TokenUsage>,
+}
+
+impl CommandClient {
+    /// `command` is a shell command line; `model` is passed through for it to interpret.
+    pub fn new(command: impl Into<String>, model: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+            model: model.into(),
+            prompt_options: PromptOptions::default(),
+            debug_log: None,
+            timeout: None,
+            usage: Mutex::new(TokenUsage::default()),
+        }
+    }
+
+    /// Set the options used when building prompts.
+    pub fn with_prompt_options(mut self, opts: PromptOptions) -> Self {
+        self.prompt_options = opts;
+        self
+    }
+
+    /// Record every prompt/response pair to the given debug log.
+    pub fn with_debug_log(mut self, debug_log: Option<Arc<DebugLog>>) -> Self {
+        self.debug_log = debug_log;
+        self
+    }
+
+    /// Kill the command if it runs longer than this (`None` = wait indefinitely).
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Run the command for one prompt pair, recording it to the debug log when enabled.
+    fn request(
+        &self,
+        step: &str,
+        system: String,
+        user: String,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        cancel::check(cancel)?;
+        let result = self.run(step, &system, &user, cancel);
+        if let Some(debug_log) = &self.debug_log {
+            debug_log.record("command", &self.model, step, &system, &user, &result);
+        }
+        let reply = result?;
+
+        // the command reports no usage, so count what was sent and received
+        let prompt = (estimate_tokens(&system) + estimate_tokens(&user)) as u64;
+        let completion = estimate_tokens(&reply) as u64;
+        let mut u = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        u.requests += 1;
+        u.record(prompt, completion, prompt + completion);
+        Ok(reply)
+    }
+
+    fn run(
+        &self,
+        step: &str,
+        system: &str,
+        user: &str,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        let input = serde_json::to_vec(&CommandRequest {
+            step,
+            model: &self.model,
+            system,
+            user,
+        })?;
+
+        let (shell, flag) = if cfg!(windows) {
+            ("cmd", "/C")
+        } else {
+            ("sh", "-c")
+        };
+        let mut child = Command::new(shell)
+            .args([flag, &self.command])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to run provider_command `{}`", self.command))?;
+
+        // Feed stdin and drain the pipes on their own threads so a large prompt or reply
+        // can't fill a pipe buffer and stall both sides.
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let writer = thread::spawn(move || stdin.write_all(&input));
+        let stdout = drain(child.stdout.take().expect("stdout is piped"));
+        let stderr = drain(child.stderr.take().expect("stderr is piped"));
+
+        let status = self.wait(&mut child, cancel)?;
+        let stdout = stdout.join().unwrap_or_default();
+        let stderr = stderr.join().unwrap_or_default();
+        // a command that ignores its input closes stdin early; that is its business
+        let _ = writer.join();
+
+        if !status.success() {
+            return Err(anyhow!(
+                "provider_command exited with status {:?}: {}",
+                status.code(),
+                String::from_utf8_lossy(&stderr).trim()
+            ));
+        }
+        let reply = String::from_utf8(stdout)
+            .map_err(|_| anyhow!("provider_command printed something other than UTF-8 text"))?;
+        let reply = reply.trim();
+        if reply.is_empty() {
+            return Err(anyhow!("provider_command printed nothing for {step}"));
+        }
+        Ok(reply.to_string())
+    }
+
+    /// Wait for `child` to exit, killing it on cancellation or when it outlives the timeout.
+    fn wait(
+        &self,
+        child: &mut Child,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<std::process::ExitStatus> {
+        let started = Instant::now();
+        loop {
+            if let Some(status) = child.try_wait()? {
+                return Ok(status);
+            }
+            let timed_out = self.timeout.is_some_and(|limit| started.elapsed() > limit);
+            if timed_out || cancel::check(cancel).is_err() {
+                let _ = child.kill();
+                let _ = child.wait();
+                cancel::check(cancel)?;
+                return Err(anyhow!(
+                    "provider_command did not finish within {}s",
+                    self.timeout.unwrap_or_default().as_secs()
+                ));
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+/// Read `pipe` to the end on a background thread.
+fn drain(mut pipe: impl Read + Send + 'static) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = pipe.read_to_end(&mut buf);
+        buf
+    })
+}
+
+impl LlmClient for CommandClient {
+    fn validate_model(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn summarize_file(
+        &self,
+        branch: &str,
+        file: &FileChange,
+        file_index: usize,
+        total_files: usize,
+        ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        let prompts = prompt_builder::file_summary_prompt(
+            branch,
+            file,
+            file_index,
+            total_files,
+            ticket_summary,
+            &self.prompt_options,
+        );
+        self.request("file_summary", prompts.system, prompts.user, cancel)
+    }
+
+    fn summarize_batch(
+        &self,
+        branch: &str,
+        files: &[&FileChange],
+        ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<Vec<Option<String>>> {
+        let prompts = prompt_builder::batch_summary_prompt(
+            branch,
+            files,
+            ticket_summary,
+            &self.prompt_options,
+        );
+        let content = self.request("batch_summary", prompts.system, prompts.user, cancel)?;
+        let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+        Ok(prompt_builder::split_batch_summary(&content, &paths))
+    }
+
+    fn generate_commit_message(
+        &self,
+        branch: &str,
+        files: &[FileChange],
+        ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        let prompts = prompt_builder::commit_message_prompt(
+            branch,
+            files,
+            ticket_summary,
+            &self.prompt_options,
+        );
+        let content = self.request("commit_message", prompts.system, prompts.user, cancel)?;
+        Ok(structured::finish_commit_message(
+            content,
+            &self.prompt_options,
+        ))
+    }
+
+    fn generate_changelog_entry(
+        &self,
+        files: &[FileChange],
+        commit_message: &str,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        let prompts =
+            prompt_builder::changelog_entry_prompt(files, commit_message, &self.prompt_options);
+        self.request("changelog", prompts.system, prompts.user, cancel)
+    }
+
+    fn generate_pr_message(
+        &self,
+        base_branch: &str,
+        from_branch: &str,
+        mode: PrSummaryMode,
+        items: &[PrItem],
+        ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        let prompts = prompt_builder::pr_message_prompt(
+            base_branch,
+            from_branch,
+            mode,
+            items,
+            ticket_summary,
+            &self.prompt_options,
+        );
+        self.request("pr_message", prompts.system, prompts.user, cancel)
+    }
+
+    fn summary_source(&self) -> Option<String> {
+        Some(format!("command:{}:{}", self.command, self.model))
+    }
+
+    fn take_and_reset_usage(&self) -> Option<TokenUsage> {
+        let mut u = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        u.take(None)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::FileCategory;
+
+    fn change() -> FileChange {
+        FileChange {
+            path: "src/lib.rs".into(),
+            category: FileCategory::Main,
+            diff: "@@ -1 +1 @@\n-a\n+b\n".into(),
+            summary: None,
+            list_only: false,
+            is_deleted: false,
+        }
+    }
+
+    #[test]
+    fn pipes_the_request_as_json_and_reads_stdout() {
+        // echo back the step and model parsed out of the request
+        let client = CommandClient::new(
+            r#"sed -n 's/.*"step":"\([^"]*\)","model":"\([^"]*\)".*/\1 for \2/p'"#,
+            "local-7b",
+        );
+        let reply = client
+            .summarize_file("main", &change(), 0, 1, None, None)
+            .unwrap();
+        assert_eq!(reply, "file_summary for local-7b");
+        assert_eq!(client.take_and_reset_usage().unwrap().requests, 1);
+    }
+
+    #[test]
+    fn reports_failures_and_timeouts() {
+        let failing = CommandClient::new("echo 'no GPU' >&2; exit 3", "m");
+        let err = failing
+            .summarize_file("main", &change(), 0, 1, None, None)
+            .unwrap_err();
+        assert!(err.to_string().contains("status Some(3): no GPU"), "{err}");
+
+        let slow = CommandClient::new("sleep 5", "m").with_timeout(Some(Duration::from_millis(50)));
+        let err = slow
+            .summarize_file("main", &change(), 0, 1, None, None)
+            .unwrap_err();
+        assert!(err.to_string().contains("did not finish"), "{err}");
+    }
+}
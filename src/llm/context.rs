@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Built-in context windows (in tokens), matched against the model name by longest prefix.
+const DEFAULT_CONTEXT_LIMITS: &[(&str, usize)] = &[
+    ("gpt-5", 400_000),
+    ("gpt-4.1", 1_047_576),
+    ("gpt-4o", 128_000),
+    ("gpt-4-turbo", 128_000),
+    ("gpt-4", 8_192),
+    ("gpt-3.5-turbo", 16_385),
+    ("o1", 200_000),
+    ("o3", 200_000),
+    ("o4-mini", 200_000),
+    ("gemini-1.5", 1_048_576),
+    ("gemini-2", 1_048_576),
+    ("claude", 200_000),
+    ("gpt-oss", 131_072),
+    ("deepseek", 131_072),
+    ("llama3.1", 131_072),
+    ("llama3.2", 131_072),
+    ("llama3", 8_192),
+    ("mistral", 32_768),
+    ("qwen2.5", 32_768),
+];
+
+/// Tokens set aside for the prompt's instructions and the reply when trimming a diff.
+pub const PROMPT_RESERVE_TOKENS: usize = 4_096;
+
+/// Token estimate modeled on how BPE tokenizers split text: runs of ASCII letters cost about
+/// one token per four characters, digits one per three, and each newline, punctuation mark,
+/// or non-ASCII character about one. A single space joins the following word for free, while
+/// longer runs (indentation) cost one token per four. This errs high for symbol-heavy diffs,
+/// which is the safe direction for a context check.
+pub fn estimate_tokens(text: &str) -> usize {
+    let mut tokens = 0;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        tokens += match c {
+            _ if c.is_ascii_alphabetic() => {
+                run_length(&mut chars, char::is_ascii_alphabetic).div_ceil(4)
+            }
+            _ if c.is_ascii_digit() => run_length(&mut chars, char::is_ascii_digit).div_ceil(3),
+            ' ' | '\t' => (run_length(&mut chars, |c| matches!(c, ' ' | '\t')) - 1).div_ceil(4),
+            _ => 1,
+        };
+    }
+    tokens
+}
+
+/// Length of a run of characters matching `matches`, counting the one already taken.
+fn run_length(chars: &mut Peekable<Chars<'_>>, matches: fn(&char) -> bool) -> usize {
+    let mut len = 1;
+    while chars.next_if(matches).is_some() {
+        len += 1;
+    }
+    len
+}
+
+/// Cut `text` at a line boundary so it is estimated at no more than `max_tokens`.
+///
+/// Returns the kept lines and how many were dropped, or `None` when `text` already fits.
+pub fn truncate_to_tokens(text: &str, max_tokens: usize) -> Option<(String, usize)> {
+    if estimate_tokens(text) <= max_tokens {
+        return None;
+    }
+    let mut used = 0;
+    let mut kept = 0;
+    for line in text.lines() {
+        let cost = estimate_tokens(line) + 1;
+        if used + cost > max_tokens {
+            break;
+        }
+        used += cost;
+        kept += 1;
+    }
+    let lines: Vec<&str> = text.lines().collect();
+    Some((lines[..kept].join("\n"), lines.len() - kept))
+}
+
+/// Context window for `model`, preferring configured overrides over the built-in table.
+///
+/// Both are matched by longest prefix; a provider prefix such as `openai/` is ignored.
+pub fn context_limit(model: &str, overrides: &HashMap<String, usize>) -> Option<usize> {
+    let name = model.rsplit('/').next().unwrap_or(model);
+    longest_prefix(name, overrides.iter().map(|(k, v)| (k.as_str(), *v)))
+        .or_else(|| longest_prefix(name, DEFAULT_CONTEXT_LIMITS.iter().copied()))
+}
+
+fn longest_prefix<'a>(name: &str, limits: impl Iterator<Item = (&'a str, usize)>) -> Option<usize> {
+    limits
+        .filter(|(prefix, _)| name.starts_with(prefix))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(_, limit)| limit)
+}
+
+/// A prompt that is estimated not to fit in the model's context window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContextOverflow {
+    pub estimated_tokens: usize,
+    pub limit: usize,
+}
+
+/// Check whether `text` likely fits; `None` when it does or the model's limit is unknown.
+pub fn check_fits(
+    model: &str,
+    text: &str,
+    overrides: &HashMap<String, usize>,
+) -> Option<ContextOverflow> {
+    let limit = context_limit(model, overrides)?;
+    let estimated_tokens = estimate_tokens(text);
+    (estimated_tokens > limit).then_some(ContextOverflow {
+        estimated_tokens,
+        limit,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn longest_prefix_wins() {
+        let none = HashMap::new();
+        assert_eq!(context_limit("gpt-4o-mini", &none), Some(128_000));
+        assert_eq!(context_limit("gpt-4-0613", &none), Some(8_192));
+        assert_eq!(context_limit("llama3.1:8b", &none), Some(131_072));
+        assert_eq!(context_limit("openai/gpt-4o", &none), Some(128_000));
+        assert_eq!(context_limit("some-local-model", &none), None);
+    }
+
+    #[test]
+    fn overrides_take_precedence() {
+        let overrides = HashMap::from([("gpt-4o".to_string(), 1_000)]);
+        assert_eq!(context_limit("gpt-4o-mini", &overrides), Some(1_000));
+        assert_eq!(context_limit("gpt-5-nano", &overrides), Some(400_000));
+    }
+
+    #[test]
+    fn estimates_like_a_tokenizer() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("x".repeat(100).as_str()), 25);
+        // "fn", "main", "(", ")", "{", "}" with free single spaces
+        assert_eq!(estimate_tokens("fn main() {}"), 6);
+        // indentation costs, digits group by three, newlines count
+        assert_eq!(estimate_tokens("+        let n = 123456;\n"), 10);
+        assert_eq!(estimate_tokens("日本語"), 3);
+    }
+
+    #[test]
+    fn truncates_at_line_boundaries() {
+        let text = "aaaa\nbbbb\ncccc\ndddd";
+        assert_eq!(truncate_to_tokens(text, 100), None);
+        assert_eq!(
+            truncate_to_tokens(text, 5),
+            Some(("aaaa\nbbbb".to_string(), 2))
+        );
+        assert_eq!(truncate_to_tokens(text, 0), Some((String::new(), 4)));
+    }
+
+    #[test]
+    fn flags_oversized_text() {
+        let overrides = HashMap::from([("tiny".to_string(), 10)]);
+        assert_eq!(check_fits("tiny", "short", &overrides), None);
+        assert_eq!(
+            check_fits("tiny", &"x".repeat(100), &overrides),
+            Some(ContextOverflow {
+                estimated_tokens: 25,
+                limit: 10
+            })
+        );
+        assert_eq!(check_fits("unknown", &"x".repeat(100), &overrides), None);
+    }
+}
@@ -0,0 +1,130 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+use std::thread::{self, ThreadId};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// One LLM round-trip as written to the debug log.
+#[derive(Serialize)]
+struct DebugLogEntry<'a> {
+    timestamp: u64,
+    provider: &'a str,
+    model: &'a str,
+    step: &'a str,
+    system: &'a str,
+    user: &'a str,
+    response: Option<&'a str>,
+    error: Option<String>,
+}
+
+/// The provider's reply to the latest call on a thread, before any cleanup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawReply {
+    pub provider: String,
+    pub model: String,
+    pub response: Option<String>,
+}
+
+/// Thread-safe JSON Lines sink for full, untruncated prompts and responses.
+///
+/// Enabled with `--debug-log <path>`; each LLM call appends one line as it completes.
+/// `--record` also keeps each thread's latest reply so the cassette can save it.
+pub struct DebugLog {
+    file: Option<Mutex<File>>,
+    replies: Option<Mutex<HashMap<ThreadId, RawReply>>>,
+}
+
+impl DebugLog {
+    /// Open (or create) the log file in append mode.
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("failed to open debug log {}", path.display()))?;
+        Ok(Self {
+            file: Some(Mutex::new(file)),
+            replies: None,
+        })
+    }
+
+    /// A log that writes nothing and only keeps the latest reply per thread.
+    pub fn in_memory() -> Self {
+        Self {
+            file: None,
+            replies: Some(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Also keep the latest reply per thread for [`take_reply`](Self::take_reply).
+    pub fn keeping_replies(mut self) -> Self {
+        self.replies = Some(Mutex::new(HashMap::new()));
+        self
+    }
+
+    /// Remove and return the latest reply recorded on the calling thread.
+    pub fn take_reply(&self) -> Option<RawReply> {
+        let mut replies = self
+            .replies
+            .as_ref()?
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        replies.remove(&thread::current().id())
+    }
+
+    /// Append a single call record. Failures are logged, never propagated.
+    pub fn record(
+        &self,
+        provider: &str,
+        model: &str,
+        step: &str,
+        system: &str,
+        user: &str,
+        result: &Result<String>,
+    ) {
+        if let Some(replies) = &self.replies {
+            let reply = RawReply {
+                provider: provider.to_string(),
+                model: model.to_string(),
+                response: result.as_ref().ok().cloned(),
+            };
+            let mut replies = replies.lock().unwrap_or_else(|e| e.into_inner());
+            replies.insert(thread::current().id(), reply);
+        }
+        let Some(file) = &self.file else {
+            return;
+        };
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let entry = DebugLogEntry {
+            timestamp,
+            provider,
+            model,
+            step,
+            system,
+            user,
+            response: result.as_ref().ok().map(String::as_str),
+            error: result.as_ref().err().map(|e| format!("{e:#}")),
+        };
+
+        let line = match serde_json::to_string(&entry) {
+            Ok(line) => line,
+            Err(err) => {
+                log::warn!("Failed to encode debug log entry: {err}");
+                return;
+            }
+        };
+
+        let mut file = file.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(err) = writeln!(file, "{line}") {
+            log::warn!("Failed to write debug log entry: {err}");
+        }
+    }
+}
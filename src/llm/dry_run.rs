@@ -0,0 +1,221 @@
+//! `--dry-run`: build every prompt a run would send and print it instead of contacting the
+//! provider.
+//!
+//! Per-file summaries come back as placeholders naming the file, so the final prompt shows
+//! where each summary would go. Diffs are printed after secret redaction and trimming, exactly
+//! as they would leave the machine.
+
+use super::context::estimate_tokens;
+use super::prompt_builder::{self, PromptOptions, PromptPair};
+use super::{CancellationToken, LlmClient};
+use crate::FileChange;
+use crate::git::{PrItem, PrSummaryMode};
+use anyhow::Result;
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+/// Prints prompts instead of sending them.
+pub struct DryRunClient {
+    summary_model: String,
+    final_model: String,
+    prompt_options: PromptOptions,
+    out: Mutex<Box<dyn Write + Send>>,
+}
+
+impl DryRunClient {
+    /// A client that prints to stdout the prompts `summary_model` and `final_model` would get.
+    pub fn new(summary_model: &str, final_model: &str) -> Self {
+        Self {
+            summary_model: summary_model.to_string(),
+            final_model: final_model.to_string(),
+            prompt_options: PromptOptions::default(),
+            out: Mutex::new(Box::new(io::stdout())),
+        }
+    }
+
+    pub fn with_prompt_options(mut self, opts: PromptOptions) -> Self {
+        self.prompt_options = opts;
+        self
+    }
+
+    /// Print to `out` instead of stdout.
+    pub fn with_writer(mut self, out: Box<dyn Write + Send>) -> Self {
+        self.out = Mutex::new(out);
+        self
+    }
+
+    /// Print one prompt as a block, so prompts built on parallel workers don't interleave.
+    fn show(&self, what: &str, model: &str, prompts: &PromptPair) -> Result<()> {
+        let tokens = estimate_tokens(&prompts.system) + estimate_tokens(&prompts.user);
+        let mut out = self.out.lock().expect("dry-run output mutex poisoned");
+        write!(
+            out,
+            "===== {what} -> {model} (~{tokens} tokens) =====\n\
+             --- system ---\n{}\n--- user ---\n{}\n\n",
+            prompts.system.trim_end(),
+            prompts.user.trim_end()
+        )?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+/// Stand-in for a summary the model would have written.
+fn placeholder_summary(file: &FileChange) -> String {
+    format!("- (dry run: summary of `{}`)", file.path)
+}
+
+impl LlmClient for DryRunClient {
+    fn validate_model(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn summarize_file(
+        &self,
+        branch: &str,
+        file: &FileChange,
+        file_index: usize,
+        total_files: usize,
+        ticket_summary: Option<&str>,
+        _cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        let prompts = prompt_builder::file_summary_prompt(
+            branch,
+            file,
+            file_index,
+            total_files,
+            ticket_summary,
+            &self.prompt_options,
+        );
+        self.show(
+            &format!("Summary of {}", file.path),
+            &self.summary_model,
+            &prompts,
+        )?;
+        Ok(placeholder_summary(file))
+    }
+
+    fn summarize_batch(
+        &self,
+        branch: &str,
+        files: &[&FileChange],
+        ticket_summary: Option<&str>,
+        _cancel: Option<&CancellationToken>,
+    ) -> Result<Vec<Option<String>>> {
+        let prompts = prompt_builder::batch_summary_prompt(
+            branch,
+            files,
+            ticket_summary,
+            &self.prompt_options,
+        );
+        self.show(
+            &format!("Combined summary of {} files", files.len()),
+            &self.summary_model,
+            &prompts,
+        )?;
+        Ok(files
+            .iter()
+            .map(|file| Some(placeholder_summary(file)))
+            .collect())
+    }
+
+    fn generate_commit_message(
+        &self,
+        branch: &str,
+        files: &[FileChange],
+        ticket_summary: Option<&str>,
+        _cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        let prompts = prompt_builder::commit_message_prompt(
+            branch,
+            files,
+            ticket_summary,
+            &self.prompt_options,
+        );
+        self.show("Commit message", &self.final_model, &prompts)?;
+        Ok("(dry run)".to_string())
+    }
+
+    fn generate_changelog_entry(
+        &self,
+        files: &[FileChange],
+        commit_message: &str,
+        _cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        let prompts =
+            prompt_builder::changelog_entry_prompt(files, commit_message, &self.prompt_options);
+        self.show("Changelog entry", &self.final_model, &prompts)?;
+        Ok("Changed: (dry run)".to_string())
+    }
+
+    fn generate_pr_message(
+        &self,
+        base_branch: &str,
+        from_branch: &str,
+        mode: PrSummaryMode,
+        items: &[PrItem],
+        ticket_summary: Option<&str>,
+        _cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        let prompts = prompt_builder::pr_message_prompt(
+            base_branch,
+            from_branch,
+            mode,
+            items,
+            ticket_summary,
+            &self.prompt_options,
+        );
+        self.show("PR description", &self.final_model, &prompts)?;
+        Ok("(dry run)".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FileCategory;
+    use std::sync::Arc;
+
+    /// A writer the test can read back after the client is done with it.
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn prints_prompts_and_threads_placeholders_through() {
+        let captured = Captured::default();
+        let client = DryRunClient::new("small", "large").with_writer(Box::new(captured.clone()));
+        let mut file = FileChange {
+            path: "src/lib.rs".into(),
+            category: FileCategory::Main,
+            diff: "@@ -1 +1 @@\n-old\n+new\n".into(),
+            summary: None,
+            list_only: false,
+            is_deleted: false,
+        };
+
+        let summary = client
+            .summarize_file("feature", &file, 0, 1, None, None)
+            .unwrap();
+        file.summary = Some(summary);
+        client
+            .generate_commit_message("feature", &[file], None, None)
+            .unwrap();
+
+        let printed = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        assert!(printed.contains("===== Summary of src/lib.rs -> small (~"));
+        assert!(printed.contains("+new"));
+        assert!(printed.contains("===== Commit message -> large (~"));
+        assert!(printed.contains("(dry run: summary of `src/lib.rs`)"));
+    }
+}
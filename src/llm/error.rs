@@ -0,0 +1,104 @@
+use serde_json::Value;
+use std::fmt;
+
+/// Non-success HTTP response from an LLM provider.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiError {
+    /// Provider display name (e.g. "OpenAI", "Ollama")
+    pub provider: &'static str,
+    /// HTTP status code
+    pub status: u16,
+    /// Response body, as returned by the provider
+    pub body: String,
+    /// Machine-readable error code (e.g. "invalid_api_key"), when the body has one
+    pub code: Option<String>,
+    /// Human-readable message from the body, when it has one
+    pub message: Option<String>,
+    /// Error type/category (OpenAI `type`, Gemini `status`), when the body has one
+    pub error_type: Option<String>,
+}
+
+impl ApiError {
+    /// Build from a raw response, parsing the structured error body providers return:
+    /// `{"error": {"message", "type", "code"}}` (OpenAI, Gemini) or `{"error": "..."}` (Ollama).
+    pub fn new(provider: &'static str, status: u16, body: impl Into<String>) -> Self {
+        let body = body.into();
+        let (code, message, error_type) = parse_error_body(&body);
+        Self {
+            provider,
+            status,
+            body,
+            code,
+            message,
+            error_type,
+        }
+    }
+
+    /// Credentials were rejected; retrying or sending more requests will not help.
+    ///
+    /// Gemini answers a bad key with HTTP 400, so its `API_KEY_INVALID` reason counts too.
+    pub fn is_auth_failure(&self) -> bool {
+        matches!(self.status, 401 | 403) || self.code.as_deref() == Some("API_KEY_INVALID")
+    }
+}
+
+type ParsedBody = (Option<String>, Option<String>, Option<String>);
+
+fn parse_error_body(body: &str) -> ParsedBody {
+    let Ok(json) = serde_json::from_str::<Value>(body) else {
+        return (None, None, None);
+    };
+    let text = |v: Option<&Value>| match v {
+        Some(Value::String(s)) if !s.trim().is_empty() => Some(s.trim().to_string()),
+        Some(Value::Number(n)) => Some(n.to_string()),
+        _ => None,
+    };
+    match json.get("error") {
+        Some(message @ Value::String(_)) => (None, text(Some(message)), None),
+        Some(err @ Value::Object(_)) => {
+            // Gemini puts the HTTP status in `code`, the symbolic name in `status`, and the
+            // specific cause (e.g. API_KEY_INVALID) in `details[].reason`.
+            let code = err.get("code").filter(|c| c.is_string()).or_else(|| {
+                err.get("details")?
+                    .as_array()?
+                    .iter()
+                    .find_map(|d| d.get("reason"))
+            });
+            (
+                text(code),
+                text(err.get("message")),
+                text(err.get("type").or_else(|| err.get("status"))),
+            )
+        }
+        _ => (None, None, None),
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(message) = &self.message else {
+            return write!(
+                f,
+                "{} API error: HTTP {} - {}",
+                self.provider, self.status, self.body
+            );
+        };
+        match self.code.as_ref().or(self.error_type.as_ref()) {
+            Some(code) => write!(
+                f,
+                "{}: {code} — {message} (HTTP {})",
+                self.provider, self.status
+            ),
+            None => write!(f, "{}: {message} (HTTP {})", self.provider, self.status),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// Whether an error means every further request will fail the same way.
+pub fn is_fatal(err: &anyhow::Error) -> bool {
+    err.chain()
+        .filter_map(|e| e.downcast_ref::<ApiError>())
+        .any(ApiError::is_auth_failure)
+}
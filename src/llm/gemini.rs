@@ -0,0 +1,550 @@
+use super::{LlmClient, structured, warn_if_truncated};
+use super::cancel::{self, CancellationToken};
+use super::debug_log::DebugLog;
+use super::error::ApiError;
+use super::retry::RetryPolicy;
+use super::usage::TokenUsage;
+use super::prompt_builder::{self, PromptOptions};
+use super::stream::{
+    DEFAULT_MAX_RESPONSE_BYTES, read_body_capped, read_stream_to_string, sse_data,
+};
+use crate::FileChange;
+use crate::git::{PrItem, PrSummaryMode};
+use anyhow::{Context, Result, anyhow};
+use reqwest::StatusCode;
+use reqwest::header::HeaderMap;
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use std::io::BufReader;
+use std::sync::{Arc, Mutex};
+
+pub const DEFAULT_BASE_URL: &str = "https://generativelanguage.googleapis.com";
+
+/// Minimal request/response structs for the Gemini generateContent API.
+#[derive(Serialize)]
+struct GenerateRequest {
+    system_instruction: GeminiContent,
+    contents: Vec<GeminiContent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    generation_config: Option<GenerationConfig>,
+}
+
+#[derive(Serialize)]
+struct GenerationConfig {
+    max_output_tokens: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GeminiContent {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    role: Option<String>,
+    #[serde(default)]
+    parts: Vec<GeminiPart>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GeminiPart {
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateResponse {
+    #[serde(default)]
+    candidates: Vec<GeminiCandidate>,
+    usage_metadata: Option<GeminiUsage>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiCandidate {
+    content: Option<GeminiContent>,
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiUsage {
+    #[serde(default)]
+    prompt_token_count: u32,
+    #[serde(default)]
+    candidates_token_count: u32,
+    #[serde(default)]
+    total_token_count: u32,
+}
+
+/// Gemini client using `models/{model}:generateContent`.
+pub struct GeminiClient {
+    client: Client,
+    api_key: String,
+    model: String,
+    api_base_url: String,
+    extra_headers: HeaderMap,
+    stream: bool,
+    prompt_options: PromptOptions,
+    debug_log: Option<Arc<DebugLog>>,
+    max_response_bytes: usize,
+    retry: RetryPolicy,
+    max_output_tokens: Option<u32>,
+    usage: Mutex<TokenUsage>,
+}
+
+impl GeminiClient {
+    /// `client` is usually [`crate::http::shared_client`], shared with other backends.
+    pub fn new(
+        client: Client,
+        api_key: String,
+        model: String,
+        api_base_url: String,
+        stream: bool,
+    ) -> Self {
+        GeminiClient {
+            client,
+            api_key,
+            model,
+            api_base_url: api_base_url.trim_end_matches('/').to_string(),
+            extra_headers: HeaderMap::new(),
+            stream,
+            prompt_options: PromptOptions::default(),
+            debug_log: None,
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            retry: RetryPolicy::default(),
+            max_output_tokens: None,
+            usage: Mutex::new(TokenUsage::default()),
+        }
+    }
+
+    /// Extra headers sent with every request (e.g. for an API gateway).
+    pub fn with_headers(mut self, headers: HeaderMap) -> Self {
+        self.extra_headers = headers;
+        self
+    }
+
+    /// Set the options used when building prompts.
+    pub fn with_prompt_options(mut self, opts: PromptOptions) -> Self {
+        self.prompt_options = opts;
+        self
+    }
+
+    /// Record every prompt/response pair to the given debug log.
+    pub fn with_debug_log(mut self, debug_log: Option<Arc<DebugLog>>) -> Self {
+        self.debug_log = debug_log;
+        self
+    }
+
+    /// Cap on a single response's size (see [`DEFAULT_MAX_RESPONSE_BYTES`]).
+    pub fn with_max_response_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_response_bytes = max_bytes;
+        self
+    }
+
+    /// Retry policy for rate limits, server errors, and dropped connections.
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// `generationConfig.maxOutputTokens` for every request; `None` leaves the model default.
+    pub fn with_max_output_tokens(mut self, max_tokens: Option<u32>) -> Self {
+        self.max_output_tokens = max_tokens;
+        self
+    }
+
+    /// Versioned API root; `/v1beta` is appended unless a version is already present.
+    fn api_root(&self) -> String {
+        if self.api_base_url.ends_with("/v1beta") || self.api_base_url.ends_with("/v1") {
+            self.api_base_url.clone()
+        } else {
+            format!("{}/v1beta", self.api_base_url)
+        }
+    }
+
+    fn model_url(&self) -> String {
+        format!("{}/models/{}", self.api_root(), self.model)
+    }
+
+    fn generate_url(&self, stream: bool) -> String {
+        if stream {
+            format!("{}:streamGenerateContent?alt=sse", self.model_url())
+        } else {
+            format!("{}:generateContent", self.model_url())
+        }
+    }
+
+    /// Send a prompt pair, recording it to the debug log when enabled.
+    fn request(
+        &self,
+        step: &str,
+        system: String,
+        user: String,
+        stream: bool,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        cancel::check(cancel)?;
+        self.usage.lock().unwrap_or_else(|e| e.into_inner()).requests += 1;
+        let Some(debug_log) = &self.debug_log else {
+            return self.generate(system, user, stream, cancel);
+        };
+
+        let result = self.generate(system.clone(), user.clone(), stream, cancel);
+        debug_log.record("gemini", &self.model, step, &system, &user, &result);
+        result
+    }
+
+    fn generate_request(&self, system: String, user: String) -> GenerateRequest {
+        GenerateRequest {
+            system_instruction: GeminiContent {
+                role: None,
+                parts: vec![GeminiPart { text: system }],
+            },
+            contents: vec![GeminiContent {
+                role: Some("user".to_string()),
+                parts: vec![GeminiPart { text: user }],
+            }],
+            generation_config: self
+                .max_output_tokens
+                .map(|max_output_tokens| GenerationConfig { max_output_tokens }),
+        }
+    }
+
+    fn generate(
+        &self,
+        system: String,
+        user: String,
+        stream: bool,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        let req = self.generate_request(system, user);
+        let url = self.generate_url(stream);
+        if stream {
+            log::info!("Streaming Gemini model {:?}", &self.model);
+        } else {
+            log::info!("Calling Gemini model {:?}", &self.model);
+        }
+
+        let resp = self
+            .retry
+            .send("Gemini", cancel, || {
+                self.client
+                    .post(&url)
+                    .headers(self.extra_headers.clone())
+                    .header("x-goog-api-key", &self.api_key)
+                    .json(&req)
+                    .send()
+            })
+            .context("failed to send request to Gemini")?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let text = resp.text().unwrap_or_default();
+            return Err(ApiError::new("Gemini", status.as_u16(), text).into());
+        }
+
+        if stream {
+            let reader = BufReader::new(resp);
+            let mut state = StreamState::default();
+            let content = read_stream_to_string(reader, cancel, self.max_response_bytes, |line| {
+                parse_stream_line(line, &mut state)
+            })?;
+            warn_if_truncated("Gemini", finish_reason(state.finish_reason.as_deref()));
+            if let Some(usage) = &state.usage {
+                self.record_usage(usage);
+            }
+            return Ok(content);
+        }
+
+        let body = read_body_capped(resp, self.max_response_bytes)?;
+        let parsed: GenerateResponse =
+            serde_json::from_str(&body).context("failed to parse Gemini response")?;
+        let reason = parsed
+            .candidates
+            .first()
+            .and_then(|c| c.finish_reason.as_deref());
+        warn_if_truncated("Gemini", finish_reason(reason));
+        let content =
+            candidate_text(&parsed).ok_or_else(|| anyhow!("no candidates returned from Gemini"))?;
+
+        if let Some(usage) = &parsed.usage_metadata {
+            self.record_usage(usage);
+        }
+
+        Ok(content.trim().to_string())
+    }
+
+    fn record_usage(&self, usage: &GeminiUsage) {
+        let mut u = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        u.record(
+            usage.prompt_token_count as u64,
+            usage.candidates_token_count as u64,
+            usage.total_token_count as u64,
+        );
+    }
+}
+
+/// What a streamed response reports besides its text: the last `finishReason` and the
+/// `usageMetadata` (cumulative, so the final chunk's totals win).
+#[derive(Default)]
+struct StreamState {
+    finish_reason: Option<String>,
+    usage: Option<GeminiUsage>,
+}
+
+/// Map Gemini's `MAX_TOKENS` finish reason onto the shared "length" spelling.
+fn finish_reason(reason: Option<&str>) -> Option<&str> {
+    match reason {
+        Some("MAX_TOKENS") => Some("length"),
+        other => other,
+    }
+}
+
+/// Concatenate the text parts of the first candidate.
+fn candidate_text(resp: &GenerateResponse) -> Option<String> {
+    let content = resp.candidates.first()?.content.as_ref()?;
+    Some(content.parts.iter().map(|p| p.text.as_str()).collect())
+}
+
+fn parse_stream_line(line: &str, state: &mut StreamState) -> Result<Option<String>> {
+    let Some(data) = sse_data(line) else {
+        return Ok(None);
+    };
+    let chunk: GenerateResponse =
+        serde_json::from_str(data).context("failed to parse Gemini streaming chunk")?;
+
+    if let Some(reason) = chunk
+        .candidates
+        .first()
+        .and_then(|c| c.finish_reason.clone())
+    {
+        state.finish_reason = Some(reason);
+    }
+    let text = candidate_text(&chunk).filter(|text| !text.is_empty());
+    if chunk.usage_metadata.is_some() {
+        state.usage = chunk.usage_metadata;
+    }
+    Ok(text)
+}
+
+impl LlmClient for GeminiClient {
+    fn validate_model(&self) -> Result<()> {
+        let url = self.model_url();
+        let resp = self
+            .client
+            .get(&url)
+            .headers(self.extra_headers.clone())
+            .header("x-goog-api-key", &self.api_key)
+            .send()
+            .context("failed to send model validation request to Gemini")?;
+
+        if resp.status() == StatusCode::OK {
+            return Ok(());
+        }
+
+        let status = resp.status();
+        let text = resp.text().unwrap_or_default();
+        Err(anyhow!(
+            "Gemini model validation failed for {:?} at {}: HTTP {} - {}",
+            self.model,
+            url,
+            status.as_u16(),
+            text
+        ))
+    }
+
+    fn summarize_file(
+        &self,
+        branch: &str,
+        file: &FileChange,
+        file_index: usize,
+        total_files: usize,
+        ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        let prompts = prompt_builder::file_summary_prompt(
+            branch,
+            file,
+            file_index,
+            total_files,
+            ticket_summary,
+            &self.prompt_options,
+        );
+        self.request("file_summary", prompts.system, prompts.user, false, cancel)
+    }
+
+    fn summarize_batch(
+        &self,
+        branch: &str,
+        files: &[&FileChange],
+        ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<Vec<Option<String>>> {
+        let prompts = prompt_builder::batch_summary_prompt(
+            branch,
+            files,
+            ticket_summary,
+            &self.prompt_options,
+        );
+        let content = self.request("batch_summary", prompts.system, prompts.user, false, cancel)?;
+        let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+        Ok(prompt_builder::split_batch_summary(&content, &paths))
+    }
+
+    fn generate_commit_message(
+        &self,
+        branch: &str,
+        files: &[FileChange],
+        ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        let prompts = prompt_builder::commit_message_prompt(
+            branch,
+            files,
+            ticket_summary,
+            &self.prompt_options,
+        );
+        let content = self.request(
+            "commit_message",
+            prompts.system,
+            prompts.user,
+            self.stream,
+            cancel,
+        )?;
+        Ok(structured::finish_commit_message(
+            content,
+            &self.prompt_options,
+        ))
+    }
+
+    fn generate_changelog_entry(
+        &self,
+        files: &[FileChange],
+        commit_message: &str,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        let prompts =
+            prompt_builder::changelog_entry_prompt(files, commit_message, &self.prompt_options);
+        self.request("changelog", prompts.system, prompts.user, false, cancel)
+    }
+
+    fn generate_pr_message(
+        &self,
+        base_branch: &str,
+        from_branch: &str,
+        mode: PrSummaryMode,
+        items: &[PrItem],
+        ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        let prompts = prompt_builder::pr_message_prompt(
+            base_branch,
+            from_branch,
+            mode,
+            items,
+            ticket_summary,
+            &self.prompt_options,
+        );
+        self.request(
+            "pr_message",
+            prompts.system,
+            prompts.user,
+            self.stream,
+            cancel,
+        )
+    }
+
+    fn summary_source(&self) -> Option<String> {
+        Some(format!("gemini:{}:{}", self.api_base_url, self.model))
+    }
+
+    fn take_and_reset_usage(&self) -> Option<TokenUsage> {
+        let mut u = self.usage.lock().unwrap_or_else(|e| {
+            log::warn!("usage mutex was poisoned, recovering token counters");
+            e.into_inner()
+        });
+        u.take(Some(&self.model))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client_with_base(base: &str) -> GeminiClient {
+        GeminiClient::new(
+            Client::new(),
+            "key".into(),
+            "gemini-2.5-flash".into(),
+            base.into(),
+            false,
+        )
+    }
+
+    #[test]
+    fn builds_generate_urls_from_root_base() {
+        let client = client_with_base(DEFAULT_BASE_URL);
+        assert_eq!(
+            client.generate_url(false),
+            "https://generativelanguage.googleapis.com/v1beta/models/gemini-2.5-flash:generateContent"
+        );
+        assert_eq!(
+            client.generate_url(true),
+            "https://generativelanguage.googleapis.com/v1beta/models/gemini-2.5-flash:streamGenerateContent?alt=sse"
+        );
+    }
+
+    #[test]
+    fn keeps_explicit_api_version() {
+        let client = client_with_base("https://gw.example/gemini/v1/");
+        assert_eq!(
+            client.model_url(),
+            "https://gw.example/gemini/v1/models/gemini-2.5-flash"
+        );
+    }
+
+    #[test]
+    fn serializes_system_instruction_and_contents() {
+        let client = client_with_base(DEFAULT_BASE_URL);
+        let req = client.generate_request("be brief".into(), "hi".into());
+        let value = serde_json::to_value(&req).unwrap();
+        assert_eq!(value["system_instruction"]["parts"][0]["text"], "be brief");
+        assert!(value["system_instruction"].get("role").is_none());
+        assert_eq!(value["contents"][0]["role"], "user");
+        assert_eq!(value["contents"][0]["parts"][0]["text"], "hi");
+        assert!(value.get("generation_config").is_none());
+
+        let client = client.with_max_output_tokens(Some(500));
+        let value = serde_json::to_value(client.generate_request("s".into(), "u".into())).unwrap();
+        assert_eq!(value["generation_config"]["max_output_tokens"], 500);
+    }
+
+    #[test]
+    fn extracts_candidate_text_and_usage() {
+        let body = r#"{
+            "candidates": [{"content": {"role": "model", "parts": [{"text": "Add "}, {"text": "login"}]}}],
+            "usageMetadata": {"promptTokenCount": 10, "candidatesTokenCount": 2, "totalTokenCount": 12}
+        }"#;
+        let parsed: GenerateResponse = serde_json::from_str(body).unwrap();
+        assert_eq!(candidate_text(&parsed).as_deref(), Some("Add login"));
+        assert_eq!(parsed.usage_metadata.unwrap().total_token_count, 12);
+    }
+
+    #[test]
+    fn parses_stream_chunks() {
+        let mut state = StreamState::default();
+        let line = r#"data: {"candidates": [{"content": {"parts": [{"text": "Fix"}]}}]}"#;
+        assert_eq!(
+            parse_stream_line(line, &mut state).unwrap().as_deref(),
+            Some("Fix")
+        );
+        assert_eq!(parse_stream_line(": keep-alive", &mut state).unwrap(), None);
+
+        let finished = r#"data: {"candidates": [{"finishReason": "MAX_TOKENS"}], "usageMetadata": {"promptTokenCount": 3, "candidatesTokenCount": 1, "totalTokenCount": 4}}"#;
+        assert_eq!(parse_stream_line(finished, &mut state).unwrap(), None);
+        assert!(warn_if_truncated(
+            "Gemini",
+            finish_reason(state.finish_reason.as_deref())
+        ));
+        assert_eq!(state.usage.unwrap().total_token_count, 4);
+        assert_eq!(finish_reason(Some("STOP")), Some("STOP"));
+    }
+}
@@ -0,0 +1,256 @@
+//! `provider = "mock"`: deterministic replies derived from the diff, for offline use, demos,
+//! and end-to-end tests of `--ask`, `pr`, and `--message-file` without a network or keys.
+//!
+//! Prompts are still built, so `--usage` reports sizes like a real run would; the counts are
+//! estimates.
+
+use super::context::estimate_tokens;
+use super::prompt_builder::{self, PromptOptions, PromptPair};
+use super::usage::TokenUsage;
+use super::{CancellationToken, LlmClient, cancel, diff_line_counts};
+use crate::FileChange;
+use crate::git::{PrItem, PrSummaryMode};
+use anyhow::Result;
+use std::sync::Mutex;
+
+/// Answers every request locally from the files and commits it is given.
+pub struct MockClient {
+    prompt_options: PromptOptions,
+    usage: Mutex<TokenUsage>,
+}
+
+impl Default for MockClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockClient {
+    pub fn new() -> Self {
+        Self {
+            prompt_options: PromptOptions::default(),
+            usage: Mutex::new(TokenUsage::default()),
+        }
+    }
+
+    pub fn with_prompt_options(mut self, opts: PromptOptions) -> Self {
+        self.prompt_options = opts;
+        self
+    }
+
+    /// Count one request as if `prompts` had been sent and `reply` received.
+    fn reply(
+        &self,
+        prompts: &PromptPair,
+        reply: String,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        cancel::check(cancel)?;
+        let prompt = (estimate_tokens(&prompts.system) + estimate_tokens(&prompts.user)) as u64;
+        let completion = estimate_tokens(&reply) as u64;
+        let mut u = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        u.requests += 1;
+        u.record(prompt, completion, prompt + completion);
+        Ok(reply)
+    }
+}
+
+/// "- Add `src/new.rs` (+12 lines)" or "- Update `src/lib.rs` (2 hunks, +5/-3 lines)".
+fn file_summary(file: &FileChange) -> String {
+    let (added, removed) = diff_line_counts(&file.diff);
+    if file.diff.starts_with("new file mode") || file.diff.contains("\nnew file mode") {
+        return format!("- Add `{}` (+{added} lines)", file.path);
+    }
+    let hunks = file
+        .diff
+        .lines()
+        .filter(|line| line.starts_with("@@"))
+        .count();
+    let plural = if hunks == 1 { "" } else { "s" };
+    format!(
+        "- Update `{}` ({hunks} hunk{plural}, +{added}/-{removed} lines)",
+        file.path
+    )
+}
+
+/// Subject line naming the file, or the count and the directory they share.
+fn commit_subject(files: &[FileChange]) -> String {
+    match files {
+        [] => "Update nothing".to_string(),
+        [file] => format!("Update {}", file.path),
+        _ => {
+            let dirs: Vec<&str> = files
+                .iter()
+                .map(|f| f.path.rsplit_once('/').map_or("", |(dir, _)| dir))
+                .collect();
+            let shared = dirs.iter().all(|d| *d == dirs[0]) && !dirs[0].is_empty();
+            if shared {
+                format!("Update {} files in {}", files.len(), dirs[0])
+            } else {
+                format!("Update {} files", files.len())
+            }
+        }
+    }
+}
+
+impl LlmClient for MockClient {
+    fn validate_model(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn summarize_file(
+        &self,
+        branch: &str,
+        file: &FileChange,
+        file_index: usize,
+        total_files: usize,
+        ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        let prompts = prompt_builder::file_summary_prompt(
+            branch,
+            file,
+            file_index,
+            total_files,
+            ticket_summary,
+            &self.prompt_options,
+        );
+        self.reply(&prompts, file_summary(file), cancel)
+    }
+
+    fn generate_commit_message(
+        &self,
+        branch: &str,
+        files: &[FileChange],
+        ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        let prompts = prompt_builder::commit_message_prompt(
+            branch,
+            files,
+            ticket_summary,
+            &self.prompt_options,
+        );
+        let body: Vec<String> = files
+            .iter()
+            .map(|f| match &f.summary {
+                Some(summary) => summary.trim().to_string(),
+                None => format!("- Touch `{}`", f.path),
+            })
+            .collect();
+        let message = format!("{}\n\n{}", commit_subject(files), body.join("\n"));
+        self.reply(&prompts, message, cancel)
+    }
+
+    fn generate_changelog_entry(
+        &self,
+        files: &[FileChange],
+        commit_message: &str,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        let prompts =
+            prompt_builder::changelog_entry_prompt(files, commit_message, &self.prompt_options);
+        let subject = commit_message.lines().next().unwrap_or("").trim();
+        self.reply(&prompts, format!("Changed: {subject}"), cancel)
+    }
+
+    fn generate_pr_message(
+        &self,
+        base_branch: &str,
+        from_branch: &str,
+        mode: PrSummaryMode,
+        items: &[PrItem],
+        ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        let prompts = prompt_builder::pr_message_prompt(
+            base_branch,
+            from_branch,
+            mode,
+            items,
+            ticket_summary,
+            &self.prompt_options,
+        );
+        let unit = match mode {
+            PrSummaryMode::ByPrs => "pull request",
+            PrSummaryMode::ByCommits => "commit",
+        };
+        let plural = if items.len() == 1 { "" } else { "s" };
+        let changes: Vec<String> = items.iter().map(|i| format!("- {}", i.title)).collect();
+        let message = format!(
+            "## Summary\n\nMerges {} {unit}{plural} from {from_branch} into {base_branch}.\n\n\
+             ## Changes\n\n{}",
+            items.len(),
+            changes.join("\n")
+        );
+        self.reply(&prompts, message, cancel)
+    }
+
+    fn take_and_reset_usage(&self) -> Option<TokenUsage> {
+        let mut u = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        u.take(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FileCategory;
+
+    fn change(path: &str, diff: &str) -> FileChange {
+        FileChange {
+            path: path.into(),
+            category: FileCategory::Main,
+            diff: diff.into(),
+            summary: None,
+            list_only: false,
+            is_deleted: false,
+        }
+    }
+
+    #[test]
+    fn replies_are_derived_from_the_diff() {
+        let client = MockClient::new();
+        let mut files = vec![
+            change(
+                "src/lib.rs",
+                "@@ -1 +1 @@\n-a\n+b\n@@ -9,2 +9,3 @@\n c\n+d\n",
+            ),
+            change(
+                "src/new.rs",
+                "new file mode 100644\n@@ -0,0 +1,2 @@\n+x\n+y\n",
+            ),
+        ];
+        for (i, file) in files.clone().iter().enumerate() {
+            let summary = client
+                .summarize_file("main", file, i, 2, None, None)
+                .unwrap();
+            files[i].summary = Some(summary);
+        }
+        assert_eq!(
+            files[0].summary.as_deref(),
+            Some("- Update `src/lib.rs` (2 hunks, +2/-1 lines)")
+        );
+        assert_eq!(
+            files[1].summary.as_deref(),
+            Some("- Add `src/new.rs` (+2 lines)")
+        );
+
+        let message = client
+            .generate_commit_message("main", &files, None, None)
+            .unwrap();
+        assert!(message.starts_with("Update 2 files in src\n\n- Update `src/lib.rs`"));
+        assert_eq!(
+            client
+                .generate_commit_message("main", &files, None, None)
+                .unwrap(),
+            message,
+            "replies are deterministic"
+        );
+
+        let usage = client.take_and_reset_usage().unwrap();
+        assert_eq!(usage.requests, 4);
+        assert!(usage.prompt_tokens > 0);
+        assert_eq!(usage.cost, None);
+    }
+}
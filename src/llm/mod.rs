@@ -1,12 +1,52 @@
+pub mod anthropic;
+pub mod cancel;
+pub mod cassette;
+pub mod command;
+pub mod context;
+pub mod debug_log;
+pub mod dry_run;
+pub mod error;
+pub mod gemini;
+pub mod mock;
 pub mod ollama;
 pub mod openai;
+pub mod per_step;
 mod prompt_builder;
 mod prompts;
+pub mod retry;
 mod stream;
+mod structured;
+pub mod usage;
 
 use crate::FileChange;
 use crate::git::{PrItem, PrSummaryMode};
-use anyhow::Result;
+use anyhow::{Result, anyhow};
+
+pub use cancel::CancellationToken;
+pub use stream::DEFAULT_MAX_RESPONSE_BYTES;
+pub use usage::TokenUsage;
+
+pub(crate) use prompt_builder::file_summary_prompt;
+pub use prompt_builder::{
+    BranchContext, CategoryWeight, MessageStyle, PromptOptions, Tone, default_category_emphasis,
+    diff_line_counts,
+};
+
+/// Warn when a provider says it stopped at its output-token limit (`finish_reason` or
+/// `done_reason` of "length", Anthropic's `stop_reason` of "max_tokens"), since the text it
+/// returned is cut off mid-sentence.
+///
+/// Returns whether the response was truncated.
+pub(crate) fn warn_if_truncated(provider: &str, reason: Option<&str>) -> bool {
+    if !matches!(reason, Some("length" | "max_tokens")) {
+        return false;
+    }
+    log::warn!(
+        "{provider} stopped at its output token limit, so this message is probably cut off. \
+         Raise the model's max output tokens if it is set low, or use a model with a larger limit."
+    );
+    true
+}
 
 /// Trait for talking to an LLM (real backend).
 pub trait LlmClient: Send + Sync {
@@ -21,16 +61,42 @@ pub trait LlmClient: Send + Sync {
         file_index: usize,
         total_files: usize,
         ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
     ) -> Result<String>;
 
+    /// Summarize several small files in one request.
+    ///
+    /// Returns one entry per input file; `None` means the file still needs its own
+    /// `summarize_file` call. Clients without batch support return `None` for every file.
+    fn summarize_batch(
+        &self,
+        _branch: &str,
+        files: &[&FileChange],
+        _ticket_summary: Option<&str>,
+        _cancel: Option<&CancellationToken>,
+    ) -> Result<Vec<Option<String>>> {
+        Ok(vec![None; files.len()])
+    }
+
     /// Generate the final commit message from file summaries + metadata.
     fn generate_commit_message(
         &self,
         branch: &str,
         files: &[FileChange],
         ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
     ) -> Result<String>;
 
+    /// Generate a one-line `Category: entry` changelog line for a finished commit message.
+    fn generate_changelog_entry(
+        &self,
+        _files: &[FileChange],
+        _commit_message: &str,
+        _cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        Err(anyhow!("This client cannot generate changelog entries"))
+    }
+
     /// PR mode: generate a PR description from commit/PR messages.
     fn generate_pr_message(
         &self,
@@ -39,10 +105,17 @@ pub trait LlmClient: Send + Sync {
         mode: PrSummaryMode,
         items: &[PrItem],
         ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
     ) -> Result<String>;
 
     /// Take aggregated token usage from the client, resetting counters.
-    fn take_and_reset_usage(&self) -> Option<(u64, u64, u64)> {
+    fn take_and_reset_usage(&self) -> Option<TokenUsage> {
+        None
+    }
+
+    /// The provider, endpoint, and model that write this client's per-file summaries, used to
+    /// key the summary cache. `None` (the default) keeps its summaries out of the cache.
+    fn summary_source(&self) -> Option<String> {
         None
     }
 }
@@ -1,17 +1,24 @@
-use anyhow::{Result, anyhow};
+use anyhow::{Context, Result, anyhow};
 use log;
 use musli::json;
 use musli::{Decode, Encode};
 use reqwest::StatusCode;
+use reqwest::header::HeaderMap;
 use reqwest::blocking::Client;
 use std::io::BufReader;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 
 use crate::FileChange;
 use crate::git::{PrItem, PrSummaryMode};
 
-use super::stream::read_stream_to_string;
-use super::{LlmClient, prompt_builder};
+use super::cancel::{self, CancellationToken};
+use super::debug_log::DebugLog;
+use super::error::ApiError;
+use super::retry::RetryPolicy;
+use super::usage::TokenUsage;
+use super::prompt_builder::PromptOptions;
+use super::stream::{DEFAULT_MAX_RESPONSE_BYTES, read_body_capped, read_stream_to_string};
+use super::{LlmClient, prompt_builder, structured, warn_if_truncated};
 
 #[derive(Debug, Encode, Decode)]
 struct OllamaMessage {
@@ -22,12 +29,29 @@ struct OllamaMessage {
 #[derive(Debug, Encode, Decode)]
 struct OllamaChatResponse {
     message: OllamaMessage,
+    #[musli(default)]
+    done_reason: Option<String>,
 }
 
 #[derive(Debug, Decode)]
 struct OllamaStreamResponse {
     message: Option<OllamaMessage>,
     done: Option<bool>,
+    #[musli(default)]
+    done_reason: Option<String>,
+    /// Prompt and reply token counts, sent on the final (`done`) chunk
+    #[musli(default)]
+    prompt_eval_count: Option<u32>,
+    #[musli(default)]
+    eval_count: Option<u32>,
+}
+
+/// What a streamed reply reports besides its text, all on the final chunk.
+#[derive(Default)]
+struct StreamState {
+    done_reason: Option<String>,
+    prompt_tokens: u32,
+    completion_tokens: u32,
 }
 
 #[derive(Debug, Decode)]
@@ -47,38 +71,151 @@ struct OllamaTagsResponse {
     models: Vec<OllamaTagModel>,
 }
 
+/// Ollama's `options`: sampling settings, and `num_predict` for the output-token cap.
+#[derive(Debug, Encode)]
+struct ChatOptions {
+    #[musli(default, skip_encoding_if = Option::is_none)]
+    temperature: Option<f32>,
+    #[musli(default, skip_encoding_if = Option::is_none)]
+    top_p: Option<f32>,
+    #[musli(default, skip_encoding_if = Option::is_none)]
+    num_predict: Option<u32>,
+}
+
+const CHAT_PATH: &str = "/api/chat";
+
 /// Synchronous Ollama client using /api/chat.
 pub struct OllamaClient {
     http: Client,
     base_url: String,
     model: String,
+    extra_headers: HeaderMap,
     stream: bool,
+    prompt_options: PromptOptions,
+    debug_log: Option<Arc<DebugLog>>,
+    max_response_bytes: usize,
+    retry: RetryPolicy,
+    temperature: Option<f32>,
+    top_p: Option<f32>,
+    max_output_tokens: Option<u32>,
     usage: Mutex<TokenUsage>,
 }
 
-#[derive(Default)]
-struct TokenUsage {
-    prompt_tokens: u64,
-    completion_tokens: u64,
-    total_tokens: u64,
-}
-
 impl OllamaClient {
-    pub fn new(base_url: impl Into<String>, model: impl Into<String>, stream: bool) -> Self {
-        let http = Client::builder()
-            .build()
-            .expect("failed to build HTTP client");
+    /// `http` is usually [`crate::http::shared_client`], shared with other backends.
+    pub fn new(
+        http: Client,
+        base_url: impl Into<String>,
+        model: impl Into<String>,
+        stream: bool,
+    ) -> Self {
         Self {
             http,
             base_url: base_url.into().trim_end_matches('/').to_string(),
             model: model.into(),
+            extra_headers: HeaderMap::new(),
             stream,
+            prompt_options: PromptOptions::default(),
+            debug_log: None,
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            retry: RetryPolicy::default(),
+            temperature: None,
+            top_p: None,
+            max_output_tokens: None,
             usage: Mutex::new(TokenUsage::default()),
         }
     }
 
-    /// Internal helper to talk to /api/chat.
-    fn chat(&self, system_prompt: String, user_prompt: String, stream: bool) -> Result<String> {
+    /// Extra headers sent with every request (e.g. for an auth proxy).
+    pub fn with_headers(mut self, headers: HeaderMap) -> Self {
+        self.extra_headers = headers;
+        self
+    }
+
+    /// Set the options used when building prompts.
+    pub fn with_prompt_options(mut self, opts: PromptOptions) -> Self {
+        self.prompt_options = opts;
+        self
+    }
+
+    /// Record every prompt/response pair to the given debug log.
+    pub fn with_debug_log(mut self, debug_log: Option<Arc<DebugLog>>) -> Self {
+        self.debug_log = debug_log;
+        self
+    }
+
+    /// Cap on a single response's size (see [`DEFAULT_MAX_RESPONSE_BYTES`]).
+    pub fn with_max_response_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_response_bytes = max_bytes;
+        self
+    }
+
+    /// Retry policy for rate limits, server errors, and dropped connections.
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Sampling temperature (`options.temperature`).
+    pub fn with_temperature(mut self, temperature: Option<f32>) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    /// Nucleus sampling (`options.top_p`).
+    pub fn with_top_p(mut self, top_p: Option<f32>) -> Self {
+        self.top_p = top_p;
+        self
+    }
+
+    /// Cap on generated tokens (`options.num_predict`).
+    pub fn with_max_output_tokens(mut self, max_tokens: Option<u32>) -> Self {
+        self.max_output_tokens = max_tokens;
+        self
+    }
+
+    /// The `options` object, left out entirely when nothing is set so the model's defaults apply.
+    fn chat_options(&self) -> Option<ChatOptions> {
+        if self.temperature.is_none() && self.top_p.is_none() && self.max_output_tokens.is_none() {
+            return None;
+        }
+        Some(ChatOptions {
+            temperature: self.temperature,
+            top_p: self.top_p,
+            num_predict: self.max_output_tokens,
+        })
+    }
+
+    /// Send a prompt pair, recording it to the debug log when enabled.
+    fn request(
+        &self,
+        step: &str,
+        system: String,
+        user: String,
+        stream: bool,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        cancel::check(cancel)?;
+        self.usage.lock().unwrap_or_else(|e| e.into_inner()).requests += 1;
+        let structured = step == "commit_message" && self.prompt_options.structured_output;
+        let Some(debug_log) = &self.debug_log else {
+            return self.chat(system, user, stream, structured, cancel);
+        };
+
+        let result = self.chat(system.clone(), user.clone(), stream, structured, cancel);
+        debug_log.record("ollama", &self.model, step, &system, &user, &result);
+        result
+    }
+
+    /// Internal helper to talk to /api/chat; `structured` asks for a JSON reply.
+    fn chat(
+        &self,
+        system_prompt: String,
+        user_prompt: String,
+        stream: bool,
+        structured: bool,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
         // Request structs we encode with musli::json.
         #[derive(Debug, Encode)]
         struct ChatMessage {
@@ -91,6 +228,10 @@ impl OllamaClient {
             model: String,
             stream: bool,
             messages: Vec<ChatMessage>,
+            #[musli(default, skip_encoding_if = Option::is_none)]
+            format: Option<String>,
+            #[musli(default, skip_encoding_if = Option::is_none)]
+            options: Option<ChatOptions>,
         }
 
         let req_body = ChatRequest {
@@ -106,6 +247,8 @@ impl OllamaClient {
                     content: user_prompt,
                 },
             ],
+            format: structured.then(|| "json".to_string()),
+            options: self.chat_options(),
         };
 
         let body_str = json::to_string(&req_body)
@@ -113,26 +256,42 @@ impl OllamaClient {
 
         log::trace!("Ollama request body: {body_str}");
 
-        let url = format!("{}/api/chat", self.base_url);
+        let url = self.chat_url();
 
         let resp = self
-            .http
-            .post(&url)
-            .header(reqwest::header::CONTENT_TYPE, "application/json")
-            .body(body_str)
-            .send()
-            .map_err(|e| anyhow!("Error calling Ollama at {url}: {e}"))?
-            .error_for_status()
-            .map_err(|e| anyhow!("Ollama HTTP error from {url}: {e}"))?;
+            .retry
+            .send("Ollama", cancel, || {
+                self.http
+                    .post(&url)
+                    .headers(self.extra_headers.clone())
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .body(body_str.clone())
+                    .send()
+            })
+            .with_context(|| format!("Error calling Ollama at {url}"))?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().unwrap_or_default();
+            return Err(ApiError::new("Ollama", status.as_u16(), body))
+                .with_context(|| format!("Ollama request to {url} failed"));
+        }
 
         if stream {
             let reader = BufReader::new(resp);
-            return read_stream_to_string(reader, parse_stream_line);
+            let mut state = StreamState::default();
+            let content = read_stream_to_string(reader, cancel, self.max_response_bytes, |line| {
+                parse_stream_line(line, &mut state)
+            })?;
+            warn_if_truncated("Ollama", state.done_reason.as_deref());
+            let (prompt, completion) = (state.prompt_tokens as u64, state.completion_tokens as u64);
+            let mut u = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+            u.record(prompt, completion, prompt + completion);
+            return Ok(content);
         }
 
-        let resp_text = resp
-            .text()
-            .map_err(|e| anyhow!("Failed to read Ollama response body: {e}"))?;
+        let resp_text = read_body_capped(resp, self.max_response_bytes)
+            .context("Failed to read Ollama response body")?;
 
         log::trace!("Ollama raw JSON response: {resp_text}");
 
@@ -140,6 +299,8 @@ impl OllamaClient {
         struct OllamaChatWrapper {
             message: OllamaMessage,
             usage: Option<OllamaUsage>,
+            #[musli(default)]
+            done_reason: Option<String>,
         }
 
         // First try decoding into the wrapper. If that fails fall back to the
@@ -153,31 +314,50 @@ impl OllamaClient {
                         log::warn!("usage mutex was poisoned, recovering token counters");
                         e.into_inner()
                     });
-                    u.prompt_tokens += usage.prompt_tokens.unwrap_or(0) as u64;
-                    u.completion_tokens += usage.completion_tokens.unwrap_or(0) as u64;
-                    u.total_tokens += usage.total_tokens.unwrap_or(0) as u64;
+                    u.record(
+                        usage.prompt_tokens.unwrap_or(0) as u64,
+                        usage.completion_tokens.unwrap_or(0) as u64,
+                        usage.total_tokens.unwrap_or(0) as u64,
+                    );
                 }
+                warn_if_truncated("Ollama", parsed.done_reason.as_deref());
                 Ok(parsed.message.content.trim().to_string())
             }
             Err(_) => {
                 // Fallback to the simple response shape.
                 let parsed: OllamaChatResponse = json::from_str(&resp_text)
                     .map_err(|e| anyhow!("Failed to decode Ollama JSON: {e}"))?;
+                warn_if_truncated("Ollama", parsed.done_reason.as_deref());
                 Ok(parsed.message.content.trim().to_string())
             }
         }
     }
 
+    /// Server root, with a full `/api/chat` endpoint in the base URL stripped off.
+    fn server_root(&self) -> &str {
+        self.base_url
+            .strip_suffix(CHAT_PATH)
+            .unwrap_or(&self.base_url)
+    }
+
+    fn chat_url(&self) -> String {
+        format!("{}{CHAT_PATH}", self.server_root())
+    }
+
     fn tags_url(&self) -> String {
-        format!("{}/api/tags", self.base_url)
+        format!("{}/api/tags", self.server_root())
     }
 }
 
-fn parse_stream_line(line: &str) -> Result<Option<String>> {
+/// Parse one streamed JSON line, recording `done_reason` and the token counts from the final one.
+fn parse_stream_line(line: &str, state: &mut StreamState) -> Result<Option<String>> {
     let parsed: OllamaStreamResponse =
         json::from_str(line).map_err(|e| anyhow!("Failed to decode Ollama stream JSON: {e}"))?;
 
     if parsed.done.unwrap_or(false) {
+        state.done_reason = parsed.done_reason;
+        state.prompt_tokens = parsed.prompt_eval_count.unwrap_or(0);
+        state.completion_tokens = parsed.eval_count.unwrap_or(0);
         return Ok(None);
     }
 
@@ -198,6 +378,7 @@ impl LlmClient for OllamaClient {
         let resp = self
             .http
             .get(&url)
+            .headers(self.extra_headers.clone())
             .send()
             .map_err(|e| anyhow!("Error calling Ollama at {url}: {e}"))?;
 
@@ -247,6 +428,7 @@ impl LlmClient for OllamaClient {
         file_index: usize,
         total_files: usize,
         ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
     ) -> Result<String> {
         let prompts = prompt_builder::file_summary_prompt(
             branch,
@@ -254,8 +436,27 @@ impl LlmClient for OllamaClient {
             file_index,
             total_files,
             ticket_summary,
+            &self.prompt_options,
         );
-        self.chat(prompts.system, prompts.user, false)
+        self.request("file_summary", prompts.system, prompts.user, false, cancel)
+    }
+
+    fn summarize_batch(
+        &self,
+        branch: &str,
+        files: &[&FileChange],
+        ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<Vec<Option<String>>> {
+        let prompts = prompt_builder::batch_summary_prompt(
+            branch,
+            files,
+            ticket_summary,
+            &self.prompt_options,
+        );
+        let content = self.request("batch_summary", prompts.system, prompts.user, false, cancel)?;
+        let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+        Ok(prompt_builder::split_batch_summary(&content, &paths))
     }
 
     fn generate_commit_message(
@@ -263,10 +464,36 @@ impl LlmClient for OllamaClient {
         branch: &str,
         files: &[FileChange],
         ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
     ) -> Result<String> {
-        let prompts = prompt_builder::commit_message_prompt(branch, files, ticket_summary);
-        let content = self.chat(prompts.system, prompts.user, self.stream)?;
-        Ok(content)
+        let prompts = prompt_builder::commit_message_prompt(
+            branch,
+            files,
+            ticket_summary,
+            &self.prompt_options,
+        );
+        let content = self.request(
+            "commit_message",
+            prompts.system,
+            prompts.user,
+            self.stream,
+            cancel,
+        )?;
+        Ok(structured::finish_commit_message(
+            content,
+            &self.prompt_options,
+        ))
+    }
+
+    fn generate_changelog_entry(
+        &self,
+        files: &[FileChange],
+        commit_message: &str,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        let prompts =
+            prompt_builder::changelog_entry_prompt(files, commit_message, &self.prompt_options);
+        self.request("changelog", prompts.system, prompts.user, false, cancel)
     }
 
     fn generate_pr_message(
@@ -276,6 +503,7 @@ impl LlmClient for OllamaClient {
         mode: PrSummaryMode,
         items: &[PrItem],
         ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
     ) -> Result<String> {
         let prompts = prompt_builder::pr_message_prompt(
             base_branch,
@@ -283,23 +511,29 @@ impl LlmClient for OllamaClient {
             mode,
             items,
             ticket_summary,
+            &self.prompt_options,
         );
-        let content = self.chat(prompts.system, prompts.user, self.stream)?;
+        let content = self.request(
+            "pr_message",
+            prompts.system,
+            prompts.user,
+            self.stream,
+            cancel,
+        )?;
         Ok(content)
     }
-    fn take_and_reset_usage(&self) -> Option<(u64, u64, u64)> {
+
+    fn summary_source(&self) -> Option<String> {
+        Some(format!("ollama:{}:{}", self.base_url, self.model))
+    }
+
+    fn take_and_reset_usage(&self) -> Option<TokenUsage> {
         let mut u = self.usage.lock().unwrap_or_else(|e| {
             log::warn!("usage mutex was poisoned, recovering token counters");
             e.into_inner()
         });
-
-        if u.total_tokens > 0 {
-            let res = (u.prompt_tokens, u.completion_tokens, u.total_tokens);
-            *u = TokenUsage::default();
-            Some(res)
-        } else {
-            None
-        }
+        // local models cost nothing worth estimating
+        u.take(None)
     }
 }
 
@@ -307,12 +541,68 @@ impl LlmClient for OllamaClient {
 mod tests {
     use super::*;
 
+    #[test]
+    fn records_done_reason_and_usage_from_final_stream_line() {
+        let mut state = StreamState::default();
+        let chunk = r#"{"message":{"role":"assistant","content":"Fix"},"done":false}"#;
+        assert_eq!(
+            parse_stream_line(chunk, &mut state).unwrap().as_deref(),
+            Some("Fix")
+        );
+        assert_eq!(state.done_reason, None);
+
+        let last = r#"{"message":{"role":"assistant","content":""},"done":true,"done_reason":"length","prompt_eval_count":26,"eval_count":290}"#;
+        assert_eq!(parse_stream_line(last, &mut state).unwrap(), None);
+        assert_eq!(state.done_reason.as_deref(), Some("length"));
+        assert_eq!((state.prompt_tokens, state.completion_tokens), (26, 290));
+    }
+
+    #[test]
+    fn decodes_responses_without_done_reason() {
+        let body = r#"{"message":{"role":"assistant","content":"Fix"},"done":true}"#;
+        let parsed: OllamaChatResponse = json::from_str(body).unwrap();
+        assert_eq!(parsed.done_reason, None);
+    }
+
+    #[test]
+    fn encodes_only_the_sampling_options_that_are_set() {
+        let client = OllamaClient::new(Client::new(), "http://localhost:11434", "llama3", false);
+        assert!(client.chat_options().is_none());
+
+        let client = client
+            .with_temperature(Some(0.0))
+            .with_max_output_tokens(Some(256));
+        let options = json::to_string(&client.chat_options().unwrap()).unwrap();
+        assert_eq!(options, r#"{"temperature":0.0,"num_predict":256}"#);
+    }
+
     #[test]
     fn trims_trailing_slash_in_tags_url() {
-        let client = OllamaClient::new("http://localhost:11434/", "qwen3-coder:30b", false);
+        let client = OllamaClient::new(
+            Client::new(),
+            "http://localhost:11434/",
+            "qwen3-coder:30b",
+            false,
+        );
         assert_eq!(client.tags_url(), "http://localhost:11434/api/tags");
     }
 
+    #[test]
+    fn uses_full_chat_path_verbatim() {
+        let client = OllamaClient::new(
+            Client::new(),
+            "https://gw.example/ollama/api/chat",
+            "llama3",
+            false,
+        );
+        assert_eq!(client.chat_url(), "https://gw.example/ollama/api/chat");
+        assert_eq!(client.tags_url(), "https://gw.example/ollama/api/tags");
+
+        let client =
+            OllamaClient::new(Client::new(), "https://gw.example/ollama/", "llama3", false);
+        assert_eq!(client.chat_url(), "https://gw.example/ollama/api/chat");
+    }
+
     #[test]
     fn decodes_ollama_tags_payload() {
         let body = r#"{"models":[{"name":"qwen3-coder:30b"},{"name":"gpt-oss:20b"}]}"#;
@@ -1,15 +1,26 @@
-use super::LlmClient;
-use super::prompt_builder;
-use super::stream::read_stream_to_string;
+use super::{LlmClient, structured, warn_if_truncated};
+use super::cancel::{self, CancellationToken};
+use super::debug_log::DebugLog;
+use super::error::ApiError;
+use super::retry::RetryPolicy;
+use super::usage::TokenUsage;
+use super::prompt_builder::{self, PromptOptions};
+use super::stream::{
+    DEFAULT_MAX_RESPONSE_BYTES, read_body_capped, read_stream_to_string, sse_data,
+};
 use crate::FileChange;
 use crate::git::{PrItem, PrSummaryMode};
 use anyhow::{Context, Result, anyhow};
 use reqwest::StatusCode;
-use reqwest::blocking::Client;
+use reqwest::header::HeaderMap;
+use reqwest::blocking::{Client, RequestBuilder};
 use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
 use std::io::BufReader;
-use std::time::Duration;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
+
+const CHAT_COMPLETIONS_PATH: &str = "/chat/completions";
+const RESPONSES_PATH: &str = "/responses";
 
 /// Minimal request/response structs for OpenAI Chat Completions API.
 #[derive(Serialize)]
@@ -17,6 +28,23 @@ struct ChatRequest {
     model: String,
     messages: Vec<ChatMessage>,
     stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    /// Output cap for standard chat models
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    /// Output cap for reasoning models (includes reasoning tokens)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_completion_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reasoning_effort: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<Value>,
+    /// `{"include_usage": true}` so a stream ends with a usage chunk
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<Value>,
 }
 
 #[derive(Serialize)]
@@ -34,6 +62,7 @@ struct ChatResponse {
 #[derive(Deserialize)]
 struct ChatChoice {
     message: ChatMessageResponse,
+    finish_reason: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -50,12 +79,22 @@ struct ChatUsage {
 
 #[derive(Deserialize)]
 struct StreamResponse {
+    #[serde(default)]
     choices: Vec<StreamChoice>,
+    usage: Option<ChatUsage>,
+}
+
+/// What a chat stream reports besides text: why it stopped, and (last) its token usage.
+#[derive(Default)]
+struct ChatStreamState {
+    finish_reason: Option<String>,
+    usage: Option<ChatUsage>,
 }
 
 #[derive(Deserialize)]
 struct StreamChoice {
     delta: StreamDelta,
+    finish_reason: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -63,59 +102,584 @@ struct StreamDelta {
     content: Option<String>,
 }
 
+/// Minimal request/response structs for the OpenAI Responses API.
+#[derive(Serialize)]
+struct ResponsesRequest {
+    model: String,
+    instructions: String,
+    input: String,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_output_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reasoning: Option<ResponsesReasoning>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<Value>,
+}
+
+#[derive(Serialize)]
+struct ResponsesReasoning {
+    effort: String,
+}
+
+#[derive(Deserialize)]
+struct ResponsesResponse {
+    #[serde(default)]
+    output: Vec<ResponsesOutputItem>,
+    usage: Option<ResponsesUsage>,
+    incomplete_details: Option<ResponsesIncomplete>,
+    error: Option<ResponsesError>,
+}
+
+#[derive(Deserialize)]
+struct ResponsesIncomplete {
+    reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ResponsesError {
+    #[serde(default)]
+    code: Option<String>,
+    #[serde(default)]
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct ResponsesOutputItem {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    content: Vec<ResponsesContent>,
+}
+
+#[derive(Deserialize)]
+struct ResponsesContent {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct ResponsesUsage {
+    input_tokens: u32,
+    output_tokens: u32,
+    total_tokens: u32,
+}
+
+/// One Responses API stream event: text deltas, the final `response`, or a top-level error
+/// (whose `code`/`message` sit on the event itself).
+#[derive(Deserialize)]
+struct ResponsesStreamEvent {
+    #[serde(rename = "type")]
+    kind: String,
+    delta: Option<String>,
+    response: Option<ResponsesResponse>,
+    #[serde(default)]
+    code: Option<String>,
+    #[serde(default)]
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct ModelList {
+    #[serde(default)]
+    data: Vec<ModelListEntry>,
+}
+
+#[derive(Deserialize)]
+struct ModelListEntry {
+    id: String,
+}
+
+/// A hosted or local service that speaks the OpenAI API (`provider = "openai-compatible"`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompatiblePreset {
+    /// Name used for `preset` in config.
+    pub name: &'static str,
+    /// Display name used in logs and errors.
+    pub label: &'static str,
+    /// API root, used verbatim (`/chat/completions` is appended directly).
+    pub base_url: &'static str,
+    /// Env var the key is read from when no commitbot key is set; `None` if no key is needed.
+    pub key_env: Option<&'static str>,
+    /// Header that carries the raw key; `None` sends `Authorization: Bearer`.
+    pub auth_header: Option<&'static str>,
+    /// Headers the service expects on every request; `[headers]` entries take precedence.
+    pub headers: &'static [(&'static str, &'static str)],
+}
+
+/// Built-in presets for `provider = "openai-compatible"`.
+pub const COMPATIBLE_PRESETS: &[CompatiblePreset] = &[
+    CompatiblePreset {
+        name: "groq",
+        label: "Groq",
+        base_url: "https://api.groq.com/openai/v1",
+        key_env: Some("GROQ_API_KEY"),
+        auth_header: None,
+        headers: &[],
+    },
+    CompatiblePreset {
+        name: "together",
+        label: "Together",
+        base_url: "https://api.together.xyz/v1",
+        key_env: Some("TOGETHER_API_KEY"),
+        auth_header: None,
+        headers: &[],
+    },
+    CompatiblePreset {
+        name: "mistral",
+        label: "Mistral",
+        base_url: "https://api.mistral.ai/v1",
+        key_env: Some("MISTRAL_API_KEY"),
+        auth_header: None,
+        headers: &[],
+    },
+    CompatiblePreset {
+        name: "lmstudio",
+        label: "LM Studio",
+        base_url: "http://localhost:1234/v1",
+        key_env: None,
+        auth_header: None,
+        headers: &[],
+    },
+    CompatiblePreset {
+        name: "openrouter",
+        label: "OpenRouter",
+        base_url: "https://openrouter.ai/api/v1",
+        key_env: Some("OPENROUTER_API_KEY"),
+        auth_header: None,
+        // app attribution: https://openrouter.ai/docs/api-reference/overview#headers
+        headers: &[
+            ("HTTP-Referer", "https://github.com/MikeGarde/commitbot"),
+            ("X-Title", "commitbot"),
+        ],
+    },
+];
+
+/// The preset a user-supplied name refers to, ignoring case and `-`, `_`, or spaces
+/// (so `LM Studio` and `lm-studio` both mean "lmstudio").
+pub fn compatible_preset(name: &str) -> Option<&'static CompatiblePreset> {
+    let name: String = name
+        .chars()
+        .filter(|c| !matches!(c, '-' | '_' | ' '))
+        .collect::<String>()
+        .to_lowercase();
+    COMPATIBLE_PRESETS.iter().find(|p| p.name == name)
+}
+
+/// Which OpenAI endpoint family to talk to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenAiApi {
+    /// `/v1/chat/completions` (default)
+    Chat,
+    /// `/v1/responses`
+    Responses,
+}
+
+impl OpenAiApi {
+    /// Parse a config value such as "chat" or "responses".
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "chat" | "chat_completions" | "chat-completions" => Ok(OpenAiApi::Chat),
+            "responses" => Ok(OpenAiApi::Responses),
+            other => Err(anyhow!(
+                "Unknown OpenAI api: {other} (expected \"chat\" or \"responses\")"
+            )),
+        }
+    }
+}
+
+/// Request parameters differ between reasoning models and standard chat models.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelFamily {
+    /// o-series and gpt-5 models: no `temperature`; `max_completion_tokens` and
+    /// `reasoning_effort` instead of `max_tokens`
+    Reasoning,
+    /// Everything else (gpt-4o, gpt-4.1, gateway-hosted open models, ...)
+    Chat,
+}
+
+impl ModelFamily {
+    /// Classify a model by name, ignoring any `provider/` prefix a gateway adds.
+    pub fn of(model: &str) -> Self {
+        let name = model.rsplit('/').next().unwrap_or(model).to_lowercase();
+        let o_series = name
+            .strip_prefix('o')
+            .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()));
+        // gpt-5-chat-* is the non-reasoning variant of gpt-5.
+        let gpt5 = name.starts_with("gpt-5") && !name.starts_with("gpt-5-chat");
+        if o_series || gpt5 {
+            ModelFamily::Reasoning
+        } else {
+            ModelFamily::Chat
+        }
+    }
+
+    /// Chat Completions role for the system prompt: reasoning models take it as a
+    /// `developer` message, and the o1 previews accept neither, so `None` folds it into the
+    /// user message.
+    pub fn instruction_role(model: &str) -> Option<&'static str> {
+        let name = model.rsplit('/').next().unwrap_or(model).to_lowercase();
+        if name.starts_with("o1-preview") || name.starts_with("o1-mini") {
+            return None;
+        }
+        match ModelFamily::of(model) {
+            ModelFamily::Reasoning => Some("developer"),
+            ModelFamily::Chat => Some("system"),
+        }
+    }
+}
+
+/// `api-version` sent to Azure OpenAI when none is configured.
+pub const AZURE_DEFAULT_API_VERSION: &str = "2024-10-21";
+
 /// OpenAI-based implementation of LlmClient.
 pub struct OpenAiClient {
     client: Client,
     api_key: String,
     model: String,
     api_base_url: String,
+    organization: Option<String>,
+    project: Option<String>,
+    extra_headers: HeaderMap,
     stream: bool,
+    api: OpenAiApi,
+    prompt_options: PromptOptions,
+    debug_log: Option<Arc<DebugLog>>,
+    max_response_bytes: usize,
+    retry: RetryPolicy,
+    temperature: Option<f32>,
+    top_p: Option<f32>,
+    max_output_tokens: Option<u32>,
+    reasoning_effort: Option<String>,
+    azure_api_version: Option<String>,
+    compatible: Option<Compatible>,
     usage: Mutex<TokenUsage>,
 }
 
-#[derive(Default)]
-struct TokenUsage {
-    prompt_tokens: u64,
-    completion_tokens: u64,
-    total_tokens: u64,
+/// Settings for an OpenAI-compatible service (see [`OpenAiClient::with_compatible`]).
+struct Compatible {
+    label: &'static str,
+    auth_header: Option<String>,
 }
 
 impl OpenAiClient {
-    pub fn new(api_key: String, model: String, api_base_url: String, stream: bool) -> Self {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(90))
-            .build()
-            .expect("failed to build HTTP client");
-
+    /// `client` is usually [`crate::http::shared_client`], shared with other backends.
+    pub fn new(
+        client: Client,
+        api_key: String,
+        model: String,
+        api_base_url: String,
+        stream: bool,
+    ) -> Self {
         OpenAiClient {
             client,
             api_key,
             model,
             api_base_url: api_base_url.trim_end_matches('/').to_string(),
+            organization: None,
+            project: None,
+            extra_headers: HeaderMap::new(),
             stream,
+            api: OpenAiApi::Chat,
+            prompt_options: PromptOptions::default(),
+            debug_log: None,
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            retry: RetryPolicy::default(),
+            temperature: None,
+            top_p: None,
+            max_output_tokens: None,
+            reasoning_effort: None,
+            azure_api_version: None,
+            compatible: None,
             usage: Mutex::new(TokenUsage::default()),
         }
     }
 
+    /// Select which OpenAI endpoint family requests are sent to.
+    pub fn with_api(mut self, api: OpenAiApi) -> Self {
+        self.api = api;
+        self
+    }
+
+    /// Send `OpenAI-Organization` on every request (billing attribution).
+    pub fn with_organization(mut self, organization: Option<String>) -> Self {
+        self.organization = organization;
+        self
+    }
+
+    /// Send `OpenAI-Project` on every request (project-scoped keys).
+    pub fn with_project(mut self, project: Option<String>) -> Self {
+        self.project = project;
+        self
+    }
+
+    /// Extra headers sent with every request (e.g. for an API gateway).
+    pub fn with_headers(mut self, headers: HeaderMap) -> Self {
+        self.extra_headers = headers;
+        self
+    }
+
+    /// Set the options used when building prompts.
+    pub fn with_prompt_options(mut self, opts: PromptOptions) -> Self {
+        self.prompt_options = opts;
+        self
+    }
+
+    /// Record every prompt/response pair to the given debug log.
+    pub fn with_debug_log(mut self, debug_log: Option<Arc<DebugLog>>) -> Self {
+        self.debug_log = debug_log;
+        self
+    }
+
+    /// Cap on a single response's size (see [`DEFAULT_MAX_RESPONSE_BYTES`]).
+    pub fn with_max_response_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_response_bytes = max_bytes;
+        self
+    }
+
+    /// Retry policy for rate limits, server errors, and dropped connections.
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Sampling temperature; not sent to reasoning models, which reject it.
+    pub fn with_temperature(mut self, temperature: Option<f32>) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    /// Nucleus sampling (`top_p`); like temperature, not sent to reasoning models.
+    pub fn with_top_p(mut self, top_p: Option<f32>) -> Self {
+        self.top_p = top_p;
+        self
+    }
+
+    /// Cap on generated tokens, sent as the field the model family expects.
+    pub fn with_max_output_tokens(mut self, max_tokens: Option<u32>) -> Self {
+        self.max_output_tokens = max_tokens;
+        self
+    }
+
+    /// `reasoning_effort` for reasoning models (e.g. "low"); ignored for chat models.
+    pub fn with_reasoning_effort(mut self, effort: Option<String>) -> Self {
+        self.reasoning_effort = effort;
+        self
+    }
+
+    /// Talk to an Azure OpenAI resource instead: the base URL is the resource endpoint, the
+    /// model is the deployment name, and the key goes in an `api-key` header.
+    pub fn with_azure(mut self, api_version: String) -> Self {
+        self.azure_api_version = Some(api_version);
+        self
+    }
+
+    /// Talk to an OpenAI-compatible service instead: the base URL is its API root, used
+    /// without guessing at `/v1`; the key is sent in `auth_header` (default `Authorization:
+    /// Bearer`) and left out entirely when empty. `label` names the service in errors.
+    pub fn with_compatible(mut self, label: &'static str, auth_header: Option<String>) -> Self {
+        self.compatible = Some(Compatible { label, auth_header });
+        self
+    }
+
+    fn provider_name(&self) -> &'static str {
+        if self.azure_api_version.is_some() {
+            "Azure OpenAI"
+        } else if let Some(compatible) = &self.compatible {
+            compatible.label
+        } else {
+            "OpenAI"
+        }
+    }
+
+    /// Chat Completions request with the parameters `self.model`'s family accepts.
+    fn chat_request(&self, system: String, user: String, stream: bool) -> ChatRequest {
+        let reasoning = ModelFamily::of(&self.model) == ModelFamily::Reasoning;
+        if reasoning && (self.temperature.is_some() || self.top_p.is_some()) {
+            log::debug!(
+                "Not sending temperature or top_p to reasoning model {}",
+                self.model
+            );
+        }
+        let messages = match ModelFamily::instruction_role(&self.model) {
+            Some(role) => vec![
+                ChatMessage {
+                    role: role.into(),
+                    content: system,
+                },
+                ChatMessage {
+                    role: "user".into(),
+                    content: user,
+                },
+            ],
+            None => vec![ChatMessage {
+                role: "user".into(),
+                content: format!("{system}\n\n{user}"),
+            }],
+        };
+        ChatRequest {
+            model: self.model.clone(),
+            messages,
+            stream,
+            temperature: self.temperature.filter(|_| !reasoning),
+            top_p: self.top_p.filter(|_| !reasoning),
+            max_tokens: self.max_output_tokens.filter(|_| !reasoning),
+            max_completion_tokens: self.max_output_tokens.filter(|_| reasoning),
+            reasoning_effort: self.reasoning_effort.clone().filter(|_| reasoning),
+            response_format: None,
+            // other services may reject the field, so only ask OpenAI and Azure
+            stream_options: (stream && self.compatible.is_none())
+                .then(|| json!({"include_usage": true})),
+        }
+    }
+
+    /// Responses API request with the parameters `self.model`'s family accepts.
+    fn responses_request(&self, system: String, user: String, stream: bool) -> ResponsesRequest {
+        let reasoning = ModelFamily::of(&self.model) == ModelFamily::Reasoning;
+        ResponsesRequest {
+            model: self.model.clone(),
+            instructions: system,
+            input: user,
+            stream,
+            temperature: self.temperature.filter(|_| !reasoning),
+            top_p: self.top_p.filter(|_| !reasoning),
+            max_output_tokens: self.max_output_tokens,
+            reasoning: self
+                .reasoning_effort
+                .clone()
+                .filter(|_| reasoning)
+                .map(|effort| ResponsesReasoning { effort }),
+            text: None,
+        }
+    }
+
+    /// Versioned API root (e.g. `https://api.openai.com/v1`).
+    ///
+    /// A base URL that already names a full endpoint is used up to that endpoint as-is;
+    /// otherwise `/v1` is appended unless already present (or the service is
+    /// OpenAI-compatible, whose base URL is the root).
+    fn api_root(&self) -> String {
+        let base = &self.api_base_url;
+        if let Some(root) = base
+            .strip_suffix(CHAT_COMPLETIONS_PATH)
+            .or_else(|| base.strip_suffix(RESPONSES_PATH))
+        {
+            return root.to_string();
+        }
+        if self.compatible.is_some() || base.ends_with("/v1") {
+            base.clone()
+        } else {
+            format!("{base}/v1")
+        }
+    }
+
+    /// Azure endpoint root (`https://NAME.openai.azure.com/openai`) and `api-version` query.
+    fn azure_root(&self) -> Option<(String, &str)> {
+        let version = self.azure_api_version.as_deref()?;
+        let base = self.api_base_url.trim_end_matches("/openai");
+        Some((format!("{base}/openai"), version))
+    }
+
     fn chat_url(&self) -> String {
-        if self.api_base_url.ends_with("/v1") {
-            format!("{}/chat/completions", self.api_base_url)
+        if let Some((root, version)) = self.azure_root() {
+            return format!(
+                "{root}/deployments/{}{CHAT_COMPLETIONS_PATH}?api-version={version}",
+                self.model
+            );
+        }
+        if self.api_base_url.ends_with(CHAT_COMPLETIONS_PATH) {
+            self.api_base_url.clone()
         } else {
-            format!("{}/v1/chat/completions", self.api_base_url)
+            format!("{}{CHAT_COMPLETIONS_PATH}", self.api_root())
         }
     }
 
-    fn model_url(&self) -> String {
-        if self.api_base_url.ends_with("/v1") {
-            format!("{}/models/{}", self.api_base_url, self.model)
+    fn responses_url(&self) -> String {
+        if let Some((root, version)) = self.azure_root() {
+            return format!("{root}{RESPONSES_PATH}?api-version={version}");
+        }
+        if self.api_base_url.ends_with(RESPONSES_PATH) {
+            self.api_base_url.clone()
         } else {
-            format!("{}/v1/models/{}", self.api_base_url, self.model)
+            format!("{}{RESPONSES_PATH}", self.api_root())
+        }
+    }
+
+    fn model_url(&self) -> String {
+        format!("{}/models/{}", self.api_root(), self.model)
+    }
+
+    /// Attach the API key, any extra headers, and the organization/project headers when set.
+    /// Azure and OpenAI-compatible services have no organizations or projects, and take the
+    /// key in their own header.
+    fn authorize(&self, builder: RequestBuilder) -> RequestBuilder {
+        let builder = builder.headers(self.extra_headers.clone());
+        if self.azure_api_version.is_some() {
+            return builder.header("api-key", &self.api_key);
+        }
+        if let Some(compatible) = &self.compatible {
+            return match &compatible.auth_header {
+                _ if self.api_key.is_empty() => builder,
+                Some(header) => builder.header(header.as_str(), &self.api_key),
+                None => builder.bearer_auth(&self.api_key),
+            };
+        }
+        let mut builder = builder.bearer_auth(&self.api_key);
+        if let Some(org) = &self.organization {
+            builder = builder.header("OpenAI-Organization", org);
+        }
+        if let Some(project) = &self.project {
+            builder = builder.header("OpenAI-Project", project);
+        }
+        builder
+    }
+
+    /// Check the model against `GET /models`, which OpenAI-compatible services implement
+    /// more consistently than the per-model lookup. An unreadable listing is not an error.
+    fn validate_listed_model(&self) -> Result<()> {
+        let url = format!("{}/models", self.api_root());
+        let resp = self
+            .authorize(self.client.get(&url))
+            .send()
+            .with_context(|| format!("failed to list models from {}", self.provider_name()))?;
+
+        let status = resp.status();
+        let text = resp.text().unwrap_or_default();
+        if !status.is_success() {
+            return Err(anyhow!(
+                "{} model listing failed at {}: HTTP {} - {}",
+                self.provider_name(),
+                url,
+                status.as_u16(),
+                text
+            ));
         }
+
+        let Ok(list) = serde_json::from_str::<ModelList>(&text) else {
+            log::debug!("Could not parse the model list from {url}; skipping validation");
+            return Ok(());
+        };
+        if list.data.is_empty() || list.data.iter().any(|m| m.id == self.model) {
+            return Ok(());
+        }
+        let available: Vec<&str> = list.data.iter().take(10).map(|m| m.id.as_str()).collect();
+        Err(anyhow!(
+            "{} does not offer model {:?} (available: {})",
+            self.provider_name(),
+            self.model,
+            available.join(", ")
+        ))
     }
 
-    fn call_chat(&self, req: &ChatRequest) -> Result<String> {
+    fn call_chat(&self, req: &ChatRequest, cancel: Option<&CancellationToken>) -> Result<String> {
         if req.stream {
-            return self.call_chat_streaming(req);
+            return self.call_chat_streaming(req, cancel);
         }
 
         let url = self.chat_url();
@@ -123,71 +687,267 @@ impl OpenAiClient {
         log::info!("Calling OpenAI model {:?}", &req.model);
 
         let resp = self
-            .client
-            .post(url)
-            .bearer_auth(&self.api_key)
-            .json(req)
-            .send()
+            .retry
+            .send(self.provider_name(), cancel, || {
+                self.authorize(self.client.post(&url)).json(req).send()
+            })
             .context("failed to send request to OpenAI")?;
 
         if !resp.status().is_success() {
             let status = resp.status();
             let text = resp.text().unwrap_or_default();
-            return Err(anyhow!(
-                "OpenAI API error: HTTP {} - {}",
-                status.as_u16(),
-                text
-            ));
+            return Err(ApiError::new(self.provider_name(), status.as_u16(), text).into());
         }
 
-        let chat_resp: ChatResponse = resp.json().context("failed to parse OpenAI response")?;
-        let content = chat_resp
+        let body = read_body_capped(resp, self.max_response_bytes)?;
+        let chat_resp: ChatResponse =
+            serde_json::from_str(&body).context("failed to parse OpenAI response")?;
+        let choice = chat_resp
             .choices
             .first()
-            .map(|c| c.message.content.clone())
             .ok_or_else(|| anyhow!("no choices returned from OpenAI"))?;
+        warn_if_truncated(self.provider_name(), choice.finish_reason.as_deref());
+        let content = choice.message.content.clone();
 
         if let Some(usage) = &chat_resp.usage {
-            // Recover from a poisoned mutex instead of panicking so the CLI
-            // can continue in the face of concurrent thread panics.
-            let mut u = self.usage.lock().unwrap_or_else(|e| e.into_inner());
-            u.prompt_tokens += usage.prompt_tokens as u64;
-            u.completion_tokens += usage.completion_tokens as u64;
-            u.total_tokens += usage.total_tokens as u64;
+            self.record_chat_usage(usage);
         }
 
         Ok(content)
     }
 
-    fn call_chat_streaming(&self, req: &ChatRequest) -> Result<String> {
+    fn call_chat_streaming(
+        &self,
+        req: &ChatRequest,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
         let url = self.chat_url();
 
         log::info!("Streaming OpenAI model {:?}", &req.model);
 
         let resp = self
-            .client
-            .post(url)
-            .bearer_auth(&self.api_key)
-            .json(req)
-            .send()
+            .retry
+            .send(self.provider_name(), cancel, || {
+                self.authorize(self.client.post(&url)).json(req).send()
+            })
             .context("failed to send streaming request to OpenAI")?;
 
         if !resp.status().is_success() {
             let status = resp.status();
             let text = resp.text().unwrap_or_default();
-            return Err(anyhow!(
-                "OpenAI API error: HTTP {} - {}",
-                status.as_u16(),
-                text
-            ));
+            return Err(ApiError::new(self.provider_name(), status.as_u16(), text).into());
         }
 
         let reader = BufReader::new(resp);
-        read_stream_to_string(reader, parse_stream_line)
+        let mut state = ChatStreamState::default();
+        let content = read_stream_to_string(reader, cancel, self.max_response_bytes, |line| {
+            parse_stream_line(line, &mut state)
+        })?;
+        warn_if_truncated(self.provider_name(), state.finish_reason.as_deref());
+        if let Some(usage) = &state.usage {
+            self.record_chat_usage(usage);
+        }
+        Ok(content)
+    }
+
+    fn record_chat_usage(&self, usage: &ChatUsage) {
+        // Recover from a poisoned mutex instead of panicking so the CLI
+        // can continue in the face of concurrent thread panics.
+        let mut u = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        u.record(
+            usage.prompt_tokens as u64,
+            usage.completion_tokens as u64,
+            usage.total_tokens as u64,
+        );
+    }
+
+    /// Send a system + user prompt pair using the configured API style.
+    fn request(
+        &self,
+        step: &str,
+        system: String,
+        user: String,
+        stream: bool,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        cancel::check(cancel)?;
+        self.usage.lock().unwrap_or_else(|e| e.into_inner()).requests += 1;
+        let structured = step == "commit_message" && self.prompt_options.structured_output;
+        let Some(debug_log) = &self.debug_log else {
+            return self.send(system, user, stream, structured, cancel);
+        };
+
+        let result = self.send(system.clone(), user.clone(), stream, structured, cancel);
+        debug_log.record("openai", &self.model, step, &system, &user, &result);
+        result
+    }
+
+    /// Send one request; `structured` asks for a reply matching the commit-message schema.
+    fn send(
+        &self,
+        system: String,
+        user: String,
+        stream: bool,
+        structured: bool,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        match self.api {
+            OpenAiApi::Chat => {
+                let mut req = self.chat_request(system, user, stream);
+                req.response_format = structured.then(|| {
+                    json!({
+                        "type": "json_schema",
+                        "json_schema": {
+                            "name": structured::SCHEMA_NAME,
+                            "schema": structured::commit_schema(),
+                            "strict": true
+                        }
+                    })
+                });
+                self.call_chat(&req, cancel)
+            }
+            OpenAiApi::Responses => {
+                let mut req = self.responses_request(system, user, stream);
+                req.text = structured.then(|| {
+                    json!({
+                        "format": {
+                            "type": "json_schema",
+                            "name": structured::SCHEMA_NAME,
+                            "schema": structured::commit_schema(),
+                            "strict": true
+                        }
+                    })
+                });
+                self.call_responses(&req, cancel)
+            }
+        }
+    }
+
+    fn call_responses(
+        &self,
+        req: &ResponsesRequest,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        let url = self.responses_url();
+
+        if req.stream {
+            log::info!("Streaming OpenAI model {:?} (responses)", &req.model);
+        } else {
+            log::info!("Calling OpenAI model {:?} (responses)", &req.model);
+        }
+
+        let resp = self
+            .retry
+            .send(self.provider_name(), cancel, || {
+                self.authorize(self.client.post(&url)).json(req).send()
+            })
+            .context("failed to send request to OpenAI")?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let text = resp.text().unwrap_or_default();
+            return Err(ApiError::new(self.provider_name(), status.as_u16(), text).into());
+        }
+
+        if req.stream {
+            let reader = BufReader::new(resp);
+            let mut last = None;
+            let content = read_stream_to_string(reader, cancel, self.max_response_bytes, |line| {
+                parse_responses_stream_line(line, &mut last)
+            })?;
+            if let Some(last) = &last {
+                self.finish_responses(last);
+            }
+            return Ok(content);
+        }
+
+        let body = read_body_capped(resp, self.max_response_bytes)?;
+        let parsed: ResponsesResponse =
+            serde_json::from_str(&body).context("failed to parse OpenAI response")?;
+        self.finish_responses(&parsed);
+        let content = responses_output_text(&parsed)
+            .ok_or_else(|| anyhow!("no output text returned from OpenAI"))?;
+
+        Ok(content)
+    }
+
+    /// Warn if a finished Responses API reply was cut off, and add up its token usage.
+    fn finish_responses(&self, resp: &ResponsesResponse) {
+        warn_if_truncated(self.provider_name(), responses_stop_reason(resp));
+
+        if let Some(usage) = &resp.usage {
+            let mut u = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+            u.record(
+                usage.input_tokens as u64,
+                usage.output_tokens as u64,
+                usage.total_tokens as u64,
+            );
+        }
+    }
+}
+
+/// Why a Responses API reply stopped early, with `max_output_tokens` mapped onto the shared
+/// "length" spelling; `None` for a complete reply.
+fn responses_stop_reason(resp: &ResponsesResponse) -> Option<&str> {
+    let reason = resp.incomplete_details.as_ref()?.reason.as_deref()?;
+    Some(if reason == "max_output_tokens" {
+        "length"
+    } else {
+        reason
+    })
+}
+
+/// Concatenate all `output_text` parts of the `message` items in a Responses API reply.
+fn responses_output_text(resp: &ResponsesResponse) -> Option<String> {
+    let mut out = String::new();
+    let mut found = false;
+    for item in resp.output.iter().filter(|i| i.kind == "message") {
+        for part in item.content.iter().filter(|c| c.kind == "output_text") {
+            out.push_str(&part.text);
+            found = true;
+        }
     }
+    found.then_some(out)
 }
 
-fn parse_stream_line(line: &str) -> Result<Option<String>> {
+fn parse_responses_stream_line(
+    line: &str,
+    last: &mut Option<ResponsesResponse>,
+) -> Result<Option<String>> {
+    let Some(data) = sse_data(line) else {
+        return Ok(None);
+    };
+    if data == "[DONE]" {
+        return Ok(None);
+    }
+
+    let event: ResponsesStreamEvent =
+        serde_json::from_str(data).context("failed to parse OpenAI streaming event")?;
+    match event.kind.as_str() {
+        "response.output_text.delta" => Ok(event.delta),
+        "response.completed" | "response.incomplete" => {
+            *last = event.response;
+            Ok(None)
+        }
+        "response.failed" => {
+            let error = event.response.and_then(|r| r.error);
+            let (code, message) = error.map_or((None, String::new()), |e| (e.code, e.message));
+            Err(anyhow!(
+                "OpenAI response failed: {} {message}",
+                code.unwrap_or_default()
+            ))
+        }
+        "error" => Err(anyhow!(
+            "OpenAI stream failed: {} {}",
+            event.code.unwrap_or_default(),
+            event.message
+        )),
+        _ => Ok(None),
+    }
+}
+
+/// Parse one chat-completions SSE line, recording the `finish_reason` of the final chunk.
+fn parse_stream_line(line: &str, state: &mut ChatStreamState) -> Result<Option<String>> {
     let line = line.trim_start();
     if !line.starts_with("data:") {
         return Ok(None);
@@ -200,18 +960,36 @@ fn parse_stream_line(line: &str) -> Result<Option<String>> {
 
     let chunk: StreamResponse =
         serde_json::from_str(data).context("failed to parse OpenAI streaming chunk")?;
-    let content = chunk.choices.first().and_then(|c| c.delta.content.clone());
+    if chunk.usage.is_some() {
+        state.usage = chunk.usage;
+    }
+    let Some(choice) = chunk.choices.into_iter().next() else {
+        return Ok(None);
+    };
+    if choice.finish_reason.is_some() {
+        state.finish_reason = choice.finish_reason;
+    }
 
-    Ok(content)
+    Ok(choice.delta.content)
 }
 
 impl LlmClient for OpenAiClient {
     fn validate_model(&self) -> Result<()> {
+        if self.azure_api_version.is_some() {
+            // Deployments can't be looked up with a data-plane key; a wrong name
+            // surfaces as a 404 on the first request instead.
+            log::debug!(
+                "Skipping model validation for Azure deployment {:?}",
+                self.model
+            );
+            return Ok(());
+        }
+        if self.compatible.is_some() {
+            return self.validate_listed_model();
+        }
         let url = self.model_url();
         let resp = self
-            .client
-            .get(&url)
-            .bearer_auth(&self.api_key)
+            .authorize(self.client.get(&url))
             .send()
             .context("failed to send model validation request to OpenAI")?;
 
@@ -237,6 +1015,7 @@ impl LlmClient for OpenAiClient {
         file_index: usize,
         total_files: usize,
         ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
     ) -> Result<String> {
         let prompts = prompt_builder::file_summary_prompt(
             branch,
@@ -244,6 +1023,7 @@ impl LlmClient for OpenAiClient {
             file_index,
             total_files,
             ticket_summary,
+            &self.prompt_options,
         );
 
         log::debug!(
@@ -260,32 +1040,41 @@ impl LlmClient for OpenAiClient {
             prompts.user
         );
 
-        let req = ChatRequest {
-            model: self.model.clone(),
-            messages: vec![
-                ChatMessage {
-                    role: "system".into(),
-                    content: prompts.system,
-                },
-                ChatMessage {
-                    role: "user".into(),
-                    content: prompts.user,
-                },
-            ],
-            stream: false,
-        };
-
-        let content = self.call_chat(&req)?;
+        let content = self.request("file_summary", prompts.system, prompts.user, false, cancel)?;
         Ok(content)
     }
 
+    fn summarize_batch(
+        &self,
+        branch: &str,
+        files: &[&FileChange],
+        ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<Vec<Option<String>>> {
+        let prompts = prompt_builder::batch_summary_prompt(
+            branch,
+            files,
+            ticket_summary,
+            &self.prompt_options,
+        );
+        let content = self.request("batch_summary", prompts.system, prompts.user, false, cancel)?;
+        let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+        Ok(prompt_builder::split_batch_summary(&content, &paths))
+    }
+
     fn generate_commit_message(
         &self,
         branch: &str,
         files: &[FileChange],
         ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
     ) -> Result<String> {
-        let prompts = prompt_builder::commit_message_prompt(branch, files, ticket_summary);
+        let prompts = prompt_builder::commit_message_prompt(
+            branch,
+            files,
+            ticket_summary,
+            &self.prompt_options,
+        );
 
         log::info!(
             "Final commit-message prompt [truncated]:\n{}",
@@ -297,23 +1086,28 @@ impl LlmClient for OpenAiClient {
             prompts.user
         );
 
-        let req = ChatRequest {
-            model: self.model.clone(),
-            messages: vec![
-                ChatMessage {
-                    role: "system".into(),
-                    content: prompts.system,
-                },
-                ChatMessage {
-                    role: "user".into(),
-                    content: prompts.user,
-                },
-            ],
-            stream: self.stream,
-        };
+        let content = self.request(
+            "commit_message",
+            prompts.system,
+            prompts.user,
+            self.stream,
+            cancel,
+        )?;
+        Ok(structured::finish_commit_message(
+            content,
+            &self.prompt_options,
+        ))
+    }
 
-        let content = self.call_chat(&req)?;
-        Ok(content)
+    fn generate_changelog_entry(
+        &self,
+        files: &[FileChange],
+        commit_message: &str,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        let prompts =
+            prompt_builder::changelog_entry_prompt(files, commit_message, &self.prompt_options);
+        self.request("changelog", prompts.system, prompts.user, false, cancel)
     }
 
     fn generate_pr_message(
@@ -323,6 +1117,7 @@ impl LlmClient for OpenAiClient {
         mode: PrSummaryMode,
         items: &[PrItem],
         ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
     ) -> Result<String> {
         let prompts = prompt_builder::pr_message_prompt(
             base_branch,
@@ -330,6 +1125,7 @@ impl LlmClient for OpenAiClient {
             mode,
             items,
             ticket_summary,
+            &self.prompt_options,
         );
 
         log::info!(
@@ -342,38 +1138,31 @@ impl LlmClient for OpenAiClient {
             prompts.user
         );
 
-        let req = ChatRequest {
-            model: self.model.clone(),
-            messages: vec![
-                ChatMessage {
-                    role: "system".into(),
-                    content: prompts.system,
-                },
-                ChatMessage {
-                    role: "user".into(),
-                    content: prompts.user,
-                },
-            ],
-            stream: self.stream,
-        };
-
-        let content = self.call_chat(&req)?;
+        let content = self.request(
+            "pr_message",
+            prompts.system,
+            prompts.user,
+            self.stream,
+            cancel,
+        )?;
         Ok(content)
     }
 
-    fn take_and_reset_usage(&self) -> Option<(u64, u64, u64)> {
+    fn summary_source(&self) -> Option<String> {
+        Some(format!(
+            "{}:{}:{}",
+            self.provider_name(),
+            self.api_base_url,
+            self.model
+        ))
+    }
+
+    fn take_and_reset_usage(&self) -> Option<TokenUsage> {
         let mut u = self.usage.lock().unwrap_or_else(|e| {
             log::warn!("usage mutex was poisoned, recovering token counters");
             e.into_inner()
         });
-
-        if u.total_tokens > 0 {
-            let res = (u.prompt_tokens, u.completion_tokens, u.total_tokens);
-            *u = TokenUsage::default();
-            Some(res)
-        } else {
-            None
-        }
+        u.take(Some(&self.model))
     }
 }
 
@@ -397,6 +1186,7 @@ mod tests {
     #[test]
     fn builds_model_url_from_root_base() {
         let client = OpenAiClient::new(
+            Client::new(),
             "test-key".into(),
             "gpt-5-nano".into(),
             "https://api.openai.com".into(),
@@ -409,9 +1199,269 @@ mod tests {
         );
     }
 
+    fn client_with_base(base: &str) -> OpenAiClient {
+        OpenAiClient::new(
+            Client::new(),
+            "test-key".into(),
+            "gpt-5-nano".into(),
+            base.into(),
+            false,
+        )
+    }
+
+    #[test]
+    fn classifies_model_families() {
+        for model in [
+            "gpt-5-nano",
+            "gpt-5",
+            "o1",
+            "o3-mini",
+            "o4-mini",
+            "openai/o3",
+        ] {
+            assert_eq!(ModelFamily::of(model), ModelFamily::Reasoning, "{model}");
+        }
+        for model in [
+            "gpt-4o-mini",
+            "gpt-4.1",
+            "gpt-5-chat-latest",
+            "ollama",
+            "llama3",
+        ] {
+            assert_eq!(ModelFamily::of(model), ModelFamily::Chat, "{model}");
+        }
+    }
+
+    fn client_for_model(model: &str) -> OpenAiClient {
+        OpenAiClient::new(
+            Client::new(),
+            "test-key".into(),
+            model.into(),
+            "https://api.openai.com".into(),
+            false,
+        )
+        .with_temperature(Some(0.2))
+        .with_top_p(Some(0.9))
+        .with_max_output_tokens(Some(500))
+        .with_reasoning_effort(Some("low".into()))
+    }
+
+    #[test]
+    fn serializes_reasoning_parameters_for_reasoning_models() {
+        let client = client_for_model("gpt-5-nano");
+        let req = serde_json::to_value(client.chat_request("s".into(), "u".into(), false)).unwrap();
+        assert_eq!(req["max_completion_tokens"], 500);
+        assert_eq!(req["reasoning_effort"], "low");
+        assert!(req.get("temperature").is_none());
+        assert!(req.get("top_p").is_none());
+        assert!(req.get("max_tokens").is_none());
+        assert_eq!(req["messages"][0]["role"], "developer");
+        assert_eq!(req["messages"][1]["role"], "user");
+
+        let preview = client_for_model("openai/o1-mini");
+        let req =
+            serde_json::to_value(preview.chat_request("s".into(), "u".into(), false)).unwrap();
+        assert_eq!(req["messages"].as_array().unwrap().len(), 1);
+        assert_eq!(req["messages"][0]["role"], "user");
+        assert_eq!(req["messages"][0]["content"], "s\n\nu");
+
+        let req =
+            serde_json::to_value(client.responses_request("s".into(), "u".into(), false)).unwrap();
+        assert_eq!(req["max_output_tokens"], 500);
+        assert_eq!(req["reasoning"]["effort"], "low");
+        assert!(req.get("temperature").is_none());
+    }
+
+    #[test]
+    fn serializes_standard_parameters_for_chat_models() {
+        let client = client_for_model("gpt-4o-mini");
+        let req = serde_json::to_value(client.chat_request("s".into(), "u".into(), false)).unwrap();
+        assert_eq!(req["max_tokens"], 500);
+        assert!((req["temperature"].as_f64().unwrap() - 0.2).abs() < 1e-6);
+        assert!((req["top_p"].as_f64().unwrap() - 0.9).abs() < 1e-6);
+        assert!(req.get("max_completion_tokens").is_none());
+        assert!(req.get("reasoning_effort").is_none());
+        assert_eq!(req["messages"][0]["role"], "system");
+
+        let req =
+            serde_json::to_value(client.responses_request("s".into(), "u".into(), false)).unwrap();
+        assert!(req.get("reasoning").is_none());
+        assert!((req["top_p"].as_f64().unwrap() - 0.9).abs() < 1e-6);
+
+        let bare = client_with_base("https://api.openai.com");
+        let req = serde_json::to_value(bare.chat_request("s".into(), "u".into(), true)).unwrap();
+        let keys: Vec<&String> = req.as_object().unwrap().keys().collect();
+        assert_eq!(keys, ["messages", "model", "stream", "stream_options"]);
+        assert_eq!(req["stream_options"]["include_usage"], true);
+    }
+
+    #[test]
+    fn builds_chat_url_for_gateway_mount() {
+        let client = client_with_base("https://gw.example/openai/v1");
+        assert_eq!(
+            client.chat_url(),
+            "https://gw.example/openai/v1/chat/completions"
+        );
+        assert_eq!(
+            client.model_url(),
+            "https://gw.example/openai/v1/models/gpt-5-nano"
+        );
+    }
+
+    #[test]
+    fn uses_full_chat_path_verbatim() {
+        let client = client_with_base("https://gw.example/custom/v2/chat/completions");
+        assert_eq!(
+            client.chat_url(),
+            "https://gw.example/custom/v2/chat/completions"
+        );
+        assert_eq!(
+            client.model_url(),
+            "https://gw.example/custom/v2/models/gpt-5-nano"
+        );
+
+        let client = client_with_base("https://gw.example/openai/v1/responses");
+        assert_eq!(
+            client.responses_url(),
+            "https://gw.example/openai/v1/responses"
+        );
+        assert_eq!(
+            client.chat_url(),
+            "https://gw.example/openai/v1/chat/completions"
+        );
+    }
+
+    #[test]
+    fn sends_org_and_project_headers_only_when_set() {
+        let client = client_with_base("https://api.openai.com");
+        let req = client
+            .authorize(client.client.post(client.chat_url()))
+            .build()
+            .unwrap();
+        assert!(req.headers().get("OpenAI-Organization").is_none());
+        assert!(req.headers().get("OpenAI-Project").is_none());
+
+        let client = client_with_base("https://api.openai.com")
+            .with_organization(Some("org-123".into()))
+            .with_project(Some("proj_456".into()));
+        let req = client
+            .authorize(client.client.post(client.chat_url()))
+            .build()
+            .unwrap();
+        assert_eq!(req.headers()["OpenAI-Organization"], "org-123");
+        assert_eq!(req.headers()["OpenAI-Project"], "proj_456");
+        assert_eq!(req.headers()["Authorization"], "Bearer test-key");
+    }
+
+    #[test]
+    fn builds_azure_deployment_urls_and_api_key_header() {
+        let client = client_with_base("https://acme.openai.azure.com/")
+            .with_azure("2024-10-21".into())
+            .with_organization(Some("org-123".into()));
+        assert_eq!(
+            client.chat_url(),
+            "https://acme.openai.azure.com/openai/deployments/gpt-5-nano/chat/completions?api-version=2024-10-21"
+        );
+        assert_eq!(
+            client.responses_url(),
+            "https://acme.openai.azure.com/openai/responses?api-version=2024-10-21"
+        );
+
+        let req = client
+            .authorize(client.client.post(client.chat_url()))
+            .build()
+            .unwrap();
+        assert_eq!(req.headers()["api-key"], "test-key");
+        assert!(req.headers().get("Authorization").is_none());
+        assert!(req.headers().get("OpenAI-Organization").is_none());
+
+        let client =
+            client_with_base("https://acme.openai.azure.com/openai").with_azure("v".into());
+        assert_eq!(
+            client.responses_url(),
+            "https://acme.openai.azure.com/openai/responses?api-version=v"
+        );
+    }
+
+    #[test]
+    fn compatible_services_use_the_base_url_as_the_api_root() {
+        let client = client_with_base("https://api.perplexity.ai")
+            .with_compatible("Perplexity", None)
+            .with_organization(Some("org-123".into()));
+        assert_eq!(
+            client.chat_url(),
+            "https://api.perplexity.ai/chat/completions"
+        );
+        let req = client
+            .authorize(client.client.post(client.chat_url()))
+            .build()
+            .unwrap();
+        assert_eq!(req.headers()["Authorization"], "Bearer test-key");
+        assert!(req.headers().get("OpenAI-Organization").is_none());
+        assert_eq!(client.provider_name(), "Perplexity");
+
+        let client = client_with_base("https://gw.example/llm")
+            .with_compatible("Gateway", Some("x-api-key".into()));
+        let req = client
+            .authorize(client.client.post(client.chat_url()))
+            .build()
+            .unwrap();
+        assert_eq!(req.headers()["x-api-key"], "test-key");
+        assert!(req.headers().get("Authorization").is_none());
+
+        let client = OpenAiClient::new(
+            Client::new(),
+            String::new(),
+            "qwen".into(),
+            "http://localhost:1234/v1".into(),
+            false,
+        )
+        .with_compatible("LM Studio", None);
+        let req = client
+            .authorize(client.client.post(client.chat_url()))
+            .build()
+            .unwrap();
+        assert!(req.headers().get("Authorization").is_none());
+    }
+
+    #[test]
+    fn looks_up_compatible_presets() {
+        assert_eq!(compatible_preset("Groq").unwrap().label, "Groq");
+        assert_eq!(compatible_preset("lm-studio").unwrap().name, "lmstudio");
+        assert_eq!(compatible_preset("LM Studio").unwrap().key_env, None);
+        assert_eq!(compatible_preset("OpenRouter").unwrap().headers.len(), 2);
+        assert!(compatible_preset("openrouterx").is_none());
+    }
+
+    #[test]
+    fn sends_extra_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Tenant-Id", "acme".parse().unwrap());
+        let client = client_with_base("https://gw.example").with_headers(headers);
+        let req = client
+            .authorize(client.client.post(client.chat_url()))
+            .build()
+            .unwrap();
+        assert_eq!(req.headers()["X-Tenant-Id"], "acme");
+        assert_eq!(req.headers()["Authorization"], "Bearer test-key");
+    }
+
+    #[test]
+    fn ignores_trailing_slash_in_base_url() {
+        let client = client_with_base("https://api.openai.com/v1/");
+        assert_eq!(
+            client.chat_url(),
+            "https://api.openai.com/v1/chat/completions"
+        );
+
+        let client = client_with_base("https://gw.example/chat/completions/");
+        assert_eq!(client.chat_url(), "https://gw.example/chat/completions");
+    }
+
     #[test]
     fn builds_model_url_from_v1_base() {
         let client = OpenAiClient::new(
+            Client::new(),
             "test-key".into(),
             "gpt-5-nano".into(),
             "https://api.openai.com/v1".into(),
@@ -423,4 +1473,98 @@ mod tests {
             "https://api.openai.com/v1/models/gpt-5-nano"
         );
     }
+
+    #[test]
+    fn builds_responses_url() {
+        let client = OpenAiClient::new(
+            Client::new(),
+            "test-key".into(),
+            "gpt-5-nano".into(),
+            "https://api.openai.com".into(),
+            false,
+        )
+        .with_api(OpenAiApi::Responses);
+
+        assert_eq!(
+            client.responses_url(),
+            "https://api.openai.com/v1/responses"
+        );
+    }
+
+    #[test]
+    fn extracts_text_from_responses_output() {
+        let body = r#"{
+            "output": [
+                {"type": "reasoning", "content": []},
+                {"type": "message", "content": [{"type": "output_text", "text": "Add login"}]}
+            ],
+            "usage": {"input_tokens": 10, "output_tokens": 3, "total_tokens": 13}
+        }"#;
+        let parsed: ResponsesResponse =
+            serde_json::from_str(body).expect("valid responses payload");
+
+        assert_eq!(responses_output_text(&parsed).as_deref(), Some("Add login"));
+    }
+
+    #[test]
+    fn parses_responses_stream_events() {
+        let line = r#"data: {"type":"response.output_text.delta","delta":"Fix"}"#;
+        let mut last = None;
+        assert_eq!(
+            parse_responses_stream_line(line, &mut last)
+                .unwrap()
+                .as_deref(),
+            Some("Fix")
+        );
+
+        let done = r#"data: {"type":"response.incomplete","response":{"incomplete_details":{"reason":"max_output_tokens"},"usage":{"input_tokens":5,"output_tokens":2,"total_tokens":7}}}"#;
+        assert_eq!(parse_responses_stream_line(done, &mut last).unwrap(), None);
+        let last = last.expect("final response recorded");
+        assert_eq!(last.usage.as_ref().unwrap().total_tokens, 7);
+        assert_eq!(responses_stop_reason(&last), Some("length"));
+        assert!(warn_if_truncated("OpenAI", responses_stop_reason(&last)));
+
+        let failed = r#"data: {"type":"response.failed","response":{"error":{"code":"server_error","message":"boom"}}}"#;
+        let err = parse_responses_stream_line(failed, &mut None).unwrap_err();
+        assert!(err.to_string().contains("server_error boom"), "{err}");
+
+        let error = r#"data: {"type":"error","code":"rate_limit_exceeded","message":"slow down"}"#;
+        let err = parse_responses_stream_line(error, &mut None).unwrap_err();
+        assert!(
+            err.to_string().contains("rate_limit_exceeded slow down"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn records_finish_reason_from_final_stream_chunk() {
+        let mut state = ChatStreamState::default();
+        let chunk = r#"data: {"choices":[{"delta":{"content":"Fix"},"finish_reason":null}]}"#;
+        assert_eq!(
+            parse_stream_line(chunk, &mut state).unwrap().as_deref(),
+            Some("Fix")
+        );
+        assert_eq!(state.finish_reason, None);
+
+        let last = r#"data: {"choices":[{"delta":{},"finish_reason":"length"}]}"#;
+        assert_eq!(parse_stream_line(last, &mut state).unwrap(), None);
+        let usage = r#"data: {"choices":[],"usage":{"prompt_tokens":9,"completion_tokens":3,"total_tokens":12}}"#;
+        assert_eq!(parse_stream_line(usage, &mut state).unwrap(), None);
+        assert_eq!(
+            parse_stream_line("data: [DONE]", &mut state).unwrap(),
+            None
+        );
+        assert_eq!(state.usage.map(|u| u.total_tokens), Some(12));
+        let reason = state.finish_reason;
+        assert_eq!(reason.as_deref(), Some("length"));
+        assert!(warn_if_truncated("OpenAI", reason.as_deref()));
+    }
+
+    #[test]
+    fn parses_finish_reason_from_chat_response() {
+        let body = r#"{"choices":[{"message":{"content":"Fix the"},"finish_reason":"length"}]}"#;
+        let resp: ChatResponse = serde_json::from_str(body).unwrap();
+        assert_eq!(resp.choices[0].finish_reason.as_deref(), Some("length"));
+        assert!(!warn_if_truncated("OpenAI", Some("stop")));
+    }
 }
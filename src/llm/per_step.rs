@@ -0,0 +1,197 @@
+use super::{CancellationToken, LlmClient, TokenUsage};
+use crate::FileChange;
+use crate::git::{PrItem, PrSummaryMode};
+use anyhow::Result;
+
+/// Routes per-file summaries and final messages to two clients built for different models.
+pub struct PerStepClient {
+    summary: Box<dyn LlmClient>,
+    final_step: Box<dyn LlmClient>,
+}
+
+impl PerStepClient {
+    pub fn new(summary: Box<dyn LlmClient>, final_step: Box<dyn LlmClient>) -> Self {
+        Self {
+            summary,
+            final_step,
+        }
+    }
+}
+
+impl LlmClient for PerStepClient {
+    fn validate_model(&self) -> Result<()> {
+        self.summary.validate_model()?;
+        self.final_step.validate_model()
+    }
+
+    fn summarize_file(
+        &self,
+        branch: &str,
+        file: &FileChange,
+        file_index: usize,
+        total_files: usize,
+        ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        self.summary.summarize_file(
+            branch,
+            file,
+            file_index,
+            total_files,
+            ticket_summary,
+            cancel,
+        )
+    }
+
+    fn summarize_batch(
+        &self,
+        branch: &str,
+        files: &[&FileChange],
+        ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<Vec<Option<String>>> {
+        self.summary
+            .summarize_batch(branch, files, ticket_summary, cancel)
+    }
+
+    fn generate_commit_message(
+        &self,
+        branch: &str,
+        files: &[FileChange],
+        ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        self.final_step
+            .generate_commit_message(branch, files, ticket_summary, cancel)
+    }
+
+    fn generate_changelog_entry(
+        &self,
+        files: &[FileChange],
+        commit_message: &str,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        self.final_step
+            .generate_changelog_entry(files, commit_message, cancel)
+    }
+
+    fn generate_pr_message(
+        &self,
+        base_branch: &str,
+        from_branch: &str,
+        mode: PrSummaryMode,
+        items: &[PrItem],
+        ticket_summary: Option<&str>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        self.final_step.generate_pr_message(
+            base_branch,
+            from_branch,
+            mode,
+            items,
+            ticket_summary,
+            cancel,
+        )
+    }
+
+    fn summary_source(&self) -> Option<String> {
+        self.summary.summary_source()
+    }
+
+    fn take_and_reset_usage(&self) -> Option<TokenUsage> {
+        let summary = self.summary.take_and_reset_usage();
+        let final_step = self.final_step.take_and_reset_usage();
+        match (summary, final_step) {
+            (Some(summary), Some(final_step)) => Some(summary.merge(final_step)),
+            (one, other) => one.or(other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Named(&'static str);
+
+    impl LlmClient for Named {
+        fn validate_model(&self) -> Result<()> {
+            Ok(())
+        }
+
+        fn summarize_file(
+            &self,
+            _branch: &str,
+            _file: &FileChange,
+            _file_index: usize,
+            _total_files: usize,
+            _ticket_summary: Option<&str>,
+            _cancel: Option<&CancellationToken>,
+        ) -> Result<String> {
+            Ok(self.0.to_string())
+        }
+
+        fn generate_commit_message(
+            &self,
+            _branch: &str,
+            _files: &[FileChange],
+            _ticket_summary: Option<&str>,
+            _cancel: Option<&CancellationToken>,
+        ) -> Result<String> {
+            Ok(self.0.to_string())
+        }
+
+        fn generate_pr_message(
+            &self,
+            _base_branch: &str,
+            _from_branch: &str,
+            _mode: PrSummaryMode,
+            _items: &[PrItem],
+            _ticket_summary: Option<&str>,
+            _cancel: Option<&CancellationToken>,
+        ) -> Result<String> {
+            Ok(self.0.to_string())
+        }
+
+        fn take_and_reset_usage(&self) -> Option<TokenUsage> {
+            Some(TokenUsage {
+                requests: 1,
+                prompt_tokens: 1,
+                completion_tokens: 2,
+                total_tokens: 3,
+                cost: None,
+            })
+        }
+    }
+
+    #[test]
+    fn routes_each_step_to_its_client() {
+        let client = PerStepClient::new(Box::new(Named("small")), Box::new(Named("large")));
+        let file = FileChange {
+            path: "a.rs".into(),
+            category: crate::FileCategory::Main,
+            diff: "+a".into(),
+            summary: None,
+            list_only: false,
+            is_deleted: false,
+        };
+
+        assert_eq!(
+            client
+                .summarize_file("main", &file, 0, 1, None, None)
+                .unwrap(),
+            "small"
+        );
+        assert_eq!(
+            client
+                .generate_commit_message("main", &[file], None, None)
+                .unwrap(),
+            "large"
+        );
+        let usage = client.take_and_reset_usage().unwrap();
+        assert_eq!(
+            (usage.requests, usage.prompt_tokens, usage.total_tokens),
+            (2, 2, 6)
+        );
+    }
+}
@@ -1,6 +1,7 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
 
-use crate::git::{PrItem, PrSummaryMode};
+use crate::git::{Forge, PrItem, PrSummaryMode, SubmoduleBump, parse_submodule_bump};
+use crate::ignore::IgnoreRules;
 use crate::llm::prompts;
 use crate::{FileCategory, FileChange};
 
@@ -9,29 +10,637 @@ pub struct PromptPair {
     pub user: String,
 }
 
+/// User-tunable knobs that shape every prompt the clients send.
+#[derive(Debug, Clone, Default)]
+pub struct PromptOptions {
+    /// Language (code or free-text name) the output should be written in.
+    /// `None` keeps the default (English).
+    pub language: Option<String>,
+    /// Forge conventions for PR/MR references in PR mode.
+    pub forge: Forge,
+    /// Order and emphasis of categories in the final prompt; empty uses the defaults.
+    pub category_weights: Vec<CategoryWeight>,
+    /// Path prefixes that split the changeset into separately described buckets.
+    pub partitions: Vec<String>,
+    /// Glob patterns that identify test files; empty uses [`DEFAULT_TEST_PATTERNS`].
+    pub test_patterns: Vec<String>,
+    /// Word-overlap ratio (0.0–1.0) at which per-file summaries in the same category are
+    /// collapsed into one entry. `None` keeps every summary.
+    pub duplicate_summary_threshold: Option<f64>,
+    /// Voice applied to every prompt; `None` keeps the default instructions unchanged.
+    pub tone: Option<Tone>,
+    /// Headings the PR description must use, in order; empty keeps the built-in sections.
+    pub pr_sections: Vec<String>,
+    /// The repository's PR template; when set, the description fills it in instead.
+    pub pr_template: Option<String>,
+    /// Whether the branch name is included in commit prompts.
+    pub branch_context: BranchContext,
+    /// Branch names `BranchContext::Auto` treats as trunk; empty uses [`DEFAULT_TRUNK_BRANCHES`].
+    pub trunk_branches: Vec<String>,
+    /// Shape of the final commit message.
+    pub style: MessageStyle,
+    /// Recent commit messages from the repository, newest first, shown as style examples.
+    pub style_examples: Vec<String>,
+    /// Ask for the commit message as `{subject, body, sections}` JSON and render it locally.
+    pub structured_output: bool,
+    /// `pr` covers everything since a tag: write release notes instead of a PR description.
+    pub release_notes: bool,
+}
+
+/// Writing voice requested via the `tone` option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tone {
+    Concise,
+    Detailed,
+    Formal,
+}
+
+impl Tone {
+    /// Parse a tone name such as "concise" (case-insensitive).
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "concise" => Some(Tone::Concise),
+            "detailed" => Some(Tone::Detailed),
+            "formal" => Some(Tone::Formal),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Tone::Concise => "concise",
+            Tone::Detailed => "detailed",
+            Tone::Formal => "formal",
+        }
+    }
+
+    /// Directive appended to the system prompt.
+    fn directive(&self) -> &'static str {
+        match self {
+            Tone::Concise => {
+                "Be terse: prefer fewer, shorter bullets and drop anything a reviewer could infer."
+            }
+            Tone::Detailed => {
+                "Be thorough: mention every notable change and briefly explain why it was made."
+            }
+            Tone::Formal => {
+                "Use a formal, neutral register: complete sentences, no slang, no exclamation marks."
+            }
+        }
+    }
+}
+
+/// Branches whose names say nothing about the change.
+pub const DEFAULT_TRUNK_BRANCHES: &[&str] = &["main", "master", "develop", "trunk"];
+
+/// When to tell the model which branch the commit is on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BranchContext {
+    /// Include it, except on trunk branches.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl BranchContext {
+    /// Parse "auto", "always", or "never" (case-insensitive).
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "auto" => Some(BranchContext::Auto),
+            "always" => Some(BranchContext::Always),
+            "never" => Some(BranchContext::Never),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BranchContext::Auto => "auto",
+            BranchContext::Always => "always",
+            BranchContext::Never => "never",
+        }
+    }
+}
+
+/// Shape of the final commit message, selected with `--style`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MessageStyle {
+    /// Subject plus bullets, grouped under headings when there are several intents.
+    #[default]
+    Grouped,
+    /// Subject plus a prose body, no headings.
+    Plain,
+    /// Subject only.
+    Oneline,
+}
+
+impl MessageStyle {
+    /// Parse "grouped", "plain", or "oneline" (case-insensitive).
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "grouped" => Some(MessageStyle::Grouped),
+            "plain" => Some(MessageStyle::Plain),
+            "oneline" => Some(MessageStyle::Oneline),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MessageStyle::Grouped => "grouped",
+            MessageStyle::Plain => "plain",
+            MessageStyle::Oneline => "oneline",
+        }
+    }
+
+    /// Base system prompt for the final commit message.
+    fn instructions(&self) -> &'static str {
+        match self {
+            MessageStyle::Grouped => prompts::SYSTEM_INSTRUCTIONS,
+            MessageStyle::Plain => prompts::PLAIN_INSTRUCTIONS,
+            MessageStyle::Oneline => prompts::ONELINE_INSTRUCTIONS,
+        }
+    }
+}
+
+/// The `Branch: ...` line for commit prompts, or nothing when the branch is left out.
+fn branch_line(branch: &str, opts: &PromptOptions) -> String {
+    let include = match opts.branch_context {
+        BranchContext::Always => true,
+        BranchContext::Never => false,
+        BranchContext::Auto => {
+            if opts.trunk_branches.is_empty() {
+                !DEFAULT_TRUNK_BRANCHES.contains(&branch)
+            } else {
+                !opts.trunk_branches.iter().any(|t| t == branch)
+            }
+        }
+    };
+    if include {
+        format!("Branch: {branch}\n")
+    } else {
+        String::new()
+    }
+}
+
+/// How prominently one category of files should feature in the final message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CategoryWeight {
+    pub category: FileCategory,
+    /// Instruction shown to the model above this category's summaries.
+    pub emphasis: String,
+}
+
+/// Built-in emphasis for each category.
+pub fn default_category_emphasis(category: FileCategory) -> &'static str {
+    match category {
+        FileCategory::Main => "Lead with these; they define the purpose of the commit.",
+        FileCategory::Supporting => "Mention briefly, as changes that enable the main purpose.",
+        FileCategory::Consequence => {
+            "Mention these only if they are not an obvious result of the main changes."
+        }
+        FileCategory::Ignored => "Do not mention these.",
+    }
+}
+
+/// Built-in order: Main, then Supporting, then Consequence.
+pub fn default_category_weights() -> Vec<CategoryWeight> {
+    [
+        FileCategory::Main,
+        FileCategory::Supporting,
+        FileCategory::Consequence,
+    ]
+    .into_iter()
+    .map(|category| CategoryWeight {
+        category,
+        emphasis: default_category_emphasis(category).to_string(),
+    })
+    .collect()
+}
+
+/// Map common ISO 639-1 codes to a language name; anything else passes through as-is.
+pub fn language_name(code: &str) -> &str {
+    match code.trim().to_lowercase().as_str() {
+        "en" => "English",
+        "es" => "Spanish",
+        "fr" => "French",
+        "de" => "German",
+        "it" => "Italian",
+        "pt" => "Portuguese",
+        "nl" => "Dutch",
+        "pl" => "Polish",
+        "ru" => "Russian",
+        "uk" => "Ukrainian",
+        "ja" => "Japanese",
+        "ko" => "Korean",
+        "zh" => "Chinese",
+        "sv" => "Swedish",
+        "tr" => "Turkish",
+        _ => code.trim(),
+    }
+}
+
+/// Primary change type suggested by [`suggest_commit_type`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommitType {
+    Feat,
+    Fix,
+    Test,
+    Docs,
+    Refactor,
+}
+
+impl CommitType {
+    /// Convert the type to its conventional-commit label.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommitType::Feat => "feat",
+            CommitType::Fix => "fix",
+            CommitType::Test => "test",
+            CommitType::Docs => "docs",
+            CommitType::Refactor => "refactor",
+        }
+    }
+}
+
+/// Lines added (`+`) or removed (`-`) in a unified diff, excluding file headers.
+fn changed_lines(diff: &str) -> impl Iterator<Item = (char, &str)> {
+    diff.lines().filter_map(|line| {
+        if line.starts_with("+++") || line.starts_with("---") {
+            return None;
+        }
+        let mut chars = line.chars();
+        match chars.next() {
+            Some(c @ ('+' | '-')) => Some((c, chars.as_str())),
+            _ => None,
+        }
+    })
+}
+
+/// Files that are documentation by name whatever their extension (`README.txt`, `LICENSE`).
+const DOC_FILE_STEMS: &[&str] = &[
+    "readme",
+    "license",
+    "licence",
+    "copying",
+    "notice",
+    "authors",
+    "contributing",
+    "changelog",
+    "changes",
+    "history",
+    "news",
+];
+
+/// Markup files, files in a docs directory, and well-known doc names; other `.txt` files
+/// (`requirements.txt`, `CMakeLists.txt`, fixtures) are not documentation.
+fn is_docs_path(path: &str) -> bool {
+    let lower = path.to_lowercase();
+    let file_name = lower.rsplit('/').next().unwrap_or(&lower);
+    let stem = file_name.split('.').next().unwrap_or(file_name);
+    ["docs/", "doc/", "documentation/"]
+        .iter()
+        .any(|dir| lower.starts_with(dir) || lower.contains(&format!("/{dir}")))
+        || [".md", ".rst", ".adoc"]
+            .iter()
+            .any(|ext| lower.ends_with(ext))
+        || DOC_FILE_STEMS.contains(&stem)
+}
+
+fn is_test_path(path: &str) -> bool {
+    let lower = path.to_lowercase();
+    let file_name = lower.rsplit('/').next().unwrap_or(&lower);
+    lower.starts_with("tests/")
+        || lower.starts_with("test/")
+        || lower.contains("/tests/")
+        || lower.contains("/test/")
+        || lower.contains("/__tests__/")
+        || file_name.starts_with("test_")
+        || file_name.contains("_test.")
+        || file_name.contains(".test.")
+        || file_name.contains("_spec.")
+        || file_name.contains(".spec.")
+}
+
+/// Default gitignore-style patterns for [`is_test_file`].
+pub const DEFAULT_TEST_PATTERNS: &[&str] = &["**/tests/**", "*_test.rs", "*.test.*"];
+
+/// Whether `path` matches the test patterns (or the defaults when `patterns` is empty).
+pub fn is_test_file(path: &str, patterns: &[String]) -> bool {
+    let rules = if patterns.is_empty() {
+        IgnoreRules::from_patterns(DEFAULT_TEST_PATTERNS)
+    } else {
+        IgnoreRules::from_patterns(patterns)
+    };
+    rules.is_ignored(path)
+}
+
+/// Paths of non-ignored files that look like tests.
+pub fn changed_test_files<'a>(files: &'a [FileChange], opts: &PromptOptions) -> Vec<&'a str> {
+    files
+        .iter()
+        .filter(|f| !matches!(f.category, FileCategory::Ignored))
+        .filter(|f| is_test_file(&f.path, &opts.test_patterns))
+        .map(|f| f.path.as_str())
+        .collect()
+}
+
+fn adds_definition(diff: &str) -> bool {
+    const DEFINITION_PREFIXES: &[&str] = &[
+        "fn ",
+        "pub fn ",
+        "pub(crate) fn ",
+        "async fn ",
+        "def ",
+        "function ",
+        "func ",
+        "class ",
+        "struct ",
+        "pub struct ",
+        "enum ",
+        "pub enum ",
+        "interface ",
+        "export function ",
+        "export class ",
+        "export interface ",
+    ];
+    diff.contains("\nnew file mode")
+        || diff.starts_with("new file mode")
+        || changed_lines(diff).any(|(sign, line)| {
+            let line = line.trim_start();
+            sign == '+' && DEFINITION_PREFIXES.iter().any(|p| line.starts_with(p))
+        })
+}
+
+fn touches_conditionals(diff: &str) -> bool {
+    // whole words only, so `diff`, `elsewhere`, or `matches` do not count
+    const CONDITIONAL_KEYWORDS: &[&str] = &[
+        "if", "else", "match", "case", "when", "unless", "catch", "except", "unwrap", "null", "nil",
+    ];
+    const CONDITIONAL_SYNTAX: &[&str] = &["?;", "return Err", "== None", "is None"];
+    changed_lines(diff).any(|(_, line)| {
+        CONDITIONAL_KEYWORDS.iter().any(|k| contains_word(line, k))
+            || CONDITIONAL_SYNTAX.iter().any(|m| line.contains(m))
+    })
+}
+
+/// Whether `word` appears in `line` on its own, not as part of a longer identifier.
+fn contains_word(line: &str, word: &str) -> bool {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    line.match_indices(word).any(|(i, _)| {
+        !line[..i].chars().next_back().is_some_and(is_ident)
+            && !line[i + word.len()..].chars().next().is_some_and(is_ident)
+    })
+}
+
+/// Deterministically suggest a primary change type from categories, paths, and diff content.
+///
+/// Rules, in order:
+/// - every relevant file is documentation -> docs
+/// - every relevant file is a test -> test
+/// - a Main file adds a new file or definition -> feat
+/// - a Main file changes conditional/error-handling lines -> fix
+/// - otherwise -> refactor
+///
+/// Ignored files are skipped; if no Main files exist, Supporting files stand in for them.
+pub fn suggest_commit_type(files: &[FileChange]) -> Option<CommitType> {
+    let relevant: Vec<&FileChange> = files
+        .iter()
+        .filter(|f| !matches!(f.category, FileCategory::Ignored))
+        .collect();
+    if relevant.is_empty() {
+        return None;
+    }
+
+    if relevant.iter().all(|f| is_docs_path(&f.path)) {
+        return Some(CommitType::Docs);
+    }
+    if relevant.iter().all(|f| is_test_path(&f.path)) {
+        return Some(CommitType::Test);
+    }
+
+    let mut primary: Vec<&FileChange> = relevant
+        .iter()
+        .copied()
+        .filter(|f| matches!(f.category, FileCategory::Main))
+        .collect();
+    if primary.is_empty() {
+        primary = relevant
+            .iter()
+            .copied()
+            .filter(|f| matches!(f.category, FileCategory::Supporting))
+            .collect();
+    }
+    let primary: Vec<&FileChange> = primary
+        .into_iter()
+        .filter(|f| !is_test_path(&f.path) && !is_docs_path(&f.path))
+        .collect();
+
+    if primary.iter().any(|f| adds_definition(&f.diff)) {
+        Some(CommitType::Feat)
+    } else if primary.iter().any(|f| touches_conditionals(&f.diff)) {
+        Some(CommitType::Fix)
+    } else {
+        Some(CommitType::Refactor)
+    }
+}
+
+/// Append the option-driven directives shared by every system prompt.
+fn push_option_directives(system: &mut String, opts: &PromptOptions) {
+    if let Some(lang) = opts.language.as_deref().filter(|l| !l.trim().is_empty()) {
+        system.push_str(&format!(
+            "\nWrite the output in {}. Keep code identifiers, file paths, and commands untranslated; \
+             length limits still apply.",
+            language_name(lang)
+        ));
+    }
+    if let Some(tone) = opts.tone {
+        system.push('\n');
+        system.push_str(tone.directive());
+    }
+}
+
+/// At most this many history messages are shown as style examples.
+const MAX_STYLE_EXAMPLES: usize = 3;
+/// Total characters of style examples added to the commit prompt.
+const MAX_STYLE_EXAMPLE_CHARS: usize = 1500;
+
+/// Append a few recent commit messages as examples of the repository's voice.
+///
+/// Messages are taken newest first while they fit in [`MAX_STYLE_EXAMPLE_CHARS`]; one that
+/// is too long on its own is skipped rather than cut off.
+fn push_style_examples(system: &mut String, examples: &[String]) {
+    let mut budget = MAX_STYLE_EXAMPLE_CHARS;
+    let picked: Vec<&str> = examples
+        .iter()
+        .map(|e| e.trim())
+        .filter(|e| {
+            let len = e.chars().count();
+            let fits = !e.is_empty() && len <= budget;
+            if fits {
+                budget -= len;
+            }
+            fits
+        })
+        .take(MAX_STYLE_EXAMPLES)
+        .collect();
+    if picked.is_empty() {
+        return;
+    }
+
+    system.push_str(
+        "\nRecent commit messages from this repository are shown below. Match their voice and \
+         conventions (mood, prefixes, capitalization), but describe only the current changes.",
+    );
+    for example in picked {
+        system.push_str("\n---\n");
+        system.push_str(example);
+    }
+    system.push_str("\n---");
+}
+
+/// Diffs with at most this many changed lines get a one-bullet target.
+const SMALL_DIFF_LINES: usize = 10;
+/// Diffs with more than this many changed lines get a 3–5 bullet target.
+const LARGE_DIFF_LINES: usize = 150;
+
+/// Count added and removed lines in the hunks of a unified diff, ignoring file headers.
+pub fn diff_line_counts(diff: &str) -> (usize, usize) {
+    let mut added = 0;
+    let mut removed = 0;
+    let mut in_hunk = false;
+    for line in diff.lines() {
+        if line.starts_with("@@") {
+            in_hunk = true;
+        } else if line.starts_with("diff --git") {
+            in_hunk = false;
+        } else if in_hunk {
+            if line.starts_with('+') {
+                added += 1;
+            } else if line.starts_with('-') {
+                removed += 1;
+            }
+        }
+    }
+    (added, removed)
+}
+
+/// Bullet-count target for a per-file summary, scaled to the number of changed lines.
+fn summary_length_hint(changed_lines: usize) -> &'static str {
+    if changed_lines <= SMALL_DIFF_LINES {
+        "Aim for 1 bullet."
+    } else if changed_lines <= LARGE_DIFF_LINES {
+        "Aim for 2–3 bullets."
+    } else {
+        "Aim for 3–5 bullets."
+    }
+}
+
+/// Language for a path, inferred from its file name or extension.
+///
+/// Returns `None` for unknown extensions so the prompt omits the line.
+pub fn language_for_path(path: &str) -> Option<&'static str> {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    match name {
+        "Dockerfile" | "Containerfile" => return Some("Dockerfile"),
+        "Makefile" | "GNUmakefile" => return Some("Makefile"),
+        "CMakeLists.txt" => return Some("CMake"),
+        "Gemfile" | "Rakefile" => return Some("Ruby"),
+        _ => {}
+    }
+    let (stem, ext) = name.rsplit_once('.')?;
+    if stem.is_empty() {
+        return None;
+    }
+    let lang = match ext.to_ascii_lowercase().as_str() {
+        "rs" => "Rust",
+        "py" | "pyi" => "Python",
+        "js" | "mjs" | "cjs" => "JavaScript",
+        "jsx" => "JavaScript (JSX)",
+        "ts" | "mts" | "cts" => "TypeScript",
+        "tsx" => "TypeScript (TSX)",
+        "go" => "Go",
+        "java" => "Java",
+        "kt" | "kts" => "Kotlin",
+        "swift" => "Swift",
+        "c" | "h" => "C",
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" => "C++",
+        "cs" => "C#",
+        "rb" => "Ruby",
+        "php" => "PHP",
+        "scala" => "Scala",
+        "sh" | "bash" | "zsh" => "Shell",
+        "sql" => "SQL",
+        "html" | "htm" => "HTML",
+        "css" => "CSS",
+        "scss" | "sass" => "Sass",
+        "vue" => "Vue",
+        "svelte" => "Svelte",
+        "md" | "markdown" => "Markdown",
+        "toml" => "TOML",
+        "yaml" | "yml" => "YAML",
+        "json" => "JSON",
+        "xml" => "XML",
+        "ini" | "cfg" => "INI",
+        "tf" => "Terraform",
+        "proto" => "Protocol Buffers",
+        "graphql" | "gql" => "GraphQL",
+        "lua" => "Lua",
+        "ex" | "exs" => "Elixir",
+        "hs" => "Haskell",
+        "dart" => "Dart",
+        _ => return None,
+    };
+    Some(lang)
+}
+
+/// Human-readable line for a submodule bump, followed by the commit subjects it pulls in.
+fn describe_submodule_bump(path: &str, bump: &SubmoduleBump) -> String {
+    let mut out = match &bump.from {
+        Some(from) => format!("bumped submodule `{path}` from `{from}` to `{}`\n", bump.to),
+        None => format!("added submodule `{path}` at `{}`\n", bump.to),
+    };
+    for subject in &bump.subjects {
+        out.push_str(&format!("  - {subject}\n"));
+    }
+    out
+}
+
 pub fn file_summary_prompt(
     branch: &str,
     file: &FileChange,
     file_index: usize,
     total_files: usize,
     ticket_summary: Option<&str>,
+    opts: &PromptOptions,
 ) -> PromptPair {
     let mut system = prompts::FILE_SUMMARY.to_owned();
+    let (added, removed) = diff_line_counts(&file.diff);
+    system.push_str(&format!(
+        "\nThis diff adds {added} and removes {removed} lines. {}",
+        summary_length_hint(added + removed)
+    ));
     if let Some(ts) = ticket_summary {
         system.push_str("\nOverall ticket goal: ");
         system.push_str(ts);
     }
+    push_option_directives(&mut system, opts);
 
     let user = format!(
-        "Branch: {branch}\n\
+        "{branch}\
          File {file_num} of {total_files}: {path}\n\
+         {language}\
          Category: {category}\n\n\
          Diff:\n\
          ```diff\n{diff}\n```",
-        branch = branch,
+        branch = branch_line(branch, opts),
         file_num = file_index + 1,
         total_files = total_files,
         path = file.path,
+        language = language_for_path(&file.path)
+            .map(|lang| format!("Language: {lang}\n"))
+            .unwrap_or_default(),
         category = file.category.as_str(),
         diff = file.diff
     );
@@ -39,22 +648,118 @@ pub fn file_summary_prompt(
     PromptPair { system, user }
 }
 
+/// Build a single prompt that summarizes several small files at once.
+///
+/// The response is expected to contain one `### <path>` section per file; see
+/// [`split_batch_summary`].
+pub fn batch_summary_prompt(
+    branch: &str,
+    files: &[&FileChange],
+    ticket_summary: Option<&str>,
+    opts: &PromptOptions,
+) -> PromptPair {
+    let mut system = prompts::BATCH_FILE_SUMMARY.to_owned();
+    if let Some(ts) = ticket_summary {
+        system.push_str("\nOverall ticket goal: ");
+        system.push_str(ts);
+    }
+    push_option_directives(&mut system, opts);
+
+    let mut user = branch_line(branch, opts);
+    if !user.is_empty() {
+        user.push('\n');
+    }
+    for file in files {
+        user.push_str(&format!(
+            "### {path}\nCategory: {category}\n```diff\n{diff}\n```\n\n",
+            path = file.path,
+            category = file.category.as_str(),
+            diff = file.diff
+        ));
+    }
+
+    PromptPair {
+        system,
+        user: user.trim_end().to_string(),
+    }
+}
+
+/// Split a batch summary response into per-file summaries, in the order of `paths`.
+///
+/// Files the model skipped come back as `None`.
+pub fn split_batch_summary(response: &str, paths: &[&str]) -> Vec<Option<String>> {
+    let mut sections: Vec<(String, String)> = Vec::new();
+    for line in response.lines() {
+        if let Some(header) = line.trim().strip_prefix("### ") {
+            let path = header.trim().trim_matches('`').to_string();
+            sections.push((path, String::new()));
+        } else if let Some((_, body)) = sections.last_mut() {
+            body.push_str(line);
+            body.push('\n');
+        }
+    }
+
+    paths
+        .iter()
+        .map(|path| {
+            sections
+                .iter()
+                .find(|(p, _)| p == path)
+                .map(|(_, body)| body.trim().to_string())
+                .filter(|body| !body.is_empty())
+        })
+        .collect()
+}
+
 pub fn commit_message_prompt(
     branch: &str,
     files: &[FileChange],
     ticket_summary: Option<&str>,
+    opts: &PromptOptions,
 ) -> PromptPair {
-    let mut system = prompts::SYSTEM_INSTRUCTIONS.to_owned();
+    let mut system = opts.style.instructions().to_owned();
     if let Some(ts) = ticket_summary {
         system.push_str("\nOverall ticket goal: ");
         system.push_str(ts);
     }
+    push_option_directives(&mut system, opts);
+    if let Some(kind) = suggest_commit_type(files) {
+        system.push_str(&format!(
+            "\nA diff heuristic suggests the primary change type is \"{}\". \
+             Treat this as a strong hint, but override it if the summaries clearly disagree.",
+            kind.as_str()
+        ));
+    }
 
-    let per_file = render_per_file_summaries(files);
+    let test_files = changed_test_files(files, opts);
+    if !test_files.is_empty() && opts.style == MessageStyle::Grouped {
+        system.push_str(&format!(
+            "\nTests were added or modified ({}). Call these out under a `## Tests` heading \
+             in the body, even for otherwise single-line messages.",
+            test_files.join(", ")
+        ));
+    }
+    if !opts.partitions.is_empty() && opts.style != MessageStyle::Oneline {
+        system.push_str(&format!(
+            "\nThe changes are partitioned by path ({}). Write the body with one clearly labeled \
+             section per partition, in the order given, so each could become its own commit.",
+            opts.partitions.join(", ")
+        ));
+    }
+    push_style_examples(&mut system, &opts.style_examples);
+    if opts.structured_output {
+        system.push_str("\n\n");
+        system.push_str(prompts::STRUCTURED_OUTPUT);
+    }
+
+    let per_file = render_per_file_summaries(files, opts);
     let file_count = files.len();
     let user = format!(
-        "Branch: {branch}\n\nFiles Changed: {file_count}\n\nPer-file summaries:\n\n{per_file}",
-        branch = branch,
+        "{branch}Files Changed: {file_count}\n\nPer-file summaries:\n\n{per_file}",
+        branch = match branch_line(branch, opts) {
+            line if line.is_empty() => line,
+            line => format!("{line}\n"),
+        },
         file_count = file_count + 1,
         per_file = per_file
     );
@@ -62,22 +767,88 @@ pub fn commit_message_prompt(
     PromptPair { system, user }
 }
 
+/// Build the prompt for a one-line Keep-a-Changelog entry describing the commit.
+pub fn changelog_entry_prompt(
+    files: &[FileChange],
+    commit_message: &str,
+    opts: &PromptOptions,
+) -> PromptPair {
+    let mut system = prompts::CHANGELOG_INSTRUCTIONS.to_owned();
+    push_option_directives(&mut system, opts);
+
+    let user = format!(
+        "Commit message:\n{}\n\nPer-file summaries:\n\n{}",
+        commit_message.trim(),
+        render_per_file_summaries(files, opts)
+    );
+
+    PromptPair { system, user }
+}
+
+/// The sections rule of the PR prompt: the built-in suggestions, or the configured headings.
+fn pr_section_rule(sections: &[String]) -> String {
+    let headings: Vec<String> = sections
+        .iter()
+        .map(|s| s.trim().trim_start_matches('#').trim())
+        .filter(|s| !s.is_empty())
+        .map(|s| format!("  ## {s}"))
+        .collect();
+    if headings.is_empty() {
+        return prompts::PR_DEFAULT_SECTIONS.to_string();
+    }
+    format!(
+        "- Then use exactly these sections, in this order, with these headings and no others:\n{}",
+        headings.join("\n")
+    )
+}
+
 pub fn pr_message_prompt(
     base_branch: &str,
     from_branch: &str,
     mode: PrSummaryMode,
     items: &[PrItem],
     ticket_summary: Option<&str>,
+    opts: &PromptOptions,
 ) -> PromptPair {
-    let mut system = prompts::PR_INSTRUCTIONS.to_owned();
+    // release notes have their own sections; a PR template doesn't apply to them
+    let template = opts
+        .pr_template
+        .as_deref()
+        .filter(|t| !t.trim().is_empty() && !opts.release_notes);
+    let mut system = if opts.release_notes {
+        prompts::RELEASE_NOTES_INSTRUCTIONS.to_string()
+    } else {
+        let sections = match template {
+            Some(_) => prompts::PR_TEMPLATE_SECTIONS.to_string(),
+            None => pr_section_rule(&opts.pr_sections),
+        };
+        prompts::PR_INSTRUCTIONS.replace("{sections}", &sections)
+    };
+    if let Some(template) = template {
+        system.push_str("\n\nTemplate:\n");
+        system.push_str(template.trim_end());
+    }
     if let Some(ts) = ticket_summary {
         system.push_str("\nOverall ticket goal: ");
         system.push_str(ts);
     }
+    push_option_directives(&mut system, opts);
+    if opts.forge == Forge::GitLab {
+        system.push_str(
+            "\nThis is a GitLab merge request: call it an MR and reference MRs as !N, not #N.",
+        );
+    }
 
+    let forge = opts.forge;
+    let label = forge.change_label();
     let mut user = String::new();
+    let base_label = if opts.release_notes {
+        "Previous release tag"
+    } else {
+        "Base branch"
+    };
     user.push_str(&format!(
-        "Base branch: {base}\nFeature branch: {from}\nSummary mode: {mode}\n\n",
+        "{base_label}: {base}\nFeature branch: {from}\nSummary mode: {mode}\n\n",
         base = base_branch,
         from = from_branch,
         mode = mode.as_str()
@@ -90,7 +861,7 @@ pub fn pr_message_prompt(
                 let short = item.commit_hash.chars().take(7).collect::<String>();
                 let pr_tag = item
                     .pr_number
-                    .map(|n| format!(" (PR #{n})"))
+                    .map(|n| format!(" ({})", forge.format_ref(n)))
                     .unwrap_or_default();
                 user.push_str(&format!(
                     "- {short}{pr_tag}: {title}\n",
@@ -116,15 +887,24 @@ pub fn pr_message_prompt(
                 }
             }
 
-            user.push_str("Pull requests contributing to this branch (oldest commits first):\n");
+            let heading = match forge {
+                Forge::GitHub => "Pull requests",
+                Forge::GitLab => "Merge requests",
+            };
+            user.push_str(&format!(
+                "{heading} contributing to this branch (oldest commits first):\n"
+            ));
 
             for (num, group) in grouped {
                 let short = group[0].commit_hash.chars().take(7).collect::<String>();
                 let title = group[0].title.trim();
-                user.push_str(&format!("\nPR #{num}: {title} [{short}]\n"));
+                user.push_str(&format!(
+                    "\n{reference}: {title} [{short}]\n",
+                    reference = forge.format_ref(num)
+                ));
 
                 if group.len() > 1 {
-                    user.push_str("Additional commits in this PR:\n");
+                    user.push_str(&format!("Additional commits in this {label}:\n"));
                     for item in group.iter().skip(1) {
                         let sh = item.commit_hash.chars().take(7).collect::<String>();
                         user.push_str(&format!("- {sh}: {title}\n", title = item.title.trim()));
@@ -133,9 +913,9 @@ pub fn pr_message_prompt(
             }
 
             if !no_pr.is_empty() {
-                user.push_str(
-                    "\nCommits without associated PR numbers (may be small fixes or direct pushes):\n",
-                );
+                user.push_str(&format!(
+                    "\nCommits without associated {label} numbers (may be small fixes or direct pushes):\n"
+                ));
                 for item in no_pr {
                     let short = item.commit_hash.chars().take(7).collect::<String>();
                     user.push_str(&format!("- {short}: {title}\n", title = item.title.trim()));
@@ -147,25 +927,762 @@ pub fn pr_message_prompt(
     PromptPair { system, user }
 }
 
-fn render_per_file_summaries(files: &[FileChange]) -> String {
-    let total_files = files.len();
+/// Words of a summary used for similarity, ignoring case and the punctuation around each word
+/// (backticks, commas, a closing period) but keeping file names whole, so summaries of
+/// different files stay apart.
+fn summary_words(summary: &str) -> HashSet<String> {
+    summary
+        .split_whitespace()
+        .map(|w| {
+            w.trim_matches(|c: char| !c.is_alphanumeric() && c != '_')
+                .to_lowercase()
+        })
+        .filter(|w| !w.is_empty())
+        .collect()
+}
+
+/// Jaccard similarity of two word sets.
+fn word_similarity(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    let shared = a.intersection(b).count();
+    shared as f64 / (a.len() + b.len() - shared) as f64
+}
+
+/// Group positions in `group` whose summaries are near-duplicates of an earlier one.
+///
+/// Each cluster lists its representative first; without a threshold every file is its
+/// own cluster. List-only files and missing summaries are never merged.
+fn cluster_similar_summaries(
+    group: &[(usize, &FileChange)],
+    threshold: Option<f64>,
+) -> Vec<Vec<usize>> {
+    let Some(threshold) = threshold else {
+        return (0..group.len()).map(|pos| vec![pos]).collect();
+    };
+
+    let mut clusters: Vec<(Option<HashSet<String>>, Vec<usize>)> = Vec::new();
+    for (pos, (_, file)) in group.iter().enumerate() {
+        let words = file
+            .summary
+            .as_deref()
+            .filter(|_| file.needs_summary())
+            .map(summary_words);
+
+        let existing = words.as_ref().and_then(|words| {
+            clusters.iter_mut().find(|(rep, _)| {
+                rep.as_ref()
+                    .is_some_and(|rep| word_similarity(rep, words) >= threshold)
+            })
+        });
+        match existing {
+            Some((_, members)) => members.push(pos),
+            None => clusters.push((words, vec![pos])),
+        }
+    }
+    clusters.into_iter().map(|(_, members)| members).collect()
+}
+
+/// Bucket name for a path: the first partition prefix it starts with, or "other".
+pub fn partition_for<'a>(path: &str, partitions: &'a [String]) -> &'a str {
+    let path = path.trim_start_matches("./");
+    partitions
+        .iter()
+        .find(|prefix| path.starts_with(prefix.trim_start_matches("./")))
+        .map(String::as_str)
+        .unwrap_or("other")
+}
+
+fn render_per_file_summaries(files: &[FileChange], opts: &PromptOptions) -> String {
+    let entries: Vec<(usize, &FileChange)> = files.iter().enumerate().collect();
+    if opts.partitions.is_empty() {
+        return render_category_groups(&entries, files.len(), opts);
+    }
+
+    let mut buckets: Vec<&str> = opts.partitions.iter().map(String::as_str).collect();
+    buckets.push("other");
+
     let mut out = String::new();
-    for (idx, file) in files
+    for bucket in buckets {
+        let members: Vec<(usize, &FileChange)> = entries
+            .iter()
+            .copied()
+            .filter(|(_, f)| partition_for(&f.path, &opts.partitions) == bucket)
+            .collect();
+        let rendered = render_category_groups(&members, files.len(), opts);
+        if rendered.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("##### Partition: {bucket} #####\n\n{rendered}"));
+    }
+    out
+}
+
+fn render_category_groups(
+    entries: &[(usize, &FileChange)],
+    total_files: usize,
+    opts: &PromptOptions,
+) -> String {
+    let weights = if opts.category_weights.is_empty() {
+        default_category_weights()
+    } else {
+        opts.category_weights.clone()
+    };
+
+    // Categories missing from the configured order still render, after the listed ones.
+    let mut order: Vec<(FileCategory, Option<&str>)> = weights
         .iter()
-        .enumerate()
-        .filter(|(_, f)| !matches!(f.category, FileCategory::Ignored))
-    {
-        out.push_str(&format!(
-            "File {file_num} of {total_files}: {path}\nCategory: {category}\nSummary:\n{summary}\n\n",
-            file_num = idx + 1,
-            total_files = total_files,
-            path = file.path,
-            category = file.category.as_str(),
-            summary = file
-                .summary
-                .as_deref()
-                .unwrap_or("[missing per-file summary]")
-        ));
+        .map(|w| (w.category, Some(w.emphasis.as_str())))
+        .collect();
+    for category in [
+        FileCategory::Main,
+        FileCategory::Supporting,
+        FileCategory::Consequence,
+    ] {
+        if !order.iter().any(|(c, _)| *c == category) {
+            order.push((category, None));
+        }
+    }
+
+    let mut out = String::new();
+    for (category, emphasis) in order {
+        if matches!(category, FileCategory::Ignored) {
+            continue;
+        }
+
+        let group: Vec<(usize, &FileChange)> = entries
+            .iter()
+            .copied()
+            .filter(|(_, f)| f.category == category)
+            .collect();
+        if group.is_empty() {
+            continue;
+        }
+
+        out.push_str(&format!("=== {} files ===\n", category.as_str()));
+        if let Some(emphasis) = emphasis.filter(|e| !e.trim().is_empty()) {
+            out.push_str(&format!("Emphasis: {}\n", emphasis.trim()));
+        }
+        out.push('\n');
+
+        for cluster in cluster_similar_summaries(&group, opts.duplicate_summary_threshold) {
+            let (idx, file) = group[cluster[0]];
+            if file.is_deleted {
+                out.push_str(&format!(
+                    "File {file_num} of {total_files}: {path}\nCategory: {category}\n\
+                     Summary:\ndeleted `{path}` (reason: [state why only if the other changes make it clear])\n\n",
+                    file_num = idx + 1,
+                    path = file.path,
+                    category = file.category.as_str(),
+                ));
+                continue;
+            }
+            if let Some(bump) = parse_submodule_bump(&file.diff) {
+                out.push_str(&format!(
+                    "File {file_num} of {total_files}: {path}\nCategory: {category}\n\
+                     Summary:\n{summary}\n",
+                    file_num = idx + 1,
+                    path = file.path,
+                    category = file.category.as_str(),
+                    summary = describe_submodule_bump(&file.path, &bump),
+                ));
+                continue;
+            }
+            if file.list_only {
+                out.push_str(&format!(
+                    "File {file_num} of {total_files}: {path}\nCategory: {category}\n\
+                     Summary:\n[listed only; diff excluded from summarization]\n\n",
+                    file_num = idx + 1,
+                    path = file.path,
+                    category = file.category.as_str(),
+                ));
+                continue;
+            }
+            let similar: Vec<&str> = cluster[1..]
+                .iter()
+                .map(|&member| group[member].1.path.as_str())
+                .collect();
+            let path = if similar.is_empty() {
+                file.path.clone()
+            } else {
+                format!(
+                    "{} (and {} similar files: {})",
+                    file.path,
+                    similar.len(),
+                    similar.join(", ")
+                )
+            };
+            out.push_str(&format!(
+                "File {file_num} of {total_files}: {path}\nCategory: {category}\nSummary:\n{summary}\n\n",
+                file_num = idx + 1,
+                total_files = total_files,
+                path = path,
+                category = file.category.as_str(),
+                summary = file
+                    .summary
+                    .as_deref()
+                    .unwrap_or("[missing per-file summary]")
+            ));
+        }
     }
     out
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_file() -> FileChange {
+        FileChange {
+            path: "src/lib.rs".into(),
+            category: FileCategory::Main,
+            diff: "+pub fn login() {}".into(),
+            summary: Some("- Add login".into()),
+            list_only: false,
+            is_deleted: false,
+        }
+    }
+
+    fn change(path: &str, category: FileCategory, diff: &str) -> FileChange {
+        FileChange {
+            path: path.into(),
+            category,
+            diff: diff.into(),
+            summary: None,
+            list_only: false,
+            is_deleted: false,
+        }
+    }
+
+    #[test]
+    fn branch_context_omits_trunk_branches() {
+        let files = vec![sample_file()];
+        let auto = PromptOptions::default();
+        let on_main = commit_message_prompt("main", &files, None, &auto);
+        assert!(!on_main.user.contains("Branch:"));
+        assert!(on_main.user.starts_with("Files Changed:"));
+        let on_feature = commit_message_prompt("feat/login", &files, None, &auto);
+        assert!(on_feature.user.starts_with("Branch: feat/login\n\n"));
+
+        let always = PromptOptions {
+            branch_context: BranchContext::Always,
+            ..PromptOptions::default()
+        };
+        assert!(
+            commit_message_prompt("main", &files, None, &always)
+                .user
+                .contains("Branch: main")
+        );
+
+        let custom_trunk = PromptOptions {
+            trunk_branches: vec!["release".into()],
+            ..PromptOptions::default()
+        };
+        let prompt = file_summary_prompt("release", &files[0], 0, 1, None, &custom_trunk);
+        assert!(prompt.user.starts_with("File 1 of 1"));
+    }
+
+    #[test]
+    fn pr_prompt_uses_configured_sections() {
+        let default = pr_message_prompt(
+            "main",
+            "feat",
+            PrSummaryMode::ByCommits,
+            &[],
+            None,
+            &PromptOptions::default(),
+        );
+        assert!(default.system.contains("## Testing / Validation"));
+        assert!(!default.system.contains("{sections}"));
+
+        let opts = PromptOptions {
+            pr_sections: vec!["Summary".into(), "## How to test".into()],
+            ..PromptOptions::default()
+        };
+        let custom = pr_message_prompt("main", "feat", PrSummaryMode::ByCommits, &[], None, &opts);
+        assert!(custom.system.contains("exactly these sections"));
+        assert!(custom.system.contains("  ## Summary\n  ## How to test\n"));
+        assert!(!custom.system.contains("## Overview"));
+    }
+
+    #[test]
+    fn pr_prompt_fills_in_repo_template() {
+        let opts = PromptOptions {
+            pr_sections: vec!["Summary".into()],
+            pr_template: Some("## What\n\n## Checklist\n- [ ] Tests added\n".into()),
+            ..PromptOptions::default()
+        };
+        let prompt = pr_message_prompt("main", "feat", PrSummaryMode::ByCommits, &[], None, &opts);
+        assert!(prompt.system.contains("Fill in the template below"));
+        assert!(prompt.system.ends_with("## Checklist\n- [ ] Tests added"));
+        assert!(!prompt.system.contains("## Summary"));
+    }
+
+    #[test]
+    fn submodule_bumps_render_their_commit_range() {
+        let diff = "diff --git a/vendor/lib b/vendor/lib\nindex 1111111..2222222 160000\n--- a/vendor/lib\n+++ b/vendor/lib\n@@ -1 +1 @@\n-Subproject commit 1111111aaaaaaaa\n+Subproject commit 2222222bbbbbbbb\n\nSubmodule vendor/lib 1111111..2222222:\n  > Fix overflow in parser\n";
+        let file = change("vendor/lib", FileCategory::Supporting, diff);
+        assert!(!file.needs_summary());
+
+        let rendered = render_per_file_summaries(&[file], &PromptOptions::default());
+        assert!(rendered.contains("bumped submodule `vendor/lib` from `1111111` to `2222222`"));
+        assert!(rendered.contains("  - Fix overflow in parser"));
+    }
+
+    #[test]
+    fn counts_hunk_lines_but_not_headers() {
+        let diff = "diff --git a/a.rs b/a.rs\n--- a/a.rs\n+++ b/a.rs\n@@ -1,3 +1,3 @@\n fn a() {\n-    old();\n+    new();\n+    more();\n }";
+        assert_eq!(diff_line_counts(diff), (2, 1));
+        assert_eq!(diff_line_counts(""), (0, 0));
+    }
+
+    #[test]
+    fn file_summary_prompt_scales_bullet_target() {
+        let opts = PromptOptions::default();
+        let tiny = change("a.rs", FileCategory::Main, "@@ -1 +1 @@\n-a\n+b");
+        let prompt = file_summary_prompt("main", &tiny, 0, 1, None, &opts);
+        assert!(
+            prompt
+                .system
+                .contains("adds 1 and removes 1 lines. Aim for 1 bullet.")
+        );
+
+        let big_diff = format!("@@ -0,0 +1,200 @@\n{}", "+line\n".repeat(200));
+        let big = change("b.rs", FileCategory::Main, &big_diff);
+        let prompt = file_summary_prompt("main", &big, 0, 1, None, &opts);
+        assert!(prompt.system.contains("Aim for 3–5 bullets."));
+    }
+
+    #[test]
+    fn style_swaps_the_commit_instructions() {
+        let files = vec![change("tests/a.rs", FileCategory::Main, "+fn t() {}")];
+        let grouped = commit_message_prompt("main", &files, None, &PromptOptions::default());
+        assert!(grouped.system.starts_with(prompts::SYSTEM_INSTRUCTIONS));
+        assert!(grouped.system.contains("## Tests"));
+
+        let opts = PromptOptions {
+            style: MessageStyle::Oneline,
+            ..PromptOptions::default()
+        };
+        let oneline = commit_message_prompt("main", &files, None, &opts);
+        assert!(oneline.system.starts_with(prompts::ONELINE_INSTRUCTIONS));
+        assert!(!oneline.system.contains("## Tests"));
+
+        assert_eq!(MessageStyle::parse(" Plain "), Some(MessageStyle::Plain));
+        assert_eq!(MessageStyle::parse("fancy"), None);
+    }
+
+    #[test]
+    fn changelog_prompt_includes_message_and_summaries() {
+        let mut file = change("src/a.rs", FileCategory::Main, "+fn a() {}");
+        file.summary = Some("- Add a".into());
+        let prompt = changelog_entry_prompt(&[file], "Add a\n", &PromptOptions::default());
+        assert!(prompt.system.starts_with(prompts::CHANGELOG_INSTRUCTIONS));
+        assert!(prompt.user.starts_with("Commit message:\nAdd a\n\n"));
+        assert!(prompt.user.contains("- Add a"));
+    }
+
+    #[test]
+    fn infers_language_from_extension() {
+        assert_eq!(language_for_path("src/main.rs"), Some("Rust"));
+        assert_eq!(language_for_path("web/App.TSX"), Some("TypeScript (TSX)"));
+        assert_eq!(language_for_path("deploy/values.yml"), Some("YAML"));
+        assert_eq!(language_for_path("docker/Dockerfile"), Some("Dockerfile"));
+        assert_eq!(language_for_path(".gitignore"), None);
+        assert_eq!(language_for_path("LICENSE"), None);
+        assert_eq!(language_for_path("data.bin"), None);
+    }
+
+    #[test]
+    fn file_summary_prompt_names_the_language_when_known() {
+        let opts = PromptOptions::default();
+        let rust = change("src/a.rs", FileCategory::Main, "+fn a() {}");
+        let prompt = file_summary_prompt("main", &rust, 0, 1, None, &opts);
+        assert!(prompt.user.contains("src/a.rs\nLanguage: Rust\nCategory:"));
+
+        let unknown = change("notes.xyz", FileCategory::Main, "+x");
+        let prompt = file_summary_prompt("main", &unknown, 0, 1, None, &opts);
+        assert!(!prompt.user.contains("Language:"));
+    }
+
+    #[test]
+    fn suggests_docs_when_only_docs_change() {
+        let files = vec![change("README.md", FileCategory::Main, "+More docs")];
+        assert_eq!(suggest_commit_type(&files), Some(CommitType::Docs));
+    }
+
+    #[test]
+    fn plain_text_files_are_not_docs_by_extension() {
+        for path in ["README.txt", "docs/setup.txt", "LICENSE", "guide/intro.rst"] {
+            assert!(is_docs_path(path), "{path}");
+        }
+        for path in [
+            "requirements.txt",
+            "CMakeLists.txt",
+            "tests/fixtures/input.txt",
+        ] {
+            assert!(!is_docs_path(path), "{path}");
+        }
+    }
+
+    #[test]
+    fn suggests_test_when_only_tests_change() {
+        let files = vec![
+            change("tests/git.rs", FileCategory::Main, "+fn new_case() {}"),
+            change("src/parser_test.go", FileCategory::Supporting, "+x"),
+        ];
+        assert_eq!(suggest_commit_type(&files), Some(CommitType::Test));
+    }
+
+    #[test]
+    fn suggests_feat_for_new_definitions() {
+        let files = vec![
+            change("src/auth.rs", FileCategory::Main, "+pub fn login() {}"),
+            change("tests/auth.rs", FileCategory::Supporting, "+fn t() {}"),
+        ];
+        assert_eq!(suggest_commit_type(&files), Some(CommitType::Feat));
+    }
+
+    #[test]
+    fn suggests_fix_for_modified_conditionals() {
+        let diff = "--- a/src/io.rs\n+++ b/src/io.rs\n-    if len > max {\n+    if len >= max {";
+        let files = vec![change("src/io.rs", FileCategory::Main, diff)];
+        assert_eq!(suggest_commit_type(&files), Some(CommitType::Fix));
+    }
+
+    #[test]
+    fn conditional_keywords_inside_identifiers_do_not_count() {
+        for line in [
+            "+let diff = staged_diff();",
+            "+// handled elsewhere",
+            "+let matches = filter(items);",
+            "+let unwrapped_len = nil_count + if_count;",
+        ] {
+            assert!(!touches_conditionals(line), "{line}");
+        }
+        assert!(touches_conditionals("+} else {"));
+        assert!(touches_conditionals("+let v = x.unwrap();"));
+        assert!(touches_conditionals("+if(ready) go();"));
+    }
+
+    #[test]
+    fn suggests_refactor_otherwise_and_skips_ignored() {
+        let files = vec![
+            change("src/io.rs", FileCategory::Main, "-let a = 1;\n+let b = 1;"),
+            change("src/new.rs", FileCategory::Ignored, "+pub fn ignored() {}"),
+        ];
+        assert_eq!(suggest_commit_type(&files), Some(CommitType::Refactor));
+        assert_eq!(suggest_commit_type(&files[1..]), None);
+    }
+
+    #[test]
+    fn language_codes_map_to_names_and_unknown_passes_through() {
+        assert_eq!(language_name("es"), "Spanish");
+        assert_eq!(language_name(" DE "), "German");
+        assert_eq!(language_name("Klingon"), "Klingon");
+    }
+
+    #[test]
+    fn language_directive_reaches_every_system_prompt() {
+        let opts = PromptOptions {
+            language: Some("es".into()),
+            ..Default::default()
+        };
+        let files = vec![sample_file()];
+
+        let file = file_summary_prompt("main", &files[0], 0, 1, None, &opts);
+        let commit = commit_message_prompt("main", &files, None, &opts);
+        let pr = pr_message_prompt("main", "feat", PrSummaryMode::ByCommits, &[], None, &opts);
+
+        for system in [file.system, commit.system, pr.system] {
+            assert!(system.contains("Write the output in Spanish."));
+        }
+    }
+
+    #[test]
+    fn gitlab_forge_renders_merge_request_references() {
+        let items = vec![PrItem {
+            commit_hash: "abcdef123456".into(),
+            title: "Add login".into(),
+            body: String::new(),
+            pr_number: Some(42),
+        }];
+        let opts = PromptOptions {
+            forge: Forge::GitLab,
+            ..Default::default()
+        };
+
+        let by_prs = pr_message_prompt("main", "feat", PrSummaryMode::ByPrs, &items, None, &opts);
+        assert!(by_prs.user.contains("MR !42: Add login"));
+        assert!(by_prs.user.contains("Merge requests contributing"));
+
+        let by_commits = pr_message_prompt(
+            "main",
+            "feat",
+            PrSummaryMode::ByCommits,
+            &items,
+            None,
+            &opts,
+        );
+        assert!(by_commits.user.contains("(MR !42)"));
+    }
+
+    #[test]
+    fn per_file_summaries_follow_category_order_with_emphasis() {
+        let files = vec![
+            change("src/ripple.rs", FileCategory::Consequence, ""),
+            change("src/core.rs", FileCategory::Main, ""),
+            change("src/skip.rs", FileCategory::Ignored, ""),
+            change("src/helper.rs", FileCategory::Supporting, ""),
+        ];
+
+        let rendered = render_per_file_summaries(&files, &PromptOptions::default());
+        let main_at = rendered.find("src/core.rs").unwrap();
+        let supporting_at = rendered.find("src/helper.rs").unwrap();
+        let consequence_at = rendered.find("src/ripple.rs").unwrap();
+        assert!(main_at < supporting_at && supporting_at < consequence_at);
+        assert!(!rendered.contains("src/skip.rs"));
+        assert!(rendered.contains(default_category_emphasis(FileCategory::Consequence)));
+
+        let opts = PromptOptions {
+            category_weights: vec![CategoryWeight {
+                category: FileCategory::Consequence,
+                emphasis: "Describe these in detail.".into(),
+            }],
+            ..Default::default()
+        };
+        let rendered = render_per_file_summaries(&files, &opts);
+        assert!(rendered.find("src/ripple.rs").unwrap() < rendered.find("src/core.rs").unwrap());
+        assert!(rendered.contains("Emphasis: Describe these in detail."));
+    }
+
+    #[test]
+    fn list_only_files_are_listed_without_summary() {
+        let mut file = change("Cargo.lock", FileCategory::Consequence, "+huge");
+        file.list_only = true;
+
+        let rendered = render_per_file_summaries(&[file], &PromptOptions::default());
+        assert!(rendered.contains("Cargo.lock"));
+        assert!(rendered.contains("[listed only; diff excluded from summarization]"));
+    }
+
+    #[test]
+    fn tone_directive_reaches_every_prompt() {
+        let opts = PromptOptions {
+            tone: Some(Tone::Concise),
+            ..Default::default()
+        };
+        let directive = Tone::Concise.directive();
+        let files = vec![sample_file()];
+
+        let summary = file_summary_prompt("main", &files[0], 0, 1, None, &opts);
+        assert!(summary.system.contains(directive));
+        let commit = commit_message_prompt("main", &files, None, &opts);
+        assert!(commit.system.contains(directive));
+        let pr = pr_message_prompt("main", "feat", PrSummaryMode::ByCommits, &[], None, &opts);
+        assert!(pr.system.contains(directive));
+
+        let plain = commit_message_prompt("main", &files, None, &PromptOptions::default());
+        assert!(!plain.system.contains(directive));
+    }
+
+    #[test]
+    fn tag_ranges_get_release_notes_instead_of_the_pr_template() {
+        let opts = PromptOptions {
+            release_notes: true,
+            pr_template: Some("## Checklist\n- [ ] Tests".into()),
+            ..Default::default()
+        };
+        let pr = pr_message_prompt("v1.2.0", "main", PrSummaryMode::ByCommits, &[], None, &opts);
+        assert!(pr.system.starts_with("You write release notes"));
+        assert!(pr.system.contains("## Fixed"));
+        assert!(!pr.system.contains("Checklist"));
+        assert!(!pr.system.contains("Overall ticket goal"));
+        assert!(pr.user.starts_with("Previous release tag: v1.2.0\n"));
+    }
+
+    #[test]
+    fn deleted_files_are_named_without_summary() {
+        let mut file = change("src/old.rs", FileCategory::Consequence, "-fn old() {}");
+        file.is_deleted = true;
+
+        let rendered = render_per_file_summaries(&[file], &PromptOptions::default());
+        assert!(rendered.contains("deleted `src/old.rs`"));
+        assert!(!rendered.contains("[missing per-file summary]"));
+    }
+
+    #[test]
+    fn batch_prompt_lists_every_file() {
+        let a = change("Cargo.lock", FileCategory::Consequence, "+dep");
+        let b = change("src/mod.rs", FileCategory::Consequence, "+mod x;");
+
+        let prompt = batch_summary_prompt("main", &[&a, &b], None, &PromptOptions::default());
+        assert!(prompt.user.contains("### Cargo.lock"));
+        assert!(prompt.user.contains("### src/mod.rs"));
+        assert!(prompt.system.contains("header line of ###"));
+    }
+
+    #[test]
+    fn split_batch_summary_matches_sections_by_path() {
+        let response = "### `src/mod.rs`\n- Register module\n\n### Cargo.lock\n- Bump deps\n";
+        let parts = split_batch_summary(response, &["Cargo.lock", "src/mod.rs", "missing.rs"]);
+
+        assert_eq!(parts[0].as_deref(), Some("- Bump deps"));
+        assert_eq!(parts[1].as_deref(), Some("- Register module"));
+        assert_eq!(parts[2], None);
+    }
+
+    #[test]
+    fn partitions_render_separate_sections() {
+        let mut files = vec![
+            change("src/auth.rs", FileCategory::Main, "+fn login() {}"),
+            change("tests/auth.rs", FileCategory::Supporting, "+#[test]"),
+            change("README.md", FileCategory::Supporting, "+docs"),
+        ];
+        for f in &mut files {
+            f.summary = Some(format!("- touch {}", f.path));
+        }
+        let opts = PromptOptions {
+            partitions: vec!["tests/".into(), "src/".into()],
+            ..PromptOptions::default()
+        };
+
+        let prompt = commit_message_prompt("main", &files, None, &opts);
+        let tests_at = prompt.user.find("##### Partition: tests/ #####").unwrap();
+        let src_at = prompt.user.find("##### Partition: src/ #####").unwrap();
+        let other_at = prompt.user.find("##### Partition: other #####").unwrap();
+        assert!(tests_at < src_at && src_at < other_at);
+        assert!(prompt.user[tests_at..src_at].contains("tests/auth.rs"));
+        assert!(prompt.user[other_at..].contains("README.md"));
+        assert!(prompt.system.contains("partitioned by path (tests/, src/)"));
+    }
+
+    #[test]
+    fn style_examples_are_capped() {
+        let files = vec![change("src/auth.rs", FileCategory::Main, "+fn login() {}")];
+        let opts = PromptOptions {
+            style_examples: vec![
+                "feat(auth): add login".into(),
+                "x".repeat(MAX_STYLE_EXAMPLE_CHARS + 1),
+                "fix(db): close pool on shutdown".into(),
+                "docs: update README".into(),
+                "chore: bump deps".into(),
+            ],
+            ..PromptOptions::default()
+        };
+
+        let system = commit_message_prompt("main", &files, None, &opts).system;
+        assert!(system.contains("---\nfeat(auth): add login\n---\nfix(db): close pool"));
+        assert!(system.contains("docs: update README"));
+        assert!(!system.contains("chore: bump deps"));
+        assert!(!system.contains("xxxx"));
+
+        let plain = commit_message_prompt("main", &files, None, &PromptOptions::default());
+        assert!(!plain.system.contains("Recent commit messages"));
+    }
+
+    #[test]
+    fn structured_output_asks_for_json() {
+        let files = vec![change("src/auth.rs", FileCategory::Main, "+fn login() {}")];
+        let opts = PromptOptions {
+            structured_output: true,
+            ..PromptOptions::default()
+        };
+        let system = commit_message_prompt("main", &files, None, &opts).system;
+        assert!(system.ends_with(prompts::STRUCTURED_OUTPUT));
+
+        let plain = commit_message_prompt("main", &files, None, &PromptOptions::default());
+        assert!(!plain.system.contains("JSON"));
+    }
+
+    #[test]
+    fn partition_for_uses_first_matching_prefix() {
+        let partitions = vec!["src/llm/".to_string(), "src/".to_string()];
+        assert_eq!(partition_for("src/llm/openai.rs", &partitions), "src/llm/");
+        assert_eq!(partition_for("./src/main.rs", &partitions), "src/");
+        assert_eq!(partition_for("Cargo.toml", &partitions), "other");
+    }
+
+    #[test]
+    fn collapses_near_duplicate_summaries() {
+        let mut files: Vec<FileChange> = ["src/a.rs", "src/b.rs", "src/c.rs", "src/d.rs"]
+            .iter()
+            .map(|p| change(p, FileCategory::Supporting, "-old_name\n+new_name"))
+            .collect();
+        files[0].summary = Some("- Rename `old_name` to `new_name` in src/a.rs".into());
+        files[1].summary = Some("- Rename `old_name` to `new_name` in src/b.rs.".into());
+        files[2].summary = Some("- rename old_name to new_name in src/c.rs".into());
+        files[3].summary = Some("- Add retry handling for flaky uploads".into());
+
+        let opts = PromptOptions {
+            duplicate_summary_threshold: Some(0.7),
+            ..PromptOptions::default()
+        };
+        let rendered = render_per_file_summaries(&files, &opts);
+        assert!(rendered.contains("src/a.rs (and 2 similar files: src/b.rs, src/c.rs)"));
+        assert!(!rendered.contains("File 2 of 4"));
+        assert!(rendered.contains("File 4 of 4: src/d.rs\n"));
+
+        let rendered = render_per_file_summaries(&files, &PromptOptions::default());
+        assert!(rendered.contains("File 2 of 4: src/b.rs"));
+    }
+
+    #[test]
+    fn summaries_naming_different_files_are_kept_apart() {
+        let mut files: Vec<FileChange> = ["src/config.rs", "src/main.rs"]
+            .iter()
+            .map(|p| change(p, FileCategory::Main, "+validate_port(port)?;"))
+            .collect();
+        files[0].summary = Some("- Validate the port in `config.rs`".into());
+        files[1].summary = Some("- Validate the port in `main.rs`.".into());
+
+        let opts = PromptOptions {
+            duplicate_summary_threshold: Some(0.7),
+            ..PromptOptions::default()
+        };
+        let rendered = render_per_file_summaries(&files, &opts);
+        assert!(rendered.contains("File 2 of 2: src/main.rs"), "{rendered}");
+        assert!(!rendered.contains("similar file"), "{rendered}");
+    }
+
+    #[test]
+    fn detects_test_files_with_default_patterns() {
+        assert!(is_test_file("tests/config.rs", &[]));
+        assert!(is_test_file("crates/core/tests/api.rs", &[]));
+        assert!(is_test_file("src/parser_test.rs", &[]));
+        assert!(is_test_file("web/app.test.ts", &[]));
+        assert!(!is_test_file("src/testing.rs", &[]));
+        assert!(!is_test_file("src/main.rs", &[]));
+    }
+
+    #[test]
+    fn custom_test_patterns_replace_defaults() {
+        let patterns = vec!["spec/**".to_string()];
+        assert!(is_test_file("spec/models/user_spec.rb", &patterns));
+        assert!(!is_test_file("tests/config.rs", &patterns));
+    }
+
+    #[test]
+    fn commit_prompt_requests_tests_heading_when_tests_change() {
+        let files = vec![
+            change("src/auth.rs", FileCategory::Main, "+fn login() {}"),
+            change("tests/auth.rs", FileCategory::Supporting, "+#[test]"),
+            change("tests/old.rs", FileCategory::Ignored, "-#[test]"),
+        ];
+
+        let prompt = commit_message_prompt("main", &files, None, &PromptOptions::default());
+        assert!(prompt.system.contains("## Tests"));
+        assert!(prompt.system.contains("(tests/auth.rs)"));
+
+        let prompt = commit_message_prompt("main", &files[..1], None, &PromptOptions::default());
+        assert!(!prompt.system.contains("## Tests"));
+    }
+
+    #[test]
+    fn no_language_directive_by_default() {
+        let files = vec![sample_file()];
+        let commit = commit_message_prompt("main", &files, None, &PromptOptions::default());
+        assert!(!commit.system.contains("Write the output in"));
+    }
+}
@@ -29,6 +29,26 @@ Rules:
 - Output only the commit message.
 - Do not add commentary or decision reasoning."#;
 
+pub const PLAIN_INSTRUCTIONS: &str = r#"You are a Git commit message assistant given per-file summaries of a changeset.
+Write a commit message with a subject line and a short prose body.
+
+Rules:
+- First line under 50 characters, imperative mood.
+- Then a blank line and one or two short paragraphs explaining what changed and why.
+- No headings, no bullet points.
+- Precise verbs over vague ones ("Extract", "Wire up", "Expose" vs "Update", "Improve").
+- Output only the commit message.
+- Do not add commentary or decision reasoning."#;
+
+pub const ONELINE_INSTRUCTIONS: &str = r#"You are a Git commit message assistant given per-file summaries of a changeset.
+Write a single-line commit message: a subject only, with no body.
+
+Rules:
+- 3–12 words, under 72 characters, imperative mood.
+- Precise verbs over vague ones ("Extract", "Wire up", "Expose" vs "Update", "Improve").
+- Output only the subject line.
+- Do not add commentary or decision reasoning."#;
+
 pub const FILE_SUMMARY: &str = r#"Summarize the intent of changes to this file into as few bullets as possible.
 
 - Focus on WHY, not WHAT (the reader has the diff).
@@ -37,16 +57,22 @@ pub const FILE_SUMMARY: &str = r#"Summarize the intent of changes to this file i
 - Output only the bullet list.
 - Use dashes '-' for bullet points, never use '*' or '•'."#;
 
+pub const BATCH_FILE_SUMMARY: &str = r#"Summarize the intent of changes to each of the following files.
+These are small ripple changes (consequences of the main change), so keep each summary to one or two bullets.
+
+- For every file, output a header line of ### followed by the path exactly as given, then its bullet list.
+- Include every file, in the order given.
+- Focus on WHY, not WHAT (the reader has the diff).
+- Label mechanical, formatting, or metadata-only changes as such.
+- No code, no speculation, no narration.
+- Use dashes '-' for bullet points, never use '*' or '•'."#;
+
 pub const PR_INSTRUCTIONS: &str = r#"You are a GitHub Pull Request description assistant.
 Summarize the *story* and *intent* of the branch, not the diff.
 
 Rules:
 - Start with a concise PR title (<= 72 characters and no formatting).
-- Then include sections such as:
-  ## Overview
-  ## Changes
-  ## Testing / Validation
-  ## Notes / Risks
+{sections}
 - Focus on user-visible behavior, system impact, and domain intent.
 - Treat the PR as a unit of work, not a list of commits.
 - De-emphasize mechanical, formatting-only, or metadata-only changes.
@@ -55,3 +81,49 @@ Rules:
 - Avoid generic phrases like "misc changes" or "small fixes".
 - Use dashes '-' for bullet points, never use '*' or '•'.
 - Do not add commentary or decision reasoning."#;
+
+/// Used instead of PR_INSTRUCTIONS when `pr` summarizes everything since a tag.
+pub const RELEASE_NOTES_INSTRUCTIONS: &str = r#"You write release notes for everything merged since the previous release tag.
+Summarize what changed for users of this release, not the individual commits.
+
+Rules:
+- Start with a concise release title (<= 72 characters and no formatting).
+- Then group the changes under these headings, leaving out any that would be empty:
+  ## Added
+  ## Changed
+  ## Fixed
+- Write one bullet per user-visible change, describing its effect rather than the implementation.
+- Combine related commits into one bullet; leave out merges, formatting-only, and release chores.
+- Reference pull request numbers or commit hashes when appropriate.
+- Use dashes '-' for bullet points, never use '*' or '•'.
+- Do not add commentary or decision reasoning."#;
+
+pub const CHANGELOG_INSTRUCTIONS: &str = r#"You write changelog entries for end users, following Keep a Changelog.
+Given a commit message and per-file summaries, write exactly one entry.
+
+Rules:
+- Output one line: "<Category>: <entry>", where Category is one of Added, Changed, Deprecated, Removed, Fixed, Security.
+- The entry is a single user-facing sentence fragment under 100 characters, without a trailing period.
+- Describe the effect for users, not the implementation; leave out file names unless users see them.
+- Output only that line.
+- Do not add commentary or decision reasoning."#;
+
+/// Appended to the commit instructions when `structured_output` is on.
+pub const STRUCTURED_OUTPUT: &str = r#"Return the commit message as a JSON object and nothing else:
+{"subject": "...", "body": "...", "sections": [{"heading": "...", "items": ["..."]}]}
+- subject: the first line of the message.
+- body: prose paragraphs, or "" when the rules above call for bullets or a single line.
+- sections: the bullet points; one section with an empty heading for plain bullets, or one per group. Items have no leading dash.
+- Leave body and sections empty when the message is a single line."#;
+
+/// Section guidance used in PR_INSTRUCTIONS when `pr_sections` is not configured.
+pub const PR_DEFAULT_SECTIONS: &str = r#"- Then include sections such as:
+  ## Overview
+  ## Changes
+  ## Testing / Validation
+  ## Notes / Risks"#;
+
+/// Section guidance used in PR_INSTRUCTIONS when the repository ships a PR template.
+pub const PR_TEMPLATE_SECTIONS: &str = r#"- Fill in the template below: keep its headings, order, and checklists, and put your content under the matching headings.
+- Keep checkbox items ('- [ ]') exactly as written unless the commits clearly show the item is done.
+- Drop the template's HTML comments and placeholder hints."#;
@@ -0,0 +1,194 @@
+//! Retrying requests that failed for reasons likely to pass: rate limits (429), server errors
+//! (5xx), and dropped or timed-out connections.
+
+use super::cancel::{self, CancellationToken};
+use anyhow::Result;
+use reqwest::blocking::Response;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::thread;
+use std::time::Duration;
+
+/// Attempts per request (the first try plus retries) unless configured otherwise.
+pub const DEFAULT_MAX_ATTEMPTS: usize = 4;
+
+/// How long to sleep between checks of the cancellation token while backing off.
+const CANCEL_POLL: Duration = Duration::from_millis(100);
+
+/// How many times to try a request and how long to wait between tries.
+///
+/// The wait doubles after each failure, starting at `base_delay` and capped at `max_delay`,
+/// and is jittered so concurrent summaries that hit a rate limit together don't retry together.
+/// A `Retry-After` header from the server takes precedence (still capped at `max_delay`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_attempts: usize,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_ATTEMPTS)
+    }
+}
+
+impl RetryPolicy {
+    pub fn new(max_attempts: usize) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+
+    /// Send a request built by `send`, retrying transient failures.
+    ///
+    /// Returns the last response once attempts run out, so a final 429 or 5xx is reported by
+    /// the caller like any other error status.
+    pub fn send<F>(
+        &self,
+        provider: &str,
+        cancel: Option<&CancellationToken>,
+        mut send: F,
+    ) -> Result<Response>
+    where
+        F: FnMut() -> reqwest::Result<Response>,
+    {
+        let mut attempt = 1;
+        loop {
+            cancel::check(cancel)?;
+            let result = send();
+            if attempt >= self.max_attempts {
+                return Ok(result?);
+            }
+
+            let (delay, reason) = match &result {
+                Ok(resp) if is_retryable_status(resp.status().as_u16()) => (
+                    retry_after(resp.headers())
+                        .map(|d| d.min(self.max_delay))
+                        .unwrap_or_else(|| self.backoff(attempt)),
+                    format!("HTTP {}", resp.status().as_u16()),
+                ),
+                Err(err) if is_transient(err) => (self.backoff(attempt), err.to_string()),
+                _ => return Ok(result?),
+            };
+
+            log::warn!(
+                "{provider} request failed ({reason}); retrying in {:.1}s (attempt {}/{})",
+                delay.as_secs_f64(),
+                attempt + 1,
+                self.max_attempts
+            );
+            sleep(delay, cancel)?;
+            attempt += 1;
+        }
+    }
+
+    /// Jittered wait before retry number `attempt`: a random point in the upper half of
+    /// `base_delay * 2^(attempt - 1)`, capped at `max_delay`.
+    fn backoff(&self, attempt: usize) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(16) as u32;
+        let full = self
+            .base_delay
+            .saturating_mul(1 << exponent)
+            .min(self.max_delay);
+        full / 2 + full.mul_f64(jitter() / 2.0)
+    }
+}
+
+/// Statuses worth retrying: timeouts, rate limits, and server-side failures that aren't
+/// permanent (501 Not Implemented and 505 won't change on a retry).
+fn is_retryable_status(status: u16) -> bool {
+    matches!(status, 408 | 429) || (500..=599).contains(&status) && !matches!(status, 501 | 505)
+}
+
+/// Connection failures and timeouts; errors building the request or decoding a body are not.
+fn is_transient(err: &reqwest::Error) -> bool {
+    err.is_connect() || err.is_timeout()
+}
+
+/// A `Retry-After` given in seconds; the HTTP-date form is rare from these APIs and ignored.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let secs: f64 = headers
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    (secs.is_finite() && secs >= 0.0).then(|| Duration::from_secs_f64(secs))
+}
+
+/// A random number in `[0, 1)`, from the standard library's randomly seeded hasher.
+fn jitter() -> f64 {
+    let bits = RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Sleep for `delay`, waking early with `Cancelled` if the token is set.
+fn sleep(delay: Duration, cancel: Option<&CancellationToken>) -> Result<()> {
+    let mut remaining = delay;
+    while !remaining.is_zero() {
+        cancel::check(cancel)?;
+        let step = remaining.min(CANCEL_POLL);
+        thread::sleep(step);
+        remaining -= step;
+    }
+    cancel::check(cancel)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn retries_rate_limits_and_server_errors() {
+        assert!(is_retryable_status(429));
+        assert!(is_retryable_status(503));
+        assert!(is_retryable_status(529));
+        assert!(!is_retryable_status(400));
+        assert!(!is_retryable_status(401));
+        assert!(!is_retryable_status(501));
+    }
+
+    #[test]
+    fn backoff_doubles_with_jitter_and_caps() {
+        let policy = RetryPolicy::new(5);
+        for _ in 0..20 {
+            let first = policy.backoff(1);
+            assert!(first >= Duration::from_millis(250) && first <= Duration::from_millis(500));
+            let third = policy.backoff(3);
+            assert!(third >= Duration::from_secs(1) && third <= Duration::from_secs(2));
+            assert!(policy.backoff(40) <= policy.max_delay);
+        }
+    }
+
+    #[test]
+    fn reads_retry_after_seconds() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("2"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(2)));
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        assert_eq!(retry_after(&headers), None);
+    }
+
+    #[test]
+    fn at_least_one_attempt() {
+        assert_eq!(RetryPolicy::new(0).max_attempts, 1);
+    }
+
+    #[test]
+    fn cancelled_backoff_stops_early() {
+        let token = CancellationToken::new();
+        token.cancel();
+        let err = sleep(Duration::from_secs(60), Some(&token)).unwrap_err();
+        assert!(err.downcast_ref::<cancel::Cancelled>().is_some());
+    }
+}
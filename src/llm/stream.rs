@@ -1,18 +1,46 @@
-use anyhow::Result;
-use std::io::{self, BufRead, Write};
+use anyhow::{Context, Result, anyhow};
+use std::io::{self, BufRead, Read, Write};
+
+use super::cancel::{self, CancellationToken};
+
+/// Default cap on the text of a single response; far above any commit message or PR description.
+pub const DEFAULT_MAX_RESPONSE_BYTES: usize = 256 * 1024;
 
 /// Read a streaming response line-by-line, printing chunks as they arrive.
-pub fn read_stream_to_string<R, F>(reader: R, mut parse_line: F) -> Result<String>
+///
+/// Stops with a `Cancelled` error (dropping the connection) as soon as `cancel` is set.
+/// Once the collected text (or a single line) exceeds `max_bytes`, reading stops with a
+/// warning and what has arrived so far is returned.
+pub fn read_stream_to_string<R, F>(
+    mut reader: R,
+    cancel: Option<&CancellationToken>,
+    max_bytes: usize,
+    mut parse_line: F,
+) -> Result<String>
 where
     R: BufRead,
     F: FnMut(&str) -> Result<Option<String>>,
 {
     let mut out = String::new();
     let mut stdout = io::stdout();
+    let mut buf = String::new();
 
-    for line in reader.lines() {
-        let line = line?;
-        let line = line.trim();
+    loop {
+        cancel::check(cancel)?;
+        buf.clear();
+        let read = (&mut reader)
+            .take(max_bytes as u64 + 1)
+            .read_line(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        if read > max_bytes {
+            log::warn!(
+                "Streamed response line exceeded {max_bytes} bytes (max_response_bytes); stopping"
+            );
+            break;
+        }
+        let line = buf.trim();
         if line.is_empty() {
             continue;
         }
@@ -22,7 +50,83 @@ where
             print!("{}", chunk);
             stdout.flush()?;
         }
+        if out.len() > max_bytes {
+            log::warn!(
+                "Response exceeded {max_bytes} bytes (max_response_bytes); keeping what arrived so far"
+            );
+            break;
+        }
     }
 
     Ok(out)
 }
+
+/// Payload of a server-sent events `data:` line; `None` for `event:`, `id:`, and comment
+/// lines, which carry nothing to parse.
+pub fn sse_data(line: &str) -> Option<&str> {
+    line.trim_start().strip_prefix("data:").map(str::trim)
+}
+
+/// Read a whole (non-streamed) response body, failing once it exceeds `max_bytes`.
+pub fn read_body_capped<R: Read>(reader: R, max_bytes: usize) -> Result<String> {
+    let mut body = Vec::new();
+    reader
+        .take(max_bytes as u64 + 1)
+        .read_to_end(&mut body)
+        .context("failed to read response body")?;
+    if body.len() > max_bytes {
+        return Err(anyhow!(
+            "Response body exceeded {max_bytes} bytes (max_response_bytes)"
+        ));
+    }
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::cancel::Cancelled;
+
+    fn parse(line: &str) -> Result<Option<String>> {
+        Ok(Some(line.to_string()))
+    }
+
+    #[test]
+    fn collects_parsed_chunks() {
+        let out = read_stream_to_string("a\n\nb\n".as_bytes(), None, 1024, parse).unwrap();
+        assert_eq!(out, "ab");
+    }
+
+    #[test]
+    fn stops_when_cancelled() {
+        let token = CancellationToken::new();
+        token.cancel();
+        let err =
+            read_stream_to_string("a\nb\n".as_bytes(), Some(&token), 1024, parse).unwrap_err();
+        assert!(err.downcast_ref::<Cancelled>().is_some());
+    }
+
+    #[test]
+    fn stops_at_the_response_cap() {
+        let out = read_stream_to_string("abc\ndef\nghi\n".as_bytes(), None, 4, parse).unwrap();
+        assert_eq!(out, "abcdef");
+
+        let endless = "x".repeat(100);
+        let out = read_stream_to_string(endless.as_bytes(), None, 10, parse).unwrap();
+        assert_eq!(out, "");
+    }
+
+    #[test]
+    fn extracts_sse_data() {
+        assert_eq!(sse_data("data: {\"a\":1}"), Some("{\"a\":1}"));
+        assert_eq!(sse_data("data:[DONE]"), Some("[DONE]"));
+        assert_eq!(sse_data("event: message_start"), None);
+        assert_eq!(sse_data(": keep-alive"), None);
+    }
+
+    #[test]
+    fn caps_non_streamed_bodies() {
+        assert_eq!(read_body_capped("{}".as_bytes(), 2).unwrap(), "{}");
+        assert!(read_body_capped("{ }".as_bytes(), 2).is_err());
+    }
+}
@@ -0,0 +1,181 @@
+//! Structured (JSON) commit messages: the schema sent to providers that can enforce it, and
+//! rendering the model's `{subject, body, sections}` reply into the final message.
+
+use super::prompt_builder::{MessageStyle, PromptOptions};
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+/// Name the schema is registered under in OpenAI's `json_schema` response format.
+pub(crate) const SCHEMA_NAME: &str = "commit_message";
+
+/// JSON schema of a structured commit message, strict enough for OpenAI's `strict` mode
+/// (every property required, no extras).
+pub(crate) fn commit_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "subject": {"type": "string"},
+            "body": {"type": "string"},
+            "sections": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "heading": {"type": "string"},
+                        "items": {"type": "array", "items": {"type": "string"}}
+                    },
+                    "required": ["heading", "items"],
+                    "additionalProperties": false
+                }
+            }
+        },
+        "required": ["subject", "body", "sections"],
+        "additionalProperties": false
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct StructuredCommit {
+    subject: String,
+    #[serde(default)]
+    body: String,
+    #[serde(default)]
+    sections: Vec<Section>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Section {
+    #[serde(default)]
+    heading: String,
+    #[serde(default)]
+    items: Vec<String>,
+}
+
+/// Render a `{subject, body, sections}` reply as a commit message in `style`, or `None` when
+/// `raw` isn't that JSON. Code fences and text around the object are ignored.
+pub fn render_commit_json(raw: &str, style: MessageStyle) -> Option<String> {
+    let start = raw.find('{')?;
+    let end = raw.rfind('}')?;
+    let commit: StructuredCommit = serde_json::from_str(raw.get(start..=end)?).ok()?;
+
+    let subject = commit.subject.lines().next().unwrap_or("").trim();
+    if subject.is_empty() {
+        return None;
+    }
+    let mut parts = vec![subject.to_string()];
+    if style == MessageStyle::Oneline {
+        return Some(subject.to_string());
+    }
+
+    let body = commit.body.trim();
+    if !body.is_empty() {
+        parts.push(body.to_string());
+    }
+    if style == MessageStyle::Grouped {
+        for section in &commit.sections {
+            let items: Vec<String> = section
+                .items
+                .iter()
+                .map(|item| item.trim().trim_start_matches(['-', '*', '•']).trim())
+                .filter(|item| !item.is_empty())
+                .map(|item| format!("- {item}"))
+                .collect();
+            if items.is_empty() {
+                continue;
+            }
+            let heading = section.heading.trim().trim_start_matches('#').trim();
+            if heading.is_empty() {
+                parts.push(items.join("\n"));
+            } else {
+                parts.push(format!("## {heading}\n{}", items.join("\n")));
+            }
+        }
+    }
+    Some(parts.join("\n\n"))
+}
+
+/// The commit message for a raw reply: rendered from JSON when structured output is on,
+/// or the reply as-is (with a warning) when the model didn't return the expected JSON.
+pub(crate) fn finish_commit_message(raw: String, opts: &PromptOptions) -> String {
+    if !opts.structured_output {
+        return raw;
+    }
+    match render_commit_json(&raw, opts.style) {
+        Some(message) => message,
+        None => {
+            log::warn!("The model did not return the expected JSON; using its reply as-is");
+            raw
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPLY: &str = r###"{
+        "subject": "Add login rate limiting",
+        "body": "Repeated failures now lock the account briefly.",
+        "sections": [
+            {"heading": "", "items": ["- Count failures per user", "Lock after five"]},
+            {"heading": "## Tests", "items": ["Cover the lockout window"]},
+            {"heading": "Empty", "items": []}
+        ]
+    }"###;
+
+    #[test]
+    fn renders_each_style() {
+        assert_eq!(
+            render_commit_json(REPLY, MessageStyle::Grouped).unwrap(),
+            "Add login rate limiting\n\n\
+             Repeated failures now lock the account briefly.\n\n\
+             - Count failures per user\n- Lock after five\n\n\
+             ## Tests\n- Cover the lockout window"
+        );
+        assert_eq!(
+            render_commit_json(REPLY, MessageStyle::Plain).unwrap(),
+            "Add login rate limiting\n\nRepeated failures now lock the account briefly."
+        );
+        assert_eq!(
+            render_commit_json(REPLY, MessageStyle::Oneline).unwrap(),
+            "Add login rate limiting"
+        );
+    }
+
+    #[test]
+    fn ignores_fences_and_narration() {
+        let raw = "Here is the message:\n```json\n{\"subject\": \"Fix typo\", \"body\": \"\", \"sections\": []}\n```";
+        assert_eq!(
+            render_commit_json(raw, MessageStyle::Grouped).as_deref(),
+            Some("Fix typo")
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_raw_reply() {
+        assert_eq!(render_commit_json("Fix typo", MessageStyle::Grouped), None);
+        assert_eq!(
+            render_commit_json(r#"{"subject": " "}"#, MessageStyle::Grouped),
+            None
+        );
+
+        let opts = PromptOptions {
+            structured_output: true,
+            ..PromptOptions::default()
+        };
+        assert_eq!(finish_commit_message("Fix typo".into(), &opts), "Fix typo");
+        let raw = r#"{"subject": "Fix typo", "body": "", "sections": []}"#;
+        assert_eq!(finish_commit_message(raw.into(), &opts), "Fix typo");
+        assert_eq!(
+            finish_commit_message(raw.into(), &PromptOptions::default()),
+            raw
+        );
+    }
+
+    #[test]
+    fn schema_is_strict() {
+        let schema = commit_schema();
+        assert_eq!(schema["additionalProperties"], false);
+        assert_eq!(schema["required"], json!(["subject", "body", "sections"]));
+    }
+}
@@ -0,0 +1,195 @@
+//! Token usage accumulated over a run, and a rough cost estimate from list prices.
+
+use std::fmt;
+
+/// Approximate list prices in USD per million (prompt, completion) tokens, matched against the
+/// model name by longest prefix. Estimates only: cached-input discounts, batch pricing, and price
+/// changes are not reflected.
+const PRICES_PER_MILLION: &[(&str, f64, f64)] = &[
+    ("gpt-5", 1.25, 10.0),
+    ("gpt-5-mini", 0.25, 2.0),
+    ("gpt-5-nano", 0.05, 0.40),
+    ("gpt-4.1", 2.0, 8.0),
+    ("gpt-4.1-mini", 0.40, 1.60),
+    ("gpt-4.1-nano", 0.10, 0.40),
+    ("gpt-4o", 2.50, 10.0),
+    ("gpt-4o-mini", 0.15, 0.60),
+    ("o1", 15.0, 60.0),
+    ("o3", 2.0, 8.0),
+    ("o3-mini", 1.10, 4.40),
+    ("o4-mini", 1.10, 4.40),
+    ("gemini-2.0-flash", 0.10, 0.40),
+    ("gemini-2.5-flash", 0.30, 2.50),
+    ("gemini-2.5-flash-lite", 0.10, 0.40),
+    ("gemini-2.5-pro", 1.25, 10.0),
+    ("claude-3-5-haiku", 0.80, 4.0),
+    ("claude-haiku", 1.0, 5.0),
+    ("claude-3-5-sonnet", 3.0, 15.0),
+    ("claude-3-7-sonnet", 3.0, 15.0),
+    ("claude-sonnet", 3.0, 15.0),
+    ("claude-opus", 15.0, 75.0),
+];
+
+/// Requests and tokens counted by a client, with an estimated cost when every request went to
+/// a model with a known price.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TokenUsage {
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+    /// Estimated cost in USD; `None` when any of the usage is for an unpriced (or local) model
+    pub cost: Option<f64>,
+}
+
+impl TokenUsage {
+    /// Add the token counts one response reported.
+    pub fn record(&mut self, prompt_tokens: u64, completion_tokens: u64, total_tokens: u64) {
+        self.prompt_tokens += prompt_tokens;
+        self.completion_tokens += completion_tokens;
+        self.total_tokens += total_tokens;
+    }
+
+    /// Take the counters, leaving zeros behind, and price them for `model` (`None` for a local
+    /// model). Returns `None` when no request was made; requests whose replies reported no token
+    /// counts are left unpriced rather than shown as free.
+    pub fn take(&mut self, model: Option<&str>) -> Option<TokenUsage> {
+        if self.requests == 0 && self.total_tokens == 0 {
+            return None;
+        }
+        let mut usage = std::mem::take(self);
+        usage.cost = model
+            .filter(|_| usage.total_tokens > 0)
+            .and_then(|model| usage.priced_for(model));
+        Some(usage)
+    }
+
+    /// Combine the usage of two clients; the cost stays known only if both are.
+    pub fn merge(self, other: TokenUsage) -> TokenUsage {
+        TokenUsage {
+            requests: self.requests + other.requests,
+            prompt_tokens: self.prompt_tokens + other.prompt_tokens,
+            completion_tokens: self.completion_tokens + other.completion_tokens,
+            total_tokens: self.total_tokens + other.total_tokens,
+            cost: self.cost.zip(other.cost).map(|(a, b)| a + b),
+        }
+    }
+
+    fn priced_for(&self, model: &str) -> Option<f64> {
+        let (prompt, completion) = price_per_million(model)?;
+        Some(
+            (self.prompt_tokens as f64 * prompt + self.completion_tokens as f64 * completion)
+                / 1_000_000.0,
+        )
+    }
+}
+
+/// List price of `model` in USD per million (prompt, completion) tokens, matched by longest
+/// prefix; a provider prefix such as `openai/` is ignored.
+pub fn price_per_million(model: &str) -> Option<(f64, f64)> {
+    let name = model.rsplit('/').next().unwrap_or(model);
+    PRICES_PER_MILLION
+        .iter()
+        .filter(|(prefix, _, _)| name.starts_with(prefix))
+        .max_by_key(|(prefix, _, _)| prefix.len())
+        .map(|&(_, prompt, completion)| (prompt, completion))
+}
+
+/// "12 requests, 48k prompt / 3k completion tokens, ~$0.09"
+impl fmt::Display for TokenUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plural = if self.requests == 1 { "" } else { "s" };
+        write!(
+            f,
+            "{} request{plural}, {} prompt / {} completion tokens",
+            self.requests,
+            compact(self.prompt_tokens),
+            compact(self.completion_tokens)
+        )?;
+        match self.cost {
+            Some(cost) if cost < 0.01 => write!(f, ", <$0.01"),
+            Some(cost) => write!(f, ", ~${cost:.2}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Token counts in thousands or millions once they get large: 950, 48k, 1.2M.
+fn compact(n: u64) -> String {
+    match n {
+        0..1_000 => n.to_string(),
+        1_000..1_000_000 => format!("{}k", (n as f64 / 1_000.0).round()),
+        _ => format!("{:.1}M", n as f64 / 1_000_000.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_a_run_summary() {
+        let usage = TokenUsage {
+            requests: 12,
+            prompt_tokens: 48_200,
+            completion_tokens: 3_100,
+            total_tokens: 51_300,
+            cost: Some(0.0907),
+        };
+        assert_eq!(
+            usage.to_string(),
+            "12 requests, 48k prompt / 3k completion tokens, ~$0.09"
+        );
+
+        let local = TokenUsage {
+            requests: 1,
+            prompt_tokens: 950,
+            completion_tokens: 40,
+            total_tokens: 990,
+            cost: None,
+        };
+        assert_eq!(
+            local.to_string(),
+            "1 request, 950 prompt / 40 completion tokens"
+        );
+    }
+
+    #[test]
+    fn prices_by_longest_prefix() {
+        let mut usage = TokenUsage {
+            requests: 1,
+            prompt_tokens: 1_000_000,
+            completion_tokens: 1_000_000,
+            total_tokens: 2_000_000,
+            cost: None,
+        };
+        let mini = usage.take(Some("openai/gpt-4o-mini")).unwrap();
+        assert_eq!(mini.cost, Some(0.75));
+        assert_eq!(usage, TokenUsage::default(), "counters are reset");
+
+        assert_eq!(mini.priced_for("my-finetune"), None);
+        assert_eq!(TokenUsage::default().take(Some("gpt-4o")), None);
+
+        let mut uncounted = TokenUsage {
+            requests: 3,
+            ..TokenUsage::default()
+        };
+        assert_eq!(uncounted.take(Some("gpt-4o")).unwrap().cost, None);
+    }
+
+    #[test]
+    fn merged_cost_needs_both_sides() {
+        let priced = TokenUsage {
+            requests: 1,
+            cost: Some(0.5),
+            ..TokenUsage::default()
+        };
+        let local = TokenUsage {
+            requests: 2,
+            ..TokenUsage::default()
+        };
+        assert_eq!(priced.merge(priced).cost, Some(1.0));
+        assert_eq!(priced.merge(local).requests, 3);
+        assert_eq!(priced.merge(local).cost, None);
+    }
+}
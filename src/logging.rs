@@ -1,9 +1,57 @@
-use std::io::Write;
+use std::env;
+use std::ffi::OsStr;
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use colored::Colorize;
 use env_logger::Builder;
 use log::{Level, LevelFilter};
 
+static COLOR: AtomicBool = AtomicBool::new(true);
+
+/// Whether output may be styled: not with `--no-color`, nor when `NO_COLOR` is set to a
+/// non-empty value (<https://no-color.org>).
+pub fn color_wanted(no_color: bool, no_color_env: Option<&OsStr>) -> bool {
+    !no_color && no_color_env.is_none_or(|v| v.is_empty())
+}
+
+/// Decide once whether to style output, for both `colored` text and [`color_enabled`].
+pub fn init_color(no_color: bool) {
+    let enabled = color_wanted(no_color, env::var_os("NO_COLOR").as_deref());
+    COLOR.store(enabled, Ordering::Relaxed);
+    // `colored` only styles log lines, which go to stderr; keep codes out of redirected logs.
+    if !enabled || !io::stderr().is_terminal() {
+        colored::control::set_override(false);
+    }
+}
+
+/// Whether styled output (colors, dim text) is allowed; see [`init_color`].
+pub fn color_enabled() -> bool {
+    COLOR.load(Ordering::Relaxed)
+}
+
+/// An indicatif progress template, unstyled when color is off (see [`plain_template`]).
+pub fn progress_template(template: &str) -> String {
+    if color_enabled() {
+        template.to_string()
+    } else {
+        plain_template(template)
+    }
+}
+
+/// `template` with its `:.style` suffixes removed, e.g. `{msg:.bold}` becomes `{msg}`.
+pub fn plain_template(template: &str) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find(":.") {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        rest = &rest[rest.find('}').unwrap_or(rest.len())..];
+    }
+    out.push_str(rest);
+    out
+}
+
 pub fn init_logger(verbosity: u8) {
     let level = match verbosity {
         0 => LevelFilter::Warn,  // default: warn & errors
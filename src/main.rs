@@ -1,12 +1,29 @@
 use anyhow::{anyhow, Result};
 use clap::Parser;
+use commitbot::budget::estimate_run;
+use commitbot::changelog::{ChangelogEntry, update_changelog_file};
+use commitbot::cli_args::with_default_args;
 use commitbot::config::Config;
 use commitbot::git::{
-    collect_pr_items, current_branch, format_pr_commit_appendix, split_diff_by_file,
-    staged_diff_for_file, staged_files, PrSummaryMode,
+    PrItem, PrSummaryMode, StatusEntry, autodetect_summary_mode, collect_pr_items, commit_diff,
+    commit_message, current_branch, decode_diff, format_pr_commit_appendix, is_binary_diff,
+    is_deleted_diff, is_merge_commit, latest_tag, message_file_has_content, parse_submodule_bump,
+    pr_template, stage_paths, staged_files, untracked_files, verify_ref, working_tree_status,
+    write_message_file,
 };
-use commitbot::llm::LlmClient;
-use commitbot::{Cli, Command, FileCategory, FileChange};
+use commitbot::llm::{LlmClient, TokenUsage};
+use commitbot::logging::{color_enabled, progress_template};
+use commitbot::pipeline::{
+    Aborted, GenerateOptions, NoChanges, SummarizeContext, SummaryProgress, build_file_changes,
+    check_budget, check_context, check_diff_sizes, check_secrets, diff_file_pairs,
+    generate_commit_message_with, staged_file_pairs, summarize_files, summary_request_sizes,
+    untracked_file_pairs, warn_on_lossy_diffs,
+};
+use commitbot::rate_limit::RateLimiter;
+use commitbot::run_state::RunState;
+use commitbot::summary_cache::SummaryCache;
+use commitbot::text::lint_message;
+use commitbot::{Cli, Command, ExitReason, FileCategory, FileChange};
 use crossterm::{
     cursor,
     event::{self, Event, KeyCode},
@@ -14,17 +31,37 @@ use crossterm::{
     style::{self, Color},
     terminal::{self, Clear, ClearType},
 };
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
 
-use std::collections::HashSet;
-use std::io::{self, Read, Write};
-use std::sync::{Arc, Mutex};
-use std::thread;
+use std::collections::{HashSet, VecDeque};
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::Path;
 use std::time::Duration;
 
-fn prompt_input(prompt: &str) -> Result<String> {
-    print!("{prompt}");
-    io::stdout().flush()?;
+/// `eprintln!` for human-facing status lines, suppressed by `--quiet`.
+///
+/// Status goes to stderr so stdout carries only the generated message.
+macro_rules! chatter {
+    ($cli:expr) => {
+        if !$cli.quiet {
+            eprintln!();
+        }
+    };
+    ($cli:expr, $($arg:tt)*) => {
+        if !$cli.quiet {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+/// Ask `prompt` on stderr and read one line. With `--yes` nothing is asked and `with_yes` is
+/// the answer, so no confirmation waits on a terminal.
+fn prompt_input(cli: &Cli, prompt: &str, with_yes: &str) -> Result<String> {
+    if cli.yes {
+        return Ok(with_yes.to_string());
+    }
+    eprint!("{prompt}");
+    io::stderr().flush()?;
 
     let mut buf = String::new();
     io::stdin().read_line(&mut buf)?;
@@ -42,22 +79,75 @@ fn tprintln<W: Write>(out: &mut W, s: &str) -> io::Result<()> {
     write!(out, "{}\r\n", s)
 }
 
+/// The first line of `text`, cut to 80 characters (not bytes, so CJK or accented text is safe).
 fn preview_snippet(text: &str) -> String {
     let trimmed = text.trim();
     let first_line = trimmed.lines().next().unwrap_or("");
     const MAX: usize = 80;
-    if first_line.len() > MAX {
-        format!("{}…", &first_line[..MAX])
+    if first_line.chars().count() > MAX {
+        format!("{}…", first_line.chars().take(MAX).collect::<String>())
     } else {
         first_line.to_string()
     }
 }
 
 fn dimmed(text: &str) -> String {
+    if !color_enabled() {
+        return text.to_string();
+    }
     format!("\x1b[2m{text}\x1b[0m")
 }
 
-fn categorize_file_interactive(idx: usize, total: usize, path: &str) -> Result<FileCategory> {
+/// Print one menu row: the cursor row in white and the rest dimmed, or a `>` marker on the
+/// cursor row when color is off.
+fn menu_row<W: Write>(out: &mut W, text: &str, current: bool) -> Result<()> {
+    if !color_enabled() {
+        let marker = if current { ">" } else { " " };
+        tprintln(out, &format!("{marker} {text}"))?;
+        return Ok(());
+    }
+    let color = if current {
+        Color::White
+    } else {
+        Color::DarkGrey
+    };
+    execute!(out, style::SetForegroundColor(color))?;
+    tprintln(out, &format!("  {text}"))?;
+    execute!(out, style::ResetColor)?;
+    Ok(())
+}
+
+/// Outcome of a single step in the interactive classification loop.
+enum CategoryChoice {
+    /// Classify the current file.
+    One(FileCategory),
+    /// Classify the current file and every remaining unclassified file.
+    AllRemaining(FileCategory),
+    /// Return to the previous file to re-classify it.
+    Back,
+    /// Revert the most recent classification (including bulk ones) and return to that file.
+    Undo,
+    /// Defer the current file to the end of the queue.
+    Skip,
+    /// Move remaining files whose path contains this text to the front of the queue.
+    Filter(String),
+}
+
+/// What the classification screen shows for the file at the front of the queue.
+struct ClassifyScreen<'a> {
+    /// Number of files already classified.
+    done: usize,
+    total: usize,
+    path: &'a str,
+    deleted: bool,
+    default: FileCategory,
+    can_go_back: bool,
+    can_undo: bool,
+    /// One-line status shown under the header (e.g. filter results).
+    notice: Option<&'a str>,
+}
+
+fn categorize_file_interactive(screen: &ClassifyScreen<'_>) -> Result<CategoryChoice> {
     use FileCategory::*;
 
     let mut stdout = io::stdout();
@@ -66,7 +156,7 @@ fn categorize_file_interactive(idx: usize, total: usize, path: &str) -> Result<F
         .map_err(|e| anyhow!("failed to flush stderr: {e}"))?;
     terminal::enable_raw_mode().map_err(|e| anyhow!("failed to enable raw mode: {e}"))?;
 
-    let res = (|| -> Result<FileCategory> {
+    let res = (|| -> Result<CategoryChoice> {
         let labels = [
             "1) Main purpose",
             "2) Supporting change",
@@ -74,26 +164,32 @@ fn categorize_file_interactive(idx: usize, total: usize, path: &str) -> Result<F
             "4) Ignore / unrelated cleanup",
         ];
 
-        let mut selected_index: usize = 0;
+        let categories = [Main, Supporting, Consequence, Ignored];
+        let mut selected_index: usize = screen.default.menu_index();
+        let mut filter_input: Option<String> = None;
 
         loop {
             execute!(stdout, Clear(ClearType::All), cursor::MoveTo(0, 0))?;
 
-            tprintln(&mut stdout, &format!("[{} / {}] {}", idx + 1, total, path))?;
+            let deleted = if screen.deleted { " (deleted)" } else { "" };
+            tprintln(
+                &mut stdout,
+                &format!(
+                    "[{} / {}] {}{deleted}",
+                    screen.done + 1,
+                    screen.total,
+                    screen.path
+                ),
+            )?;
+            if let Some(notice) = screen.notice {
+                tprintln(&mut stdout, &dimmed(notice))?;
+            }
             tprintln(&mut stdout, "")?;
             tprintln(&mut stdout, "How does this file relate to the ticket?")?;
             tprintln(&mut stdout, "")?;
 
             for (i, label) in labels.iter().enumerate() {
-                let color = if i == selected_index {
-                    Color::White
-                } else {
-                    Color::DarkGrey
-                };
-
-                execute!(stdout, style::SetForegroundColor(color))?;
-                tprintln(&mut stdout, &format!("  {}", label))?;
-                execute!(stdout, style::ResetColor)?;
+                menu_row(&mut stdout, label, i == selected_index)?;
             }
 
             tprintln(&mut stdout, "")?;
@@ -101,41 +197,77 @@ fn categorize_file_interactive(idx: usize, total: usize, path: &str) -> Result<F
                 &mut stdout,
                 "Use ↑/↓ to move, Enter to select, or 1–4 as a shortcut.",
             )?;
+            tprintln(
+                &mut stdout,
+                "Press 'a' to apply the highlighted category to all remaining files, 'b' to go back.",
+            )?;
+            tprintln(
+                &mut stdout,
+                "Press 's' to skip this file for now, '/' to jump to files matching a search.",
+            )?;
+            if screen.can_undo {
+                tprintln(&mut stdout, "Press 'u' to undo the last classification.")?;
+            }
+
+            if let Some(query) = &filter_input {
+                tprintln(&mut stdout, "")?;
+                tprintln(&mut stdout, &format!("/{query}"))?;
+            }
 
             stdout.flush()?;
 
             let ev = event::read()?;
-            if let Event::Key(key) = ev {
+            let Event::Key(key) = ev else {
+                continue;
+            };
+
+            if let Some(query) = filter_input.as_mut() {
                 match key.code {
-                    KeyCode::Up => {
-                        if selected_index == 0 {
-                            selected_index = labels.len() - 1;
-                        } else {
-                            selected_index -= 1;
-                        }
-                    }
-                    KeyCode::Down => {
-                        selected_index = (selected_index + 1) % labels.len();
+                    KeyCode::Char(c) => query.push(c),
+                    KeyCode::Backspace => {
+                        query.pop();
                     }
-                    KeyCode::Char('1') => return Ok(Main),
-                    KeyCode::Char('2') => return Ok(Supporting),
-                    KeyCode::Char('3') => return Ok(Consequence),
-                    KeyCode::Char('4') => return Ok(Ignored),
                     KeyCode::Enter => {
-                        let cat = match selected_index {
-                            0 => Main,
-                            1 => Supporting,
-                            2 => Consequence,
-                            3 => Ignored,
-                            _ => Main,
-                        };
-                        return Ok(cat);
-                    }
-                    KeyCode::Esc => {
-                        return Err(anyhow!("aborted by user"));
+                        let query = filter_input.take().unwrap_or_default();
+                        if !query.is_empty() {
+                            return Ok(CategoryChoice::Filter(query));
+                        }
                     }
+                    KeyCode::Esc => filter_input = None,
                     _ => {}
                 }
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Up => {
+                    if selected_index == 0 {
+                        selected_index = labels.len() - 1;
+                    } else {
+                        selected_index -= 1;
+                    }
+                }
+                KeyCode::Down => {
+                    selected_index = (selected_index + 1) % labels.len();
+                }
+                KeyCode::Char('1') => return Ok(CategoryChoice::One(Main)),
+                KeyCode::Char('2') => return Ok(CategoryChoice::One(Supporting)),
+                KeyCode::Char('3') => return Ok(CategoryChoice::One(Consequence)),
+                KeyCode::Char('4') => return Ok(CategoryChoice::One(Ignored)),
+                KeyCode::Char('a') => {
+                    return Ok(CategoryChoice::AllRemaining(categories[selected_index]));
+                }
+                KeyCode::Char('b') if screen.can_go_back => return Ok(CategoryChoice::Back),
+                KeyCode::Char('u') if screen.can_undo => return Ok(CategoryChoice::Undo),
+                KeyCode::Char('s') => return Ok(CategoryChoice::Skip),
+                KeyCode::Char('/') => filter_input = Some(String::new()),
+                KeyCode::Enter => {
+                    return Ok(CategoryChoice::One(categories[selected_index]));
+                }
+                KeyCode::Esc => {
+                    return Err(Aborted("Aborted by user.".to_string()).into());
+                }
+                _ => {}
             }
         }
     })();
@@ -144,134 +276,333 @@ fn categorize_file_interactive(idx: usize, total: usize, path: &str) -> Result<F
     res
 }
 
-type SummarizeResultInner = Vec<(usize, Result<String>)>;
-type SummarizeResults = Arc<Mutex<SummarizeResultInner>>;
+/// Multi-select of working-tree files to stage. Returns the chosen indices; empty if
+/// the user confirmed nothing or cancelled.
+fn select_files_to_stage(entries: &[StatusEntry]) -> Result<Vec<usize>> {
+    let mut stdout = io::stdout();
+    terminal::enable_raw_mode().map_err(|e| anyhow!("failed to enable raw mode: {e}"))?;
+
+    let res = (|| -> Result<Vec<usize>> {
+        let mut selected = vec![false; entries.len()];
+        let mut cursor_index: usize = 0;
+
+        loop {
+            execute!(stdout, Clear(ClearType::All), cursor::MoveTo(0, 0))?;
+
+            tprintln(
+                &mut stdout,
+                "No staged changes found. Select files to stage:",
+            )?;
+            tprintln(&mut stdout, "")?;
+
+            for (i, entry) in entries.iter().enumerate() {
+                let mark = if selected[i] { "x" } else { " " };
+                menu_row(
+                    &mut stdout,
+                    &format!("[{mark}] {} {}", entry.code(), entry.path),
+                    i == cursor_index,
+                )?;
+            }
+
+            tprintln(&mut stdout, "")?;
+            tprintln(
+                &mut stdout,
+                "Use ↑/↓ to move, Space to toggle, 'a' to toggle all, Enter to stage, Esc to cancel.",
+            )?;
+            stdout.flush()?;
+
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+
+            match key.code {
+                KeyCode::Up => {
+                    cursor_index = cursor_index.checked_sub(1).unwrap_or(entries.len() - 1);
+                }
+                KeyCode::Down => cursor_index = (cursor_index + 1) % entries.len(),
+                KeyCode::Char(' ') => selected[cursor_index] = !selected[cursor_index],
+                KeyCode::Char('a') => {
+                    let all = selected.iter().all(|s| *s);
+                    selected.iter_mut().for_each(|s| *s = !all);
+                }
+                KeyCode::Enter => {
+                    return Ok((0..entries.len()).filter(|&i| selected[i]).collect());
+                }
+                KeyCode::Esc => return Ok(Vec::new()),
+                _ => {}
+            }
+        }
+    })();
 
-struct SummarizeContext<'a> {
-    branch: &'a str,
-    ticket_summary: Option<&'a str>,
-    llm: &'a dyn LlmClient,
-    max_concurrent_requests: usize,
+    let _ = terminal::disable_raw_mode();
+    res
 }
 
-fn summarize_files_concurrently(
-    file_changes: &mut [FileChange],
-    indices: &[usize],
-    ctx: &SummarizeContext<'_>,
-    pb: &ProgressBar,
-    file_lines: Option<&[ProgressBar]>,
-) -> Result<()> {
-    if indices.is_empty() {
-        return Ok(());
+/// With nothing staged, offer to stage working-tree changes when running in a terminal.
+/// Returns the staged files afterwards (empty if nothing was staged).
+fn offer_to_stage(cli: &Cli) -> Result<Vec<String>> {
+    if cli.yes || !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return Ok(Vec::new());
     }
 
-    let max_concurrent = ctx.max_concurrent_requests.max(1);
-    let total_files = file_changes.len();
+    let candidates: Vec<StatusEntry> = working_tree_status()?
+        .into_iter()
+        .filter(StatusEntry::has_unstaged_changes)
+        .collect();
+    if candidates.is_empty() {
+        return Ok(Vec::new());
+    }
 
-    let results: SummarizeResults = Arc::new(Mutex::new(Vec::new()));
+    let chosen = select_files_to_stage(&candidates)?;
+    execute!(io::stdout(), Clear(ClearType::All), cursor::MoveTo(0, 0))?;
+    let paths: Vec<&str> = chosen
+        .iter()
+        .map(|&i| candidates[i].path.as_str())
+        .collect();
+    stage_paths(&paths)?;
+    staged_files()
+}
 
-    for chunk in indices.chunks(max_concurrent) {
-        thread::scope(|scope| {
-            for &file_idx in chunk {
-                let results = Arc::clone(&results);
-                let pb = pb.clone();
-                let file_line = file_lines.and_then(|lines| lines.get(file_idx)).cloned();
+/// Move queued files whose path contains `query` (case-insensitive) to the front,
+/// preserving relative order. Returns how many matched.
+fn prioritize_matches(queue: &mut VecDeque<usize>, paths: &[&str], query: &str) -> usize {
+    let needle = query.to_lowercase();
+    let (matches, rest): (Vec<usize>, Vec<usize>) = queue
+        .iter()
+        .partition(|&&i| paths[i].to_lowercase().contains(&needle));
+    let count = matches.len();
+    if count > 0 {
+        *queue = matches.into_iter().chain(rest).collect();
+    }
+    count
+}
 
-                if let Some(line) = &file_line {
-                    line.set_message("summarizing...");
-                }
+/// What to do with an `--ask` run over `interactive_max_files`.
+enum LargeChangeset {
+    Simple,
+    AllSupporting,
+    Classify,
+}
 
-                let path = file_changes[file_idx].path.clone();
-                let diff = file_changes[file_idx].diff.clone();
-                let category = file_changes[file_idx].category;
-
-                scope.spawn(move || {
-                    log::debug!("Summarizing file: {}", path);
-
-                    let res = (|| -> Result<String> {
-                        let fc = FileChange {
-                            path,
-                            category,
-                            diff,
-                            summary: None,
-                        };
-
-                        let summary = ctx.llm.summarize_file(
-                            ctx.branch,
-                            &fc,
-                            file_idx,
-                            total_files,
-                            ctx.ticket_summary,
-                        )?;
-                        Ok(summary)
-                    })();
-
-                    pb.inc(1);
-
-                    if let Some(line) = &file_line {
-                        match &res {
-                            Ok(summary) => {
-                                let snippet = preview_snippet(summary);
-                                line.finish_with_message(dimmed(&snippet));
-                            }
-                            Err(err) => {
-                                line.finish_with_message(dimmed(&format!("error: {err}")));
-                            }
+fn ask_about_large_changeset(cli: &Cli, count: usize, max_files: usize) -> Result<LargeChangeset> {
+    log::warn!("{count} files changed, more than interactive_max_files ({max_files})");
+    let ans = prompt_input(
+        cli,
+        &format!(
+            "Classifying {count} files one by one will take a while. Use [s]imple mode, classify \
+             [a]ll as Supporting, or [c]lassify anyway? [S/a/c] "
+        ),
+        "c",
+    )?;
+    Ok(match ans.to_lowercase().as_str() {
+        "a" | "all" => LargeChangeset::AllSupporting,
+        "c" | "classify" => LargeChangeset::Classify,
+        _ => LargeChangeset::Simple,
+    })
+}
+
+/// Category a file starts with: Consequence for deletions, otherwise `default_category`.
+fn preset_category(deleted: bool, default_category: FileCategory) -> FileCategory {
+    if deleted {
+        FileCategory::Consequence
+    } else {
+        default_category
+    }
+}
+
+/// Classification state captured before each decision so `u` can restore it.
+struct ClassifySnapshot {
+    categories: Vec<Option<FileCategory>>,
+    queue: VecDeque<usize>,
+    history: Vec<usize>,
+}
+
+/// Phase 1 of interactive mode: classify every file via a work queue that supports
+/// skipping, searching, going back, undoing, and bulk-accepting.
+///
+/// Deleted files start with Consequence highlighted instead of `default_category`. With
+/// `--yes` every file keeps that preset category and no menu is shown.
+fn classify_files(
+    cli: &Cli,
+    paths: &[&str],
+    deleted: &[bool],
+    default_category: FileCategory,
+    previous: &[FileCategory],
+) -> Result<Vec<FileCategory>> {
+    let total = paths.len();
+    let preset = |idx: usize| {
+        previous
+            .get(idx)
+            .copied()
+            .unwrap_or_else(|| preset_category(deleted[idx], default_category))
+    };
+    if cli.yes {
+        return Ok((0..total).map(preset).collect());
+    }
+    let mut categories: Vec<Option<FileCategory>> = vec![None; total];
+    let mut queue: VecDeque<usize> = (0..total).collect();
+    let mut history: Vec<usize> = Vec::new();
+    let mut undo_stack: Vec<ClassifySnapshot> = Vec::new();
+    let mut consecutive_skips = 0usize;
+    let mut notice: Option<String> = None;
+
+    while let Some(&idx) = queue.front() {
+        let screen = ClassifyScreen {
+            done: history.len(),
+            total,
+            path: paths[idx],
+            deleted: deleted[idx],
+            default: categories[idx].unwrap_or_else(|| preset(idx)),
+            can_go_back: !history.is_empty(),
+            can_undo: !undo_stack.is_empty(),
+            notice: notice.as_deref(),
+        };
+        let choice = categorize_file_interactive(&screen)?;
+        notice = None;
+
+        if matches!(
+            choice,
+            CategoryChoice::One(_) | CategoryChoice::AllRemaining(_)
+        ) {
+            undo_stack.push(ClassifySnapshot {
+                categories: categories.clone(),
+                queue: queue.clone(),
+                history: history.clone(),
+            });
+        }
+
+        match choice {
+            CategoryChoice::One(category) => {
+                categories[idx] = Some(category);
+                queue.pop_front();
+                history.push(idx);
+                consecutive_skips = 0;
+            }
+            CategoryChoice::AllRemaining(category) => {
+                categories[idx] = Some(category);
+                for i in queue.drain(..) {
+                    categories[i].get_or_insert(category);
+                    history.push(i);
+                }
+            }
+            CategoryChoice::Back => {
+                if let Some(prev) = history.pop() {
+                    queue.push_front(prev);
+                }
+                consecutive_skips = 0;
+            }
+            CategoryChoice::Undo => {
+                if let Some(snapshot) = undo_stack.pop() {
+                    categories = snapshot.categories;
+                    queue = snapshot.queue;
+                    history = snapshot.history;
+                    if let Some(&front) = queue.front() {
+                        notice = Some(format!("Undid classification of {}.", paths[front]));
+                    }
+                }
+                consecutive_skips = 0;
+            }
+            CategoryChoice::Skip => {
+                queue.rotate_left(1);
+                consecutive_skips += 1;
+
+                if consecutive_skips >= queue.len() {
+                    let ans = prompt_input(
+                        cli,
+                        &format!(
+                            "All {} remaining files were skipped. Classify them as {}? [y/N] ",
+                            queue.len(),
+                            default_category.as_str()
+                        ),
+                        "y",
+                    )?;
+                    if ans.eq_ignore_ascii_case("y") || ans.eq_ignore_ascii_case("yes") {
+                        for i in queue.drain(..) {
+                            categories[i].get_or_insert(default_category);
+                            history.push(i);
                         }
                     }
-
-                    let mut lock = results.lock().expect("results mutex poisoned");
-                    lock.push((file_idx, res));
+                    consecutive_skips = 0;
+                }
+            }
+            CategoryChoice::Filter(query) => {
+                let matched = prioritize_matches(&mut queue, paths, &query);
+                notice = Some(if matched == 0 {
+                    format!("No remaining files match '{query}'.")
+                } else {
+                    format!("{matched} remaining file(s) match '{query}'.")
                 });
+                consecutive_skips = 0;
             }
-        });
+        }
     }
 
-    let results = Arc::try_unwrap(results)
-        .expect("results Arc still has multiple owners")
-        .into_inner()
-        .expect("results mutex poisoned");
+    Ok(categories
+        .into_iter()
+        .map(|c| c.unwrap_or(default_category))
+        .collect())
+}
+
+/// Drives the overall progress bar and per-file spinner lines from pipeline callbacks.
+struct BarProgress<'a> {
+    pb: &'a ProgressBar,
+    file_lines: &'a [ProgressBar],
+}
 
-    let mut first_err: Option<anyhow::Error> = None;
+impl SummaryProgress for BarProgress<'_> {
+    fn throttled(&self, idx: usize) {
+        if let Some(line) = self.file_lines.get(idx) {
+            line.set_message("waiting for rate limit...");
+        }
+    }
 
-    for (idx, res) in results {
-        match res {
-            Ok(summary) => {
-                file_changes[idx].summary = Some(summary);
-            }
-            Err(e) => {
-                if first_err.is_none() {
-                    first_err = Some(e);
+    fn started(&self, idx: usize, batched: bool) {
+        if let Some(line) = self.file_lines.get(idx) {
+            line.set_message(if batched {
+                "summarizing (batched)..."
+            } else {
+                "summarizing..."
+            });
+        }
+    }
+
+    fn finished(&self, idx: usize, result: &Result<String>) {
+        self.pb.inc(1);
+        if let Some(line) = self.file_lines.get(idx) {
+            match result {
+                Ok(summary) => {
+                    line.finish_with_message(format!("✓ {}", dimmed(&preview_snippet(summary))))
                 }
+                Err(err) => line.finish_with_message(format!("✗ {}", dimmed(&format!("{err}")))),
             }
         }
     }
 
-    if let Some(err) = first_err {
-        return Err(err);
+    fn cancelled(&self, idx: usize) {
+        if let Some(line) = self.file_lines.get(idx) {
+            line.finish_with_message(format!("✗ {}", dimmed("cancelled")));
+        }
     }
+}
 
-    Ok(())
+/// Persist interactive progress so a failed run can be resumed; failing to save only loses that.
+/// A dry run leaves the saved state alone, since its summaries are placeholders.
+fn save_run_state(cfg: &Config, branch: &str, ticket_summary: Option<&str>, files: &[FileChange]) {
+    if cfg.dry_run {
+        return;
+    }
+    if let Err(err) = RunState::capture(branch, ticket_summary, files).save() {
+        log::debug!("Could not save run state: {err}");
+    }
 }
 
 fn run_interactive(cli: &Cli, cfg: &Config, llm: &dyn LlmClient) -> Result<()> {
-    let (branch, file_pairs) = if let Some(ref diff_arg) = cli.diff {
-        let combined = if diff_arg == "-" {
-            let mut buf = String::new();
-            io::stdin().read_to_string(&mut buf)?;
-            buf
-        } else {
-            std::fs::read_to_string(diff_arg)
-                .map_err(|e| anyhow!("Failed to read diff file '{}': {}", diff_arg, e))?
-        };
+    let (branch, mut file_pairs) = if let Some(ref diff_arg) = cli.diff {
+        let combined = read_external_diff(diff_arg)?;
         if combined.trim().is_empty() {
-            println!("No diff content found.");
-            return Ok(());
-        }
-        let mut per_file = split_diff_by_file(&combined);
-        if per_file.is_empty() {
-            per_file = vec![("(diff)".to_string(), combined)];
+            return nothing_to_commit(cli, "No diff content found.");
         }
+        let per_file = diff_file_pairs(&combined);
         let branch = cli
             .branch
             .clone()
@@ -279,66 +610,126 @@ fn run_interactive(cli: &Cli, cfg: &Config, llm: &dyn LlmClient) -> Result<()> {
         (branch, per_file)
     } else {
         let branch = current_branch()?;
-        let files = staged_files()?;
-        if files.is_empty() {
-            println!("No staged changes found.");
-            return Ok(());
+        let file_pairs = local_file_pairs(cli, cfg)?;
+        if file_pairs.is_empty() {
+            return nothing_to_commit(cli, "No staged changes found.");
         }
-        let mut pairs = Vec::new();
-        for path in files {
-            let diff = staged_diff_for_file(&path)?;
-            pairs.push((path, diff));
+        (branch, file_pairs)
+    };
+
+    check_secrets(&mut file_pairs, cfg)?;
+
+    let resumed = match RunState::load().filter(|state| state.matches(&branch, &file_pairs)) {
+        Some(state) => {
+            let ans = prompt_input(
+                cli,
+                &format!(
+                    "Resume the previous classification of {} files? [Y/n] ",
+                    state.files.len()
+                ),
+                "y",
+            )?;
+            (ans.is_empty() || ans.eq_ignore_ascii_case("y") || ans.eq_ignore_ascii_case("yes"))
+                .then_some(state)
         }
-        (branch, pairs)
+        None => None,
     };
 
-    let mut ticket_summary = resolved_ticket_summary(cli);
-    if ticket_summary.is_none() {
-        let ans = prompt_input("Optional: brief ticket summary (enter to skip): ")?;
+    let mut all_supporting = false;
+    let max_files = cfg.interactive_max_files;
+    if resumed.is_none() && max_files > 0 && file_pairs.len() > max_files {
+        match ask_about_large_changeset(cli, file_pairs.len(), max_files)? {
+            LargeChangeset::Simple => return summarize_simple(cli, cfg, llm, &branch, file_pairs),
+            LargeChangeset::AllSupporting => all_supporting = true,
+            LargeChangeset::Classify => {}
+        }
+    }
+
+    let mut ticket_summary = resolved_ticket_summary(cli)
+        .or_else(|| resumed.as_ref().and_then(|s| s.ticket_summary.clone()));
+    if ticket_summary.is_none() && resumed.is_none() {
+        let ans = prompt_input(cli, "Optional: brief ticket summary (enter to skip): ", "")?;
         if !ans.is_empty() {
             ticket_summary = Some(ans);
         }
     }
 
-    let mut file_changes: Vec<FileChange> = Vec::new();
+    let default_category = cli
+        .default_category
+        .and_then(FileCategory::from_menu_number)
+        .unwrap_or(FileCategory::Main);
 
-    let total_files = file_pairs.len();
-    for (idx, (path, diff)) in file_pairs.into_iter().enumerate() {
-        let category = categorize_file_interactive(idx, total_files, &path)?;
-        file_changes.push(FileChange {
-            path,
-            category,
-            diff,
-            summary: None,
-        });
+    let paths: Vec<&str> = file_pairs.iter().map(|(path, _)| path.as_str()).collect();
+    let deleted: Vec<bool> = file_pairs
+        .iter()
+        .map(|(_, diff)| is_deleted_diff(diff))
+        .collect();
+    let categories = match &resumed {
+        Some(state) => state.categories(),
+        None if all_supporting => deleted
+            .iter()
+            .map(|&d| preset_category(d, FileCategory::Supporting))
+            .collect(),
+        None => classify_files(cli, &paths, &deleted, default_category, &[])?,
+    };
+    let classified_by_hand = resumed.is_none() && !all_supporting;
+
+    let rate_limiter = (cfg.requests_per_minute > 0)
+        .then(|| RateLimiter::new(cfg.requests_per_minute, cfg.max_concurrent_requests));
+    let cache = SummaryCache::open(cfg, llm);
+    let ctx = SummarizeContext {
+        branch: &branch,
+        ticket_summary: ticket_summary.as_deref(),
+        llm,
+        max_concurrent_requests: cfg.max_concurrent_requests,
+        rate_limiter: rate_limiter.as_ref(),
+        batch_consequence: cfg.batch_consequence,
+        trivial_diff_lines: cfg.trivial_diff_lines,
+        cache: cache.as_ref(),
+    };
+
+    let mut file_changes = build_file_changes(file_pairs, categories);
+    while classified_by_hand && !confirm_classification(cli, &file_changes, &ctx, cfg)? {
+        let paths: Vec<&str> = file_changes.iter().map(|fc| fc.path.as_str()).collect();
+        let previous: Vec<FileCategory> = file_changes.iter().map(|fc| fc.category).collect();
+        let categories = classify_files(cli, &paths, &deleted, default_category, &previous)?;
+        for (fc, category) in file_changes.iter_mut().zip(categories) {
+            fc.category = category;
+        }
     }
+    if let Some(state) = &resumed {
+        state.restore_summaries(&mut file_changes);
+    }
+    save_run_state(cfg, &branch, ticket_summary.as_deref(), &file_changes);
+    warn_on_lossy_diffs(&file_changes);
+    check_diff_sizes(&mut file_changes, cfg)?;
+    check_budget(&mut file_changes, cfg)?;
 
-    println!();
-    println!("Asking {}...", cfg.model);
+    chatter!(cli);
+    chatter!(cli, "Asking {}...", cfg.final_model());
 
     let total = file_changes.len();
-    let mp = MultiProgress::new();
+    let mp = MultiProgress::with_draw_target(progress_target(cli));
     let mut file_lines = Vec::new();
 
-    for fc in &file_changes {
-        let line = mp.add(ProgressBar::new_spinner());
-        line.set_style(
-            ProgressStyle::with_template("{spinner:.cyan} {prefix:.bold}: {msg}")
-                .expect("progress style template"),
-        );
-        line.set_prefix(fc.path.clone());
-        if matches!(fc.category, FileCategory::Ignored) {
-            line.finish_with_message(dimmed("ignored"));
-        } else {
-            line.enable_steady_tick(Duration::from_millis(120));
-            line.set_message("waiting");
+    if show_file_lines(cli) {
+        for fc in &file_changes {
+            let done = if matches!(fc.category, FileCategory::Ignored) {
+                Some("ignored".to_string())
+            } else if let Some(summary) = fc.summary.as_deref() {
+                Some(format!("resumed: {}", preview_snippet(summary)))
+            } else if !fc.needs_summary() {
+                Some(skip_reason(fc).to_string())
+            } else {
+                None
+            };
+            file_lines.push(add_file_line(&mp, fc.path.clone(), done));
         }
-        file_lines.push(line);
     }
 
     let pb = mp.add(ProgressBar::new((total + 1) as u64));
     pb.set_style(
-        ProgressStyle::with_template("{wide_bar:.green} {pos}/{len} files")
+        ProgressStyle::with_template(&progress_template("{wide_bar:.green} {pos}/{len} files"))
             .unwrap_or_else(|_| ProgressStyle::default_bar()),
     );
 
@@ -346,7 +737,9 @@ fn run_interactive(cli: &Cli, cfg: &Config, llm: &dyn LlmClient) -> Result<()> {
     let mut ignored_count = 0usize;
 
     for (idx, fc) in file_changes.iter().enumerate() {
-        if matches!(fc.category, FileCategory::Ignored) {
+        if fc.summary.is_some() {
+            pb.inc(1);
+        } else if matches!(fc.category, FileCategory::Ignored) || !fc.needs_summary() {
             pb.inc(1);
             ignored_count += 1;
         } else {
@@ -361,177 +754,521 @@ fn run_interactive(cli: &Cli, cfg: &Config, llm: &dyn LlmClient) -> Result<()> {
         cfg.max_concurrent_requests,
     );
 
-    let ctx = SummarizeContext {
-        branch: &branch,
-        ticket_summary: ticket_summary.as_deref(),
-        llm,
-        max_concurrent_requests: cfg.max_concurrent_requests,
+    let progress = BarProgress {
+        pb: &pb,
+        file_lines: &file_lines,
     };
-
-    summarize_files_concurrently(
-        &mut file_changes,
-        &indices_to_summarize,
-        &ctx,
-        &pb,
-        Some(&file_lines),
-    )?;
+    let summarized = summarize_files(&mut file_changes, &indices_to_summarize, &ctx, &progress);
+    save_run_state(cfg, &branch, ticket_summary.as_deref(), &file_changes);
+    if summarized.is_err() {
+        log::warn!(
+            "Classifications and finished summaries were saved; re-run with --ask to resume"
+        );
+    }
+    summarized?;
 
     pb.inc(1);
     pb.finish_with_message("Done");
 
-    println!();
+    chatter!(cli);
 
-    if cfg.stream {
-        let _msg = llm.generate_commit_message(&branch, &file_changes, ticket_summary.as_deref())?;
+    let msg = llm
+        .generate_commit_message(&branch, &file_changes, ticket_summary.as_deref(), None)
+        .inspect_err(|_| {
+            log::warn!("Classifications and summaries were saved; re-run with --ask to resume");
+        })?;
+    if !cfg.dry_run {
+        RunState::clear();
+    }
+    emit_commit_message(&msg, cli, cfg)?;
+    emit_changelog_entry(cli, llm, &file_changes, &msg)?;
+
+    chatter!(cli);
+    report_usage(cli, llm.take_and_reset_usage());
+
+    Ok(())
+}
+
+/// Files listed per category on the confirmation screen before the rest are counted.
+const CONFIRM_FILES_PER_CATEGORY: usize = 8;
+
+/// Show the classification grouped by category, with the requests and rough cost it will
+/// take, and ask whether to go on. Returns `false` to re-classify; `q` aborts the run.
+/// `--yes` goes on without showing it.
+fn confirm_classification(
+    cli: &Cli,
+    file_changes: &[FileChange],
+    ctx: &SummarizeContext<'_>,
+    cfg: &Config,
+) -> Result<bool> {
+    if cli.yes {
+        return Ok(true);
+    }
+    eprintln!();
+    for category in (1..=4).filter_map(FileCategory::from_menu_number) {
+        let paths: Vec<&str> = file_changes
+            .iter()
+            .filter(|fc| fc.category == category)
+            .map(|fc| fc.path.as_str())
+            .collect();
+        if paths.is_empty() {
+            continue;
+        }
+        eprintln!("{} ({}):", category.label(), paths.len());
+        for path in paths.iter().take(CONFIRM_FILES_PER_CATEGORY) {
+            eprintln!("  {path}");
+        }
+        if paths.len() > CONFIRM_FILES_PER_CATEGORY {
+            eprintln!(
+                "  {}",
+                dimmed(&format!(
+                    "... and {} more",
+                    paths.len() - CONFIRM_FILES_PER_CATEGORY
+                ))
+            );
+        }
+    }
+
+    let indices: Vec<usize> = (0..file_changes.len())
+        .filter(|&idx| {
+            let fc = &file_changes[idx];
+            fc.category != FileCategory::Ignored && fc.needs_summary()
+        })
+        .collect();
+    let sizes = summary_request_sizes(file_changes, &indices, ctx);
+    let estimate = estimate_run(&sizes, cfg.summary_model(), cfg.final_model());
+    let cost = match estimate.cost {
+        Some(cost) if cost < 0.01 => ", <$0.01".to_string(),
+        Some(cost) => format!(", ~${cost:.2}"),
+        None => String::new(),
+    };
+    eprintln!();
+    eprintln!(
+        "{} summary request(s) to {}, then one for the message (~{} tokens{cost}).",
+        sizes.len(),
+        cfg.summary_model(),
+        estimate.total_tokens()
+    );
+
+    loop {
+        let ans = prompt_input(cli, "Continue? [Y]es / [r]e-classify / [q]uit: ", "y")?;
+        match ans.to_lowercase().as_str() {
+            "" | "y" | "yes" => return Ok(true),
+            "r" | "re-classify" | "reclassify" => return Ok(false),
+            "q" | "quit" => return Err(Aborted("Aborted by user.".to_string()).into()),
+            _ => {}
+        }
+    }
+}
+
+/// Report an empty changeset: exit 0 with `--allow-empty`, otherwise fail with a distinct code.
+fn nothing_to_commit(cli: &Cli, message: &str) -> Result<()> {
+    if cli.allow_empty {
+        chatter!(cli, "{message}");
+        return Ok(());
+    }
+    Err(NoChanges(message.to_string()).into())
+}
+
+/// Lint the generated commit message, print it (unless it was already streamed), and
+/// write it to `--message-file` when given. A dry run has no message to emit.
+fn emit_commit_message(msg: &str, cli: &Cli, cfg: &Config) -> Result<()> {
+    if cfg.dry_run {
+        return Ok(());
+    }
+    let outcome = lint_message(msg);
+    for finding in &outcome.findings {
+        log::info!("Commit message lint: {finding}");
+    }
+    let final_msg = if cfg.fixups {
+        outcome.message.as_str()
     } else {
-        let msg = llm.generate_commit_message(&branch, &file_changes, ticket_summary.as_deref())?;
-        if msg.ends_with('\n') {
-            print!("{msg}");
-        } else {
-            println!("{msg}");
+        msg
+    };
+
+    if cfg.stream {
+        // Already printed token by token; fixes can only be reported.
+        if !outcome.findings.is_empty() && cfg.fixups {
+            log::info!("Streamed output is shown unfixed; use --no-stream to apply fixups");
         }
+    } else if final_msg.ends_with('\n') {
+        print!("{final_msg}");
+    } else {
+        println!("{final_msg}");
     }
 
-    println!();
-    if let Some((p, c, t)) = llm.take_and_reset_usage() {
-        println!("Token usage: prompt={}, completion={}, total={}", p, c, t);
+    if let Some(path) = &cli.message_file {
+        confirm_overwrite(Path::new(path), cli)?;
+        write_message_file(Path::new(path), final_msg)?;
+        log::info!("Wrote commit message to {path}");
     }
+    Ok(())
+}
 
+/// With `--changelog`, ask for a one-line changelog entry, print it, and add it to
+/// `--changelog-file` when given.
+fn emit_changelog_entry(
+    cli: &Cli,
+    llm: &dyn LlmClient,
+    files: &[FileChange],
+    msg: &str,
+) -> Result<()> {
+    if !cli.changelog && cli.changelog_file.is_none() {
+        return Ok(());
+    }
+    let reply = llm.generate_changelog_entry(files, msg, None)?;
+    if cli.dry_run {
+        return Ok(());
+    }
+    let entry = ChangelogEntry::parse(&reply)
+        .ok_or_else(|| anyhow!("The model returned an empty changelog entry"))?;
+
+    chatter!(cli);
+    chatter!(cli, "Changelog ({}): {}", entry.category, entry.text);
+    if let Some(path) = &cli.changelog_file {
+        update_changelog_file(Path::new(path), &entry)?;
+        chatter!(
+            cli,
+            "Added to {path} under [Unreleased] / {}",
+            entry.category
+        );
+    }
     Ok(())
 }
 
+/// Guard against clobbering a message that was already typed into `path`.
+///
+/// Asks on a terminal; elsewhere (e.g. a hook with no tty) refuses unless `--force` or
+/// `--yes` is set.
+fn confirm_overwrite(path: &Path, cli: &Cli) -> Result<()> {
+    if cli.force || !message_file_has_content(path) {
+        return Ok(());
+    }
+    let terminal = io::stdin().is_terminal() && io::stderr().is_terminal();
+    if !terminal && !cli.yes {
+        return Err(anyhow!(
+            "{} already has content; pass --force to overwrite it.",
+            path.display()
+        ));
+    }
+
+    // The prompt goes to stderr: hooks commonly send stdout to /dev/null.
+    let prompt = format!("{} already has content; overwrite? [y/N] ", path.display());
+    let ans = prompt_input(cli, &prompt, "y")?;
+    if ans.eq_ignore_ascii_case("y") || ans.eq_ignore_ascii_case("yes") {
+        Ok(())
+    } else {
+        Err(Aborted(format!("Left {} unchanged.", path.display())).into())
+    }
+}
+
+/// Read a diff from a file or stdin (`-`), tolerating non-UTF-8 content.
+fn read_external_diff(diff_arg: &str) -> Result<String> {
+    let bytes = if diff_arg == "-" {
+        let mut buf = Vec::new();
+        io::stdin().read_to_end(&mut buf)?;
+        buf
+    } else {
+        std::fs::read(diff_arg)
+            .map_err(|e| anyhow!("Failed to read diff file '{}': {}", diff_arg, e))?
+    };
+    Ok(decode_diff(&bytes))
+}
+
+/// Short reason shown on the progress line of a file that is not summarized.
+fn skip_reason(fc: &FileChange) -> &'static str {
+    if fc.is_deleted {
+        "deleted"
+    } else if parse_submodule_bump(&fc.diff).is_some() {
+        "submodule bump"
+    } else if is_binary_diff(&fc.diff) {
+        "listed only (binary)"
+    } else {
+        "listed only (.commitbotignore)"
+    }
+}
+
+/// Staged (and, with `--include-untracked`, untracked) files as (path, diff) pairs.
+///
+/// When there is nothing to describe, offers to stage working-tree changes first.
+fn local_file_pairs(cli: &Cli, cfg: &Config) -> Result<Vec<(String, String)>> {
+    let mut files = staged_files()?;
+    let untracked = if cli.include_untracked {
+        untracked_files()?
+    } else {
+        Vec::new()
+    };
+    if files.is_empty() && untracked.is_empty() {
+        files = offer_to_stage(cli)?;
+    }
+
+    let mut pairs = staged_file_pairs(&files, cfg)?;
+    pairs.extend(untracked_file_pairs(&untracked)?);
+    Ok(pairs)
+}
+
 fn run_auto(cli: &Cli, cfg: &Config, llm: &dyn LlmClient) -> Result<()> {
-    let using_external_diff = cli.diff.is_some();
-    let (branch, file_pairs): (String, Vec<(String, String)>) =
+    let (branch, mut file_pairs): (String, Vec<(String, String)>) =
         if let Some(ref diff_arg) = cli.diff {
-            let combined = if diff_arg == "-" {
-                let mut buf = String::new();
-                io::stdin().read_to_string(&mut buf)?;
-                buf
-            } else {
-                std::fs::read_to_string(diff_arg)
-                    .map_err(|e| anyhow!("Failed to read diff file '{}': {}", diff_arg, e))?
-            };
+            let combined = read_external_diff(diff_arg)?;
 
             if combined.trim().is_empty() {
-                println!("No diff content found.");
-                return Ok(());
+                return nothing_to_commit(cli, "No diff content found.");
             }
 
-            let mut per_file = split_diff_by_file(&combined);
-            if per_file.is_empty() {
-                per_file = vec![("(diff)".to_string(), combined)];
-            }
+            let per_file = diff_file_pairs(&combined);
             let branch = cli.branch.clone().unwrap_or_else(|| {
                 current_branch().unwrap_or_else(|_| "unknown-branch".to_string())
             });
             (branch, per_file)
         } else {
             let branch = current_branch()?;
-            let files = staged_files()?;
-            if files.is_empty() {
-                println!("No staged changes found.");
-                return Ok(());
-            }
-            let mut pairs = Vec::new();
-            for path in files {
-                let diff = staged_diff_for_file(&path)?;
-                pairs.push((path, diff));
+            let file_pairs = local_file_pairs(cli, cfg)?;
+            if file_pairs.is_empty() {
+                return nothing_to_commit(cli, "No staged changes found.");
             }
-            (branch, pairs)
+            (branch, file_pairs)
         };
 
+    check_secrets(&mut file_pairs, cfg)?;
+    summarize_simple(cli, cfg, llm, &branch, file_pairs)
+}
+
+/// Simple mode once the (path, diff) pairs are collected: every file is Main.
+fn summarize_simple(
+    cli: &Cli,
+    cfg: &Config,
+    llm: &dyn LlmClient,
+    branch: &str,
+    file_pairs: Vec<(String, String)>,
+) -> Result<()> {
+    let using_external_diff = cli.diff.is_some();
     let ticket_summary = resolved_ticket_summary(cli);
 
-    let mut file_changes: Vec<FileChange> = file_pairs
-        .into_iter()
-        .map(|(path, diff)| FileChange {
-            path,
-            category: FileCategory::Main,
-            diff,
-            summary: None,
-        })
-        .collect();
+    let categories = vec![FileCategory::Main; file_pairs.len()];
+    let mut file_changes = build_file_changes(file_pairs, categories);
+    warn_on_lossy_diffs(&file_changes);
+    check_diff_sizes(&mut file_changes, cfg)?;
+    check_budget(&mut file_changes, cfg)?;
 
-    println!();
-    println!("Asking {}...", cfg.model);
+    chatter!(cli);
+    chatter!(cli, "Asking {}...", cfg.final_model());
     if let Some(ref diff_arg) = cli.diff {
         let diff_source = if diff_arg == "-" { "stdin" } else { diff_arg };
-        println!("Using external diff: {diff_source}");
+        chatter!(cli, "Using external diff: {diff_source}");
     }
 
     let total = file_changes.len();
-    let mp = MultiProgress::new();
+    let mp = MultiProgress::with_draw_target(progress_target(cli));
     let mut file_lines = Vec::new();
 
-    for fc in &file_changes {
-        let line = mp.add(ProgressBar::new_spinner());
-        line.set_style(
-            ProgressStyle::with_template("{spinner:.cyan} {prefix:.bold}: {msg}")
-                .expect("progress style template"),
-        );
-        let prefix = if using_external_diff {
-            format!("[diff] {}", fc.path)
-        } else {
-            fc.path.clone()
-        };
-        line.set_prefix(prefix);
-        line.enable_steady_tick(Duration::from_millis(120));
-        line.set_message("waiting");
-        file_lines.push(line);
+    if show_file_lines(cli) {
+        for fc in &file_changes {
+            let prefix = if using_external_diff {
+                format!("[diff] {}", fc.path)
+            } else {
+                fc.path.clone()
+            };
+            let done = (!fc.needs_summary()).then(|| skip_reason(fc).to_string());
+            file_lines.push(add_file_line(&mp, prefix, done));
+        }
     }
 
     let pb = mp.add(ProgressBar::new((total + 1) as u64));
     pb.set_style(
-        ProgressStyle::with_template("{wide_bar:.green} {pos}/{len} files")
+        ProgressStyle::with_template(&progress_template("{wide_bar:.green} {pos}/{len} files"))
             .unwrap_or_else(|_| ProgressStyle::default_bar()),
     );
 
-    let indices_to_summarize: Vec<usize> = (0..total).collect();
+    let indices_to_summarize: Vec<usize> = (0..total)
+        .filter(|&idx| file_changes[idx].needs_summary())
+        .collect();
+    pb.inc((total - indices_to_summarize.len()) as u64);
 
     log::info!(
         "Auto-summarizing {} files. max_concurrent_requests = {}",
-        total,
+        indices_to_summarize.len(),
         cfg.max_concurrent_requests,
     );
 
+    let rate_limiter = (cfg.requests_per_minute > 0)
+        .then(|| RateLimiter::new(cfg.requests_per_minute, cfg.max_concurrent_requests));
+    let cache = SummaryCache::open(cfg, llm);
     let ctx = SummarizeContext {
-        branch: &branch,
+        branch,
         ticket_summary: ticket_summary.as_deref(),
         llm,
         max_concurrent_requests: cfg.max_concurrent_requests,
+        rate_limiter: rate_limiter.as_ref(),
+        batch_consequence: cfg.batch_consequence,
+        trivial_diff_lines: cfg.trivial_diff_lines,
+        cache: cache.as_ref(),
     };
 
-    summarize_files_concurrently(
-        &mut file_changes,
-        &indices_to_summarize,
-        &ctx,
-        &pb,
-        Some(&file_lines),
-    )?;
+    let progress = BarProgress {
+        pb: &pb,
+        file_lines: &file_lines,
+    };
+    summarize_files(&mut file_changes, &indices_to_summarize, &ctx, &progress)?;
 
     pb.inc(1);
     pb.finish_with_message("Done");
 
-    println!();
+    chatter!(cli);
+
+    let msg =
+        llm.generate_commit_message(branch, &file_changes, ticket_summary.as_deref(), None)?;
+    emit_commit_message(&msg, cli, cfg)?;
+    emit_changelog_entry(cli, llm, &file_changes, &msg)?;
+
+    chatter!(cli);
+    report_usage(cli, llm.take_and_reset_usage());
+
+    Ok(())
+}
+
+/// Generate a replacement message for an existing (non-merge) commit.
+fn run_reword(cli: &Cli, cfg: &Config, llm: &dyn LlmClient, rev: &str) -> Result<()> {
+    if is_merge_commit(rev)? {
+        return Err(anyhow!(
+            "{rev} is a merge commit; reword only supports commits with a single parent."
+        ));
+    }
+    let diff = commit_diff(rev)?;
+    let existing = commit_message(rev)?;
+    let subject = existing
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+
+    // The current subject is the best available hint of the commit's intent.
+    let ticket_summary = resolved_ticket_summary(cli)
+        .or_else(|| (!subject.is_empty()).then(|| format!("Current message: {subject}")));
+    let opts = GenerateOptions {
+        branch: cli.branch.clone(),
+        diff: Some(diff),
+        ticket_summary,
+        summary_cache: true,
+    };
 
     if cfg.stream {
-        let _msg = llm.generate_commit_message(&branch, &file_changes, ticket_summary.as_deref())?;
+        chatter!(cli, "Asking {} to reword {rev}...", cfg.final_model());
+        chatter!(cli);
+    }
+    let spinner = (!cfg.stream)
+        .then(|| pr_spinner(cli, format!("Asking {} to reword {rev}", cfg.final_model())));
+    let out = generate_commit_message_with(cfg, llm, opts);
+    if let Some(spinner) = spinner {
+        spinner.finish_and_clear();
+    }
+    let out = match out {
+        Err(err) if err.downcast_ref::<NoChanges>().is_some() => {
+            return Err(anyhow!("{rev} has no diff to describe."));
+        }
+        other => other?,
+    };
+
+    emit_commit_message(&out.message, cli, cfg)?;
+    chatter!(cli);
+    report_usage(cli, out.usage);
+    Ok(())
+}
+
+/// Show the run's requests, tokens, and estimated cost; `--usage` shows it even with `--quiet`.
+fn report_usage(cli: &Cli, usage: Option<TokenUsage>) {
+    let Some(usage) = usage else {
+        return;
+    };
+    if cli.usage {
+        eprintln!("Token usage: {usage}");
     } else {
-        let msg = llm.generate_commit_message(&branch, &file_changes, ticket_summary.as_deref())?;
-        if msg.ends_with('\n') {
-            print!("{msg}");
-        } else {
-            println!("{msg}");
+        chatter!(cli, "Token usage: {usage}");
+    }
+}
+
+/// Where progress bars draw: stderr normally, nowhere with `--quiet`.
+fn progress_target(cli: &Cli) -> ProgressDrawTarget {
+    if cli.quiet {
+        ProgressDrawTarget::hidden()
+    } else {
+        ProgressDrawTarget::stderr()
+    }
+}
+
+/// Per-file spinner lines need a terminal; elsewhere only the overall bar is kept.
+fn show_file_lines(cli: &Cli) -> bool {
+    !cli.quiet && io::stderr().is_terminal()
+}
+
+/// Add a spinner line for one file; `done` finishes it immediately with that status.
+fn add_file_line(mp: &MultiProgress, prefix: String, done: Option<String>) -> ProgressBar {
+    let line = mp.add(ProgressBar::new_spinner());
+    line.set_style(
+        ProgressStyle::with_template(&progress_template("{spinner:.cyan} {prefix:.bold}: {msg}"))
+            .expect("progress style template"),
+    );
+    line.set_prefix(prefix);
+    match done {
+        Some(status) => line.finish_with_message(dimmed(&status)),
+        None => {
+            line.enable_steady_tick(Duration::from_millis(120));
+            line.set_message("waiting");
         }
     }
+    line
+}
 
-    println!();
-    if let Some((p, c, t)) = llm.take_and_reset_usage() {
-        println!("Token usage: prompt={}, completion={}, total={}", p, c, t);
+/// Spinner for a PR-mode phase that has no per-item progress.
+fn pr_spinner(cli: &Cli, message: String) -> ProgressBar {
+    let spinner = ProgressBar::with_draw_target(None, progress_target(cli));
+    spinner.set_style(
+        ProgressStyle::with_template(&progress_template("{spinner:.cyan} {msg} {elapsed:.dim}"))
+            .expect("progress style template"),
+    );
+    spinner.enable_steady_tick(Duration::from_millis(120));
+    spinner.set_message(message);
+    spinner
+}
+
+/// "5 commits" or "3 PRs (5 commits)", depending on the summary mode.
+fn describe_items(mode: PrSummaryMode, items: &[PrItem]) -> String {
+    match mode {
+        PrSummaryMode::ByCommits => format!("{} commits", items.len()),
+        PrSummaryMode::ByPrs => {
+            let prs: HashSet<u32> = items.iter().filter_map(|i| i.pr_number).collect();
+            format!("{} PRs ({} commits)", prs.len(), items.len())
+        }
     }
+}
 
-    Ok(())
+/// Start of the PR range and the branch to describe.
+///
+/// The base is a branch, or a tag when writing release notes. With a tag option, the first
+/// positional argument is the branch to describe, since the tag takes the base's place.
+fn resolve_pr_range<'a>(
+    base: Option<&'a str>,
+    from: Option<&'a str>,
+    tag: Option<&str>,
+    since_tag: bool,
+) -> Result<(String, Option<&'a str>)> {
+    if tag.is_none() && !since_tag {
+        let base =
+            base.ok_or_else(|| anyhow!("Pass a base branch, --since-tag, or --tag <name>"))?;
+        return Ok((base.to_string(), from));
+    }
+    if from.is_some() {
+        return Err(anyhow!(
+            "With --tag or --since-tag, pass only the branch to describe; the tag is the base"
+        ));
+    }
+    let tag = match tag {
+        Some(tag) => tag.to_string(),
+        None => latest_tag(base.unwrap_or("HEAD"))?,
+    };
+    log::info!("Using tag {tag} as the PR base");
+    Ok((tag, base))
 }
 
 fn run_pr(
@@ -548,9 +1285,23 @@ fn run_pr(
         None => current_branch()?,
     };
 
-    let items = collect_pr_items(base, &from_branch)?;
+    verify_ref(base)?;
+    verify_ref(&from_branch)?;
+
+    let gather = pr_spinner(cli, format!("Collecting commits in {base}..{from_branch}"));
+    let items = match collect_pr_items(base, &from_branch, cfg.forge) {
+        Ok(items) => items,
+        Err(err) => {
+            gather.finish_and_clear();
+            return Err(err);
+        }
+    };
+    gather.finish_with_message(format!(
+        "Collected {} commits in {base}..{from_branch}",
+        items.len()
+    ));
     if items.is_empty() {
-        println!("No commits found between {base} and {from_branch}.");
+        chatter!(cli, "No commits found between {base} and {from_branch}.");
         return Ok(());
     }
 
@@ -559,38 +1310,78 @@ fn run_pr(
     } else if commit_flag {
         PrSummaryMode::ByCommits
     } else {
-        let mut distinct_prs: HashSet<u32> = HashSet::new();
-        for item in &items {
-            if let Some(n) = item.pr_number {
-                distinct_prs.insert(n);
-            }
-        }
-        if distinct_prs.len() >= 2 {
-            PrSummaryMode::ByPrs
-        } else {
-            PrSummaryMode::ByCommits
-        }
+        let (mode, reason) =
+            autodetect_summary_mode(&items, cfg.pr_autodetect_min, cfg.pr_autodetect_ratio);
+        log::info!("Auto-detected summary mode {}: {reason}", mode.as_str());
+        mode
     };
 
     log::info!(
-        "PR mode: base={base}, from={from}, mode={mode}",
+        "PR mode: base={base}, from={from}, mode={mode}, forge={forge}",
         base = base,
         from = from_branch,
-        mode = mode.as_str()
+        mode = mode.as_str(),
+        forge = cfg.forge.as_str()
     );
     log::info!("Found {} commits in range.", items.len());
 
+    let history: String = items
+        .iter()
+        .map(|item| format!("{}\n{}\n", item.title, item.body))
+        .collect();
+    check_context(
+        cfg,
+        cfg.final_model(),
+        &format!("The history for {base}..{from_branch}"),
+        &history,
+        "consider a closer base branch",
+    )?;
+
     let ticket_summary = resolved_ticket_summary(cli);
     let _pr_message = if cfg.stream {
-        println!();
-        let msg =
-            llm.generate_pr_message(base, &from_branch, mode, &items, ticket_summary.as_deref())?;
-        println!();
+        chatter!(
+            cli,
+            "Asking {} to describe {}...",
+            cfg.final_model(),
+            describe_items(mode, &items)
+        );
+        chatter!(cli);
+        let msg = llm.generate_pr_message(
+            base,
+            &from_branch,
+            mode,
+            &items,
+            ticket_summary.as_deref(),
+            None,
+        )?;
+        // end the streamed description's last line, as the buffered path does
+        if !msg.ends_with('\n') {
+            println!();
+        }
         msg
-        } else {
-        println!();
-        let msg =
-            llm.generate_pr_message(base, &from_branch, mode, &items, ticket_summary.as_deref())?;
+    } else {
+        let asking = pr_spinner(
+            cli,
+            format!(
+                "Asking {} to describe {}",
+                cfg.final_model(),
+                describe_items(mode, &items)
+            ),
+        );
+        let msg = llm.generate_pr_message(
+            base,
+            &from_branch,
+            mode,
+            &items,
+            ticket_summary.as_deref(),
+            None,
+        );
+        asking.finish_and_clear();
+        let msg = msg?;
+        if cfg.dry_run {
+            return Ok(());
+        }
+        chatter!(cli);
 
         if msg.ends_with('\n') {
             print!("{msg}");
@@ -606,11 +1397,37 @@ fn run_pr(
         println!("{appendix}");
     }
 
+    if cli.usage {
+        eprintln!();
+        report_usage(cli, llm.take_and_reset_usage());
+    }
+
     Ok(())
 }
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
+/// Run commitbot and exit with the code for how it ended (see [`ExitReason`]).
+///
+/// Errors are printed to stderr; stdout only ever carries the generated text.
+fn main() {
+    if let Err(err) = run() {
+        let reason = ExitReason::of(&err);
+        match reason {
+            ExitReason::Error => eprintln!("Error: {err:?}"),
+            ExitReason::NoChanges | ExitReason::Aborted => eprintln!("{err}"),
+        }
+        std::process::exit(reason.code());
+    }
+}
+
+fn run() -> Result<()> {
+    let mut cli = Cli::parse();
+    let default_args = Config::default_args(&cli)?;
+    if !default_args.is_empty() {
+        cli = Cli::parse_from(with_default_args(
+            std::env::args_os().collect(),
+            &default_args,
+        ));
+    }
 
     // Handle custom --version early so we print just the version number.
     if cli.version {
@@ -618,6 +1435,7 @@ fn main() -> Result<()> {
         return Ok(());
     }
 
+    commitbot::logging::init_color(cli.no_color);
     commitbot::logging::init_logger(cli.verbose);
 
     if cli.diff.is_some() && matches!(&cli.command, Some(Command::Pr { .. })) {
@@ -626,8 +1444,19 @@ fn main() -> Result<()> {
              PR mode analyzes commit history, not staged diffs."
         ));
     }
+    if cli.diff.is_some() && matches!(&cli.command, Some(Command::Reword { .. })) {
+        return Err(anyhow!(
+            "The --diff flag cannot be used with the 'reword' command.\n\
+             Reword reads the diff of the given commit."
+        ));
+    }
 
-    let cfg = Config::from_sources(&cli)?;
+    let mut cfg = Config::from_sources(&cli)?;
+    if let Some(Command::Pr { no_template, .. }) = &cli.command
+        && !no_template
+    {
+        cfg.pr_template = pr_template();
+    }
 
     if cli.stage {
         commitbot::git::stage_all()?;
@@ -642,15 +1471,23 @@ fn main() -> Result<()> {
             from,
             pr_mode,
             commit_mode,
-        }) => run_pr(
-            &cli,
-            &cfg,
-            boxed_client.as_ref(),
-            base.as_str(),
-            from.as_deref(),
-            *pr_mode,
-            *commit_mode,
-        ),
+            since_tag,
+            tag,
+            ..
+        }) => {
+            let (base, from) =
+                resolve_pr_range(base.as_deref(), from.as_deref(), tag.as_deref(), *since_tag)?;
+            run_pr(
+                &cli,
+                &cfg,
+                boxed_client.as_ref(),
+                &base,
+                from,
+                *pr_mode,
+                *commit_mode,
+            )
+        }
+        Some(Command::Reword { rev }) => run_reword(&cli, &cfg, boxed_client.as_ref(), rev),
         Some(Command::Summary(_)) | None => {
             if cli.ask {
                 run_interactive(&cli, &cfg, boxed_client.as_ref())
@@ -660,3 +1497,18 @@ fn main() -> Result<()> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preview_snippet_cuts_on_character_boundaries() {
+        let summary = "- 更新配置文件以支持多个提供商".repeat(6);
+        let preview = preview_snippet(&summary);
+        assert_eq!(preview.chars().count(), 81);
+        assert!(preview.ends_with('…'));
+
+        assert_eq!(preview_snippet("  - Añade caché\nmore"), "- Añade caché");
+    }
+}
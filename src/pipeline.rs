@@ -0,0 +1,852 @@
+//! The commit-message pipeline, usable without the CLI.
+//!
+//! [`generate_commit_message`] runs the whole flow (collect diffs, summarize each file,
+//! write the message) with no terminal output. The building blocks are public too, so the
+//! binary can drive the same steps with progress bars and prompts layered on top.
+
+use crate::budget::{diff_cap, estimate_run, over_budget};
+use crate::config::Config;
+use crate::git::{
+    DiffOptions, current_branch, split_diff_by_file, staged_diff_for_file_with, staged_files,
+    untracked_diff_for_file,
+};
+use crate::git::{has_replacement_chars, is_binary_diff, is_deleted_diff};
+use crate::ignore::{IgnoreRules, load_repo_ignore};
+use crate::llm::context::{
+    PROMPT_RESERVE_TOKENS, check_fits, context_limit, estimate_tokens, truncate_to_tokens,
+};
+use crate::llm::error::is_fatal;
+use crate::llm::usage::price_per_million;
+use crate::llm::{CancellationToken, LlmClient, TokenUsage, diff_line_counts};
+use crate::rate_limit::RateLimiter;
+use crate::security::scan_diff;
+use crate::summary_cache::SummaryCache;
+use crate::text::lint_message;
+use crate::trivial::trivial_summary;
+use crate::{FileCategory, FileChange, setup};
+use anyhow::{Result, anyhow};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::thread;
+
+/// Raised when there is nothing to describe (no staged changes or an empty diff).
+#[derive(Debug)]
+pub struct NoChanges(pub String);
+
+impl std::fmt::Display for NoChanges {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for NoChanges {}
+
+/// Raised when the user backs out of an interactive prompt.
+#[derive(Debug)]
+pub struct Aborted(pub String);
+
+impl std::fmt::Display for Aborted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Aborted {}
+
+/// Inputs for [`generate_commit_message`].
+#[derive(Debug, Clone, Default)]
+pub struct GenerateOptions {
+    /// Branch name given to the model (default: the current branch)
+    pub branch: Option<String>,
+    /// Combined diff to describe instead of the staged changes
+    pub diff: Option<String>,
+    /// Short description of the overall goal (e.g. the ticket title)
+    pub ticket_summary: Option<String>,
+    /// Reuse and store per-file summaries in the user's cache directory, as the CLI does
+    /// (still subject to `cfg.summary_cache`)
+    pub summary_cache: bool,
+}
+
+/// Result of [`generate_commit_message`].
+#[derive(Debug, Clone)]
+pub struct CommitOutput {
+    /// The commit message, with fixups applied unless `cfg.fixups` is off
+    pub message: String,
+    /// Problems the linter found in the raw model output
+    pub lint_findings: Vec<String>,
+    /// Every file that was considered, with its summary filled in
+    pub files: Vec<FileChange>,
+    /// Requests and token usage, when any request was made
+    pub usage: Option<TokenUsage>,
+}
+
+/// Generate a commit message for the staged changes (or `opts.diff`).
+///
+/// Builds the configured LLM client and validates the model first. Streaming is turned off
+/// so nothing is written to stdout.
+pub fn generate_commit_message(cfg: &Config, opts: GenerateOptions) -> Result<CommitOutput> {
+    let cfg = Config {
+        stream: false,
+        ..cfg.clone()
+    };
+    let llm = setup::build_llm_client(&cfg)?;
+    llm.validate_model()?;
+    generate_commit_message_with(&cfg, llm.as_ref(), opts)
+}
+
+/// Like [`generate_commit_message`], but with a caller-supplied client.
+pub fn generate_commit_message_with(
+    cfg: &Config,
+    llm: &dyn LlmClient,
+    opts: GenerateOptions,
+) -> Result<CommitOutput> {
+    let (branch, mut pairs) = match opts.diff.as_deref() {
+        Some(diff) => {
+            if diff.trim().is_empty() {
+                return Err(NoChanges("No diff content found.".to_string()).into());
+            }
+            let branch = opts
+                .branch
+                .unwrap_or_else(|| current_branch().unwrap_or_else(|_| "unknown-branch".into()));
+            (branch, diff_file_pairs(diff))
+        }
+        None => {
+            let files = staged_files()?;
+            if files.is_empty() {
+                return Err(NoChanges("No staged changes found.".to_string()).into());
+            }
+            let branch = match opts.branch {
+                Some(branch) => branch,
+                None => current_branch()?,
+            };
+            (branch, staged_file_pairs(&files, cfg)?)
+        }
+    };
+
+    check_secrets(&mut pairs, cfg)?;
+
+    let categories = vec![FileCategory::Main; pairs.len()];
+    let mut files = build_file_changes(pairs, categories);
+    warn_on_lossy_diffs(&files);
+    check_diff_sizes(&mut files, cfg)?;
+    check_budget(&mut files, cfg)?;
+
+    let indices: Vec<usize> = (0..files.len())
+        .filter(|&i| files[i].needs_summary())
+        .collect();
+    let rate_limiter = (cfg.requests_per_minute > 0)
+        .then(|| RateLimiter::new(cfg.requests_per_minute, cfg.max_concurrent_requests));
+    let cache = if opts.summary_cache {
+        SummaryCache::open(cfg, llm)
+    } else {
+        None
+    };
+    let ctx = SummarizeContext {
+        branch: &branch,
+        ticket_summary: opts.ticket_summary.as_deref(),
+        llm,
+        max_concurrent_requests: cfg.max_concurrent_requests,
+        rate_limiter: rate_limiter.as_ref(),
+        batch_consequence: cfg.batch_consequence,
+        trivial_diff_lines: cfg.trivial_diff_lines,
+        cache: cache.as_ref(),
+    };
+    summarize_files(&mut files, &indices, &ctx, &NoProgress)?;
+
+    let raw = llm.generate_commit_message(&branch, &files, opts.ticket_summary.as_deref(), None)?;
+    let outcome = lint_message(&raw);
+    let message = if cfg.fixups { outcome.message } else { raw };
+
+    Ok(CommitOutput {
+        message,
+        lint_findings: outcome.findings,
+        files,
+        usage: llm.take_and_reset_usage(),
+    })
+}
+
+/// Split a combined diff into per-file pairs, keeping it whole if it has no file headers.
+pub fn diff_file_pairs(combined: &str) -> Vec<(String, String)> {
+    let per_file = split_diff_by_file(combined);
+    if per_file.is_empty() {
+        vec![("(diff)".to_string(), combined.to_string())]
+    } else {
+        per_file
+    }
+}
+
+/// Staged diff for each of `files`, as (path, diff) pairs, using `cfg`'s diff options.
+///
+/// With `word_diff`, a file whose line diff trips the secret scan keeps its line diff:
+/// porcelain word diffs split a changed line into separate `-`/`+` words, so a changed
+/// value no longer sits next to the key that makes it look like a secret.
+pub fn staged_file_pairs(files: &[String], cfg: &Config) -> Result<Vec<(String, String)>> {
+    let opts = cfg.diff_options();
+    files
+        .iter()
+        .map(|path| {
+            if !opts.word_diff {
+                return Ok((path.clone(), staged_diff_for_file_with(path, opts)?));
+            }
+            let line_opts = DiffOptions {
+                word_diff: false,
+                ..opts
+            };
+            let line_diff = staged_diff_for_file_with(path, line_opts)?;
+            let (found, _) = scan_diff(path, &line_diff, &cfg.secret_allowlist);
+            let diff = if found.is_empty() {
+                staged_diff_for_file_with(path, opts)?
+            } else {
+                line_diff
+            };
+            Ok((path.clone(), diff))
+        })
+        .collect()
+}
+
+/// Untracked `files` as all-added (path, diff) pairs; nothing is staged.
+pub fn untracked_file_pairs(files: &[String]) -> Result<Vec<(String, String)>> {
+    files
+        .iter()
+        .map(|path| Ok((path.clone(), untracked_diff_for_file(path)?)))
+        .collect()
+}
+
+/// Turn (path, diff) pairs into [`FileChange`]s, marking ignored and binary files list-only
+/// and flagging deletions.
+pub fn build_file_changes(
+    pairs: Vec<(String, String)>,
+    categories: Vec<FileCategory>,
+) -> Vec<FileChange> {
+    let ignore_rules = load_repo_ignore();
+    pairs
+        .into_iter()
+        .zip(categories)
+        .map(|((path, diff), category)| FileChange {
+            list_only: is_list_only(ignore_rules.as_ref(), &path, &diff),
+            is_deleted: is_deleted_diff(&diff),
+            path,
+            category,
+            diff,
+            summary: None,
+        })
+        .collect()
+}
+
+/// Whether a file should only be listed, not summarized: it matches `.commitbotignore`
+/// or its diff is binary.
+pub fn is_list_only(rules: Option<&IgnoreRules>, path: &str, diff: &str) -> bool {
+    if rules.is_some_and(|r| r.is_ignored(path)) {
+        log::debug!("{path} matches .commitbotignore; listing without summary");
+        return true;
+    }
+    if is_binary_diff(diff) {
+        log::debug!("{path} has a binary diff; listing without summary");
+        return true;
+    }
+    false
+}
+
+/// Warn (or fail with `strict_context`) when `text` likely exceeds `model`'s context window.
+pub fn check_context(cfg: &Config, model: &str, what: &str, text: &str, hint: &str) -> Result<()> {
+    let Some(overflow) = check_fits(model, text, &cfg.context_limits) else {
+        return Ok(());
+    };
+    let message = format!(
+        "{what} is ~{} tokens, more than {model}'s ~{}-token context window; {hint}",
+        overflow.estimated_tokens, overflow.limit
+    );
+    if cfg.strict_context {
+        return Err(anyhow!("{message} (--strict-context)"));
+    }
+    log::warn!("{message}");
+    Ok(())
+}
+
+/// Check each diff that will be sent for summarization against the context window, first
+/// cutting oversized ones down to size when `trim_context` is set.
+pub fn check_diff_sizes(file_changes: &mut [FileChange], cfg: &Config) -> Result<()> {
+    for fc in file_changes.iter_mut().filter(|fc| fc.needs_summary()) {
+        if cfg.trim_context {
+            trim_to_context(fc, cfg);
+        }
+        check_context(
+            cfg,
+            cfg.summary_model(),
+            &format!("The diff for {}", fc.path),
+            &fc.diff,
+            "consider listing it in .commitbotignore or splitting the commit",
+        )?;
+    }
+    Ok(())
+}
+
+/// Keep as much of `fc`'s diff as fits the summary model's window, leaving room for the
+/// prompt and reply, and note how much was cut so the model doesn't describe a partial change
+/// as the whole.
+fn trim_to_context(fc: &mut FileChange, cfg: &Config) {
+    let Some(limit) = context_limit(cfg.summary_model(), &cfg.context_limits) else {
+        return;
+    };
+    let budget = limit.saturating_sub(PROMPT_RESERVE_TOKENS).max(limit / 2);
+    if let Some(dropped) = trim_diff(fc, budget, "the context window") {
+        log::warn!(
+            "Trimmed {dropped} line(s) from the diff for {} to fit {}'s context window",
+            fc.path,
+            cfg.summary_model()
+        );
+    }
+}
+
+/// Cut `fc`'s diff to about `max_tokens`, including a note saying how many lines were
+/// dropped to fit `reason`. Returns the number dropped, or `None` if it already fit.
+fn trim_diff(fc: &mut FileChange, max_tokens: usize, reason: &str) -> Option<usize> {
+    let note = |dropped: usize| format!("\n[... {dropped} more line(s) omitted to fit {reason}]\n");
+    let room = max_tokens.saturating_sub(estimate_tokens(&note(fc.diff.lines().count())));
+    let (kept, dropped) = truncate_to_tokens(&fc.diff, room.max(max_tokens / 2))?;
+    fc.diff = kept + &note(dropped);
+    Some(dropped)
+}
+
+/// Estimate the run's tokens and cost before anything is sent and hold it to
+/// `max_tokens_per_run` / `max_cost_per_run`: fail when over, or with `trim_context` cut the
+/// largest diffs until the estimate fits.
+pub fn check_budget(file_changes: &mut [FileChange], cfg: &Config) -> Result<()> {
+    let budget = cfg.budget();
+    if budget.is_unlimited() {
+        return Ok(());
+    }
+    let (summary_model, final_model) = (cfg.summary_model(), cfg.final_model());
+    if budget.max_cost.is_some()
+        && (price_per_million(summary_model).is_none() || price_per_million(final_model).is_none())
+    {
+        log::warn!(
+            "No price is known for {summary_model} or {final_model}; max_cost_per_run is not enforced"
+        );
+    }
+
+    let mut pending: Vec<&mut FileChange> = file_changes
+        .iter_mut()
+        .filter(|fc| {
+            fc.needs_summary()
+                && trivial_summary(&fc.path, &fc.diff, cfg.trivial_diff_lines).is_none()
+        })
+        .collect();
+    let sizes: Vec<usize> = pending.iter().map(|fc| estimate_tokens(&fc.diff)).collect();
+    let Some(reason) = over_budget(&estimate_run(&sizes, summary_model, final_model), &budget)
+    else {
+        return Ok(());
+    };
+
+    let cap = diff_cap(&sizes, summary_model, final_model, &budget);
+    let Some(cap) = cap.filter(|_| cfg.trim_context) else {
+        let hint = if cap.is_some() {
+            "list large files in .commitbotignore, raise the limit, or pass --trim-context to cut \
+             the largest diffs"
+        } else {
+            "even without diffs the run would not fit; raise the limit or split the commit"
+        };
+        return Err(anyhow!(
+            "Estimated {reason}; stopping before any request is sent ({hint})"
+        ));
+    };
+    for (fc, &size) in pending.iter_mut().zip(&sizes) {
+        if size > cap
+            && let Some(dropped) = trim_diff(fc, cap, "the run budget")
+        {
+            log::warn!(
+                "Trimmed {dropped} line(s) from the diff for {} to keep the run within budget",
+                fc.path
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Warn about (or, with `strict_secrets`, refuse) secret-looking additions, then redact them
+/// from the diffs so they are never sent to the provider.
+///
+/// Files matching `.commitbotignore` and binary files are skipped: their diffs are never sent.
+pub fn check_secrets(pairs: &mut [(String, String)], cfg: &Config) -> Result<()> {
+    let ignore_rules = load_repo_ignore();
+    let mut findings = Vec::new();
+    for (path, diff) in pairs.iter_mut() {
+        if ignore_rules.as_ref().is_some_and(|r| r.is_ignored(path)) || is_binary_diff(diff) {
+            continue;
+        }
+        let (found, redacted) = scan_diff(path, diff, &cfg.secret_allowlist);
+        if !found.is_empty() {
+            *diff = redacted;
+            findings.extend(found);
+        }
+    }
+    if findings.is_empty() {
+        return Ok(());
+    }
+
+    let listing: Vec<String> = findings
+        .iter()
+        .map(|f| format!("  {}:{} ({})", f.path, f.line, f.kind))
+        .collect();
+    let message = format!(
+        "Staged changes look like they contain secrets:\n{}",
+        listing.join("\n")
+    );
+    if cfg.strict_secrets {
+        return Err(anyhow!(
+            "{message}\nRemove them, or add an entry to secret_allowlist if they are safe (--strict-secrets)"
+        ));
+    }
+    log::warn!("{message}\nThese values were redacted before sending the diff.");
+    Ok(())
+}
+
+/// Warn about files whose diffs were not valid UTF-8, since their summaries may be garbled.
+pub fn warn_on_lossy_diffs(file_changes: &[FileChange]) {
+    let lossy: Vec<&str> = file_changes
+        .iter()
+        .filter(|fc| fc.needs_summary() && has_replacement_chars(&fc.diff))
+        .map(|fc| fc.path.as_str())
+        .collect();
+    if !lossy.is_empty() {
+        log::warn!(
+            "Diffs for these files are not valid UTF-8; their summaries may be unreliable: {}",
+            lossy.join(", ")
+        );
+    }
+}
+
+/// Per-file progress callbacks from [`summarize_files`]; every method defaults to a no-op.
+///
+/// `idx` is the file's position in the slice passed to `summarize_files`.
+pub trait SummaryProgress: Sync {
+    /// The file is waiting on the rate limiter.
+    fn throttled(&self, _idx: usize) {}
+    /// A request covering the file was sent (`batched` for the combined Consequence request).
+    fn started(&self, _idx: usize, _batched: bool) {}
+    /// The file's summary arrived or failed.
+    fn finished(&self, _idx: usize, _result: &Result<String>) {}
+    /// The file was never sent because an earlier fatal error cancelled the run.
+    fn cancelled(&self, _idx: usize) {}
+}
+
+/// [`SummaryProgress`] that reports nothing.
+pub struct NoProgress;
+
+impl SummaryProgress for NoProgress {}
+
+type SummarizeResults = Mutex<Vec<(usize, Result<String>)>>;
+
+/// Shared inputs for summarizing a set of files.
+pub struct SummarizeContext<'a> {
+    pub branch: &'a str,
+    pub ticket_summary: Option<&'a str>,
+    pub llm: &'a dyn LlmClient,
+    pub max_concurrent_requests: usize,
+    pub rate_limiter: Option<&'a RateLimiter>,
+    pub batch_consequence: bool,
+    /// Diffs changing at most this many lines may get a local summary (0 = never).
+    pub trivial_diff_lines: usize,
+    /// Summaries from earlier runs; `None` always asks the model.
+    pub cache: Option<&'a SummaryCache>,
+}
+
+/// Summarize Consequence files with one combined request.
+///
+/// Returns the indices that still need a dedicated per-file call: everything that
+/// is not a Consequence file, plus any file the batch response did not cover.
+fn summarize_consequence_batch(
+    file_changes: &mut [FileChange],
+    indices: &[usize],
+    ctx: &SummarizeContext<'_>,
+    progress: &dyn SummaryProgress,
+) -> Result<Vec<usize>> {
+    let (batch, mut remaining): (Vec<usize>, Vec<usize>) = indices
+        .iter()
+        .partition(|&&idx| matches!(file_changes[idx].category, FileCategory::Consequence));
+
+    if batch.len() < 2 {
+        remaining.extend(batch);
+        remaining.sort_unstable();
+        return Ok(remaining);
+    }
+
+    for &idx in &batch {
+        progress.started(idx, true);
+    }
+    if let Some(limiter) = ctx.rate_limiter {
+        limiter.acquire();
+    }
+
+    log::debug!("Batch-summarizing {} consequence files", batch.len());
+    let files: Vec<&FileChange> = batch.iter().map(|&idx| &file_changes[idx]).collect();
+    let summaries = ctx
+        .llm
+        .summarize_batch(ctx.branch, &files, ctx.ticket_summary, None)?;
+
+    for (&idx, summary) in batch.iter().zip(summaries) {
+        match summary.filter(|s| !s.trim().is_empty()) {
+            Some(summary) => {
+                let res = Ok(summary);
+                progress.finished(idx, &res);
+                file_changes[idx].summary = res.ok();
+            }
+            None => {
+                log::debug!(
+                    "Batch response missed {}; summarizing it separately",
+                    file_changes[idx].path
+                );
+                remaining.push(idx);
+            }
+        }
+    }
+
+    remaining.sort_unstable();
+    Ok(remaining)
+}
+
+/// Diff tokens of each request [`summarize_files`] would send for the files at `indices`.
+///
+/// Mirrors its stages without calling the model: trivial diffs and cached summaries need no
+/// request, batched Consequence files share one, and identical diffs are sent once.
+pub fn summary_request_sizes(
+    file_changes: &[FileChange],
+    indices: &[usize],
+    ctx: &SummarizeContext<'_>,
+) -> Vec<usize> {
+    let needs_model: Vec<usize> = indices
+        .iter()
+        .copied()
+        .filter(|&idx| {
+            let file = &file_changes[idx];
+            trivial_summary(&file.path, &file.diff, ctx.trivial_diff_lines).is_none()
+                && ctx
+                    .cache
+                    .is_none_or(|cache| cache.get(ctx.branch, ctx.ticket_summary, file).is_none())
+        })
+        .collect();
+
+    let mut sizes = Vec::new();
+    let (batch, rest): (Vec<usize>, Vec<usize>) = needs_model.iter().partition(|&&idx| {
+        ctx.batch_consequence && matches!(file_changes[idx].category, FileCategory::Consequence)
+    });
+    let rest = if batch.len() < 2 {
+        needs_model
+    } else {
+        sizes.push(
+            batch
+                .iter()
+                .map(|&idx| estimate_tokens(&file_changes[idx].diff))
+                .sum(),
+        );
+        rest
+    };
+
+    let (unique, _) = dedupe_identical_diffs(file_changes, &rest);
+    sizes.extend(
+        unique
+            .iter()
+            .map(|&idx| estimate_tokens(&file_changes[idx].diff)),
+    );
+    sizes
+}
+
+/// Summarize the files at `indices` with up to `max_concurrent_requests` parallel calls,
+/// storing each summary on its [`FileChange`].
+///
+/// Workers pull from a shared queue, so a slot is refilled as soon as its request finishes
+/// and one slow file never holds up the others.
+///
+/// Trivial diffs (blank lines, whitespace, a changed import) are summarized locally without
+/// a request; see [`trivial_summary`]. Files summarized by an earlier run with the same diff
+/// and prompt reuse that summary when `ctx.cache` is set; see [`SummaryCache`].
+///
+/// Files whose hunks are identical (e.g. two copies of the same new file) share one call;
+/// the copies get the first file's summary with its path swapped for theirs.
+///
+/// A fatal provider error (e.g. a rejected API key) stops the remaining files; the first
+/// such error is returned in preference to ordinary failures.
+pub fn summarize_files(
+    file_changes: &mut [FileChange],
+    indices: &[usize],
+    ctx: &SummarizeContext<'_>,
+    progress: &dyn SummaryProgress,
+) -> Result<()> {
+    let needs_model = summarize_trivial_diffs(file_changes, indices, ctx, progress);
+    let needs_model = restore_cached_summaries(file_changes, &needs_model, ctx, progress);
+    let indices = needs_model.as_slice();
+
+    let batched_remaining;
+    let indices = if ctx.batch_consequence {
+        batched_remaining = summarize_consequence_batch(file_changes, indices, ctx, progress)?;
+        batched_remaining.as_slice()
+    } else {
+        indices
+    };
+
+    if indices.is_empty() {
+        return Ok(());
+    }
+    let (unique, duplicates) = dedupe_identical_diffs(file_changes, indices);
+    let indices = unique.as_slice();
+
+    let workers = ctx.max_concurrent_requests.max(1).min(indices.len());
+    let queue: Mutex<VecDeque<usize>> = Mutex::new(indices.iter().copied().collect());
+    let results: SummarizeResults = Mutex::new(Vec::new());
+    // Set by the first unrecoverable error (e.g. a rejected API key) so idle workers stop
+    // pulling new files instead of repeating the same failing request.
+    let cancel = CancellationToken::new();
+
+    {
+        let files: &[FileChange] = file_changes;
+        let worker = || summarize_worker(files, &queue, &results, &cancel, ctx, progress);
+
+        if workers == 1 {
+            // Stay on the calling thread so debug logs and panics read in file order.
+            worker();
+        } else {
+            thread::scope(|scope| {
+                for _ in 0..workers {
+                    scope.spawn(worker);
+                }
+            });
+        }
+    }
+
+    if cancel.is_cancelled() {
+        let skipped = queue.into_inner().expect("queue mutex poisoned");
+        for idx in skipped {
+            progress.cancelled(idx);
+        }
+    }
+
+    let results = results.into_inner().expect("results mutex poisoned");
+
+    let mut first_err: Option<anyhow::Error> = None;
+
+    for (idx, res) in results {
+        match res {
+            Ok(summary) => {
+                if let Some(cache) = ctx.cache
+                    && summary != fallback_summary(&file_changes[idx])
+                {
+                    cache.put(ctx.branch, ctx.ticket_summary, &file_changes[idx], &summary);
+                }
+                file_changes[idx].summary = Some(summary);
+            }
+            Err(e) => {
+                // A fatal error explains every other failure, so report it first.
+                if first_err.is_none()
+                    || (is_fatal(&e) && !first_err.as_ref().is_some_and(is_fatal))
+                {
+                    first_err = Some(e);
+                }
+            }
+        }
+    }
+
+    for (dup, original) in duplicates {
+        let Some(summary) = file_changes[original].summary.as_deref() else {
+            progress.cancelled(dup);
+            continue;
+        };
+        let summary = summary.replace(&file_changes[original].path, &file_changes[dup].path);
+        log::debug!(
+            "Reusing the summary of {} for identical {}",
+            file_changes[original].path,
+            file_changes[dup].path
+        );
+        let res = Ok(summary);
+        progress.started(dup, false);
+        progress.finished(dup, &res);
+        file_changes[dup].summary = res.ok();
+    }
+
+    if let Some(err) = first_err {
+        return Err(err);
+    }
+
+    Ok(())
+}
+
+/// Summarize trivial diffs locally, returning the indices that still need the model.
+fn summarize_trivial_diffs(
+    file_changes: &mut [FileChange],
+    indices: &[usize],
+    ctx: &SummarizeContext<'_>,
+    progress: &dyn SummaryProgress,
+) -> Vec<usize> {
+    let mut remaining = Vec::with_capacity(indices.len());
+    for &idx in indices {
+        let file = &file_changes[idx];
+        match trivial_summary(&file.path, &file.diff, ctx.trivial_diff_lines) {
+            Some(summary) => {
+                log::debug!("Summarized trivial change to {} locally", file.path);
+                let res = Ok(summary);
+                progress.started(idx, false);
+                progress.finished(idx, &res);
+                file_changes[idx].summary = res.ok();
+            }
+            None => remaining.push(idx),
+        }
+    }
+    remaining
+}
+
+/// Fill in summaries cached by earlier runs, returning the indices that still need the model.
+fn restore_cached_summaries(
+    file_changes: &mut [FileChange],
+    indices: &[usize],
+    ctx: &SummarizeContext<'_>,
+    progress: &dyn SummaryProgress,
+) -> Vec<usize> {
+    let Some(cache) = ctx.cache else {
+        return indices.to_vec();
+    };
+    let mut remaining = Vec::with_capacity(indices.len());
+    for &idx in indices {
+        let file = &file_changes[idx];
+        match cache.get(ctx.branch, ctx.ticket_summary, file) {
+            Some(summary) => {
+                log::debug!("Reusing the cached summary of {}", file.path);
+                let res = Ok(summary);
+                progress.started(idx, false);
+                progress.finished(idx, &res);
+                file_changes[idx].summary = res.ok();
+            }
+            None => remaining.push(idx),
+        }
+    }
+    remaining
+}
+
+/// Split `indices` into files to summarize and `(duplicate, original)` pairs whose hunks
+/// (the diff from the first `@@`, so paths and blob ids are ignored) and category match
+/// an earlier file. Diffs without hunks, such as binary files, are never merged.
+fn dedupe_identical_diffs(
+    file_changes: &[FileChange],
+    indices: &[usize],
+) -> (Vec<usize>, Vec<(usize, usize)>) {
+    let mut first_by_hunks: HashMap<(FileCategory, &str), usize> = HashMap::new();
+    let mut unique = Vec::with_capacity(indices.len());
+    let mut duplicates = Vec::new();
+
+    for &idx in indices {
+        let file = &file_changes[idx];
+        let hunks = if file.diff.starts_with("@@") {
+            Some(file.diff.as_str())
+        } else {
+            file.diff.find("\n@@").map(|i| &file.diff[i + 1..])
+        };
+        let Some(hunks) = hunks else {
+            unique.push(idx);
+            continue;
+        };
+        match first_by_hunks.entry((file.category, hunks)) {
+            Entry::Occupied(original) => {
+                duplicates.push((idx, *original.get()));
+            }
+            Entry::Vacant(slot) => {
+                slot.insert(idx);
+                unique.push(idx);
+            }
+        }
+    }
+
+    (unique, duplicates)
+}
+
+/// Summarize one file, retrying once and then falling back to a line-count summary when
+/// the model returns nothing.
+fn summarize_one(
+    files: &[FileChange],
+    file_idx: usize,
+    ctx: &SummarizeContext<'_>,
+    cancel: &CancellationToken,
+) -> Result<String> {
+    let file = &files[file_idx];
+    let call = || {
+        ctx.llm.summarize_file(
+            ctx.branch,
+            file,
+            file_idx,
+            files.len(),
+            ctx.ticket_summary,
+            Some(cancel),
+        )
+    };
+
+    let summary = call()?;
+    if !summary.trim().is_empty() {
+        return Ok(summary);
+    }
+    log::info!("Empty summary for {}; retrying once", file.path);
+    if let Some(limiter) = ctx.rate_limiter {
+        limiter.acquire();
+    }
+    let summary = call()?;
+    if !summary.trim().is_empty() {
+        return Ok(summary);
+    }
+    log::info!(
+        "Empty summary for {} again; using a line-count fallback",
+        file.path
+    );
+    Ok(fallback_summary(file))
+}
+
+/// Summary used when the model returns nothing for a file.
+pub fn fallback_summary(file: &FileChange) -> String {
+    let (added, removed) = diff_line_counts(&file.diff);
+    format!("- Changed `{}` (+{added}/-{removed} lines)", file.path)
+}
+
+/// Pull files off `queue` and summarize them until it is empty or `cancel` fires.
+fn summarize_worker(
+    files: &[FileChange],
+    queue: &Mutex<VecDeque<usize>>,
+    results: &SummarizeResults,
+    cancel: &CancellationToken,
+    ctx: &SummarizeContext<'_>,
+    progress: &dyn SummaryProgress,
+) {
+    loop {
+        if cancel.is_cancelled() {
+            break;
+        }
+        let next = queue.lock().expect("queue mutex poisoned").pop_front();
+        let Some(file_idx) = next else {
+            break;
+        };
+
+        if let Some(limiter) = ctx.rate_limiter {
+            progress.throttled(file_idx);
+            let waited = limiter.acquire();
+            if !waited.is_zero() {
+                log::debug!(
+                    "Throttled {} for {:.2}s",
+                    files[file_idx].path,
+                    waited.as_secs_f64()
+                );
+            }
+        }
+        progress.started(file_idx, false);
+
+        log::debug!("Summarizing file: {}", files[file_idx].path);
+
+        let res = summarize_one(files, file_idx, ctx, cancel);
+
+        if let Err(err) = &res
+            && is_fatal(err)
+            && cancel.cancel()
+        {
+            log::debug!("Fatal error from provider; cancelling remaining files");
+        }
+
+        progress.finished(file_idx, &res);
+
+        let mut lock = results.lock().expect("results mutex poisoned");
+        lock.push((file_idx, res));
+    }
+}
@@ -0,0 +1,62 @@
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Token-bucket limiter used to keep LLM calls under a requests-per-minute cap.
+///
+/// The bucket starts full with `burst` tokens and refills continuously at
+/// `requests_per_minute / 60` tokens per second. Each request takes one token,
+/// blocking the calling thread until one is available.
+pub struct RateLimiter {
+    capacity: f64,
+    refill_per_sec: f64,
+    state: Mutex<Bucket>,
+}
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    /// Create a limiter allowing `requests_per_minute` steady-state with bursts up to `burst`.
+    pub fn new(requests_per_minute: usize, burst: usize) -> Self {
+        let capacity = burst.clamp(1, requests_per_minute.max(1)) as f64;
+        Self {
+            capacity,
+            refill_per_sec: requests_per_minute.max(1) as f64 / 60.0,
+            state: Mutex::new(Bucket {
+                tokens: capacity,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    /// Block until a token is available, returning how long the caller waited.
+    pub fn acquire(&self) -> Duration {
+        let mut waited = Duration::ZERO;
+        loop {
+            let wait = {
+                let mut bucket = self.state.lock().unwrap_or_else(|e| e.into_inner());
+                let now = Instant::now();
+                let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+                bucket.tokens = (bucket.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+                bucket.last_refill = now;
+
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return waited;
+                }
+
+                Duration::from_secs_f64((1.0 - bucket.tokens) / self.refill_per_sec)
+            };
+
+            log::debug!(
+                "Rate limit reached; waiting {:.2}s for a request slot",
+                wait.as_secs_f64()
+            );
+            thread::sleep(wait);
+            waited += wait;
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! Interactive-mode progress saved between runs.
+//!
+//! Classifications and per-file summaries are written to `.git/commitbot-cache/last-run.json`
+//! as they are made, so a run that fails late (e.g. on the final commit-message request) can be
+//! resumed without classifying every file again. The state only applies when the branch and
+//! every file's diff are unchanged.
+
+use crate::git::git_dir;
+use crate::{FileCategory, FileChange};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+const RUN_STATE_DIR: &str = "commitbot-cache";
+const RUN_STATE_FILE: &str = "last-run.json";
+
+/// Saved progress of an interactive run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunState {
+    pub branch: String,
+    pub ticket_summary: Option<String>,
+    pub files: Vec<SavedFile>,
+}
+
+/// One file's classification and summary, keyed by a fingerprint of its diff.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedFile {
+    pub path: String,
+    pub diff_fingerprint: String,
+    pub category: FileCategory,
+    pub summary: Option<String>,
+}
+
+impl RunState {
+    /// Snapshot the current classifications and any summaries produced so far.
+    pub fn capture(branch: &str, ticket_summary: Option<&str>, files: &[FileChange]) -> Self {
+        RunState {
+            branch: branch.to_string(),
+            ticket_summary: ticket_summary.map(str::to_string),
+            files: files
+                .iter()
+                .map(|fc| SavedFile {
+                    path: fc.path.clone(),
+                    diff_fingerprint: fingerprint(&fc.diff),
+                    category: fc.category,
+                    summary: fc.summary.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Whether this state describes exactly these (path, diff) pairs on `branch`.
+    pub fn matches(&self, branch: &str, pairs: &[(String, String)]) -> bool {
+        self.branch == branch
+            && self.files.len() == pairs.len()
+            && self.files.iter().zip(pairs).all(|(saved, (path, diff))| {
+                saved.path == *path && saved.diff_fingerprint == fingerprint(diff)
+            })
+    }
+
+    /// Saved categories, in file order.
+    pub fn categories(&self) -> Vec<FileCategory> {
+        self.files.iter().map(|f| f.category).collect()
+    }
+
+    /// Copy saved summaries onto matching files that do not have one yet.
+    pub fn restore_summaries(&self, files: &mut [FileChange]) {
+        for (fc, saved) in files.iter_mut().zip(&self.files) {
+            if fc.summary.is_none() && fc.path == saved.path {
+                fc.summary = saved.summary.clone();
+            }
+        }
+    }
+
+    /// Load the saved state, if any. A missing or unreadable file yields `None`.
+    pub fn load() -> Option<Self> {
+        let path = state_path().ok()?;
+        let raw = fs::read_to_string(&path).ok()?;
+        match serde_json::from_str(&raw) {
+            Ok(state) => Some(state),
+            Err(err) => {
+                log::debug!("Ignoring unreadable run state {}: {err}", path.display());
+                None
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = state_path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create {}", dir.display()))?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(&path, json).with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    /// Remove the saved state after a successful run.
+    pub fn clear() {
+        if let Ok(path) = state_path()
+            && path.exists()
+            && let Err(err) = fs::remove_file(&path)
+        {
+            log::debug!("Could not remove run state {}: {err}", path.display());
+        }
+    }
+}
+
+fn state_path() -> Result<PathBuf> {
+    Ok(git_dir()?.join(RUN_STATE_DIR).join(RUN_STATE_FILE))
+}
+
+/// Stable 64-bit FNV-1a hash of a diff, hex encoded.
+pub fn fingerprint(diff: &str) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in diff.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{hash:016x}")
+}
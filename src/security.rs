@@ -0,0 +1,238 @@
+//! Scan added diff lines for things that look like credentials.
+//!
+//! Matches are reported (file and new-file line number) and redacted from the diff before it
+//! is sent to a provider. The checks are heuristics: known key formats, private-key blocks,
+//! `password = "..."` style assignments, and long high-entropy tokens.
+
+use std::collections::HashMap;
+
+/// Replacement text for redacted values.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Tokens at least this long are checked for entropy.
+const MIN_ENTROPY_TOKEN_LEN: usize = 32;
+/// Shannon entropy (bits per char) above which a mixed letter/digit token looks random.
+const ENTROPY_THRESHOLD: f64 = 4.0;
+
+/// Known token prefixes and the minimum total token length for each.
+const TOKEN_PREFIXES: &[(&str, usize, &str)] = &[
+    ("AKIA", 20, "AWS access key"),
+    ("ASIA", 20, "AWS access key"),
+    ("ghp_", 36, "GitHub token"),
+    ("gho_", 36, "GitHub token"),
+    ("github_pat_", 40, "GitHub token"),
+    ("glpat-", 26, "GitLab token"),
+    ("xoxb-", 20, "Slack token"),
+    ("xoxp-", 20, "Slack token"),
+    ("sk-", 24, "API secret key"),
+];
+
+/// Package-manager lockfiles, whose integrity hashes and checksums would all look random; only
+/// the known token formats and assignments are checked in them.
+const LOCKFILES: &[&str] = &[
+    "Cargo.lock",
+    "Gemfile.lock",
+    "composer.lock",
+    "go.sum",
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "pnpm-lock.yaml",
+    "poetry.lock",
+    "uv.lock",
+    "yarn.lock",
+];
+
+/// Assignment names whose literal values are treated as secrets.
+const SECRET_NAMES: &[&str] = &[
+    "password",
+    "passwd",
+    "secret",
+    "api_key",
+    "apikey",
+    "access_token",
+    "auth_token",
+];
+
+/// One suspected secret on an added line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretFinding {
+    pub path: String,
+    /// Line number in the new version of the file
+    pub line: usize,
+    /// What kind of secret it looks like (e.g. "AWS access key")
+    pub kind: &'static str,
+}
+
+/// Scan `diff` for secrets on added lines, returning the findings and the redacted diff.
+///
+/// Lines containing any `allowlist` entry are skipped, and lockfiles are not checked for
+/// high-entropy strings.
+pub fn scan_diff(path: &str, diff: &str, allowlist: &[String]) -> (Vec<SecretFinding>, String) {
+    let mut findings = Vec::new();
+    let mut out = String::with_capacity(diff.len());
+    let mut new_line = 0usize;
+    // a `diff --git` header runs until the first hunk; a bare list of +/- lines has none
+    let mut in_hunk = true;
+    let mut in_private_key = false;
+    let check_entropy = !is_lockfile(path);
+
+    for line in diff.split_inclusive('\n') {
+        let (body, eol) = match line.strip_suffix('\n') {
+            Some(body) => (body, "\n"),
+            None => (line, ""),
+        };
+
+        if let Some(start) = hunk_new_start(body) {
+            new_line = start;
+            in_hunk = true;
+            out.push_str(line);
+            continue;
+        }
+        if body.starts_with("diff ") {
+            in_hunk = false;
+        }
+        // inside a hunk, an added line that itself starts with `++` is still content
+        let added = in_hunk && body.starts_with('+');
+        if !added {
+            if body.starts_with(' ') {
+                new_line += 1;
+            }
+            out.push_str(line);
+            continue;
+        }
+
+        let content = &body[1..];
+        let line_no = new_line;
+        new_line += 1;
+        if allowlist
+            .iter()
+            .any(|a| !a.is_empty() && content.contains(a.as_str()))
+        {
+            out.push_str(line);
+            continue;
+        }
+
+        if in_private_key || (content.contains("-----BEGIN") && content.contains("PRIVATE KEY")) {
+            if !in_private_key {
+                findings.push(SecretFinding {
+                    path: path.to_string(),
+                    line: line_no,
+                    kind: "private key",
+                });
+            }
+            in_private_key = !(content.contains("-----END") && content.contains("PRIVATE KEY"));
+            out.push('+');
+            out.push_str(REDACTED);
+            out.push_str(eol);
+            continue;
+        }
+
+        let (redacted, kinds) = redact_line(content, check_entropy);
+        for kind in kinds {
+            findings.push(SecretFinding {
+                path: path.to_string(),
+                line: line_no,
+                kind,
+            });
+        }
+        out.push('+');
+        out.push_str(&redacted);
+        out.push_str(eol);
+    }
+
+    (findings, out)
+}
+
+/// New-file start line from a hunk header like `@@ -1,4 +10,6 @@`.
+fn hunk_new_start(line: &str) -> Option<usize> {
+    let rest = line.strip_prefix("@@ ")?;
+    let plus = rest.split_whitespace().find(|p| p.starts_with('+'))?;
+    plus[1..].split(',').next()?.parse().ok()
+}
+
+/// Whether `path` names a package-manager lockfile.
+fn is_lockfile(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    LOCKFILES.contains(&name)
+}
+
+/// Redact secret-looking values in one line, returning the new text and what was found.
+fn redact_line(content: &str, check_entropy: bool) -> (String, Vec<&'static str>) {
+    let mut kinds = Vec::new();
+    let mut values: Vec<String> = Vec::new();
+
+    if let Some(value) = assigned_secret_value(content) {
+        kinds.push("password or secret assignment");
+        values.push(value.to_string());
+    }
+
+    for token in content.split(|c: char| !(c.is_ascii_alphanumeric() || "+/=_-".contains(c))) {
+        if token.is_empty() || values.iter().any(|v| v == token) {
+            continue;
+        }
+        if let Some(kind) = known_token_kind(token) {
+            kinds.push(kind);
+            values.push(token.to_string());
+        } else if check_entropy && looks_random(token) {
+            kinds.push("high-entropy string");
+            values.push(token.to_string());
+        }
+    }
+
+    let mut redacted = content.to_string();
+    for value in &values {
+        redacted = redacted.replace(value.as_str(), REDACTED);
+    }
+    (redacted, kinds)
+}
+
+fn known_token_kind(token: &str) -> Option<&'static str> {
+    TOKEN_PREFIXES
+        .iter()
+        .find(|(prefix, min_len, _)| token.starts_with(prefix) && token.len() >= *min_len)
+        .map(|(_, _, kind)| *kind)
+}
+
+/// The quoted literal in `password = "..."` / `secret: '...'` style assignments.
+fn assigned_secret_value(content: &str) -> Option<&str> {
+    let lower = content.to_ascii_lowercase();
+    let name_end = SECRET_NAMES
+        .iter()
+        .filter_map(|name| lower.find(name).map(|i| i + name.len()))
+        .min()?;
+
+    let rest = content[name_end..].trim_start_matches(|c: char| {
+        c.is_ascii_alphanumeric() || c == '_' || c == '"' || c == '\'' || c.is_whitespace()
+    });
+    let rest = rest
+        .strip_prefix(":=")
+        .or_else(|| rest.strip_prefix('='))
+        .or_else(|| rest.strip_prefix(':'))?
+        .trim_start();
+    let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let value = rest[1..].split(quote).next()?;
+    (value.len() >= 4).then_some(value)
+}
+
+/// Long tokens that mix letters and digits with high character entropy.
+fn looks_random(token: &str) -> bool {
+    token.len() >= MIN_ENTROPY_TOKEN_LEN
+        && token.chars().any(|c| c.is_ascii_digit())
+        && token.chars().any(|c| c.is_ascii_alphabetic())
+        && shannon_entropy(token) > ENTROPY_THRESHOLD
+}
+
+fn shannon_entropy(s: &str) -> f64 {
+    let mut counts: HashMap<char, usize> = HashMap::new();
+    for c in s.chars() {
+        *counts.entry(c).or_default() += 1;
+    }
+    let len = s.chars().count() as f64;
+    counts
+        .values()
+        .map(|&n| {
+            let p = n as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
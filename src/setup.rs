@@ -1,11 +1,131 @@
 use anyhow::{anyhow, Result};
 use crate::config::Config;
-use crate::llm::LlmClient;
+use crate::git;
+use crate::http::shared_client;
+use crate::llm::{LlmClient, PromptOptions};
+use crate::llm::debug_log::DebugLog;
+use crate::llm::dry_run::DryRunClient;
+use crate::llm::anthropic::{self, AnthropicClient};
+use crate::llm::cassette::CassetteClient;
+use crate::llm::command::CommandClient;
+use crate::llm::gemini::{self, GeminiClient};
+use crate::llm::mock::MockClient;
 use crate::llm::ollama::OllamaClient;
-use crate::llm::openai::OpenAiClient;
+use crate::llm::openai::{OpenAiApi, OpenAiClient};
+use crate::llm::per_step::PerStepClient;
+use crate::llm::retry::RetryPolicy;
+use reqwest::blocking::Client;
+use reqwest::header::{HeaderMap, HeaderValue};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Build the prompt options from config, without the style examples read from history.
+pub fn prompt_options(cfg: &Config) -> PromptOptions {
+    PromptOptions {
+        language: cfg.language.clone(),
+        forge: cfg.forge,
+        category_weights: cfg.category_weights.clone(),
+        partitions: cfg.partitions.clone(),
+        test_patterns: cfg.test_patterns.clone(),
+        duplicate_summary_threshold: (cfg.duplicate_summary_threshold > 0.0)
+            .then_some(cfg.duplicate_summary_threshold),
+        tone: cfg.tone,
+        pr_sections: cfg.pr_sections.clone(),
+        pr_template: cfg.pr_template.clone(),
+        branch_context: cfg.branch_context,
+        trunk_branches: cfg.trunk_branches.clone(),
+        style: cfg.style,
+        style_examples: Vec::new(),
+        structured_output: cfg.structured_output,
+        release_notes: cfg.release_notes,
+    }
+}
 
 /// Build the LLM client based on CLI + config.
+///
+/// When `summary_model` and `final_model` differ, per-file summaries and the final message
+/// go to separate clients. With `dry_run`, prompts are printed instead of sent; with
+/// `record` or `replay`, exchanges are saved to or answered from a cassette directory.
 pub fn build_llm_client(cfg: &Config) -> Result<Box<dyn LlmClient>> {
+    let opts = PromptOptions {
+        style_examples: style_examples(cfg),
+        ..prompt_options(cfg)
+    };
+    if cfg.dry_run {
+        return Ok(Box::new(
+            DryRunClient::new(cfg.summary_model(), cfg.final_model()).with_prompt_options(opts),
+        ));
+    }
+    if let Some(dir) = &cfg.replay {
+        log::debug!("Replaying responses from {}", dir.display());
+        return Ok(Box::new(
+            CassetteClient::replay(dir)?.with_prompt_options(opts),
+        ));
+    }
+
+    let debug_log = match cfg.debug_log.as_deref() {
+        Some(path) => {
+            log::debug!("Writing LLM debug log to {path}");
+            Some(DebugLog::open(Path::new(path))?)
+        }
+        None => None,
+    };
+    let Some(dir) = &cfg.record else {
+        return build_live_client(cfg, &opts, debug_log.map(Arc::new));
+    };
+    // the cassette reads each raw reply back from the log the client writes to
+    let debug_log = Arc::new(match debug_log {
+        Some(log) => log.keeping_replies(),
+        None => DebugLog::in_memory(),
+    });
+    let client = build_live_client(cfg, &opts, Some(debug_log.clone()))?;
+    log::debug!("Recording requests and responses to {}", dir.display());
+    Ok(Box::new(
+        CassetteClient::record(dir, client)?
+            .with_prompt_options(opts)
+            .with_raw_replies(debug_log),
+    ))
+}
+
+/// The last `style_from_history` commit messages, read once when the client is built.
+fn style_examples(cfg: &Config) -> Vec<String> {
+    if cfg.style_from_history == 0 {
+        return Vec::new();
+    }
+    git::recent_commit_messages(cfg.style_from_history).unwrap_or_else(|err| {
+        log::debug!("No commit history for style examples: {err}");
+        Vec::new()
+    })
+}
+
+/// The client that talks to the configured provider.
+fn build_live_client(
+    cfg: &Config,
+    opts: &PromptOptions,
+    debug_log: Option<Arc<DebugLog>>,
+) -> Result<Box<dyn LlmClient>> {
+    let http = shared_client(cfg)?;
+
+    let (summary_model, final_model) = (cfg.summary_model(), cfg.final_model());
+    if summary_model == final_model {
+        return build_provider_client(cfg, http, final_model, debug_log, opts);
+    }
+    log::debug!("Summarizing files with {summary_model}, writing the message with {final_model}");
+    Ok(Box::new(PerStepClient::new(
+        build_provider_client(cfg, http.clone(), summary_model, debug_log.clone(), opts)?,
+        build_provider_client(cfg, http, final_model, debug_log, opts)?,
+    )))
+}
+
+fn build_provider_client(
+    cfg: &Config,
+    http: Client,
+    model: &str,
+    debug_log: Option<Arc<DebugLog>>,
+    opts: &PromptOptions,
+) -> Result<Box<dyn LlmClient>> {
+    let prompt_options = opts.clone();
+
     match cfg.provider.as_str() {
         "openai" => {
             let key = cfg
@@ -16,19 +136,100 @@ pub fn build_llm_client(cfg: &Config) -> Result<Box<dyn LlmClient>> {
                 .base_url
                 .clone()
                 .unwrap_or_else(|| "https://api.openai.com".to_string());
+            let api = OpenAiApi::parse(&cfg.api)?;
 
             log::debug!(
-                "Using OpenAiClient with model: {} (stream={})",
-                cfg.model,
-                cfg.stream
+                "Using OpenAiClient with model: {} (stream={}, api={:?})",
+                model,
+                cfg.stream,
+                api
             );
 
-            Ok(Box::new(OpenAiClient::new(
-                key,
-                cfg.model.clone(),
-                base_url,
+            Ok(Box::new(
+                OpenAiClient::new(http, key, model.to_string(), base_url, cfg.stream)
+                    .with_api(api)
+                    .with_organization(cfg.openai_org.clone())
+                    .with_project(cfg.openai_project.clone())
+                    .with_headers(cfg.extra_headers.clone())
+                    .with_prompt_options(prompt_options)
+                    .with_debug_log(debug_log)
+                    .with_max_response_bytes(cfg.max_response_bytes)
+                    .with_retry(RetryPolicy::new(cfg.max_attempts))
+                    .with_temperature(cfg.temperature)
+                    .with_top_p(cfg.top_p)
+                    .with_max_output_tokens(cfg.max_output_tokens)
+                    .with_reasoning_effort(cfg.reasoning_effort.clone()),
+            ))
+        }
+        "openai-compatible" | "openrouter" => {
+            let base_url = cfg
+                .base_url
+                .clone()
+                .ok_or_else(|| anyhow!("base_url must be set for provider=openai-compatible"))?;
+            let api = OpenAiApi::parse(&cfg.api)?;
+            let label = cfg.preset.map_or("OpenAI-compatible", |p| p.label);
+
+            log::debug!(
+                "Using OpenAiClient for {label} at {base_url} with model: {} (stream={}, api={:?})",
+                model,
+                cfg.stream,
+                api
+            );
+
+            Ok(Box::new(
+                OpenAiClient::new(
+                    http,
+                    cfg.openai_api_key.clone().unwrap_or_default(),
+                    model.to_string(),
+                    base_url,
+                    cfg.stream,
+                )
+                .with_compatible(label, cfg.auth_header.clone())
+                .with_api(api)
+                .with_headers(preset_headers(cfg))
+                .with_prompt_options(prompt_options)
+                .with_debug_log(debug_log)
+                .with_max_response_bytes(cfg.max_response_bytes)
+                .with_retry(RetryPolicy::new(cfg.max_attempts))
+                .with_temperature(cfg.temperature)
+                .with_top_p(cfg.top_p)
+                .with_max_output_tokens(cfg.max_output_tokens)
+                .with_reasoning_effort(cfg.reasoning_effort.clone()),
+            ))
+        }
+        "azure" => {
+            let key = cfg
+                .azure_api_key
+                .clone()
+                .ok_or_else(|| anyhow!("AZURE_OPENAI_API_KEY must be set for provider=azure"))?;
+            let base_url = cfg
+                .base_url
+                .clone()
+                .ok_or_else(|| anyhow!("base_url must be set for provider=azure"))?;
+            let api = OpenAiApi::parse(&cfg.api)?;
+
+            log::debug!(
+                "Using OpenAiClient for Azure deployment: {} (stream={}, api={:?}, api-version={})",
+                model,
                 cfg.stream,
-            )))
+                api,
+                cfg.azure_api_version
+            );
+
+            Ok(Box::new(
+                OpenAiClient::new(http, key, model.to_string(), base_url, cfg.stream)
+                    .with_azure(cfg.azure_api_version.clone())
+                    .with_api(api)
+                    .with_headers(cfg.extra_headers.clone())
+                    .with_prompt_options(prompt_options)
+                    .with_debug_log(debug_log)
+                    .with_max_response_bytes(cfg.max_response_bytes)
+                    .with_retry(RetryPolicy::new(cfg.max_attempts))
+                    .with_temperature(cfg.temperature)
+                    .with_top_p(cfg.top_p)
+                    .with_max_output_tokens(cfg.max_output_tokens)
+                    .with_reasoning_effort(cfg.reasoning_effort.clone()),
+            ))
         }
         "ollama" => {
             let base_url = cfg
@@ -38,16 +239,107 @@ pub fn build_llm_client(cfg: &Config) -> Result<Box<dyn LlmClient>> {
 
             log::debug!(
                 "Using OllamaClient with model: {} (stream={})",
-                cfg.model,
+                model,
                 cfg.stream
             );
 
-            Ok(Box::new(OllamaClient::new(
-                base_url,
-                cfg.model.clone(),
-                cfg.stream,
-            )))
+            Ok(Box::new(
+                OllamaClient::new(http, base_url, model.to_string(), cfg.stream)
+                    .with_headers(cfg.extra_headers.clone())
+                    .with_prompt_options(prompt_options)
+                    .with_debug_log(debug_log)
+                    .with_max_response_bytes(cfg.max_response_bytes)
+                    .with_retry(RetryPolicy::new(cfg.max_attempts))
+                    .with_temperature(cfg.temperature)
+                    .with_top_p(cfg.top_p)
+                    .with_max_output_tokens(cfg.max_output_tokens),
+            ))
+        }
+        "gemini" => {
+            let key = cfg
+                .gemini_api_key
+                .clone()
+                .ok_or_else(|| anyhow!("GEMINI_API_KEY must be set for provider=gemini"))?;
+            let base_url = cfg
+                .base_url
+                .clone()
+                .unwrap_or_else(|| gemini::DEFAULT_BASE_URL.to_string());
+
+            log::debug!(
+                "Using GeminiClient with model: {} (stream={})",
+                model,
+                cfg.stream
+            );
+
+            Ok(Box::new(
+                GeminiClient::new(http, key, model.to_string(), base_url, cfg.stream)
+                    .with_headers(cfg.extra_headers.clone())
+                    .with_prompt_options(prompt_options)
+                    .with_debug_log(debug_log)
+                    .with_max_response_bytes(cfg.max_response_bytes)
+                    .with_retry(RetryPolicy::new(cfg.max_attempts))
+                    .with_max_output_tokens(cfg.max_output_tokens),
+            ))
+        }
+        "anthropic" => {
+            let key = cfg
+                .anthropic_api_key
+                .clone()
+                .ok_or_else(|| anyhow!("ANTHROPIC_API_KEY must be set for provider=anthropic"))?;
+            let base_url = cfg
+                .base_url
+                .clone()
+                .unwrap_or_else(|| anthropic::DEFAULT_BASE_URL.to_string());
+
+            log::debug!(
+                "Using AnthropicClient with model: {} (stream={})",
+                model,
+                cfg.stream
+            );
+
+            Ok(Box::new(
+                AnthropicClient::new(http, key, model.to_string(), base_url, cfg.stream)
+                    .with_headers(cfg.extra_headers.clone())
+                    .with_prompt_options(prompt_options)
+                    .with_debug_log(debug_log)
+                    .with_max_response_bytes(cfg.max_response_bytes)
+                    .with_retry(RetryPolicy::new(cfg.max_attempts))
+                    .with_max_output_tokens(cfg.max_output_tokens),
+            ))
+        }
+        "command" => {
+            let command = cfg
+                .provider_command
+                .clone()
+                .ok_or_else(|| anyhow!("provider_command must be set for provider=command"))?;
+
+            log::debug!("Using CommandClient with model: {model} (command: {command})");
+
+            Ok(Box::new(
+                CommandClient::new(command, model)
+                    .with_prompt_options(prompt_options)
+                    .with_debug_log(debug_log)
+                    .with_timeout(cfg.request_timeout),
+            ))
+        }
+        "mock" => {
+            log::debug!("Using MockClient; no requests leave this machine");
+            Ok(Box::new(
+                MockClient::new().with_prompt_options(prompt_options),
+            ))
         }
         other => Err(anyhow!("Unknown provider: {}", other)),
     }
 }
+
+/// `[headers]` plus any the preset expects (e.g. OpenRouter's attribution headers) that
+/// `[headers]` doesn't already set.
+fn preset_headers(cfg: &Config) -> HeaderMap {
+    let mut headers = cfg.extra_headers.clone();
+    for (name, value) in cfg.preset.map_or(&[][..], |p| p.headers) {
+        if !headers.contains_key(*name) {
+            headers.insert(*name, HeaderValue::from_static(value));
+        }
+    }
+    headers
+}
@@ -0,0 +1,129 @@
+//! Per-file summaries cached across runs under the user's cache directory
+//! (`~/.cache/commitbot/summaries` on Linux).
+//!
+//! An entry is keyed by the file's staged blob id (from the diff's `index` line), the provider,
+//! endpoint, and model of the client that writes summaries, and a fingerprint of the exact
+//! summary prompt, so any change to the diff, the branch, the prompt wording, or prompt options
+//! such as `language` is a miss. Re-running
+//! `--ask` after editing one file then only summarizes that file again.
+
+use crate::FileChange;
+use crate::config::Config;
+use crate::llm::{LlmClient, PromptOptions, file_summary_prompt};
+use crate::run_state::fingerprint;
+use crate::setup::prompt_options;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Bumped when the layout or meaning of an entry changes, so old entries are ignored.
+const CACHE_VERSION: u32 = 1;
+
+/// Entries not used for this long are removed when the cache is opened.
+const MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Summaries from earlier runs, looked up by file.
+#[derive(Debug, Clone)]
+pub struct SummaryCache {
+    dir: PathBuf,
+    model: String,
+    prompt_options: PromptOptions,
+}
+
+impl SummaryCache {
+    /// The cache for summaries written by `llm`, or `None` when `summary_cache` is off, the
+    /// client doesn't say where its summaries come from, or there is no cache directory.
+    pub fn open(cfg: &Config, llm: &dyn LlmClient) -> Option<Self> {
+        if !cfg.summary_cache {
+            return None;
+        }
+        let model = llm.summary_source()?;
+        let dir = dirs::cache_dir()?.join("commitbot").join("summaries");
+        prune(&dir, MAX_AGE);
+        Some(Self::in_dir(dir, &model, prompt_options(cfg)))
+    }
+
+    /// A cache stored in `dir` for summaries written by `model`.
+    pub fn in_dir(dir: PathBuf, model: &str, prompt_options: PromptOptions) -> Self {
+        Self {
+            dir,
+            model: model.to_string(),
+            prompt_options,
+        }
+    }
+
+    /// The cached summary of `file`, if it was summarized before with the same prompt.
+    pub fn get(
+        &self,
+        branch: &str,
+        ticket_summary: Option<&str>,
+        file: &FileChange,
+    ) -> Option<String> {
+        let path = self.entry_path(branch, ticket_summary, file);
+        let summary = fs::read_to_string(&path).ok()?;
+        // keep entries that are still in use from being pruned
+        if let Ok(f) = fs::File::options().append(true).open(&path) {
+            f.set_modified(SystemTime::now()).ok();
+        }
+        Some(summary).filter(|s| !s.trim().is_empty())
+    }
+
+    /// Remember `summary` for `file`. Failures are logged and otherwise ignored.
+    pub fn put(
+        &self,
+        branch: &str,
+        ticket_summary: Option<&str>,
+        file: &FileChange,
+        summary: &str,
+    ) {
+        let path = self.entry_path(branch, ticket_summary, file);
+        let written = fs::create_dir_all(&self.dir).and_then(|_| fs::write(&path, summary));
+        if let Err(err) = written {
+            log::debug!("Could not cache the summary of {}: {err}", file.path);
+        }
+    }
+
+    fn entry_path(&self, branch: &str, ticket_summary: Option<&str>, file: &FileChange) -> PathBuf {
+        // The position in the file list is left out, so adding or dropping a file elsewhere
+        // keeps this entry valid.
+        let prompt = file_summary_prompt(branch, file, 0, 1, ticket_summary, &self.prompt_options);
+        let key = fingerprint(&format!(
+            "{CACHE_VERSION}\0{}\0{}\0{}",
+            self.model, prompt.system, prompt.user
+        ));
+        let name = match staged_blob(&file.diff) {
+            Some(blob) => format!("{blob}-{key}"),
+            None => key,
+        };
+        self.dir.join(name)
+    }
+}
+
+/// The post-image blob id from a diff's `index <old>..<new>` line.
+fn staged_blob(diff: &str) -> Option<&str> {
+    let ids = diff
+        .lines()
+        .take_while(|line| !line.starts_with("@@"))
+        .find_map(|line| line.strip_prefix("index "))?;
+    let (_, new) = ids.split_once("..")?;
+    let new = new.split_whitespace().next()?;
+    new.bytes().all(|b| b.is_ascii_hexdigit()).then_some(new)
+}
+
+/// Remove entries in `dir` last used more than `max_age` ago.
+fn prune(dir: &Path, max_age: Duration) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let stale = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age > max_age);
+        if stale && let Err(err) = fs::remove_file(entry.path()) {
+            log::debug!("Could not prune {}: {err}", entry.path().display());
+        }
+    }
+}
@@ -0,0 +1,71 @@
+//! Deterministic clean-up of generated commit messages.
+
+/// Result of linting a commit message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintOutcome {
+    /// The message with all fixes applied
+    pub message: String,
+    /// Human-readable description of each problem that was found
+    pub findings: Vec<String>,
+}
+
+/// Enforce git commit conventions the prompt cannot guarantee:
+/// - no trailing period on the subject line
+/// - exactly one blank line between subject and body
+/// - no runs of blank lines inside the body
+pub fn lint_message(message: &str) -> LintOutcome {
+    let mut findings = Vec::new();
+    let lines: Vec<&str> = message.lines().map(str::trim_end).collect();
+
+    let Some(subject_idx) = lines.iter().position(|l| !l.is_empty()) else {
+        return LintOutcome {
+            message: String::new(),
+            findings,
+        };
+    };
+
+    let mut subject = lines[subject_idx].to_string();
+    if subject.ends_with('.') && !subject.ends_with("..") {
+        subject.pop();
+        findings.push("removed trailing period from subject".to_string());
+    }
+
+    let rest = &lines[subject_idx + 1..];
+    let separator = rest.iter().take_while(|l| l.is_empty()).count();
+    let body_lines = &rest[separator..];
+
+    let mut body: Vec<&str> = Vec::new();
+    let mut collapsed = 0usize;
+    for line in body_lines {
+        if line.is_empty() && body.last().is_some_and(|l| l.is_empty()) {
+            collapsed += 1;
+            continue;
+        }
+        body.push(line);
+    }
+    while body.last().is_some_and(|l| l.is_empty()) {
+        body.pop();
+    }
+
+    if !body.is_empty() {
+        match separator {
+            0 => findings.push("inserted blank line between subject and body".to_string()),
+            1 => {}
+            n => findings.push(format!(
+                "removed {} extra blank line(s) after subject",
+                n - 1
+            )),
+        }
+    }
+    if collapsed > 0 {
+        findings.push(format!("collapsed {collapsed} extra blank line(s) in body"));
+    }
+
+    let message = if body.is_empty() {
+        subject
+    } else {
+        format!("{subject}\n\n{}", body.join("\n"))
+    };
+
+    LintOutcome { message, findings }
+}
@@ -0,0 +1,111 @@
+//! Local summaries for diffs too small to be worth a model call.
+//!
+//! Only changes that can be described with confidence are handled: blank-line and
+//! trailing-whitespace edits, and added or removed import lines. Anything else returns `None`
+//! and goes to the model as usual.
+
+/// Line prefixes that mark an import in common languages.
+const IMPORT_PREFIXES: &[&str] = &[
+    "use ",
+    "pub use ",
+    "import ",
+    "from ",
+    "#include ",
+    "require ",
+    "require_once ",
+];
+
+/// Summarize `diff` locally when it changes at most `max_lines` lines and the change is
+/// one [`trivial_summary`] understands. `max_lines == 0` disables the check.
+pub fn trivial_summary(path: &str, diff: &str, max_lines: usize) -> Option<String> {
+    if max_lines == 0 {
+        return None;
+    }
+    let (added, removed) = changed_lines(diff)?;
+    let total = added.len() + removed.len();
+    if total == 0 || total > max_lines {
+        return None;
+    }
+
+    if added.iter().chain(&removed).all(|l| l.trim().is_empty()) {
+        let verb = match (added.is_empty(), removed.is_empty()) {
+            (false, true) => "Add",
+            (true, false) => "Remove",
+            _ => "Adjust",
+        };
+        return Some(format!("- {verb} blank lines in `{path}`"));
+    }
+
+    if is_trailing_whitespace_only(&added, &removed) {
+        let removed_only =
+            removed.iter().any(|l| l.trim_end() != *l) && added.iter().all(|l| l.trim_end() == *l);
+        let what = if removed_only {
+            "Remove trailing whitespace"
+        } else {
+            "Adjust trailing whitespace"
+        };
+        return Some(format!("- {what} in `{path}`"));
+    }
+
+    let added_imports = imports(&added)?;
+    let removed_imports = imports(&removed)?;
+    let mut bullets = Vec::new();
+    for import in added_imports {
+        bullets.push(format!("- Add import `{import}` in `{path}`"));
+    }
+    for import in removed_imports {
+        bullets.push(format!("- Remove import `{import}` in `{path}`"));
+    }
+    Some(bullets.join("\n"))
+}
+
+/// Added and removed line contents from the hunks of `diff`; `None` when it has no hunks.
+fn changed_lines(diff: &str) -> Option<(Vec<&str>, Vec<&str>)> {
+    let mut added = Vec::new();
+    let mut removed = Vec::new();
+    let mut in_hunk = false;
+    let mut saw_hunk = false;
+    for line in diff.lines() {
+        if line.starts_with("@@") {
+            in_hunk = true;
+            saw_hunk = true;
+        } else if line.starts_with("diff --git") {
+            in_hunk = false;
+        } else if in_hunk {
+            if let Some(content) = line.strip_prefix('+') {
+                added.push(content);
+            } else if let Some(content) = line.strip_prefix('-') {
+                removed.push(content);
+            }
+        }
+    }
+    saw_hunk.then_some((added, removed))
+}
+
+/// Whether the added lines equal the removed ones once trailing whitespace and blank lines are
+/// ignored. Indentation and spacing within a line can carry meaning (Python, YAML, Makefiles,
+/// string literals), so those changes still go to the model.
+fn is_trailing_whitespace_only(added: &[&str], removed: &[&str]) -> bool {
+    let content = |lines: &[&str]| -> Vec<String> {
+        lines
+            .iter()
+            .map(|l| l.trim_end().to_string())
+            .filter(|l| !l.is_empty())
+            .collect()
+    };
+    !removed.is_empty() && content(added) == content(removed)
+}
+
+/// The import statements among non-blank `lines`, or `None` if any line is something else.
+fn imports<'a>(lines: &[&'a str]) -> Option<Vec<&'a str>> {
+    lines
+        .iter()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .map(|l| {
+            let is_import = IMPORT_PREFIXES.iter().any(|p| l.starts_with(p))
+                && (!l.starts_with("from ") || l.contains(" import "));
+            is_import.then(|| l.trim_end_matches(';'))
+        })
+        .collect()
+}
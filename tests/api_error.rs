@@ -0,0 +1,85 @@
+use anyhow::{Context, anyhow};
+use commitbot::llm::error::{ApiError, is_fatal};
+
+#[test]
+fn auth_failures_are_fatal() {
+    let unauthorized: anyhow::Error = ApiError::new("OpenAI", 401, "invalid api key").into();
+    let forbidden: anyhow::Error = ApiError::new("OpenAI", 403, "").into();
+    assert!(is_fatal(&unauthorized));
+    assert!(is_fatal(&forbidden));
+}
+
+#[test]
+fn geminis_rejected_key_is_fatal() {
+    let bad_key: anyhow::Error = ApiError::new(
+        "Gemini",
+        400,
+        r#"{"error": {"code": 400, "message": "API key not valid. Please pass a valid API key.", "status": "INVALID_ARGUMENT", "details": [{"@type": "type.googleapis.com/google.rpc.ErrorInfo", "reason": "API_KEY_INVALID", "domain": "googleapis.com"}]}}"#,
+    )
+    .into();
+    assert!(is_fatal(&bad_key));
+    assert_eq!(
+        bad_key.to_string(),
+        "Gemini: API_KEY_INVALID — API key not valid. Please pass a valid API key. (HTTP 400)"
+    );
+
+    let bad_request: anyhow::Error = ApiError::new(
+        "Gemini",
+        400,
+        r#"{"error": {"code": 400, "message": "Invalid JSON payload.", "status": "INVALID_ARGUMENT"}}"#,
+    )
+    .into();
+    assert!(!is_fatal(&bad_request));
+}
+
+#[test]
+fn other_errors_are_not_fatal() {
+    let server: anyhow::Error = ApiError::new("OpenAI", 500, "oops").into();
+    assert!(!is_fatal(&server));
+    assert!(!is_fatal(&anyhow!("connection reset")));
+}
+
+#[test]
+fn fatal_errors_survive_added_context() {
+    let err = Err::<(), _>(ApiError::new("Ollama", 401, "nope"))
+        .context("Ollama request failed")
+        .unwrap_err();
+    assert!(is_fatal(&err));
+    assert_eq!(
+        ApiError::new("OpenAI", 401, "bad key").to_string(),
+        "OpenAI API error: HTTP 401 - bad key"
+    );
+}
+
+#[test]
+fn structured_error_bodies_are_parsed() {
+    let openai = ApiError::new(
+        "OpenAI",
+        401,
+        r#"{"error": {"message": "Incorrect API key provided", "type": "invalid_request_error", "code": "invalid_api_key"}}"#,
+    );
+    assert_eq!(openai.code.as_deref(), Some("invalid_api_key"));
+    assert_eq!(openai.error_type.as_deref(), Some("invalid_request_error"));
+    assert_eq!(
+        openai.to_string(),
+        "OpenAI: invalid_api_key — Incorrect API key provided (HTTP 401)"
+    );
+
+    let ollama = ApiError::new("Ollama", 404, r#"{"error": "model 'llama9' not found"}"#);
+    assert_eq!(ollama.code, None);
+    assert_eq!(
+        ollama.to_string(),
+        "Ollama: model 'llama9' not found (HTTP 404)"
+    );
+
+    let gemini = ApiError::new(
+        "Gemini",
+        400,
+        r#"{"error": {"code": 400, "message": "API key not valid.", "status": "INVALID_ARGUMENT"}}"#,
+    );
+    assert_eq!(gemini.code, None);
+    assert_eq!(
+        gemini.to_string(),
+        "Gemini: INVALID_ARGUMENT — API key not valid. (HTTP 400)"
+    );
+}
@@ -0,0 +1,101 @@
+//! Key selection that depends on environment variables. These tests change the process
+//! environment, so they live in their own binary and run one after another in a single test.
+
+use clap::Parser;
+use commitbot::Cli;
+use commitbot::config::Config;
+use std::env;
+use std::fs;
+
+mod common;
+use common::unique_temp_path;
+
+fn config_for(args: &[&str]) -> Config {
+    let mut argv = vec!["commitbot"];
+    argv.extend_from_slice(args);
+    Config::from_sources(&Cli::parse_from(argv)).unwrap()
+}
+
+#[test]
+fn presets_never_send_the_openai_key() {
+    let config_path = unique_temp_path("preset_keys").with_extension("toml");
+    fs::write(
+        &config_path,
+        "[default]\nopenai_api_key = \"sk-from-file\"\n",
+    )
+    .unwrap();
+    let path = config_path.to_str().expect("utf-8 path");
+
+    // SAFETY: the only test in this binary, so no other thread reads the environment.
+    unsafe {
+        env::remove_var("COMMITBOT_API_KEY_COMMAND");
+        env::remove_var("COMMITBOT_API_KEY_FILE");
+        env::set_var("OPENAI_API_KEY", "sk-openai");
+        env::set_var("OPENROUTER_API_KEY", "sk-or");
+        env::set_var("GROQ_API_KEY", "gsk-groq");
+    }
+
+    let cfg = config_for(&["--config", path, "--provider", "openrouter"]);
+    assert_eq!(cfg.openai_api_key.as_deref(), Some("sk-or"));
+
+    let cfg = config_for(&[
+        "--config",
+        path,
+        "--provider",
+        "compatible",
+        "--preset",
+        "groq",
+    ]);
+    assert_eq!(cfg.openai_api_key.as_deref(), Some("gsk-groq"));
+
+    let cfg = config_for(&[
+        "--config",
+        path,
+        "--provider",
+        "compatible",
+        "--preset",
+        "groq",
+        "--api-key",
+        "gsk-cli",
+    ]);
+    assert_eq!(cfg.openai_api_key.as_deref(), Some("gsk-cli"));
+
+    let cfg = config_for(&[
+        "--config",
+        path,
+        "--provider",
+        "compatible",
+        "--preset",
+        "lmstudio",
+    ]);
+    assert_eq!(
+        cfg.openai_api_key, None,
+        "a keyless preset gets no key at all"
+    );
+
+    // SAFETY: as above.
+    unsafe { env::remove_var("OPENROUTER_API_KEY") };
+    let err = Config::from_sources(&Cli::parse_from([
+        "commitbot",
+        "--config",
+        path,
+        "--provider",
+        "openrouter",
+    ]))
+    .unwrap_err()
+    .to_string();
+    assert!(err.contains("OPENROUTER_API_KEY (or --api-key)"), "{err}");
+
+    // without a preset, a custom base_url still uses the OpenAI key sources
+    let cfg = config_for(&[
+        "--config",
+        path,
+        "--provider",
+        "compatible",
+        "--url",
+        "https://llm.internal/v1",
+    ]);
+    assert_eq!(cfg.openai_api_key.as_deref(), Some("sk-openai"));
+
+    fs::remove_file(config_path).ok();
+}
@@ -0,0 +1,24 @@
+use commitbot::llm::CancellationToken;
+use commitbot::llm::cancel::{self, Cancelled};
+
+#[test]
+fn clones_share_the_flag() {
+    let token = CancellationToken::new();
+    let other = token.clone();
+    assert!(!other.is_cancelled());
+
+    assert!(token.cancel());
+    assert!(other.is_cancelled());
+    assert!(!other.cancel(), "second cancel reports it was already set");
+}
+
+#[test]
+fn check_fails_with_cancelled_once_set() {
+    let token = CancellationToken::new();
+    assert!(token.check().is_ok());
+    assert!(cancel::check(None).is_ok());
+
+    token.cancel();
+    let err = cancel::check(Some(&token)).unwrap_err();
+    assert!(err.downcast_ref::<Cancelled>().is_some());
+}
@@ -0,0 +1,76 @@
+use commitbot::changelog::{ChangelogEntry, insert_unreleased};
+
+fn entry(category: &'static str, text: &str) -> ChangelogEntry {
+    ChangelogEntry {
+        category,
+        text: text.to_string(),
+    }
+}
+
+#[test]
+fn parses_category_and_text() {
+    assert_eq!(
+        ChangelogEntry::parse("\nfixed: Crash on empty config\nextra"),
+        Some(entry("Fixed", "Crash on empty config"))
+    );
+    assert_eq!(
+        ChangelogEntry::parse("- Support --yes"),
+        Some(entry("Changed", "Support --yes"))
+    );
+    assert_eq!(ChangelogEntry::parse("  \n"), None);
+}
+
+#[test]
+fn prepends_under_an_existing_category() {
+    let content = "\
+# Changelog
+
+## [Unreleased]
+
+### Fixed
+- Older fix
+
+## [1.0.0] - 2024-01-01
+";
+    let out = insert_unreleased(content, &entry("Fixed", "New fix"));
+    assert_eq!(
+        out,
+        "\
+# Changelog
+
+## [Unreleased]
+
+### Fixed
+- New fix
+- Older fix
+
+## [1.0.0] - 2024-01-01
+"
+    );
+}
+
+#[test]
+fn adds_a_missing_category_to_the_unreleased_section() {
+    let content = "## [Unreleased]\n\n### Fixed\n- Older fix\n";
+    let out = insert_unreleased(content, &entry("Added", "A flag"));
+    assert_eq!(
+        out,
+        "## [Unreleased]\n\n### Added\n- A flag\n\n### Fixed\n- Older fix\n"
+    );
+}
+
+#[test]
+fn creates_the_unreleased_section_above_the_latest_release() {
+    let content = "# Changelog\n\n## [1.0.0] - 2024-01-01\n\n### Added\n- First\n";
+    let out = insert_unreleased(content, &entry("Fixed", "A crash"));
+    assert_eq!(
+        out,
+        "# Changelog\n\n## [Unreleased]\n\n### Fixed\n- A crash\n\n## [1.0.0] - 2024-01-01\n\n### Added\n- First\n"
+    );
+
+    let out = insert_unreleased("# Changelog\n", &entry("Added", "Everything"));
+    assert_eq!(
+        out,
+        "# Changelog\n\n## [Unreleased]\n\n### Added\n- Everything\n"
+    );
+}
@@ -1,6 +1,9 @@
 use assert_cmd::cargo;
 use clap::Parser;
-use commitbot::{Cli, Command};
+use commitbot::cli_args::with_default_args;
+use commitbot::pipeline::{Aborted, NoChanges};
+use commitbot::{Cli, Command, ExitReason};
+use std::ffi::OsString;
 
 #[test]
 fn prints_help() {
@@ -12,6 +15,18 @@ fn prints_help() {
         .stdout(predicates::str::contains("Usage"));
 }
 
+#[test]
+fn help_documents_exit_codes() {
+    let mut cmd = cargo::cargo_bin_cmd!();
+
+    cmd.arg("--help")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("Exit codes:"))
+        .stdout(predicates::str::contains("3  aborted by user"))
+        .stdout(predicates::str::contains("--allow-empty"));
+}
+
 #[test]
 fn prints_version() {
     let mut cmd = cargo::cargo_bin_cmd!();
@@ -31,3 +46,139 @@ fn cli_parsing() {
         other => panic!("expected summary command, got {:?}", other),
     }
 }
+
+#[test]
+fn pr_accepts_a_tag_instead_of_a_base() {
+    let cli = Cli::parse_from(["commitbot", "pr", "--since-tag"]);
+    match cli.command {
+        Some(Command::Pr {
+            base, since_tag, ..
+        }) => {
+            assert_eq!(base, None);
+            assert!(since_tag);
+        }
+        other => panic!("expected pr command, got {:?}", other),
+    }
+
+    assert!(Cli::try_parse_from(["commitbot", "pr"]).is_err());
+    assert!(Cli::try_parse_from(["commitbot", "pr", "--since-tag", "--tag", "v1.0.0"]).is_err());
+
+    // with a tag option, the positional names the branch to describe
+    let cli = Cli::parse_from(["commitbot", "pr", "--tag", "v1.0.0", "feature"]);
+    match cli.command {
+        Some(Command::Pr { base, tag, .. }) => {
+            assert_eq!(base.as_deref(), Some("feature"));
+            assert_eq!(tag.as_deref(), Some("v1.0.0"));
+        }
+        other => panic!("expected pr command, got {:?}", other),
+    }
+}
+
+#[test]
+fn yes_is_a_global_short_flag() {
+    let cli = Cli::parse_from(["commitbot", "--ask", "-y", "--default-category", "2"]);
+    assert!(cli.yes && cli.ask);
+    assert_eq!(cli.default_category, Some(2));
+
+    let cli = Cli::parse_from(["commitbot", "reword", "HEAD", "--yes"]);
+    assert!(cli.yes);
+}
+
+#[test]
+fn run_shaping_flags_parse_after_a_subcommand() {
+    let cli = Cli::parse_from(["commitbot", "reword", "HEAD", "--batch-consequence"]);
+    assert!(cli.batch_consequence);
+
+    let cli = Cli::parse_from([
+        "commitbot",
+        "reword",
+        "HEAD",
+        "--partition",
+        "src/",
+        "--partition",
+        "docs/",
+    ]);
+    assert_eq!(cli.partition, ["src/", "docs/"]);
+}
+
+fn args(list: &[&str]) -> Vec<OsString> {
+    list.iter().map(OsString::from).collect()
+}
+
+fn strings(list: &[&str]) -> Vec<String> {
+    list.iter().map(|a| a.to_string()).collect()
+}
+
+#[test]
+fn default_args_are_overridden_by_explicit_flags() {
+    let defaults = strings(&["--default-category", "2", "--partition", "src/"]);
+
+    let merged = with_default_args(args(&["commitbot", "--ask"]), &defaults);
+    let cli = Cli::parse_from(merged);
+    assert!(cli.ask);
+    assert_eq!(cli.default_category, Some(2));
+    assert_eq!(cli.partition, vec!["src/"]);
+
+    let merged = with_default_args(args(&["commitbot", "--default-category", "1"]), &defaults);
+    assert_eq!(Cli::parse_from(merged).default_category, Some(1));
+}
+
+#[test]
+fn default_subcommand_only_applies_without_one_on_the_command_line() {
+    let defaults = strings(&["--ask", "pr", "develop"]);
+
+    let merged = with_default_args(args(&["commitbot", "--yes"]), &defaults);
+    assert_eq!(
+        merged,
+        args(&["commitbot", "--ask", "--yes", "pr", "develop"])
+    );
+    let cli = Cli::parse_from(merged);
+    assert!(matches!(cli.command, Some(Command::Pr { base: Some(ref b), .. }) if b == "develop"));
+
+    let merged = with_default_args(args(&["commitbot", "reword", "HEAD"]), &defaults);
+    assert_eq!(merged, args(&["commitbot", "--ask", "reword", "HEAD"]));
+    assert!(matches!(
+        Cli::parse_from(merged).command,
+        Some(Command::Reword { .. })
+    ));
+}
+
+#[cfg(unix)]
+#[test]
+fn default_args_keep_non_utf8_arguments() {
+    use std::os::unix::ffi::OsStringExt;
+
+    let path = OsString::from_vec(b"notes-\xff.md".to_vec());
+    let mut cmdline = args(&["commitbot", "--message-file"]);
+    cmdline.push(path.clone());
+
+    let merged = with_default_args(cmdline, &strings(&["--ask"]));
+    assert_eq!(merged.last(), Some(&path));
+}
+
+#[test]
+fn repeatable_flags_still_accumulate() {
+    let cli = Cli::parse_from([
+        "commitbot",
+        "-v",
+        "-v",
+        "--partition",
+        "a",
+        "--partition",
+        "b",
+    ]);
+    assert_eq!(cli.verbose, 2);
+    assert_eq!(cli.partition, vec!["a", "b"]);
+}
+
+#[test]
+fn exit_reasons_map_to_documented_codes() {
+    let no_changes = anyhow::Error::new(NoChanges("No staged changes found.".into()));
+    let aborted = anyhow::Error::new(Aborted("Aborted by user.".into()));
+    let other = anyhow::anyhow!("boom").context("while summarizing");
+
+    assert_eq!(ExitReason::of(&no_changes).code(), 2);
+    assert_eq!(ExitReason::of(&aborted).code(), 3);
+    assert_eq!(ExitReason::of(&other), ExitReason::Error);
+    assert_eq!(ExitReason::Error.code(), 1);
+}
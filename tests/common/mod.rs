@@ -0,0 +1,15 @@
+//! Helpers shared by the integration tests.
+
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A fresh path in the system temp directory, `commitbot_{name}_{nanos}`; nothing is created.
+///
+/// Add an extension with [`PathBuf::with_extension`] when the file type matters.
+pub fn unique_temp_path(name: &str) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time before unix epoch")
+        .as_nanos();
+    std::env::temp_dir().join(format!("commitbot_{name}_{nanos}"))
+}
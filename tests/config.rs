@@ -1,33 +1,45 @@
 use clap::Parser;
-use commitbot::config::Config;
-use commitbot::Cli;
+use commitbot::config::{Config, canonical_provider};
+use commitbot::http::shared_client;
+use commitbot::llm::{MessageStyle, Tone};
+use commitbot::{Cli, FileCategory};
 use std::fs;
 use std::path::PathBuf;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::Duration;
 
-fn unique_config_path(name: &str) -> PathBuf {
-    let nanos = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .expect("system time before unix epoch")
-        .as_nanos();
-    std::env::temp_dir().join(format!("commitbot_{name}_{nanos}.toml"))
-}
+mod common;
+use common::unique_temp_path;
 
 fn write_temp_config(name: &str, contents: &str) -> PathBuf {
-    let path = unique_config_path(name);
+    let path = unique_temp_path(name).with_extension("toml");
     fs::write(&path, contents).expect("write temp config");
     path
 }
 
 #[test]
-fn missing_config_uses_cli_values() {
-    let missing_path = unique_config_path("missing");
+fn missing_explicit_config_is_an_error() {
+    let missing_path = unique_temp_path("missing").with_extension("toml");
     let cli = Cli::parse_from([
         "commitbot",
         "--config",
         missing_path.to_str().expect("utf-8 path"),
         "--provider",
         "ollama",
+    ]);
+
+    let err = Config::from_sources(&cli).expect_err("explicit missing config should fail");
+    assert!(err.to_string().contains("Config file not found"));
+}
+
+#[test]
+fn empty_config_uses_cli_values() {
+    let config_path = write_temp_config("empty", "");
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        config_path.to_str().expect("utf-8 path"),
+        "--provider",
+        "ollama",
         "--model",
         "qwen3-coder:30b",
         "--url",
@@ -39,6 +51,8 @@ fn missing_config_uses_cli_values() {
     assert_eq!(cfg.model, "qwen3-coder:30b");
     assert_eq!(cfg.base_url.as_deref(), Some("http://localhost:11434"));
     assert!(cfg.stream);
+
+    fs::remove_file(config_path).ok();
 }
 
 #[test]
@@ -133,3 +147,932 @@ model = "gpt-5-nano"
 
     fs::remove_file(config_path).ok();
 }
+
+#[test]
+fn dry_run_needs_no_api_key() {
+    let config_path = write_temp_config(
+        "dry_run",
+        "[default]\nprovider = \"openai\"\nmodel = \"gpt-5-nano\"\n",
+    );
+    let path = config_path.to_str().expect("utf-8 path");
+
+    let cli = Cli::parse_from(["commitbot", "--config", path, "--dry-run"]);
+    let cfg = Config::from_sources(&cli).expect("a dry run sends nothing");
+    assert!(cfg.dry_run);
+    assert!(!cfg.stream, "prompts are printed whole");
+    assert!(!cfg.summary_cache, "placeholder summaries are never cached");
+
+    fs::remove_file(config_path).ok();
+}
+
+#[test]
+fn azure_requires_an_endpoint_and_defaults_the_api_version() {
+    let config_path = write_temp_config(
+        "azure",
+        "[default]\nprovider = \"azure-openai\"\nmodel = \"prod-gpt\"\nazure_api_key = \"az-key\"\n",
+    );
+    let path = config_path.to_str().expect("utf-8 path");
+
+    let cli = Cli::parse_from(["commitbot", "--config", path]);
+    let err = Config::from_sources(&cli).unwrap_err().to_string();
+    assert!(err.contains("base_url must be set"), "{err}");
+
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        path,
+        "--url",
+        "https://acme.openai.azure.com",
+    ]);
+    let cfg = Config::from_sources(&cli).unwrap();
+    assert_eq!(cfg.provider, "azure");
+    assert_eq!(cfg.azure_api_key.as_deref(), Some("az-key"));
+    assert_eq!(cfg.azure_api_version, "2024-10-21");
+
+    fs::remove_file(config_path).ok();
+}
+
+#[test]
+fn openai_compatible_presets_fill_in_the_base_url() {
+    let config_path = write_temp_config("compatible", "");
+    let path = config_path.to_str().expect("utf-8 path");
+
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        path,
+        "--provider",
+        "compatible",
+        "--preset",
+        "LM Studio",
+    ]);
+    let cfg = Config::from_sources(&cli).unwrap();
+    assert_eq!(cfg.provider, "openai-compatible");
+    assert_eq!(cfg.preset.map(|p| p.name), Some("lmstudio"));
+    assert_eq!(cfg.base_url.as_deref(), Some("http://localhost:1234/v1"));
+    assert_eq!(cfg.openai_api_key, None);
+
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        path,
+        "--provider",
+        "openai-compatible",
+        "--preset",
+        "groq",
+    ]);
+    let err = Config::from_sources(&cli).unwrap_err().to_string();
+    assert!(err.contains("GROQ_API_KEY"), "{err}");
+
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        path,
+        "--provider",
+        "openai-compatible",
+    ]);
+    let err = Config::from_sources(&cli).unwrap_err().to_string();
+    assert!(err.contains("base_url or preset must be set"), "{err}");
+
+    let cli = Cli::parse_from(["commitbot", "--config", path, "--preset", "grok"]);
+    let err = Config::from_sources(&cli).unwrap_err().to_string();
+    assert!(err.contains("Unknown preset: grok"), "{err}");
+
+    fs::remove_file(config_path).ok();
+}
+
+#[test]
+fn openrouter_uses_its_preset_and_vendor_prefixed_models() {
+    let config_path = write_temp_config("openrouter", "[default]\nprovider = \"openrouter\"\n");
+    let path = config_path.to_str().expect("utf-8 path");
+
+    let err = Config::from_sources(&Cli::parse_from(["commitbot", "--config", path]))
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("OPENROUTER_API_KEY"), "{err}");
+
+    let cli = Cli::parse_from(["commitbot", "--config", path, "--api-key", "sk-or"]);
+    let cfg = Config::from_sources(&cli).unwrap();
+    assert_eq!(cfg.model, "openai/gpt-5-nano");
+    assert_eq!(cfg.preset.map(|p| p.name), Some("openrouter"));
+    assert_eq!(
+        cfg.base_url.as_deref(),
+        Some("https://openrouter.ai/api/v1")
+    );
+
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        path,
+        "--api-key",
+        "sk-or",
+        "--model",
+        "gpt-5",
+    ]);
+    let err = Config::from_sources(&cli).unwrap_err().to_string();
+    assert!(err.contains("\"openai/gpt-5\""), "{err}");
+
+    fs::remove_file(config_path).ok();
+}
+
+#[test]
+fn structured_output_turns_off_streaming() {
+    let config_path = write_temp_config("structured", "[default]\nprovider = \"ollama\"\n");
+    let path = config_path.to_str().expect("utf-8 path");
+
+    let cfg = Config::from_sources(&Cli::parse_from(["commitbot", "--config", path])).unwrap();
+    assert!(!cfg.structured_output);
+    assert!(cfg.stream);
+
+    let cli = Cli::parse_from(["commitbot", "--config", path, "--structured-output"]);
+    let cfg = Config::from_sources(&cli).unwrap();
+    assert!(cfg.structured_output);
+    assert!(!cfg.stream);
+
+    fs::remove_file(config_path).ok();
+}
+
+#[test]
+fn api_key_can_come_from_a_command_or_file() {
+    let key_path = unique_temp_path("key_file").with_extension("toml");
+    fs::write(&key_path, "sk-from-file\n").expect("write key file");
+    let config_path = write_temp_config(
+        "api_key_sources",
+        &format!(
+            r#"
+[default]
+provider = "openai"
+api_key_file = "{}"
+"#,
+            key_path.display()
+        ),
+    );
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        config_path.to_str().expect("utf-8 path"),
+    ]);
+    let cfg = Config::from_sources(&cli).expect("config should load");
+    assert_eq!(cfg.openai_api_key.as_deref(), Some("sk-from-file"));
+
+    fs::write(
+        &config_path,
+        "[default]\nprovider = \"openai\"\napi_key_command = \"echo sk-from-command\"\n",
+    )
+    .expect("rewrite config");
+    let cfg = Config::from_sources(&cli).expect("config should load");
+    assert_eq!(cfg.openai_api_key.as_deref(), Some("sk-from-command"));
+
+    fs::write(
+        &config_path,
+        "[default]\nprovider = \"openai\"\napi_key_command = \"exit 3\"\n",
+    )
+    .expect("rewrite config");
+    let err = Config::from_sources(&cli).expect_err("failing command should be an error");
+    assert!(err.to_string().contains("api_key_command exited"), "{err}");
+
+    fs::remove_file(config_path).ok();
+    fs::remove_file(key_path).ok();
+}
+
+#[test]
+fn language_resolves_from_file_and_cli() {
+    let config_path = write_temp_config(
+        "language",
+        r#"
+[default]
+provider = "ollama"
+language = "es"
+"#,
+    );
+    let path = config_path.to_str().expect("utf-8 path");
+
+    let cli = Cli::parse_from(["commitbot", "--config", path]);
+    let cfg = Config::from_sources(&cli).expect("config should resolve");
+    assert_eq!(cfg.language.as_deref(), Some("es"));
+
+    let cli = Cli::parse_from(["commitbot", "--config", path, "--language", "French"]);
+    let cfg = Config::from_sources(&cli).expect("config should resolve");
+    assert_eq!(cfg.language.as_deref(), Some("French"));
+
+    fs::remove_file(config_path).ok();
+}
+
+#[test]
+fn category_weights_follow_configured_order_and_emphasis() {
+    let config_path = write_temp_config(
+        "category_weights",
+        r#"
+[default]
+provider = "ollama"
+category_order = ["main", "consequence"]
+
+[default.category_emphasis]
+consequence = "Skip unless surprising."
+"#,
+    );
+
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        config_path.to_str().expect("utf-8 path"),
+    ]);
+
+    let cfg = Config::from_sources(&cli).expect("config should resolve");
+    let order: Vec<FileCategory> = cfg.category_weights.iter().map(|w| w.category).collect();
+    assert_eq!(order, vec![FileCategory::Main, FileCategory::Consequence]);
+    assert_eq!(cfg.category_weights[1].emphasis, "Skip unless surprising.");
+
+    fs::remove_file(config_path).ok();
+}
+
+#[test]
+fn unknown_category_in_order_is_an_error() {
+    let config_path = write_temp_config(
+        "category_unknown",
+        r#"
+[default]
+provider = "ollama"
+category_order = ["main", "chores"]
+"#,
+    );
+
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        config_path.to_str().expect("utf-8 path"),
+    ]);
+
+    let err = Config::from_sources(&cli).expect_err("unknown category should fail");
+    assert!(err.to_string().contains("Unknown category: chores"));
+
+    fs::remove_file(config_path).ok();
+}
+
+#[test]
+fn ignored_category_cannot_be_weighted() {
+    let config_path = write_temp_config(
+        "category_ignored",
+        r#"
+[default]
+provider = "ollama"
+category_emphasis = { ignored = "Mention these too." }
+"#,
+    );
+
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        config_path.to_str().expect("utf-8 path"),
+    ]);
+
+    let err = Config::from_sources(&cli).expect_err("ignored category should fail");
+    assert!(
+        err.to_string()
+            .contains("Category ignored cannot be weighted: ignored files are left out"),
+        "{err}"
+    );
+
+    fs::remove_file(config_path).ok();
+}
+
+#[test]
+fn duplicate_summary_threshold_from_file() {
+    let config_path = write_temp_config(
+        "dup_threshold",
+        r#"
+[default]
+provider = "ollama"
+duplicate_summary_threshold = 0.5
+"#,
+    );
+
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        config_path.to_str().expect("utf-8 path"),
+    ]);
+
+    let cfg = Config::from_sources(&cli).expect("config should load");
+    assert_eq!(cfg.duplicate_summary_threshold, 0.5);
+
+    fs::remove_file(config_path).ok();
+}
+
+#[test]
+fn duplicate_summary_threshold_out_of_range_is_an_error() {
+    let config_path = write_temp_config(
+        "dup_threshold_bad",
+        r#"
+[default]
+provider = "ollama"
+duplicate_summary_threshold = 1.5
+"#,
+    );
+
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        config_path.to_str().expect("utf-8 path"),
+    ]);
+
+    assert!(Config::from_sources(&cli).is_err());
+
+    fs::remove_file(config_path).ok();
+}
+
+#[test]
+fn max_attempts_defaults_to_four_and_rejects_zero() {
+    let config_path = write_temp_config("max_attempts", "[default]\nprovider = \"ollama\"\n");
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        config_path.to_str().expect("utf-8 path"),
+    ]);
+    let cfg = Config::from_sources(&cli).expect("config should load");
+    assert_eq!(cfg.max_attempts, 4);
+    fs::remove_file(config_path).ok();
+
+    let config_path = write_temp_config(
+        "max_attempts_zero",
+        "[default]\nprovider = \"ollama\"\nmax_attempts = 0\n",
+    );
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        config_path.to_str().expect("utf-8 path"),
+    ]);
+    assert!(Config::from_sources(&cli).is_err());
+    fs::remove_file(config_path).ok();
+}
+
+#[test]
+fn timeouts_default_by_provider_and_cli_overrides() {
+    let config_path = write_temp_config(
+        "timeouts",
+        "[default]\nprovider = \"ollama\"\nconnect_timeout = 5\n",
+    );
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        config_path.to_str().expect("utf-8 path"),
+    ]);
+    let cfg = Config::from_sources(&cli).expect("config should load");
+    assert_eq!(cfg.request_timeout, None, "Ollama waits indefinitely");
+    assert_eq!(cfg.connect_timeout, Some(Duration::from_secs(5)));
+
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        config_path.to_str().expect("utf-8 path"),
+        "--provider",
+        "openai",
+        "--api-key",
+        "sk-test",
+    ]);
+    let cfg = Config::from_sources(&cli).expect("config should load");
+    assert_eq!(cfg.request_timeout, Some(Duration::from_secs(90)));
+
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        config_path.to_str().expect("utf-8 path"),
+        "--request-timeout",
+        "600",
+        "--connect-timeout",
+        "0",
+    ]);
+    let cfg = Config::from_sources(&cli).expect("config should load");
+    assert_eq!(cfg.request_timeout, Some(Duration::from_secs(600)));
+    assert_eq!(cfg.connect_timeout, None);
+
+    fs::remove_file(config_path).ok();
+}
+
+#[test]
+fn ca_bundle_without_certificates_is_an_error() {
+    let bundle = unique_temp_path("ca_bundle_empty").with_extension("toml");
+    fs::write(&bundle, "not a certificate\n").expect("write bundle");
+    let config_path = write_temp_config("ca_bundle", "[default]\nprovider = \"ollama\"\n");
+
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        config_path.to_str().expect("utf-8 path"),
+        "--ca-bundle",
+        bundle.to_str().expect("utf-8 path"),
+    ]);
+    let cfg = Config::from_sources(&cli).expect("config should load");
+    assert_eq!(cfg.ca_bundle.as_deref(), Some(bundle.as_path()));
+    let err = shared_client(&cfg).expect_err("an empty bundle should fail");
+    assert!(err.to_string().contains("no PEM certificates"), "{err}");
+
+    fs::remove_file(bundle).ok();
+    fs::remove_file(config_path).ok();
+}
+
+#[test]
+fn client_certificate_settings_are_checked() {
+    let cert = unique_temp_path("client_cert").with_extension("toml");
+    fs::write(&cert, "not a certificate\n").expect("write cert");
+    let config_path = write_temp_config("client_cert", "[default]\nprovider = \"ollama\"\n");
+    let path = config_path.to_str().expect("utf-8 path");
+
+    let cli = Cli::parse_from(["commitbot", "--config", path, "--client-key", "key.pem"]);
+    let err = Config::from_sources(&cli).expect_err("a key needs a certificate");
+    assert!(err.to_string().contains("client_cert"), "{err}");
+
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        path,
+        "--client-cert",
+        cert.to_str().expect("utf-8 path"),
+    ]);
+    let cfg = Config::from_sources(&cli).expect("config should load");
+    assert_eq!(cfg.client_cert.as_deref(), Some(cert.as_path()));
+    assert!(
+        shared_client(&cfg).is_err(),
+        "an unreadable identity should fail"
+    );
+
+    fs::remove_file(cert).ok();
+    fs::remove_file(config_path).ok();
+}
+
+#[test]
+fn model_alias_expands_to_configured_target() {
+    let config_path = write_temp_config(
+        "aliases",
+        r#"
+[aliases]
+fast = "llama3.1:8b-instruct-q5_K_M"
+
+[default]
+provider = "ollama"
+model = "fast"
+"#,
+    );
+
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        config_path.to_str().expect("utf-8 path"),
+    ]);
+    let cfg = Config::from_sources(&cli).expect("config should load");
+    assert_eq!(cfg.model, "llama3.1:8b-instruct-q5_K_M");
+
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        config_path.to_str().expect("utf-8 path"),
+        "--model",
+        "qwen3-coder:30b",
+    ]);
+    let cfg = Config::from_sources(&cli).expect("config should load");
+    assert_eq!(
+        cfg.model, "qwen3-coder:30b",
+        "unknown names are used literally"
+    );
+
+    fs::remove_file(config_path).ok();
+}
+
+#[test]
+fn model_aliases_can_chain() {
+    let config_path = write_temp_config(
+        "alias_chain",
+        r#"
+[aliases]
+default = "smart"
+smart = "qwen3-coder:30b"
+loop-a = "loop-b"
+loop-b = "loop-a"
+
+[default]
+provider = "ollama"
+model = "default"
+final_model = "loop-a"
+"#,
+    );
+
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        config_path.to_str().expect("utf-8 path"),
+    ]);
+    let cfg = Config::from_sources(&cli).expect("config should load");
+    assert_eq!(cfg.model, "qwen3-coder:30b");
+    assert_eq!(
+        cfg.final_model(),
+        "loop-b",
+        "a cycle stops before repeating"
+    );
+
+    fs::remove_file(config_path).ok();
+}
+
+#[test]
+fn per_step_models_fall_back_to_model() {
+    let config_path = write_temp_config(
+        "per_step_models",
+        r#"
+[aliases]
+big = "qwen3-coder:30b"
+
+[default]
+provider = "ollama"
+model = "llama3.1:8b"
+final_model = "big"
+"#,
+    );
+
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        config_path.to_str().expect("utf-8 path"),
+    ]);
+    let cfg = Config::from_sources(&cli).expect("config should load");
+    assert_eq!(cfg.summary_model(), "llama3.1:8b");
+    assert_eq!(cfg.final_model(), "qwen3-coder:30b");
+
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        config_path.to_str().expect("utf-8 path"),
+        "--summary-model",
+        "llama3.2:3b",
+    ]);
+    let cfg = Config::from_sources(&cli).expect("config should load");
+    assert_eq!(cfg.summary_model(), "llama3.2:3b");
+
+    fs::remove_file(config_path).ok();
+}
+
+#[test]
+fn quiet_forces_streaming_off() {
+    let config_path = write_temp_config(
+        "quiet",
+        r#"
+[default]
+provider = "ollama"
+stream = true
+"#,
+    );
+    let path = config_path.to_str().expect("utf-8 path");
+
+    let cli = Cli::parse_from(["commitbot", "--config", path]);
+    assert!(
+        Config::from_sources(&cli)
+            .expect("config should resolve")
+            .stream
+    );
+
+    let cli = Cli::parse_from(["commitbot", "--config", path, "-q"]);
+    assert!(
+        !Config::from_sources(&cli)
+            .expect("config should resolve")
+            .stream
+    );
+
+    fs::remove_file(config_path).ok();
+}
+
+#[test]
+fn tone_resolves_and_rejects_unknown_names() {
+    let config_path = write_temp_config(
+        "tone",
+        r#"
+[default]
+provider = "ollama"
+tone = "Formal"
+"#,
+    );
+    let path = config_path.to_str().expect("utf-8 path");
+
+    let cli = Cli::parse_from(["commitbot", "--config", path]);
+    let cfg = Config::from_sources(&cli).expect("config should resolve");
+    assert_eq!(cfg.tone, Some(Tone::Formal));
+
+    let cli = Cli::parse_from(["commitbot", "--config", path, "--tone", "chatty"]);
+    assert!(Config::from_sources(&cli).is_err());
+
+    fs::remove_file(config_path).ok();
+}
+
+#[test]
+fn style_defaults_to_grouped_and_cli_overrides_file() {
+    let config_path = write_temp_config(
+        "style",
+        r#"
+[default]
+provider = "ollama"
+style = "plain"
+"#,
+    );
+    let path = config_path.to_str().expect("utf-8 path");
+
+    let cli = Cli::parse_from(["commitbot", "--config", path]);
+    let cfg = Config::from_sources(&cli).expect("config should resolve");
+    assert_eq!(cfg.style, MessageStyle::Plain);
+
+    let cli = Cli::parse_from(["commitbot", "--config", path, "--style", "oneline"]);
+    let cfg = Config::from_sources(&cli).expect("config should resolve");
+    assert_eq!(cfg.style, MessageStyle::Oneline);
+
+    let cli = Cli::parse_from(["commitbot", "--config", path, "--style", "fancy"]);
+    assert!(Config::from_sources(&cli).is_err());
+
+    fs::remove_file(config_path).ok();
+}
+
+#[test]
+fn extra_headers_merge_and_validate() {
+    let config_path = write_temp_config(
+        "headers",
+        r#"
+[headers]
+X-Tenant-Id = "acme"
+X-Proxy-Token = "global"
+
+[default]
+provider = "ollama"
+headers = { X-Proxy-Token = "secret" }
+"#,
+    );
+    let path = config_path.to_str().expect("utf-8 path");
+
+    let cli = Cli::parse_from(["commitbot", "--config", path]);
+    let cfg = Config::from_sources(&cli).expect("config should resolve");
+    assert_eq!(cfg.extra_headers["x-tenant-id"], "acme");
+    assert_eq!(cfg.extra_headers["x-proxy-token"], "secret");
+    assert!(cfg.extra_headers["x-proxy-token"].is_sensitive());
+
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        path,
+        "--header",
+        "X-Proxy-Token: from-cli",
+        "--header",
+        "X-Team-Id=platform",
+    ]);
+    let cfg = Config::from_sources(&cli).expect("config should resolve");
+    assert_eq!(cfg.extra_headers["x-proxy-token"], "from-cli");
+    assert_eq!(cfg.extra_headers["x-team-id"], "platform");
+
+    let cli = Cli::parse_from(["commitbot", "--config", path, "--header", "X-Team-Id"]);
+    assert!(Config::from_sources(&cli).is_err());
+    fs::remove_file(config_path).ok();
+
+    let config_path = write_temp_config(
+        "extra_headers",
+        r#"
+[extra_headers]
+X-Api-Gateway-Key = "gw"
+
+[default]
+provider = "ollama"
+extra_headers = { X-Team-Id = "platform" }
+"#,
+    );
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        config_path.to_str().expect("utf-8 path"),
+    ]);
+    let cfg = Config::from_sources(&cli).expect("config should resolve");
+    assert_eq!(cfg.extra_headers["x-api-gateway-key"], "gw");
+    assert_eq!(cfg.extra_headers["x-team-id"], "platform");
+    fs::remove_file(config_path).ok();
+
+    let config_path = write_temp_config(
+        "bad_headers",
+        r#"
+[headers]
+"Bad Header" = "x"
+
+[default]
+provider = "ollama"
+"#,
+    );
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        config_path.to_str().expect("utf-8 path"),
+    ]);
+    let err = Config::from_sources(&cli).unwrap_err();
+    assert!(err.to_string().contains("Bad Header"), "{err}");
+    fs::remove_file(config_path).ok();
+}
+
+#[test]
+fn headers_and_extra_headers_together_are_an_error() {
+    for contents in [
+        "[headers]\nX-A = \"1\"\n\n[extra_headers]\nX-B = \"2\"\n",
+        "[default]\nprovider = \"ollama\"\nheaders = { X-A = \"1\" }\nextra_headers = { X-B = \"2\" }\n",
+    ] {
+        let config_path = write_temp_config("both_headers", contents);
+        let cli = Cli::parse_from([
+            "commitbot",
+            "--config",
+            config_path.to_str().expect("utf-8 path"),
+        ]);
+        let err = Config::from_sources(&cli).unwrap_err().to_string();
+        assert!(err.contains("both headers and extra_headers"), "{err}");
+        assert!(
+            err.contains(config_path.to_str().expect("utf-8 path")),
+            "{err}"
+        );
+        fs::remove_file(config_path).ok();
+    }
+}
+
+#[test]
+fn interactive_max_files_defaults_to_forty() {
+    let config_path = write_temp_config(
+        "interactive_max_files",
+        r#"
+[default]
+provider = "ollama"
+"#,
+    );
+    let path = config_path.to_str().expect("utf-8 path");
+    let cli = Cli::parse_from(["commitbot", "--config", path]);
+    let cfg = Config::from_sources(&cli).expect("config should resolve");
+    assert_eq!(cfg.interactive_max_files, 40);
+
+    fs::write(
+        &config_path,
+        "[default]\nprovider = \"ollama\"\ninteractive_max_files = 0\n",
+    )
+    .unwrap();
+    let cfg = Config::from_sources(&cli).expect("config should resolve");
+    assert_eq!(cfg.interactive_max_files, 0);
+
+    fs::remove_file(config_path).ok();
+}
+
+#[test]
+fn provider_names_are_normalized() {
+    assert_eq!(canonical_provider(" OpenAI "), Some("openai"));
+    assert_eq!(canonical_provider("open_ai"), Some("openai"));
+    assert_eq!(canonical_provider("oai"), Some("openai"));
+    assert_eq!(canonical_provider("Local"), Some("ollama"));
+    assert_eq!(canonical_provider("google"), Some("gemini"));
+    assert_eq!(canonical_provider("Claude"), Some("anthropic"));
+    assert_eq!(canonical_provider("openia"), None);
+
+    let config_path = write_temp_config("provider_alias", "[default]\nprovider = \"Local \"\n");
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        config_path.to_str().expect("utf-8 path"),
+    ]);
+    assert_eq!(Config::from_sources(&cli).unwrap().provider, "ollama");
+
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        config_path.to_str().expect("utf-8 path"),
+        "--provider",
+        "openia",
+    ]);
+    let err = Config::from_sources(&cli).unwrap_err().to_string();
+    assert!(err.contains("Unknown provider: openia"), "{err}");
+    assert!(err.contains("ollama (local)"), "{err}");
+
+    fs::remove_file(config_path).ok();
+}
+
+#[test]
+fn each_provider_has_its_own_default_model() {
+    let config_path = write_temp_config("default_models", "");
+    let path = config_path.to_str().expect("utf-8 path");
+
+    for (provider, model) in [
+        ("openai", "gpt-5-nano"),
+        ("anthropic", "claude-haiku-4-5"),
+        ("gemini", "gemini-2.5-flash-lite"),
+        ("openrouter", "openai/gpt-5-nano"),
+    ] {
+        let cli = Cli::parse_from([
+            "commitbot",
+            "--config",
+            path,
+            "--provider",
+            provider,
+            "--api-key",
+            "test-key",
+        ]);
+        assert_eq!(
+            Config::from_sources(&cli).unwrap().model,
+            model,
+            "{provider}"
+        );
+    }
+
+    fs::remove_file(config_path).ok();
+}
+
+#[test]
+fn ollama_defaults_to_one_concurrent_request() {
+    let config_path = write_temp_config("ollama_concurrency", "");
+    let path = config_path.to_str().expect("utf-8 path");
+
+    let cli = Cli::parse_from(["commitbot", "--config", path, "--provider", "ollama"]);
+    assert_eq!(
+        Config::from_sources(&cli).unwrap().max_concurrent_requests,
+        1
+    );
+
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        path,
+        "--provider",
+        "ollama",
+        "--max",
+        "3",
+    ]);
+    assert_eq!(
+        Config::from_sources(&cli).unwrap().max_concurrent_requests,
+        3
+    );
+
+    fs::remove_file(config_path).ok();
+}
+
+#[test]
+fn reads_reasoning_parameters() {
+    let config_path = write_temp_config(
+        "reasoning",
+        "[default]\nprovider = \"ollama\"\nreasoning_effort = \"Low\"\nmax_output_tokens = 800\n",
+    );
+    let path = config_path.to_str().expect("utf-8 path");
+
+    let cfg = Config::from_sources(&Cli::parse_from(["commitbot", "--config", path])).unwrap();
+    assert_eq!(cfg.reasoning_effort.as_deref(), Some("low"));
+    assert_eq!(cfg.max_output_tokens, Some(800));
+
+    let cli = Cli::parse_from(["commitbot", "--config", path, "--reasoning-effort", "max"]);
+    let err = Config::from_sources(&cli).unwrap_err().to_string();
+    assert!(err.contains("Unknown reasoning_effort: max"), "{err}");
+
+    fs::remove_file(config_path).ok();
+}
+
+#[test]
+fn reads_sampling_parameters() {
+    let config_path = write_temp_config(
+        "sampling",
+        "[default]\nprovider = \"ollama\"\ntemperature = 0\ntop_p = 0.9\n",
+    );
+    let path = config_path.to_str().expect("utf-8 path");
+
+    let cfg = Config::from_sources(&Cli::parse_from(["commitbot", "--config", path])).unwrap();
+    assert_eq!(cfg.temperature, Some(0.0));
+    assert_eq!(cfg.top_p, Some(0.9));
+    assert_eq!(cfg.max_output_tokens, None);
+
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        path,
+        "--temperature",
+        "0.7",
+        "--max-output-tokens",
+        "300",
+    ]);
+    let cfg = Config::from_sources(&cli).unwrap();
+    assert_eq!(cfg.temperature, Some(0.7));
+    assert_eq!(cfg.max_output_tokens, Some(300));
+
+    let cli = Cli::parse_from(["commitbot", "--config", path, "--top-p", "1.5"]);
+    let err = Config::from_sources(&cli).unwrap_err().to_string();
+    assert!(err.contains("top_p must be a number from 0 to 1"), "{err}");
+
+    fs::remove_file(config_path).ok();
+}
+
+#[test]
+fn style_from_history_is_off_for_pull_requests() {
+    let config_path = write_temp_config("style_history", "[default]\nstyle_from_history = 5\n");
+    let path = config_path.to_str().expect("utf-8 path");
+
+    let cli = Cli::parse_from(["commitbot", "--config", path, "--provider", "ollama"]);
+    assert_eq!(Config::from_sources(&cli).unwrap().style_from_history, 5);
+
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        path,
+        "--provider",
+        "ollama",
+        "pr",
+        "main",
+    ]);
+    assert_eq!(Config::from_sources(&cli).unwrap().style_from_history, 0);
+
+    fs::remove_file(config_path).ok();
+}
@@ -0,0 +1,78 @@
+use anyhow::anyhow;
+use commitbot::llm::debug_log::DebugLog;
+use std::fs;
+
+mod common;
+use common::unique_temp_path;
+
+#[test]
+fn records_one_json_line_per_call() {
+    let path = unique_temp_path("debug_log").with_extension("jsonl");
+
+    let log = DebugLog::open(&path).expect("open debug log");
+    log.record(
+        "openai",
+        "gpt-5-nano",
+        "file_summary",
+        "system prompt",
+        "user prompt",
+        &Ok("- Add login".to_string()),
+    );
+    log.record(
+        "openai",
+        "gpt-5-nano",
+        "commit_message",
+        "system",
+        "user",
+        &Err(anyhow!("HTTP 401")),
+    );
+
+    let contents = fs::read_to_string(&path).expect("read debug log");
+    let lines: Vec<serde_json::Value> = contents
+        .lines()
+        .map(|l| serde_json::from_str(l).expect("valid json line"))
+        .collect();
+
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["step"], "file_summary");
+    assert_eq!(lines[0]["user"], "user prompt");
+    assert_eq!(lines[0]["response"], "- Add login");
+    assert_eq!(lines[1]["error"], "HTTP 401");
+    assert!(lines[1]["response"].is_null());
+
+    fs::remove_file(path).ok();
+}
+
+#[test]
+fn keeps_the_latest_reply_per_thread() {
+    let log = DebugLog::in_memory();
+    let record = |reply: &str| {
+        log.record(
+            "ollama",
+            "llama3",
+            "summary",
+            "s",
+            "u",
+            &Ok(reply.to_string()),
+        );
+    };
+    record("first");
+    record("second");
+
+    let reply = log.take_reply().expect("a reply on this thread");
+    assert_eq!(
+        (reply.provider.as_str(), reply.model.as_str()),
+        ("ollama", "llama3")
+    );
+    assert_eq!(reply.response.as_deref(), Some("second"));
+    assert_eq!(log.take_reply(), None);
+
+    std::thread::scope(|scope| {
+        scope.spawn(|| record("elsewhere"));
+    });
+    assert_eq!(
+        log.take_reply(),
+        None,
+        "another thread's reply stays with it"
+    );
+}
@@ -1,8 +1,15 @@
 use commitbot::git::{
-    find_first_pr_number, format_pr_commit_appendix_with_remote, parse_remote_repo,
-    short_commit_hash, split_diff_by_file, PrItem, PrSummaryMode,
+    DiffOptions, Forge, PrItem, PrSummaryMode, autodetect_summary_mode, closest_ref, decode_diff,
+    find_body_change_number, find_first_change_number, find_first_pr_number,
+    format_pr_commit_appendix_with_remote, has_replacement_chars, is_binary_diff, is_deleted_diff,
+    message_file_has_content, parse_commit_messages, parse_pr_log, parse_remote_repo,
+    parse_status_porcelain, parse_submodule_bump, pr_template_in, short_commit_hash,
+    split_diff_by_file, untracked_diff_for_file, write_message_file,
 };
 
+mod common;
+use common::unique_temp_path;
+
 #[test]
 fn parses_github_ssh_remote() {
     let remote = parse_remote_repo("git@github.com:owner/repo.git").unwrap();
@@ -141,3 +148,280 @@ fn pr_summary_mode_as_str() {
     assert_eq!(PrSummaryMode::ByCommits.as_str(), "commits");
     assert_eq!(PrSummaryMode::ByPrs.as_str(), "prs");
 }
+
+#[test]
+fn find_gitlab_merge_request_number() {
+    let body = "Merge branch 'feat/login' into 'main'\n\nSee merge request group/app!87";
+    assert_eq!(find_first_change_number(body, Forge::GitLab), Some(87));
+    assert_eq!(find_first_change_number("Fixes #12", Forge::GitLab), None);
+    assert_eq!(
+        find_first_change_number("Fixes #12", Forge::GitHub),
+        Some(12)
+    );
+}
+
+#[test]
+fn gitlab_remote_implies_gitlab_forge() {
+    let remote = parse_remote_repo("git@gitlab.com:group/app.git").unwrap();
+    assert_eq!(remote.forge(), Forge::GitLab);
+
+    let items = vec![PrItem {
+        commit_hash: "abcdef123456".to_string(),
+        title: "Add login".to_string(),
+        body: String::new(),
+        pr_number: None,
+    }];
+    let appendix = format_pr_commit_appendix_with_remote(&items, Some(&remote));
+    assert!(appendix.starts_with("Commits in this MR:"));
+}
+
+#[test]
+fn forge_parse_and_format() {
+    assert_eq!(Forge::parse("GitLab"), Some(Forge::GitLab));
+    assert_eq!(Forge::parse("github"), Some(Forge::GitHub));
+    assert_eq!(Forge::parse("gitea"), None);
+    assert_eq!(Forge::GitLab.format_ref(5), "MR !5");
+    assert_eq!(Forge::GitHub.format_ref(5), "PR #5");
+}
+
+#[test]
+fn parses_porcelain_status_entries() {
+    let output =
+        " M src/main.rs\0?? notes/todo.md\0R  new.rs\0old.rs\0MM src/lib.rs\0 D gone.txt\0";
+    let entries = parse_status_porcelain(output);
+
+    let paths: Vec<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        vec![
+            "src/main.rs",
+            "notes/todo.md",
+            "new.rs",
+            "src/lib.rs",
+            "gone.txt"
+        ]
+    );
+    assert!(entries[1].is_untracked());
+    assert_eq!(entries[0].code(), " M");
+    assert!(!entries[2].has_unstaged_changes());
+    assert!(entries[3].has_unstaged_changes());
+    assert!(entries[4].has_unstaged_changes());
+}
+
+#[test]
+fn message_file_keeps_git_comment_lines() {
+    let path = unique_temp_path("msg");
+    std::fs::write(
+        &path,
+        "\n# Please enter the commit message\n# On branch main\n",
+    )
+    .unwrap();
+
+    write_message_file(&path, "Add login\n\n- Wire up handler\n\n").unwrap();
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(
+        contents,
+        "Add login\n\n- Wire up handler\n\n# Please enter the commit message\n# On branch main\n"
+    );
+    std::fs::remove_file(path).ok();
+}
+
+#[test]
+fn decode_diff_strips_bom_from_content_lines() {
+    let diff = "diff --git a/a.txt b/a.txt\n+\u{feff}hello\n-\u{feff}old\n world\n";
+    assert_eq!(
+        decode_diff(diff.as_bytes()),
+        "diff --git a/a.txt b/a.txt\n+hello\n-old\n world\n"
+    );
+}
+
+#[test]
+fn decode_diff_flags_non_utf8_bytes() {
+    // "café" in latin-1
+    let decoded = decode_diff(b"+caf\xe9\n");
+    assert!(has_replacement_chars(&decoded));
+    assert!(!has_replacement_chars(&decode_diff("+café\n".as_bytes())));
+}
+
+#[test]
+fn binary_diffs_are_detected() {
+    let diff = "diff --git a/logo.png b/logo.png\nindex 1..2 100644\nBinary files a/logo.png and b/logo.png differ";
+    assert!(is_binary_diff(diff));
+    assert!(!is_binary_diff(
+        "diff --git a/a.rs b/a.rs\n+// Binary files are skipped\n"
+    ));
+}
+
+fn pr_item(hash: &str, pr_number: Option<u32>) -> PrItem {
+    PrItem {
+        commit_hash: hash.to_string(),
+        title: format!("Commit {hash}"),
+        body: String::new(),
+        pr_number,
+    }
+}
+
+#[test]
+fn autodetect_uses_pr_count_and_ratio() {
+    let items = vec![
+        pr_item("a", Some(1)),
+        pr_item("b", Some(2)),
+        pr_item("c", None),
+        pr_item("d", None),
+        pr_item("e", None),
+    ];
+
+    let (mode, _) = autodetect_summary_mode(&items, 2, 0.0);
+    assert!(matches!(mode, PrSummaryMode::ByPrs));
+
+    let (mode, _) = autodetect_summary_mode(&items, 3, 0.0);
+    assert!(matches!(mode, PrSummaryMode::ByCommits));
+
+    let (mode, reason) = autodetect_summary_mode(&items, 2, 0.5);
+    assert!(matches!(mode, PrSummaryMode::ByCommits));
+    assert!(reason.contains("2/5 commits"), "{reason}");
+}
+
+#[test]
+fn deleted_file_diffs_are_detected() {
+    let deleted = "diff --git a/old.rs b/old.rs\ndeleted file mode 100644\nindex 1..0\n--- a/old.rs\n+++ /dev/null\n@@ -1 +0,0 @@\n-fn old() {}";
+    assert!(is_deleted_diff(deleted));
+
+    let edited = "diff --git a/a.rs b/a.rs\n--- a/a.rs\n+++ b/a.rs\n@@ -1 +1 @@\n-deleted file mode 100644\n+x";
+    assert!(!is_deleted_diff(edited));
+}
+
+#[test]
+fn message_file_content_ignores_git_comments() {
+    let path = unique_temp_path("existing");
+    assert!(!message_file_has_content(&path));
+
+    std::fs::write(&path, "\n# Please enter the commit message\n").unwrap();
+    assert!(!message_file_has_content(&path));
+
+    std::fs::write(&path, "WIP: half-typed subject\n# On branch main\n").unwrap();
+    assert!(message_file_has_content(&path));
+    std::fs::remove_file(path).ok();
+}
+
+#[test]
+fn submodule_bumps_are_parsed() {
+    let diff = "diff --git a/vendor/lib b/vendor/lib\nindex 1111111..2222222 160000\n--- a/vendor/lib\n+++ b/vendor/lib\n@@ -1 +1 @@\n-Subproject commit 1111111aaaaaaaaaaaa\n+Subproject commit 2222222bbbbbbbbbbbb\n\nSubmodule vendor/lib 1111111..2222222:\n  > Fix overflow in parser\n  > Add streaming reader\n";
+    let bump = parse_submodule_bump(diff).expect("submodule bump");
+    assert_eq!(bump.from.as_deref(), Some("1111111"));
+    assert_eq!(bump.to, "2222222");
+    assert_eq!(
+        bump.subjects,
+        vec!["Fix overflow in parser", "Add streaming reader"]
+    );
+
+    let added = "diff --git a/sub b/sub\nnew file mode 160000\n--- /dev/null\n+++ b/sub\n@@ -0,0 +1 @@\n+Subproject commit 3333333cccccccc\n";
+    assert_eq!(parse_submodule_bump(added).unwrap().from, None);
+
+    assert_eq!(parse_submodule_bump("@@ -1 +1 @@\n-a\n+b\n"), None);
+
+    // a regular file whose content mentions a subproject commit is not a gitlink
+    let text = "diff --git a/notes.txt b/notes.txt\nindex 1111111..2222222 100644\n--- a/notes.txt\n+++ b/notes.txt\n@@ -1 +1 @@\n-Subproject commit 1111111aaaaaaaa\n+Subproject commit 2222222bbbbbbbb\n";
+    assert_eq!(parse_submodule_bump(text), None);
+}
+
+#[test]
+fn closest_ref_suggests_near_misses_only() {
+    let refs = ["main", "develop", "origin/develop", "v1.2.0"];
+    assert_eq!(closest_ref("develp", &refs), Some("develop"));
+    assert_eq!(closest_ref("mian", &refs), Some("main"));
+    assert_eq!(closest_ref("release", &refs), None);
+}
+
+#[test]
+fn untracked_files_diff_as_all_added() {
+    let path = unique_temp_path("untracked").with_extension("txt");
+    std::fs::write(&path, "first\nsecond\n").unwrap();
+
+    let diff = untracked_diff_for_file(path.to_str().unwrap()).unwrap();
+    assert!(diff.contains("new file mode"), "{diff}");
+    assert!(diff.contains("+first\n+second"), "{diff}");
+    std::fs::remove_file(path).ok();
+}
+
+#[test]
+fn pr_template_found_in_conventional_location() {
+    let root = unique_temp_path("template");
+    assert_eq!(pr_template_in(&root), None);
+
+    std::fs::create_dir_all(root.join(".github")).unwrap();
+    std::fs::write(
+        root.join(".github/pull_request_template.md"),
+        "## Summary\n- [ ] Tested\n",
+    )
+    .unwrap();
+    assert_eq!(
+        pr_template_in(&root).as_deref(),
+        Some("## Summary\n- [ ] Tested\n")
+    );
+    std::fs::remove_dir_all(root).ok();
+}
+
+#[test]
+fn diff_options_map_to_git_flags() {
+    assert!(DiffOptions::default().git_args().is_empty());
+    let opts = DiffOptions {
+        ignore_whitespace: true,
+        word_diff: true,
+    };
+    assert_eq!(opts.git_args(), ["-w", "--word-diff=porcelain"]);
+}
+
+#[test]
+fn body_scan_ignores_issue_references() {
+    assert_eq!(find_body_change_number("Fixes #45", Forge::GitHub), None);
+    assert_eq!(
+        find_body_change_number("Some context.\n\nCloses (#45)", Forge::GitHub),
+        None
+    );
+    assert_eq!(
+        find_body_change_number("* Add login (#432)\n\nFixes #45", Forge::GitHub),
+        Some(432)
+    );
+    assert_eq!(
+        find_body_change_number("Merge pull request #77 from me/login", Forge::GitHub),
+        Some(77)
+    );
+    assert_eq!(
+        find_body_change_number("See merge request group/app!87", Forge::GitLab),
+        Some(87)
+    );
+}
+
+#[test]
+fn parses_pr_log_records() {
+    let raw = "aaa\0Add login (#12)\0First paragraph.\n\nSecond paragraph.\n\x1e\n\
+               bbb\0--- tidy up ---\0\x1e\n\
+               ccc\0Harden parser\0Body line\r\n---END---\r\nFixes #45\r\n\x1e";
+    let items = parse_pr_log(raw, Forge::GitHub);
+
+    assert_eq!(items.len(), 3);
+    assert_eq!(items[0].commit_hash, "aaa");
+    assert_eq!(items[0].pr_number, Some(12));
+    assert_eq!(items[0].body, "First paragraph.\n\nSecond paragraph.");
+
+    assert_eq!(items[1].title, "--- tidy up ---");
+    assert_eq!(items[1].body, "");
+    assert_eq!(items[1].pr_number, None);
+
+    assert_eq!(items[2].body, "Body line\n---END---\nFixes #45");
+    assert_eq!(items[2].pr_number, None);
+
+    assert!(parse_pr_log("", Forge::GitHub).is_empty());
+}
+
+#[test]
+fn parses_recent_commit_messages() {
+    let raw =
+        "feat: add login\r\n\r\nBody line\r\n\0\nfixup! feat: add login\n\0\n\n\0\ndocs: typo\n\0";
+    assert_eq!(
+        parse_commit_messages(raw),
+        vec!["feat: add login\n\nBody line", "docs: typo"]
+    );
+}
@@ -0,0 +1,36 @@
+use commitbot::ignore::{glob_match, IgnoreRules};
+
+#[test]
+fn glob_star_does_not_cross_directories() {
+    assert!(glob_match("*.lock", "Cargo.lock"));
+    assert!(!glob_match("src/*.rs", "src/llm/mod.rs"));
+    assert!(glob_match("src/**/*.rs", "src/llm/mod.rs"));
+    assert!(glob_match("src/**/*.rs", "src/main.rs"));
+    assert!(glob_match("file?.txt", "file1.txt"));
+}
+
+#[test]
+fn unanchored_patterns_match_at_any_depth() {
+    let rules = IgnoreRules::parse("# generated\n*.lock\ndist/\n");
+    assert!(rules.is_ignored("Cargo.lock"));
+    assert!(rules.is_ignored("web/package.lock"));
+    assert!(rules.is_ignored("web/dist/app.js"));
+    assert!(!rules.is_ignored("src/dist"));
+    assert!(!rules.is_ignored("src/main.rs"));
+}
+
+#[test]
+fn anchored_patterns_and_negation() {
+    let rules = IgnoreRules::parse("/docs/generated\nsnapshots/**\n!snapshots/keep.snap\n");
+    assert!(rules.is_ignored("docs/generated/api.md"));
+    assert!(!rules.is_ignored("web/docs/generated/api.md"));
+    assert!(rules.is_ignored("snapshots/a.snap"));
+    assert!(!rules.is_ignored("snapshots/keep.snap"));
+}
+
+#[test]
+fn empty_rules_ignore_nothing() {
+    let rules = IgnoreRules::parse("\n# only comments\n");
+    assert!(rules.is_empty());
+    assert!(!rules.is_ignored("anything.rs"));
+}
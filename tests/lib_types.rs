@@ -10,6 +10,12 @@ fn file_category_str_representation() {
     assert_eq!(FileCategory::Ignored.as_str(), "ignored");
 }
 
+#[test]
+fn file_category_labels() {
+    assert_eq!(FileCategory::Main.label(), "Main purpose");
+    assert_eq!(FileCategory::Consequence.label(), "Consequence");
+}
+
 #[test]
 fn file_change_creation() {
     let file_change = FileChange {
@@ -17,9 +23,21 @@ fn file_change_creation() {
         category: FileCategory::Main,
         diff: "diff --git a/src/main.rs b/src/main.rs\n--- a/src/main.rs\n+++ b/src/main.rs\n@@ -1,3 +1,3 @@\n-println!(\"Hello world\");\n+println!(\"Hello commitbot\");\n".to_string(),
         summary: Some("Update hello message".to_string()),
+        list_only: false,
+        is_deleted: false,
     };
 
     assert_eq!(file_change.path, "src/main.rs");
     assert_eq!(file_change.category, FileCategory::Main);
     assert_eq!(file_change.summary.as_deref(), Some("Update hello message"));
 }
+
+#[test]
+fn file_category_menu_numbers_round_trip() {
+    for n in 1..=4u8 {
+        let category = FileCategory::from_menu_number(n).expect("valid menu number");
+        assert_eq!(category.menu_index(), (n - 1) as usize);
+    }
+    assert_eq!(FileCategory::from_menu_number(0), None);
+    assert_eq!(FileCategory::from_menu_number(5), None);
+}
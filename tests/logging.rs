@@ -0,0 +1,20 @@
+use commitbot::logging::{color_enabled, color_wanted, plain_template, progress_template};
+use std::ffi::OsStr;
+
+#[test]
+fn no_color_flag_or_env_turns_styling_off() {
+    assert!(color_wanted(false, None));
+    assert!(color_wanted(false, Some(OsStr::new(""))));
+    assert!(!color_wanted(false, Some(OsStr::new("1"))));
+    assert!(!color_wanted(true, None));
+}
+
+#[test]
+fn plain_templates_drop_styles() {
+    assert_eq!(
+        plain_template("{spinner:.cyan} {prefix:.bold}: {msg} {elapsed:.dim}"),
+        "{spinner} {prefix}: {msg} {elapsed}"
+    );
+    assert!(color_enabled());
+    assert_eq!(progress_template("{bar:.green}"), "{bar:.green}");
+}
@@ -0,0 +1,352 @@
+use assert_cmd::cargo;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+
+mod common;
+use common::unique_temp_path;
+
+/// A fresh repository with one commit on `main`, in its own temp directory that also serves
+/// as `HOME`, so no user config or cache leaks in.
+fn temp_repo(name: &str) -> PathBuf {
+    let dir = unique_temp_path(&format!("mock_{name}"));
+    fs::create_dir_all(&dir).expect("create temp repo");
+    git(&dir, &["init", "-q", "-b", "main"]);
+    fs::write(dir.join("README.md"), "# demo\n").unwrap();
+    git(&dir, &["add", "."]);
+    git(&dir, &["commit", "-q", "-m", "Initial commit"]);
+    dir
+}
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .status()
+        .expect("run git");
+    assert!(status.success(), "git {args:?} failed");
+}
+
+fn commitbot(dir: &Path) -> assert_cmd::Command {
+    let mut cmd = cargo::cargo_bin_cmd!();
+    cmd.current_dir(dir)
+        .env("HOME", dir)
+        .env("XDG_CACHE_HOME", dir.join(".cache"))
+        .env_remove("COMMITBOT_PROVIDER")
+        .args(["--provider", "mock", "--quiet"]);
+    cmd
+}
+
+#[test]
+fn writes_a_commit_message_offline() {
+    let dir = temp_repo("commit");
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(
+        dir.join("src/lib.rs"),
+        "pub fn answer() -> u32 {\n    42\n}\n",
+    )
+    .unwrap();
+    fs::write(dir.join("README.md"), "# demo\n\nNow with a library.\n").unwrap();
+    git(&dir, &["add", "."]);
+    // left over from the initial commit; a hook would get an empty file
+    fs::remove_file(dir.join(".git/COMMIT_EDITMSG")).unwrap();
+
+    let output = commitbot(&dir)
+        .args(["--message-file", ".git/COMMIT_EDITMSG"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let message = fs::read_to_string(dir.join(".git/COMMIT_EDITMSG")).unwrap();
+    assert!(message.starts_with("Update 2 files\n\n"), "{message}");
+    assert!(
+        message.contains("- Add `src/lib.rs` (+3 lines)"),
+        "{message}"
+    );
+    assert!(
+        message.contains("- Update `README.md` (1 hunk, +2/-0 lines)"),
+        "{message}"
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), message);
+
+    fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn word_diffs_still_catch_changed_secrets() {
+    let dir = temp_repo("word_diff_secret");
+    fs::write(dir.join("settings.env"), "db_password = \"hunter22\"\n").unwrap();
+    git(&dir, &["add", "."]);
+    git(&dir, &["commit", "-q", "-m", "Add settings"]);
+    // the word diff alone shows only the quoted value, away from its key
+    fs::write(dir.join("settings.env"), "db_password = \"swordfish9\"\n").unwrap();
+    git(&dir, &["add", "."]);
+
+    let output = commitbot(&dir)
+        .args(["--word-diff", "--strict-secrets"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success(), "{output:?}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("look like they contain secrets"),
+        "{stderr}"
+    );
+    assert!(stderr.contains("settings.env:1"), "{stderr}");
+
+    fs::remove_dir_all(dir).ok();
+}
+
+#[cfg(unix)]
+#[test]
+fn asks_an_external_command() {
+    let dir = temp_repo("command");
+    fs::write(dir.join("notes.txt"), "remember the milk\n").unwrap();
+    git(&dir, &["add", "."]);
+    // answers every step with its name, and keeps the last request for inspection
+    let script = r#"tee .last-request.json | sed -n 's/.*"step":"\([a-z_]*\)".*/reply to \1/p'"#;
+
+    let output = commitbot(&dir)
+        .args(["--provider", "command", "--provider-command", script])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        "reply to commit_message"
+    );
+    let request = fs::read_to_string(dir.join(".last-request.json")).unwrap();
+    assert!(request.contains("\"model\":\"default\""), "{request}");
+    assert!(request.contains("reply to file_summary"), "{request}");
+
+    fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn writes_release_notes_since_a_tag() {
+    let dir = temp_repo("tags");
+    git(&dir, &["tag", "v1.0.0"]);
+    git(&dir, &["checkout", "-q", "-b", "release"]);
+    for (file, title) in [("a.txt", "Add a"), ("b.txt", "Add b")] {
+        fs::write(dir.join(file), "x\n").unwrap();
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "-q", "-m", title]);
+    }
+    // described from another branch, so the tag has to come from `release`, not HEAD
+    git(&dir, &["checkout", "-q", "main"]);
+
+    let output = commitbot(&dir)
+        .args(["pr", "--since-tag", "release", "--commit"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Merges 2 commits from release into v1.0.0."),
+        "{stdout}"
+    );
+
+    let output = commitbot(&dir)
+        .args(["pr", "--tag", "v1.0.0", "release", "--commit", "--dry-run"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let prompts = String::from_utf8_lossy(&output.stdout);
+    assert!(prompts.contains("You write release notes"), "{prompts}");
+    assert!(prompts.contains("Previous release tag: v1.0.0\nFeature branch: release"));
+    assert!(!prompts.contains("Overall ticket goal"), "{prompts}");
+
+    fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn repository_config_cannot_run_commands() {
+    let cases: [(&str, &[&str]); 4] = [
+        (
+            "provider = \"command\"\nprovider_command = \"touch PWNED; echo hi\"",
+            &[],
+        ),
+        // choosing the provider yourself still doesn't pick up the repository's command
+        (
+            "provider_command = \"touch PWNED; echo hi\"",
+            &["--provider", "command"],
+        ),
+        (
+            "api_key_command = \"touch PWNED; echo sk-ant-x\"",
+            &["--provider", "anthropic"],
+        ),
+        (
+            "default_args = [\"--provider\", \"command\", \"--provider-command\", \"touch PWNED; echo hi\"]",
+            &[],
+        ),
+    ];
+    for (local_config, args) in cases {
+        let dir = temp_repo("untrusted");
+        fs::write(dir.join(".commitbot.toml"), format!("{local_config}\n")).unwrap();
+        fs::write(dir.join("notes.txt"), "remember the milk\n").unwrap();
+        git(&dir, &["add", "notes.txt"]);
+
+        // the setting is dropped, so the run stops for want of a key or command
+        let mut plain = cargo::cargo_bin_cmd!();
+        let output = plain
+            .current_dir(&dir)
+            .env("HOME", &dir)
+            .env("XDG_CACHE_HOME", dir.join(".cache"))
+            .env_remove("COMMITBOT_PROVIDER")
+            .env_remove("COMMITBOT_PROVIDER_COMMAND")
+            .env_remove("COMMITBOT_API_KEY_COMMAND")
+            .env_remove("OPENAI_API_KEY")
+            .env_remove("ANTHROPIC_API_KEY")
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(!output.status.success(), "{local_config}: {output:?}");
+        assert!(!dir.join("PWNED").exists(), "{local_config}: {output:?}");
+        fs::remove_dir_all(dir).ok();
+    }
+}
+
+#[test]
+fn replays_a_recorded_run() {
+    let dir = temp_repo("cassette");
+    fs::write(dir.join("notes.txt"), "remember the milk\n").unwrap();
+    git(&dir, &["add", "."]);
+    let cassette = dir.join(".cassette");
+
+    let recorded = commitbot(&dir)
+        .arg("--record")
+        .arg(&cassette)
+        .output()
+        .unwrap();
+    assert!(recorded.status.success(), "{recorded:?}");
+    assert!(fs::read_dir(&cassette).unwrap().count() >= 2);
+
+    // a provider that would need a key and a network, answered from the recording instead
+    let replayed = commitbot(&dir)
+        .env_remove("OPENAI_API_KEY")
+        .args(["--provider", "openai", "--model", "gpt-4o-mini", "--replay"])
+        .arg(&cassette)
+        .output()
+        .unwrap();
+    assert!(replayed.status.success(), "{replayed:?}");
+    assert_eq!(replayed.stdout, recorded.stdout);
+
+    fs::write(dir.join("notes.txt"), "remember the eggs\n").unwrap();
+    git(&dir, &["add", "."]);
+    let missed = commitbot(&dir)
+        .arg("--replay")
+        .arg(&cassette)
+        .output()
+        .unwrap();
+    assert!(!missed.status.success());
+    assert!(
+        String::from_utf8_lossy(&missed.stderr).contains("No recorded summary response"),
+        "{missed:?}"
+    );
+
+    fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn describes_a_branch_offline() {
+    let dir = temp_repo("pr");
+    git(&dir, &["checkout", "-q", "-b", "feature"]);
+    for (file, title) in [("a.txt", "Add a"), ("b.txt", "Add b")] {
+        fs::write(dir.join(file), "x\n").unwrap();
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "-q", "-m", title]);
+    }
+
+    let output = commitbot(&dir)
+        .args(["pr", "main", "--commit"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Merges 2 commits from feature into main."),
+        "{stdout}"
+    );
+    assert!(stdout.contains("- Add a\n- Add b"), "{stdout}");
+
+    fs::remove_dir_all(dir).ok();
+}
+
+/// Serve Ollama's tag list and stream `reply` for every chat request, on a local port.
+fn fake_ollama(reply: &'static str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut head = String::new();
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+                if line.trim().is_empty() {
+                    break;
+                }
+                head.push_str(&line);
+            }
+            reader.read_exact(&mut vec![0; length]).unwrap();
+
+            let body = if head.starts_with("GET /api/tags") {
+                r#"{"models":[{"name":"llama3"}]}"#.to_string()
+            } else {
+                let chunk = |content: &str, done: bool| {
+                    format!(
+                        "{{\"message\":{{\"role\":\"assistant\",\"content\":{content:?}}},\"done\":{done}}}\n"
+                    )
+                };
+                chunk(reply, false) + &chunk("", true)
+            };
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            )
+            .unwrap();
+        }
+    });
+    url
+}
+
+#[test]
+fn streamed_pr_descriptions_keep_status_off_stdout() {
+    let dir = temp_repo("stream");
+    git(&dir, &["checkout", "-q", "-b", "feature"]);
+    fs::write(dir.join("a.txt"), "x\n").unwrap();
+    git(&dir, &["add", "."]);
+    git(&dir, &["commit", "-q", "-m", "Add a"]);
+    let url = fake_ollama("Adds a.");
+
+    let mut cmd = cargo::cargo_bin_cmd!();
+    let output = cmd
+        .current_dir(&dir)
+        .env("HOME", &dir)
+        .env("XDG_CACHE_HOME", dir.join(".cache"))
+        .env_remove("COMMITBOT_PROVIDER")
+        .args(["--provider", "ollama", "--model", "llama3", "--url", &url])
+        .args(["pr", "main", "--commit"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Asking llama3 to describe"), "{stderr}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // the description, then the commit list; nothing else
+    let (description, appendix) = stdout.split_once("\n\n").expect("two blocks");
+    assert_eq!(description, "Adds a.");
+    assert!(appendix.starts_with("Commits in this PR:\n"), "{stdout}");
+
+    fs::remove_dir_all(dir).ok();
+}
@@ -0,0 +1,415 @@
+use anyhow::Result;
+use clap::Parser;
+use commitbot::git::{PrItem, PrSummaryMode};
+use commitbot::llm::context::estimate_tokens;
+use commitbot::llm::{CancellationToken, PromptOptions};
+use commitbot::pipeline::{
+    GenerateOptions, NoChanges, NoProgress, SummarizeContext, SummaryProgress, check_budget,
+    check_diff_sizes, generate_commit_message_with, summarize_files, summary_request_sizes,
+};
+use commitbot::summary_cache::SummaryCache;
+use commitbot::{Cli, Config, FileCategory, FileChange, LlmClient};
+use std::fs;
+use std::sync::Mutex;
+use std::thread::{self, ThreadId};
+use std::time::Duration;
+
+mod common;
+use common::unique_temp_path;
+
+/// Stands in for a provider: answers each file with "summary of <path>" (or `reply`), records
+/// which files it was asked about, and joins the summaries into the commit message.
+#[derive(Default)]
+struct TestClient {
+    /// Summary returned for every file instead of "summary of <path>"
+    reply: Option<&'static str>,
+    /// File that takes a while to summarize
+    slow_path: Option<&'static str>,
+    summarized: Mutex<Vec<String>>,
+}
+
+impl TestClient {
+    /// The files it summarized, in the order the requests arrived.
+    fn summarized(self) -> Vec<String> {
+        self.summarized.into_inner().unwrap()
+    }
+}
+
+impl LlmClient for TestClient {
+    fn validate_model(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn summarize_file(
+        &self,
+        _branch: &str,
+        file: &FileChange,
+        _file_index: usize,
+        _total_files: usize,
+        _ticket_summary: Option<&str>,
+        _cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        self.summarized.lock().unwrap().push(file.path.clone());
+        if self.slow_path == Some(file.path.as_str()) {
+            thread::sleep(Duration::from_millis(300));
+        }
+        Ok(match self.reply {
+            Some(reply) => reply.to_string(),
+            None => format!("summary of {}", file.path),
+        })
+    }
+
+    fn generate_commit_message(
+        &self,
+        branch: &str,
+        files: &[FileChange],
+        _ticket_summary: Option<&str>,
+        _cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        let summaries: Vec<&str> = files.iter().filter_map(|f| f.summary.as_deref()).collect();
+        Ok(format!(
+            "Update {branch}.\n\n\n- {}\n",
+            summaries.join("\n- ")
+        ))
+    }
+
+    fn generate_pr_message(
+        &self,
+        _base_branch: &str,
+        _from_branch: &str,
+        _mode: PrSummaryMode,
+        _items: &[PrItem],
+        _ticket_summary: Option<&str>,
+        _cancel: Option<&CancellationToken>,
+    ) -> Result<String> {
+        unreachable!("not used by the commit pipeline")
+    }
+}
+
+/// A Main file with `diff`.
+fn file_change(path: &str, diff: impl Into<String>) -> FileChange {
+    FileChange {
+        path: path.into(),
+        category: FileCategory::Main,
+        diff: diff.into(),
+        summary: None,
+        list_only: false,
+        is_deleted: false,
+    }
+}
+
+/// One request at a time through `llm`, with no rate limit, batching, local summaries, or cache.
+fn context(llm: &dyn LlmClient) -> SummarizeContext<'_> {
+    SummarizeContext {
+        branch: "feature",
+        ticket_summary: None,
+        llm,
+        max_concurrent_requests: 1,
+        rate_limiter: None,
+        batch_consequence: false,
+        trivial_diff_lines: 0,
+        cache: None,
+    }
+}
+
+fn ollama_config() -> Config {
+    let path = unique_temp_path("pipeline").with_extension("toml");
+    // keep test summaries out of the user's summary cache
+    fs::write(&path, "[default]\nsummary_cache = false\n").expect("write temp config");
+    let cli = Cli::parse_from([
+        "commitbot",
+        "--config",
+        path.to_str().expect("utf-8 path"),
+        "--provider",
+        "ollama",
+    ]);
+    let cfg = Config::from_sources(&cli).expect("config should resolve");
+    fs::remove_file(path).ok();
+    cfg
+}
+
+#[test]
+fn generates_a_linted_message_from_a_diff() {
+    let diff = "\
+diff --git a/src/a.rs b/src/a.rs
++fn a() {}
+diff --git a/logo.png b/logo.png
+Binary files a/logo.png and b/logo.png differ
+";
+    let opts = GenerateOptions {
+        branch: Some("feature".to_string()),
+        diff: Some(diff.to_string()),
+        ticket_summary: None,
+        summary_cache: false,
+    };
+
+    let out = generate_commit_message_with(&ollama_config(), &TestClient::default(), opts).unwrap();
+
+    assert_eq!(out.message, "Update feature\n\n- summary of src/a.rs");
+    assert_eq!(out.lint_findings.len(), 2, "{:?}", out.lint_findings);
+    assert_eq!(out.files.len(), 2);
+    assert!(out.files[1].list_only, "binary file is listed only");
+    assert!(out.files[1].summary.is_none());
+}
+
+#[test]
+fn empty_diff_reports_no_changes() {
+    let opts = GenerateOptions {
+        diff: Some("  \n".to_string()),
+        ..GenerateOptions::default()
+    };
+    let err =
+        generate_commit_message_with(&ollama_config(), &TestClient::default(), opts).unwrap_err();
+    assert!(err.downcast_ref::<NoChanges>().is_some());
+}
+
+#[test]
+fn oversized_diffs_are_trimmed_when_asked() {
+    let diff: String = (0..40).map(|i| format!("+line {i}\n")).collect();
+    let file = file_change("src/big.rs", diff);
+    let mut cfg = ollama_config();
+    cfg.model = "tiny".into();
+    cfg.context_limits.insert("tiny".into(), 80);
+    cfg.strict_context = true;
+
+    let mut files = vec![file.clone()];
+    assert!(check_diff_sizes(&mut files, &cfg).is_err());
+
+    cfg.trim_context = true;
+    let mut files = vec![file];
+    check_diff_sizes(&mut files, &cfg).unwrap();
+    let trimmed = &files[0].diff;
+    assert!(trimmed.starts_with("+line 0\n+line 1\n"), "{trimmed}");
+    assert!(trimmed.contains("more line(s) omitted"), "{trimmed}");
+}
+
+#[test]
+fn runs_over_budget_stop_or_trim() {
+    let big: String = (0..2_000)
+        .map(|i| format!("+let value_{i} = {i};\n"))
+        .collect();
+    let small: String = (0..10).map(|i| format!("+small {i}\n")).collect();
+    let files = vec![
+        file_change("src/big.rs", big),
+        file_change("src/small.rs", small),
+    ];
+    let mut cfg = ollama_config();
+    cfg.max_tokens_per_run = 5_000;
+
+    let mut stopped = files.clone();
+    let err = check_budget(&mut stopped, &cfg).unwrap_err().to_string();
+    assert!(err.contains("max_tokens_per_run"), "{err}");
+    assert_eq!(
+        stopped[0].diff, files[0].diff,
+        "nothing is changed when stopping"
+    );
+
+    cfg.trim_context = true;
+    let mut trimmed = files.clone();
+    check_budget(&mut trimmed, &cfg).unwrap();
+    assert!(trimmed[0].diff.contains("omitted to fit the run budget"));
+    assert_eq!(trimmed[1].diff, files[1].diff, "small diffs are left whole");
+
+    cfg.max_tokens_per_run = 0;
+    let mut unlimited = files.clone();
+    check_budget(&mut unlimited, &cfg).unwrap();
+    assert_eq!(unlimited[0].diff, files[0].diff);
+}
+
+/// Records the order of progress events and the thread each ran on.
+#[derive(Default)]
+struct Recorder(Mutex<Vec<(String, ThreadId)>>);
+
+impl SummaryProgress for Recorder {
+    fn started(&self, idx: usize, _batched: bool) {
+        let mut events = self.0.lock().unwrap();
+        events.push((format!("start {idx}"), thread::current().id()));
+    }
+
+    fn finished(&self, idx: usize, _result: &Result<String>) {
+        let mut events = self.0.lock().unwrap();
+        events.push((format!("finish {idx}"), thread::current().id()));
+    }
+}
+
+#[test]
+fn concurrency_one_summarizes_in_order_on_the_calling_thread() {
+    let mut files: Vec<FileChange> = (0..3)
+        .map(|i| file_change(&format!("src/{i}.rs"), "+x"))
+        .collect();
+    let llm = TestClient::default();
+    let ctx = context(&llm);
+    let progress = Recorder::default();
+
+    summarize_files(&mut files, &[0, 1, 2], &ctx, &progress).unwrap();
+
+    let events = progress.0.into_inner().unwrap();
+    let order: Vec<&str> = events.iter().map(|(e, _)| e.as_str()).collect();
+    assert_eq!(
+        order,
+        [
+            "start 0", "finish 0", "start 1", "finish 1", "start 2", "finish 2"
+        ]
+    );
+    assert!(events.iter().all(|(_, id)| *id == thread::current().id()));
+    assert_eq!(files[2].summary.as_deref(), Some("summary of src/2.rs"));
+}
+
+#[test]
+fn a_slow_file_does_not_hold_up_the_others() {
+    let mut files: Vec<FileChange> = ["slow.rs", "a.rs", "b.rs", "c.rs", "d.rs"]
+        .iter()
+        .map(|path| file_change(path, format!("+{path}")))
+        .collect();
+    let llm = TestClient {
+        slow_path: Some("slow.rs"),
+        ..TestClient::default()
+    };
+    let ctx = SummarizeContext {
+        max_concurrent_requests: 2,
+        ..context(&llm)
+    };
+    let progress = Recorder::default();
+
+    summarize_files(&mut files, &[0, 1, 2, 3, 4], &ctx, &progress).unwrap();
+
+    let events = progress.0.into_inner().unwrap();
+    let last = events.last().map(|(e, _)| e.as_str());
+    assert_eq!(
+        last,
+        Some("finish 0"),
+        "the other slot kept working: {events:?}"
+    );
+    assert!(files.iter().all(|f| f.summary.is_some()));
+}
+
+#[test]
+fn empty_summaries_are_retried_then_replaced_with_a_fallback() {
+    let mut files = vec![file_change("src/a.rs", "@@ -1 +1,2 @@\n-a\n+b\n+c")];
+    let llm = TestClient {
+        reply: Some("  \n"),
+        ..TestClient::default()
+    };
+
+    summarize_files(&mut files, &[0], &context(&llm), &NoProgress).unwrap();
+
+    assert_eq!(llm.summarized().len(), 2);
+    assert_eq!(
+        files[0].summary.as_deref(),
+        Some("- Changed `src/a.rs` (+2/-1 lines)")
+    );
+}
+
+#[test]
+fn identical_diffs_share_one_request() {
+    let new_file = |path: &str, body: &str| {
+        file_change(
+            path,
+            format!(
+                "diff --git a/{path} b/{path}\nnew file mode 100644\n--- /dev/null\n+++ b/{path}\n@@ -0,0 +1 @@\n+{body}\n"
+            ),
+        )
+    };
+    let mut files = vec![
+        new_file("a/LICENSE", "MIT"),
+        new_file("b/LICENSE", "MIT"),
+        new_file("c/LICENSE", "Apache-2.0"),
+    ];
+    let llm = TestClient::default();
+    let ctx = SummarizeContext {
+        max_concurrent_requests: 4,
+        ..context(&llm)
+    };
+
+    summarize_files(&mut files, &[0, 1, 2], &ctx, &NoProgress).unwrap();
+
+    let mut calls = llm.summarized();
+    calls.sort();
+    assert_eq!(calls, ["a/LICENSE", "c/LICENSE"]);
+    assert_eq!(files[1].summary.as_deref(), Some("summary of b/LICENSE"));
+    assert_eq!(files[2].summary.as_deref(), Some("summary of c/LICENSE"));
+}
+
+#[test]
+fn trivial_diffs_are_summarized_without_a_request() {
+    let mut files = vec![
+        file_change("src/a.rs", "@@ -1 +1,2 @@\n+use std::fmt;\n fn a() {}"),
+        file_change("src/b.rs", "@@ -1 +1 @@\n-a()\n+b()"),
+    ];
+    let llm = TestClient::default();
+    let ctx = SummarizeContext {
+        trivial_diff_lines: 3,
+        ..context(&llm)
+    };
+
+    summarize_files(&mut files, &[0, 1], &ctx, &NoProgress).unwrap();
+
+    assert_eq!(llm.summarized(), ["src/b.rs"]);
+    assert_eq!(
+        files[0].summary.as_deref(),
+        Some("- Add import `use std::fmt` in `src/a.rs`")
+    );
+}
+
+#[test]
+fn cached_summaries_skip_unchanged_files() {
+    let dir = unique_temp_path("cache");
+    let cache = SummaryCache::in_dir(dir.clone(), "ollama:test", PromptOptions::default());
+    let file = |path: &str, blob: &str, line: &str| {
+        file_change(
+            path,
+            format!(
+                "diff --git a/{path} b/{path}\nindex 1111111..{blob} 100644\n@@ -1 +1 @@\n-old\n+{line}\n"
+            ),
+        )
+    };
+    let run = |files: &mut Vec<FileChange>| {
+        let llm = TestClient::default();
+        let ctx = SummarizeContext {
+            cache: Some(&cache),
+            ..context(&llm)
+        };
+        summarize_files(files, &[0, 1], &ctx, &NoProgress).unwrap();
+        llm.summarized()
+    };
+
+    let mut first = vec![file("a.rs", "aaaaaaa", "a"), file("b.rs", "bbbbbbb", "b")];
+    assert_eq!(run(&mut first), ["a.rs", "b.rs"]);
+
+    let mut second = vec![file("a.rs", "aaaaaaa", "a"), file("b.rs", "ccccccc", "b2")];
+    assert_eq!(
+        run(&mut second),
+        ["b.rs"],
+        "only the edited file is summarized again"
+    );
+    assert_eq!(second[0].summary.as_deref(), Some("summary of a.rs"));
+
+    fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn request_sizes_count_only_what_reaches_the_model() {
+    let consequence = |path: &str| FileChange {
+        category: FileCategory::Consequence,
+        ..file_change(path, format!("@@ -1 +1 @@\n-{path} 1\n+{path} 2"))
+    };
+    let files = vec![
+        file_change("a/LICENSE", "@@ -0,0 +1 @@\n+MIT"),
+        file_change("b/LICENSE", "@@ -0,0 +1 @@\n+MIT"),
+        file_change("src/a.rs", "@@ -1 +1,2 @@\n+use std::fmt;\n fn a() {}"),
+        consequence("Cargo.lock"),
+        consequence("package-lock.json"),
+    ];
+    let llm = TestClient::default();
+    let ctx = SummarizeContext {
+        batch_consequence: true,
+        trivial_diff_lines: 3,
+        ..context(&llm)
+    };
+
+    let sizes = summary_request_sizes(&files, &[0, 1, 2, 3, 4], &ctx);
+
+    let batch = estimate_tokens(&files[3].diff) + estimate_tokens(&files[4].diff);
+    assert_eq!(sizes, [batch, estimate_tokens(&files[0].diff)]);
+    assert!(llm.summarized().is_empty());
+}
@@ -0,0 +1,21 @@
+use commitbot::rate_limit::RateLimiter;
+use std::time::Duration;
+
+#[test]
+fn burst_is_served_without_waiting() {
+    let limiter = RateLimiter::new(600, 3);
+    for _ in 0..3 {
+        assert_eq!(limiter.acquire(), Duration::ZERO);
+    }
+}
+
+#[test]
+fn waits_once_burst_is_exhausted() {
+    // 600 rpm = one token every 100ms.
+    let limiter = RateLimiter::new(600, 1);
+    assert_eq!(limiter.acquire(), Duration::ZERO);
+
+    let waited = limiter.acquire();
+    assert!(waited >= Duration::from_millis(50), "waited {waited:?}");
+    assert!(waited <= Duration::from_millis(250), "waited {waited:?}");
+}
@@ -0,0 +1,64 @@
+use commitbot::run_state::{RunState, fingerprint};
+use commitbot::{FileCategory, FileChange};
+
+fn change(path: &str, diff: &str, category: FileCategory, summary: Option<&str>) -> FileChange {
+    FileChange {
+        path: path.to_string(),
+        category,
+        diff: diff.to_string(),
+        summary: summary.map(str::to_string),
+        list_only: false,
+        is_deleted: false,
+    }
+}
+
+#[test]
+fn state_matches_only_the_same_branch_and_diffs() {
+    let files = vec![
+        change("src/a.rs", "+a", FileCategory::Main, Some("- Add a")),
+        change("src/b.rs", "+b", FileCategory::Consequence, None),
+    ];
+    let state = RunState::capture("feature", Some("Ticket"), &files);
+
+    let pairs = vec![
+        ("src/a.rs".to_string(), "+a".to_string()),
+        ("src/b.rs".to_string(), "+b".to_string()),
+    ];
+    assert!(state.matches("feature", &pairs));
+    assert!(!state.matches("main", &pairs));
+
+    let edited = vec![
+        pairs[0].clone(),
+        ("src/b.rs".to_string(), "+b2".to_string()),
+    ];
+    assert!(!state.matches("feature", &edited));
+    assert!(!state.matches("feature", &pairs[..1]));
+
+    assert_eq!(
+        state.categories(),
+        vec![FileCategory::Main, FileCategory::Consequence]
+    );
+}
+
+#[test]
+fn restores_saved_summaries_onto_fresh_changes() {
+    let done = vec![
+        change("src/a.rs", "+a", FileCategory::Main, Some("- Add a")),
+        change("src/b.rs", "+b", FileCategory::Main, None),
+    ];
+    let state = RunState::capture("feature", None, &done);
+
+    let mut fresh = vec![
+        change("src/a.rs", "+a", FileCategory::Main, None),
+        change("src/b.rs", "+b", FileCategory::Main, None),
+    ];
+    state.restore_summaries(&mut fresh);
+    assert_eq!(fresh[0].summary.as_deref(), Some("- Add a"));
+    assert_eq!(fresh[1].summary, None);
+}
+
+#[test]
+fn fingerprint_is_stable() {
+    assert_eq!(fingerprint(""), "cbf29ce484222325");
+    assert_ne!(fingerprint("+a"), fingerprint("+b"));
+}
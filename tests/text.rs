@@ -0,0 +1,46 @@
+use commitbot::text::lint_message;
+
+#[test]
+fn strips_trailing_period_from_subject() {
+    let outcome = lint_message("Add login endpoint.\n\n- Wire up handler");
+    assert_eq!(outcome.message, "Add login endpoint\n\n- Wire up handler");
+    assert_eq!(
+        outcome.findings,
+        vec!["removed trailing period from subject"]
+    );
+}
+
+#[test]
+fn keeps_ellipsis_on_subject() {
+    let outcome = lint_message("Wait for it...");
+    assert_eq!(outcome.message, "Wait for it...");
+    assert!(outcome.findings.is_empty());
+}
+
+#[test]
+fn separates_subject_and_body_with_one_blank_line() {
+    let outcome = lint_message("Add login\n- Wire up handler");
+    assert_eq!(outcome.message, "Add login\n\n- Wire up handler");
+
+    let outcome = lint_message("Add login\n\n\n\n- Wire up handler");
+    assert_eq!(outcome.message, "Add login\n\n- Wire up handler");
+    assert_eq!(outcome.findings.len(), 1);
+}
+
+#[test]
+fn collapses_double_blank_lines_in_body() {
+    let outcome = lint_message("\nAdd login\n\n- One\n\n\n- Two  \n\n");
+    assert_eq!(outcome.message, "Add login\n\n- One\n\n- Two");
+    assert_eq!(
+        outcome.findings,
+        vec!["collapsed 1 extra blank line(s) in body"]
+    );
+}
+
+#[test]
+fn clean_message_is_unchanged() {
+    let msg = "Add login\n\n- One\n- Two";
+    let outcome = lint_message(msg);
+    assert_eq!(outcome.message, msg);
+    assert!(outcome.findings.is_empty());
+}
@@ -0,0 +1,74 @@
+use commitbot::trivial::trivial_summary;
+
+fn diff(lines: &str) -> String {
+    format!(
+        "diff --git a/src/a.rs b/src/a.rs\n--- a/src/a.rs\n+++ b/src/a.rs\n@@ -1,3 +1,3 @@\n{lines}"
+    )
+}
+
+#[test]
+fn summarizes_blank_line_changes() {
+    assert_eq!(
+        trivial_summary("src/a.rs", &diff(" fn a() {}\n+\n"), 3).as_deref(),
+        Some("- Add blank lines in `src/a.rs`")
+    );
+    assert_eq!(
+        trivial_summary("src/a.rs", &diff("-\n-   \n"), 3).as_deref(),
+        Some("- Remove blank lines in `src/a.rs`")
+    );
+}
+
+#[test]
+fn summarizes_whitespace_only_changes() {
+    assert_eq!(
+        trivial_summary("src/a.rs", &diff("-let x = 1;  \n+let x = 1;\n"), 3).as_deref(),
+        Some("- Remove trailing whitespace in `src/a.rs`")
+    );
+    assert_eq!(
+        trivial_summary("src/a.rs", &diff("-let x = 1;\n+let x = 1;\t\n"), 3).as_deref(),
+        Some("- Adjust trailing whitespace in `src/a.rs`")
+    );
+}
+
+#[test]
+fn leaves_indentation_and_inner_spacing_to_the_model() {
+    // re-indenting moves a Python statement out of its block
+    assert_eq!(
+        trivial_summary("app.py", &diff("-    return x\n+return x\n"), 3),
+        None
+    );
+    assert_eq!(
+        trivial_summary("src/a.rs", &diff("-let x=1;\n+let x = 1;\n"), 3),
+        None
+    );
+    assert_eq!(
+        trivial_summary("src/a.rs", &diff("-msg(\"a b\")\n+msg(\"a  b\")\n"), 3),
+        None
+    );
+}
+
+#[test]
+fn summarizes_import_changes() {
+    assert_eq!(
+        trivial_summary("src/a.rs", &diff("+use std::fmt;\n-use std::io;\n"), 3).as_deref(),
+        Some(
+            "- Add import `use std::fmt` in `src/a.rs`\n- Remove import `use std::io` in `src/a.rs`"
+        )
+    );
+    assert_eq!(
+        trivial_summary("app.py", &diff("+from os import path\n"), 3).as_deref(),
+        Some("- Add import `from os import path` in `app.py`")
+    );
+}
+
+#[test]
+fn leaves_real_changes_to_the_model() {
+    assert_eq!(trivial_summary("src/a.rs", &diff("-a()\n+b()\n"), 3), None);
+    assert_eq!(
+        trivial_summary("src/a.rs", &diff("+from here on\n"), 3),
+        None
+    );
+    assert_eq!(trivial_summary("src/a.rs", &diff("+\n+\n+\n+\n"), 3), None);
+    assert_eq!(trivial_summary("src/a.rs", &diff("+\n"), 0), None);
+    assert_eq!(trivial_summary("src/a.rs", "Binary files differ", 3), None);
+}